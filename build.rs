@@ -13,7 +13,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 "protobuf/ycchat/v1/services/message/reaction.proto",
                 "protobuf/ycchat/v1/services/auth/auth.proto",
                 "protobuf/ycchat/v1/services/account/account.proto",
+                "protobuf/ycchat/v1/services/attachment/attachment.proto",
                 "protobuf/ycchat/v1/services/connect/connect.proto",
+                "protobuf/ycchat/v1/services/me/channel/me_channel.proto",
+                "protobuf/ycchat/v1/services/me/mention/me_mention.proto",
                 "protobuf/ycchat/v1/services/me/server/me_server.proto",
                 "protobuf/ycchat/v1/services/me/user/me_user.proto",
             ],
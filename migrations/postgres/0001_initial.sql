@@ -0,0 +1,177 @@
+-- mirrors the SurrealDB schema once every migration of migrations/surreal is applied.
+-- ids are ulid strings, compared bytewise so they sort in creation order. like SurrealDB record
+-- links they aren't foreign keys, deletes behave the same on both backends.
+
+-- auth
+CREATE TABLE IF NOT EXISTS auth (
+    id TEXT COLLATE "C" PRIMARY KEY,
+    username TEXT NOT NULL CHECK (username ~ '[a-z][a-z0-9_]{1,20}'),
+    password TEXT NOT NULL,
+    email TEXT NOT NULL CHECK (email = lower(email) AND email LIKE '%_@_%'),
+    is_email_verified BOOLEAN NOT NULL DEFAULT false,
+    create_time TIMESTAMPTZ NOT NULL DEFAULT now(),
+    update_time TIMESTAMPTZ,
+    last_login_time TIMESTAMPTZ
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS auth_username_index ON auth (username);
+CREATE INDEX IF NOT EXISTS auth_email_index ON auth (email);
+
+-- attachment
+CREATE TABLE IF NOT EXISTS attachment (
+    id TEXT COLLATE "C" PRIMARY KEY,
+    url TEXT NOT NULL,
+    filename TEXT NOT NULL,
+    mime_type TEXT NOT NULL,
+    file_size BIGINT NOT NULL CHECK (file_size > 0), -- unit: bytes
+    metadata JSONB,
+    create_time TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- attachment_uploaded
+CREATE TABLE IF NOT EXISTS attachment_uploaded (
+    attachment_id TEXT COLLATE "C" PRIMARY KEY,
+    user_id TEXT COLLATE "C" NOT NULL
+);
+
+-- user
+CREATE TABLE IF NOT EXISTS "user" (
+    id TEXT COLLATE "C" PRIMARY KEY,
+    display_name TEXT NOT NULL CHECK (char_length(display_name) <= 30),
+    description TEXT NOT NULL CHECK (char_length(description) <= 255),
+    avatar TEXT COLLATE "C",
+    region_code TEXT,
+    language_code TEXT,
+    time_zone TEXT,
+    settings JSONB NOT NULL DEFAULT '{}',
+    create_time TIMESTAMPTZ NOT NULL DEFAULT now(),
+    update_time TIMESTAMPTZ
+);
+
+-- server
+CREATE TABLE IF NOT EXISTS server (
+    id TEXT COLLATE "C" PRIMARY KEY,
+    display_name TEXT NOT NULL CHECK (char_length(display_name) <= 50),
+    description TEXT NOT NULL CHECK (char_length(description) <= 255),
+    owner TEXT COLLATE "C" NOT NULL,
+    author TEXT COLLATE "C" NOT NULL,
+    icon TEXT COLLATE "C",
+    create_time TIMESTAMPTZ NOT NULL DEFAULT now(),
+    update_time TIMESTAMPTZ
+);
+
+-- server_category
+CREATE TABLE IF NOT EXISTS server_category (
+    id TEXT COLLATE "C" PRIMARY KEY,
+    display_name TEXT NOT NULL CHECK (char_length(display_name) <= 50),
+    description TEXT NOT NULL CHECK (char_length(description) <= 255),
+    server TEXT COLLATE "C" NOT NULL,
+    icon TEXT COLLATE "C",
+    "order" BIGINT NOT NULL CHECK ("order" >= 0),
+    create_time TIMESTAMPTZ NOT NULL DEFAULT now(),
+    update_time TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS server_category_server_index ON server_category (server);
+
+-- channel
+CREATE TABLE IF NOT EXISTS channel (
+    id TEXT COLLATE "C" PRIMARY KEY,
+    channel_type TEXT NOT NULL CHECK (channel_type IN ('SAVED', 'DIRECT', 'GROUP', 'SERVER')),
+    owner TEXT COLLATE "C", -- SAVED and GROUP only.
+    server TEXT COLLATE "C", -- SERVER only.
+    display_name TEXT NOT NULL CHECK (char_length(display_name) <= 50),
+    description TEXT NOT NULL CHECK (char_length(description) <= 255),
+    icon TEXT COLLATE "C",
+    "order" BIGINT NOT NULL CHECK ("order" >= 0),
+    category TEXT COLLATE "C",
+    members TEXT[] COLLATE "C" NOT NULL DEFAULT '{}', -- only used when channel_type is not SERVER.
+    last_message_time TIMESTAMPTZ,
+    create_time TIMESTAMPTZ NOT NULL DEFAULT now(),
+    update_time TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS channel_server_index ON channel (server);
+CREATE INDEX IF NOT EXISTS channel_members_index ON channel USING GIN (members);
+
+-- message
+CREATE TABLE IF NOT EXISTS message (
+    id TEXT COLLATE "C" PRIMARY KEY,
+    author TEXT COLLATE "C" NOT NULL,
+    channel TEXT COLLATE "C" NOT NULL,
+    content TEXT NOT NULL,
+    message_type TEXT NOT NULL CHECK (message_type IN ('DEFAULT', 'CHANNEL_PINNED_MESSAGE', 'GROUP_MEMBER_ADDED', 'GROUP_MEMBER_REMOVED', 'GROUP_MEMBER_LEFT')),
+    reference TEXT COLLATE "C", -- message id, used by system messages.
+    attachments TEXT[] COLLATE "C" NOT NULL DEFAULT '{}',
+    mention_users TEXT[] COLLATE "C" NOT NULL DEFAULT '{}',
+    mention_roles TEXT[] COLLATE "C" NOT NULL DEFAULT '{}',
+    mention_channels TEXT[] COLLATE "C" NOT NULL DEFAULT '{}',
+    mention_everyone BOOLEAN NOT NULL DEFAULT false,
+    mention_here BOOLEAN NOT NULL DEFAULT false,
+    pinned_by TEXT COLLATE "C",
+    pin_time TIMESTAMPTZ,
+    create_time TIMESTAMPTZ NOT NULL DEFAULT now(),
+    update_time TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS message_channel_index ON message (channel, id);
+CREATE INDEX IF NOT EXISTS message_pin_time_index ON message (channel, pin_time);
+-- full-text search over message content, see postgres::message_search.
+CREATE INDEX IF NOT EXISTS message_content_search ON message USING GIN (to_tsvector('simple', content));
+
+-- mention
+CREATE TABLE IF NOT EXISTS mention (
+    id TEXT COLLATE "C" PRIMARY KEY,
+    "user" TEXT COLLATE "C" NOT NULL,
+    message TEXT COLLATE "C" NOT NULL,
+    channel TEXT COLLATE "C" NOT NULL,
+    create_time TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS mention_user_index ON mention ("user", id);
+
+-- read_state
+CREATE TABLE IF NOT EXISTS read_state (
+    id TEXT COLLATE "C" PRIMARY KEY,
+    "user" TEXT COLLATE "C" NOT NULL,
+    channel TEXT COLLATE "C" NOT NULL,
+    last_read_message TEXT COLLATE "C" NOT NULL,
+    update_time TIMESTAMPTZ NOT NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS unique_read_state ON read_state ("user", channel);
+
+-- message_acknowledge
+CREATE TABLE IF NOT EXISTS message_acknowledge (
+    id TEXT COLLATE "C" PRIMARY KEY,
+    message_id TEXT COLLATE "C" NOT NULL,
+    user_id TEXT COLLATE "C" NOT NULL,
+    create_time TIMESTAMPTZ NOT NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS unique_message_acknowledge ON message_acknowledge (message_id, user_id);
+
+-- server_member
+CREATE TABLE IF NOT EXISTS server_member (
+    id TEXT COLLATE "C" PRIMARY KEY,
+    "user" TEXT COLLATE "C" NOT NULL,
+    server TEXT COLLATE "C" NOT NULL,
+    display_name TEXT NOT NULL CHECK (char_length(display_name) <= 50),
+    description TEXT NOT NULL CHECK (char_length(description) <= 255),
+    avatar TEXT COLLATE "C",
+    create_time TIMESTAMPTZ NOT NULL,
+    update_time TIMESTAMPTZ
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS unique_member ON server_member ("user", server);
+CREATE INDEX IF NOT EXISTS server_member_server_index ON server_member (server, id);
+
+-- reaction
+CREATE TABLE IF NOT EXISTS reaction (
+    id TEXT COLLATE "C" PRIMARY KEY,
+    "user" TEXT COLLATE "C" NOT NULL,
+    message TEXT COLLATE "C" NOT NULL,
+    create_time TIMESTAMPTZ NOT NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS unique_reaction ON reaction ("user", message);
@@ -1,15 +1,12 @@
-use crate::util::variable::JWT_SECRET;
 use jsonwebtoken::{
     decode as jwt_decode, encode, get_current_timestamp, DecodingKey, EncodingKey, Validation,
 };
 use serde::{Deserialize, Serialize};
 
-use crate::models::user::UserId;
+use crate::{config::AuthConfig, models::user::UserId};
 
 pub const ALGORITHM: jsonwebtoken::Algorithm = jsonwebtoken::Algorithm::HS256;
 const ISS: &str = "ycchat";
-const EXP_ACCESS_TOKEN: u64 = 3600; // 1 hour
-const EXP_REFRESH_TOKEN: u64 = 3600 * 24 * 14; // 14 day
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
@@ -20,38 +17,45 @@ pub struct Claims {
     pub exp: u64,
 }
 
-pub fn generate_access_token(user_id: &UserId) -> Result<String, jsonwebtoken::errors::Error> {
-    let key = EncodingKey::from_secret(JWT_SECRET.as_bytes());
+pub fn generate_access_token(
+    config: &AuthConfig,
+    user_id: &UserId,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    let key = EncodingKey::from_secret(config.jwt_secret.as_bytes());
 
     let claims = Claims {
         sub: "access_token".to_string(),
         aud: *user_id,
         iss: ISS.to_string(),
         iat: get_current_timestamp(),
-        exp: get_current_timestamp() + EXP_ACCESS_TOKEN,
+        exp: get_current_timestamp() + config.access_token_ttl,
     };
 
     encode(&jsonwebtoken::Header::new(ALGORITHM), &claims, &key)
 }
 
-pub fn generate_refresh_token(user_id: &UserId) -> Result<String, jsonwebtoken::errors::Error> {
-    let key = EncodingKey::from_secret(JWT_SECRET.as_bytes());
+pub fn generate_refresh_token(
+    config: &AuthConfig,
+    user_id: &UserId,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    let key = EncodingKey::from_secret(config.jwt_secret.as_bytes());
 
     let claims = Claims {
         sub: "refresh_token".to_string(),
         aud: *user_id,
         iss: ISS.to_string(),
         iat: get_current_timestamp(),
-        exp: get_current_timestamp() + EXP_REFRESH_TOKEN,
+        exp: get_current_timestamp() + config.refresh_token_ttl,
     };
 
     encode(&jsonwebtoken::Header::new(ALGORITHM), &claims, &key)
 }
 
 pub fn decode(
+    config: &AuthConfig,
     jwt_token: &str,
 ) -> Result<jsonwebtoken::TokenData<Claims>, jsonwebtoken::errors::Error> {
-    let key = DecodingKey::from_secret(JWT_SECRET.as_bytes());
+    let key = DecodingKey::from_secret(config.jwt_secret.as_bytes());
     let mut validation = Validation::new(ALGORITHM);
     validation.validate_aud = false;
 
@@ -1,7 +1,4 @@
-use std::{
-    collections::{HashMap, HashSet},
-    hash::Hash,
-};
+use std::collections::HashMap;
 
 use crate::services::ycchat::v1::services::connect::{
     server_signal::Payload, ChannelReceiveMessage,
@@ -13,88 +10,101 @@ use crate::{
         ycchat::v1::services::connect::{ConnectResponse, ServerSignal},
     },
 };
-use tokio::sync::{
-    mpsc::channel,
-    mpsc::{Receiver, Sender},
-    RwLock,
-};
+use tokio::sync::{mpsc::Sender, RwLock};
 use ulid::Ulid;
 
+/// signals queued for a stream before it counts as stalled.
+pub const STREAM_BUFFER_SIZE: usize = 64;
+
 pub struct Broadcaster {
-    sender: Sender<Message>,
-    receiver: Receiver<Message>,
-    streams: RwLock<HashMap<UserId, HashSet<Stream>>>,
+    /// connected streams of every user, by stream id.
+    streams: RwLock<HashMap<UserId, HashMap<Ulid, Stream>>>,
 }
 
 impl Broadcaster {
     pub fn new() -> Self {
-        let streams = RwLock::new(HashMap::new());
-        let (sender, mut receiver) = channel::<Message>(10);
-
         Self {
-            sender,
-            receiver,
-            streams,
+            streams: RwLock::new(HashMap::new()),
         }
     }
 
-    pub async fn send_msg(&self, user_ids: &[UserId], message: Message) {
-        self.sender
-            .send(message)
-            .await
-            .expect("Failed to send data");
-    }
-
+    /// delivers `message` to every connected stream of `user_ids`.
     pub async fn send_message(&self, user_ids: &[UserId], message: Message) {
-        let streams = self.streams.read().await;
+        let payload = Payload::ChannelReceiveMessage(ChannelReceiveMessage {
+            message: Some(message),
+        });
+
+        self.send_signal(user_ids, payload).await;
+    }
 
-        while let Some(user_id) = user_ids.first() {
-            let hash_set = streams.get(user_id);
+    /// delivers `payload` to every connected stream of `user_ids`.
+    ///
+    /// never waits for a client: a stream whose queue is full stopped reading, it is dropped
+    /// like a closed one and its connection ends.
+    pub async fn send_signal(&self, user_ids: &[UserId], payload: Payload) {
+        let mut dropped = vec![];
 
-            if let Some(hash_set) = hash_set {
-                while let Some(stream) = hash_set.iter().next() {
-                    let channel_receive_message =
-                        Payload::ChannelReceiveMessage(ChannelReceiveMessage {
-                            message: Some(message.clone()),
-                        });
+        {
+            let streams = self.streams.read().await;
 
-                    let server_signal = ServerSignal {
-                        payload: Some(channel_receive_message),
-                    };
+            for user_id in user_ids.iter() {
+                let Some(user_streams) = streams.get(user_id) else {
+                    continue;
+                };
 
+                for stream in user_streams.values() {
                     let conn_response = ConnectResponse {
-                        server_signal: Some(server_signal),
+                        server_signal: Some(ServerSignal {
+                            payload: Some(payload.clone()),
+                        }),
                     };
-                    let _ = stream.sender.send(conn_response).await;
+
+                    if stream.sender.try_send(conn_response).is_err() {
+                        dropped.push((*user_id, stream.id));
+                    }
                 }
             }
         }
+
+        for (user_id, stream_id) in dropped {
+            self.remove_stream(user_id, stream_id).await;
+        }
+    }
+
+    /// users of `user_ids` with at least one connected stream.
+    pub async fn online_users(&self, user_ids: &[UserId]) -> Vec<UserId> {
+        let streams = self.streams.read().await;
+
+        user_ids
+            .iter()
+            .filter(|user_id| {
+                streams
+                    .get(user_id)
+                    .is_some_and(|user_streams| !user_streams.is_empty())
+            })
+            .cloned()
+            .collect()
     }
 
     pub async fn set_stream(&mut self, user_id: UserId, stream: Stream) {
         let mut streams = self.streams.write().await;
-        let hash_set = streams.get_mut(&user_id);
 
-        match hash_set {
-            Some(hash_set) => {
-                hash_set.insert(stream);
-            }
-            None => {
-                let mut hash_set = HashSet::new();
-                hash_set.insert(stream);
-
-                streams.insert(user_id, hash_set);
-            }
-        };
+        streams
+            .entry(user_id)
+            .or_default()
+            .insert(stream.id, stream);
     }
 
-    pub async fn remove_stream(&self, user_id: UserId, stream: Stream) {
+    pub async fn remove_stream(&self, user_id: UserId, stream_id: Ulid) {
         let mut streams = self.streams.write().await;
-        let hash_set = streams.get_mut(&user_id);
 
-        if let Some(hash_set) = hash_set {
-            hash_set.remove(&stream);
-        };
+        if let Some(user_streams) = streams.get_mut(&user_id) {
+            user_streams.remove(&stream_id);
+
+            if user_streams.is_empty() {
+                streams.remove(&user_id);
+            }
+        }
     }
 }
 
@@ -110,17 +120,82 @@ impl Stream {
             sender,
         }
     }
-}
 
-impl PartialEq for Stream {
-    fn eq(&self, other: &Self) -> bool {
-        self.id == other.id
+    pub fn id(&self) -> Ulid {
+        self.id
     }
 }
-impl Eq for Stream {}
 
-impl Hash for Stream {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        state.write(&self.id.to_bytes());
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::sync::mpsc::{self, Receiver};
+
+    use super::*;
+
+    async fn connect(broadcaster: &mut Broadcaster, user_id: UserId) -> Receiver<ConnectResponse> {
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER_SIZE);
+        broadcaster.set_stream(user_id, Stream::new(tx)).await;
+
+        rx
+    }
+
+    fn payload() -> Payload {
+        Payload::ChannelReceiveMessage(ChannelReceiveMessage { message: None })
+    }
+
+    #[tokio::test]
+    async fn send_signal_reaches_every_stream_of_the_users() {
+        let mut broadcaster = Broadcaster::new();
+        let (user, other, stranger) = (UserId::new(), UserId::new(), UserId::new());
+        let mut first = connect(&mut broadcaster, user).await;
+        let mut second = connect(&mut broadcaster, user).await;
+        let mut other_rx = connect(&mut broadcaster, other).await;
+        let mut stranger_rx = connect(&mut broadcaster, stranger).await;
+
+        broadcaster.send_signal(&[user, other], payload()).await;
+
+        assert!(first.try_recv().is_ok());
+        assert!(second.try_recv().is_ok());
+        assert!(other_rx.try_recv().is_ok());
+        assert!(stranger_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn send_signal_drops_a_stalled_stream() {
+        let mut broadcaster = Broadcaster::new();
+        let user = UserId::new();
+        let mut rx = connect(&mut broadcaster, user).await;
+
+        // nobody reads `rx`, the queue fills up and must not block the sender.
+        let send_all = async {
+            for _ in 0..=STREAM_BUFFER_SIZE {
+                broadcaster.send_signal(&[user], payload()).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(1), send_all)
+            .await
+            .expect("send_signal waited for a stalled stream");
+
+        assert!(broadcaster.online_users(&[user]).await.is_empty());
+
+        // the queued signals are still delivered, then the stream ends.
+        for _ in 0..STREAM_BUFFER_SIZE {
+            assert!(rx.recv().await.is_some());
+        }
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn send_signal_drops_a_closed_stream() {
+        let mut broadcaster = Broadcaster::new();
+        let (user, other) = (UserId::new(), UserId::new());
+        drop(connect(&mut broadcaster, user).await);
+        let _other_rx = connect(&mut broadcaster, other).await;
+
+        broadcaster.send_signal(&[user, other], payload()).await;
+
+        assert_eq!(broadcaster.online_users(&[user, other]).await, vec![other]);
     }
 }
@@ -0,0 +1,350 @@
+use std::{env, fmt, fs, io, net::SocketAddr, str::FromStr};
+
+use serde::Deserialize;
+
+use crate::db::{
+    postgres::PostgresConfig,
+    surreal::{SurrealConfig, SurrealEngine},
+};
+
+/// read when `YCCHAT_CONFIG` isn't set, the file is optional.
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+/// settings of the whole server, read from a TOML file and overridden by `YCCHAT_*` environment
+/// variables. see `config.example.toml`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub auth: AuthConfig,
+    pub storage: StorageConfig,
+    pub limits: LimitsConfig,
+    pub paging: PagingConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub addr: SocketAddr,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub backend: DatabaseBackend,
+    /// applies pending migrations on start, otherwise the server refuses to start until
+    /// `ycChat migrate` applied them.
+    pub auto_migrate: bool,
+    pub surreal: SurrealConfig,
+    pub postgres: PostgresConfig,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DatabaseBackend {
+    #[default]
+    Surreal,
+    Postgres,
+    /// kept in the process and lost on exit, for tests and demos.
+    Memory,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// HS256 key of the access and refresh tokens, `openssl rand -base64 32`.
+    pub jwt_secret: String,
+    /// seconds
+    pub access_token_ttl: u64,
+    /// seconds
+    pub refresh_token_ttl: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    pub attachment_dir: String,
+    pub attachment_base_url: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// bytes
+    pub max_file_size: i64,
+    pub max_pinned_messages: usize,
+    pub max_acknowledge_batch_size: i32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PagingConfig {
+    /// HMAC-SHA256 key of the page tokens, `openssl rand -base64 32`.
+    pub page_token_secret: String,
+    /// seconds
+    pub page_token_ttl: u64,
+}
+
+impl FromStr for DatabaseBackend {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "surreal" => Ok(DatabaseBackend::Surreal),
+            "postgres" => Ok(DatabaseBackend::Postgres),
+            "memory" => Ok(DatabaseBackend::Memory),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            addr: SocketAddr::from(([0, 0, 0, 0], 50051)),
+        }
+    }
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            backend: DatabaseBackend::default(),
+            auto_migrate: true,
+            surreal: SurrealConfig::default(),
+            postgres: PostgresConfig::default(),
+        }
+    }
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            jwt_secret: String::new(),
+            access_token_ttl: 3600,            // 1 hour
+            refresh_token_ttl: 3600 * 24 * 14, // 14 days
+        }
+    }
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        StorageConfig {
+            attachment_dir: "./data/attachments".to_string(),
+            attachment_base_url: "http://127.0.0.1:8080/attachments".to_string(),
+        }
+    }
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        LimitsConfig {
+            max_file_size: 25 * 1024 * 1024, // 25MB
+            max_pinned_messages: 50,
+            max_acknowledge_batch_size: 100,
+        }
+    }
+}
+
+impl Default for PagingConfig {
+    fn default() -> Self {
+        PagingConfig {
+            page_token_secret: String::new(),
+            page_token_ttl: 3600 * 24, // 1 day
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read { path: String, err: io::Error },
+    Parse { path: String, err: toml::de::Error },
+    Env { name: &'static str, value: String },
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read { path, err } => write!(f, "failed to read {}: {}", path, err),
+            ConfigError::Parse { path, err } => write!(f, "failed to parse {}: {}", path, err),
+            ConfigError::Env { name, value } => write!(f, "invalid {}: `{}`", name, value),
+            ConfigError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// the file at `YCCHAT_CONFIG` (or `config.toml` when it exists), then the environment.
+    pub fn load() -> Result<Self, ConfigError> {
+        let (path, is_required) = match env::var("YCCHAT_CONFIG") {
+            Ok(path) => (path, true),
+            Err(_) => (DEFAULT_CONFIG_PATH.to_string(), false),
+        };
+
+        let mut config = match fs::read_to_string(&path) {
+            Ok(content) => {
+                toml::from_str::<Config>(&content).map_err(|err| ConfigError::Parse {
+                    path: path.clone(),
+                    err,
+                })?
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound && !is_required => Config::default(),
+            Err(err) => return Err(ConfigError::Read { path, err }),
+        };
+
+        config.apply_env()?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    fn apply_env(&mut self) -> Result<(), ConfigError> {
+        override_with(&mut self.server.addr, "YCCHAT_SERVER_ADDR")?;
+
+        override_with(&mut self.database.backend, "YCCHAT_DATABASE_BACKEND")?;
+        override_with(
+            &mut self.database.auto_migrate,
+            "YCCHAT_DATABASE_AUTO_MIGRATE",
+        )?;
+
+        let database = &mut self.database.surreal;
+        override_with(&mut database.engine, "YCCHAT_SURREAL_ENGINE")?;
+        override_with(&mut database.endpoint, "YCCHAT_SURREAL_ENDPOINT")?;
+        override_with(&mut database.path, "YCCHAT_SURREAL_PATH")?;
+        override_with(&mut database.namespace, "YCCHAT_SURREAL_NAMESPACE")?;
+        override_with(&mut database.database, "YCCHAT_SURREAL_DATABASE")?;
+        override_with(&mut database.username, "YCCHAT_SURREAL_USER")?;
+        override_with(&mut database.password, "YCCHAT_SURREAL_PASSWORD")?;
+        override_with(
+            &mut database.connect_attempts,
+            "YCCHAT_SURREAL_CONNECT_ATTEMPTS",
+        )?;
+
+        let postgres = &mut self.database.postgres;
+        override_with(&mut postgres.host, "YCCHAT_PG_HOST")?;
+        override_with(&mut postgres.port, "YCCHAT_PG_PORT")?;
+        override_with(&mut postgres.user, "YCCHAT_PG_USER")?;
+        override_with(&mut postgres.password, "YCCHAT_PG_PASSWORD")?;
+        override_with(&mut postgres.dbname, "YCCHAT_PG_DBNAME")?;
+        override_with(&mut postgres.pool_size, "YCCHAT_PG_POOL_SIZE")?;
+        override_with(&mut postgres.connect_attempts, "YCCHAT_PG_CONNECT_ATTEMPTS")?;
+
+        let auth = &mut self.auth;
+        override_with(&mut auth.jwt_secret, "YCCHAT_JWT_SECRET")?;
+        override_with(&mut auth.access_token_ttl, "YCCHAT_ACCESS_TOKEN_TTL")?;
+        override_with(&mut auth.refresh_token_ttl, "YCCHAT_REFRESH_TOKEN_TTL")?;
+
+        let storage = &mut self.storage;
+        override_with(&mut storage.attachment_dir, "YCCHAT_ATTACHMENT_DIR")?;
+        override_with(
+            &mut storage.attachment_base_url,
+            "YCCHAT_ATTACHMENT_BASE_URL",
+        )?;
+
+        let limits = &mut self.limits;
+        override_with(&mut limits.max_file_size, "YCCHAT_MAX_FILE_SIZE")?;
+        override_with(
+            &mut limits.max_pinned_messages,
+            "YCCHAT_MAX_PINNED_MESSAGES",
+        )?;
+        override_with(
+            &mut limits.max_acknowledge_batch_size,
+            "YCCHAT_MAX_ACKNOWLEDGE_BATCH_SIZE",
+        )?;
+
+        let paging = &mut self.paging;
+        override_with(&mut paging.page_token_secret, "YCCHAT_PAGE_TOKEN_SECRET")?;
+        override_with(&mut paging.page_token_ttl, "YCCHAT_PAGE_TOKEN_TTL")?;
+
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |message: &str| Err(ConfigError::Invalid(message.to_string()));
+
+        if self.database.backend == DatabaseBackend::Surreal {
+            let surreal = &self.database.surreal;
+
+            match surreal.engine {
+                SurrealEngine::Remote => {
+                    if surreal.endpoint.is_empty() {
+                        return invalid("database.surreal.endpoint is required.");
+                    }
+                    if surreal.username.is_empty() || surreal.password.is_empty() {
+                        return invalid(
+                            "database.surreal.username and database.surreal.password are required.",
+                        );
+                    }
+                }
+                SurrealEngine::RocksDb if !cfg!(feature = "kv-rocksdb") => {
+                    return invalid(
+                        "database.surreal.engine `rocksdb` needs the kv-rocksdb feature.",
+                    );
+                }
+                SurrealEngine::RocksDb if surreal.path.is_empty() => {
+                    return invalid("database.surreal.path is required.");
+                }
+                _ => {}
+            }
+
+            if surreal.namespace.is_empty() || surreal.database.is_empty() {
+                return invalid(
+                    "database.surreal.namespace and database.surreal.database are required.",
+                );
+            }
+        }
+        if self.database.backend == DatabaseBackend::Postgres {
+            let postgres = &self.database.postgres;
+
+            if postgres.host.is_empty() || postgres.user.is_empty() || postgres.dbname.is_empty() {
+                return invalid(
+                    "database.postgres.host, database.postgres.user and database.postgres.dbname are required.",
+                );
+            }
+            if postgres.pool_size == 0 {
+                return invalid("database.postgres.pool_size must be positive.");
+            }
+        }
+        if self.auth.jwt_secret.len() < 32 {
+            return invalid("auth.jwt_secret must be at least 32 bytes.");
+        }
+        if self.auth.access_token_ttl == 0
+            || self.auth.refresh_token_ttl <= self.auth.access_token_ttl
+        {
+            return invalid("auth.refresh_token_ttl must be longer than auth.access_token_ttl.");
+        }
+        if self.storage.attachment_dir.is_empty() || self.storage.attachment_base_url.is_empty() {
+            return invalid("storage.attachment_dir and storage.attachment_base_url are required.");
+        }
+        if self.limits.max_file_size <= 0
+            || self.limits.max_pinned_messages == 0
+            || self.limits.max_acknowledge_batch_size <= 0
+        {
+            return invalid("limits must be positive.");
+        }
+        if self.paging.page_token_secret.len() < 32 {
+            return invalid("paging.page_token_secret must be at least 32 bytes.");
+        }
+        if self.paging.page_token_ttl == 0 {
+            return invalid("paging.page_token_ttl must be positive.");
+        }
+
+        Ok(())
+    }
+}
+
+/// replaces `value` with the environment variable `name` when it's set.
+fn override_with<T: FromStr>(value: &mut T, name: &'static str) -> Result<(), ConfigError> {
+    if let Ok(env_value) = env::var(name) {
+        *value = env_value.parse().map_err(|_| ConfigError::Env {
+            name,
+            value: env_value.clone(),
+        })?;
+    }
+
+    Ok(())
+}
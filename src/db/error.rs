@@ -0,0 +1,135 @@
+use std::fmt;
+
+/// errors of the repository layer, independent of the backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepositoryError {
+    /// the record the operation depends on doesn't exist. `resource` names it for clients,
+    /// `message` is the backend detail.
+    NotFound {
+        resource: &'static str,
+        message: String,
+    },
+    /// the record already exists, or a unique index already holds the value.
+    Conflict(String),
+    /// a schema assertion or field type rejected the record.
+    ConstraintViolation(String),
+    /// the backend can't be reached or timed out, the operation may be retried.
+    Unavailable(String),
+    Internal(String),
+}
+
+impl fmt::Display for RepositoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RepositoryError::NotFound { resource, message } => {
+                write!(f, "{} not found: {}", resource, message)
+            }
+            RepositoryError::Conflict(message) => write!(f, "conflict: {}", message),
+            RepositoryError::ConstraintViolation(message) => {
+                write!(f, "constraint violation: {}", message)
+            }
+            RepositoryError::Unavailable(message) => write!(f, "unavailable: {}", message),
+            RepositoryError::Internal(message) => write!(f, "internal: {}", message),
+        }
+    }
+}
+
+impl RepositoryError {
+    /// writes the error with its backend text to the server log.
+    pub fn log(&self) {
+        eprintln!("repository error: {}", self);
+    }
+}
+
+impl std::error::Error for RepositoryError {}
+
+impl From<surrealdb::Error> for RepositoryError {
+    fn from(err: surrealdb::Error) -> Self {
+        use surrealdb::error::{Api, Db};
+
+        match err {
+            surrealdb::Error::Db(err) => match err {
+                Db::RecordExists { .. } | Db::IndexExists { .. } | Db::TxKeyAlreadyExists => {
+                    RepositoryError::Conflict(err.to_string())
+                }
+                Db::FieldCheck { .. } | Db::FieldValue { .. } => {
+                    RepositoryError::ConstraintViolation(err.to_string())
+                }
+                Db::QueryTimedout | Db::TxFailure => RepositoryError::Unavailable(err.to_string()),
+                _ => RepositoryError::Internal(err.to_string()),
+            },
+            surrealdb::Error::Api(err) => match err {
+                Api::Ws(_) | Api::Http(_) | Api::ConnectionUninitialised => {
+                    RepositoryError::Unavailable(err.to_string())
+                }
+                // errors of the server come back as text over the remote client.
+                Api::Query(message) => from_query_message(message),
+                _ => RepositoryError::Internal(err.to_string()),
+            },
+        }
+    }
+}
+
+fn from_query_message(message: String) -> RepositoryError {
+    if message.contains("already exists") || message.contains("already contains") {
+        RepositoryError::Conflict(message)
+    } else if message.contains("but field must conform to") || message.contains("but expected a") {
+        RepositoryError::ConstraintViolation(message)
+    } else if message.contains("exceeded the timeout") {
+        RepositoryError::Unavailable(message)
+    } else {
+        RepositoryError::Internal(message)
+    }
+}
+
+impl From<tokio_postgres::Error> for RepositoryError {
+    fn from(err: tokio_postgres::Error) -> Self {
+        use std::error::Error;
+        use tokio_postgres::error::SqlState;
+
+        let Some(code) = err.code() else {
+            // no SQLSTATE, the connection failed before the server answered.
+            let is_io = err
+                .source()
+                .is_some_and(|source| source.is::<std::io::Error>());
+            if err.is_closed() || is_io {
+                return RepositoryError::Unavailable(err.to_string());
+            }
+            return RepositoryError::Internal(err.to_string());
+        };
+
+        // the server message, `Display` of the error only says "db error".
+        let message = err
+            .as_db_error()
+            .map_or_else(|| err.to_string(), |err| err.to_string());
+
+        match code {
+            c if *c == SqlState::UNIQUE_VIOLATION => RepositoryError::Conflict(message),
+            c if *c == SqlState::CHECK_VIOLATION
+                || *c == SqlState::NOT_NULL_VIOLATION
+                || *c == SqlState::STRING_DATA_RIGHT_TRUNCATION =>
+            {
+                RepositoryError::ConstraintViolation(message)
+            }
+            c if *c == SqlState::T_R_SERIALIZATION_FAILURE
+                || *c == SqlState::T_R_DEADLOCK_DETECTED
+                || *c == SqlState::QUERY_CANCELED
+                || *c == SqlState::ADMIN_SHUTDOWN
+                || *c == SqlState::CANNOT_CONNECT_NOW
+                || *c == SqlState::TOO_MANY_CONNECTIONS =>
+            {
+                RepositoryError::Unavailable(message)
+            }
+            _ => RepositoryError::Internal(message),
+        }
+    }
+}
+
+impl From<deadpool_postgres::PoolError> for RepositoryError {
+    fn from(err: deadpool_postgres::PoolError) -> Self {
+        match err {
+            deadpool_postgres::PoolError::Backend(err) => RepositoryError::from(err),
+            _ => RepositoryError::Unavailable(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,603 @@
+use std::fmt;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use ulid::Ulid;
+
+/// filters longer than this are refused before parsing.
+pub const MAX_FILTER_LEN: usize = 1024;
+/// how deep `NOT` and parentheses may nest.
+const MAX_DEPTH: usize = 16;
+
+/// the type of a field, which decides the comparisons and values it takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    /// `=`, `!=` and `:`, which matches a part of the text regardless of case.
+    Text,
+    /// RFC 3339 times or `YYYY-MM-DD` dates, quoted or not, `=`, `!=`, `<`, `<=`, `>` and `>=`.
+    Time,
+    /// `users/{id}`, `=` and `!=`.
+    User,
+}
+
+/// a field filters of a list may name. each list has an allowlist of them, backends only ever
+/// see these names.
+#[derive(Debug, Clone, Copy)]
+pub struct FilterField {
+    pub name: &'static str,
+    pub kind: FieldKind,
+}
+
+impl FilterField {
+    pub const fn new(name: &'static str, kind: FieldKind) -> Self {
+        FilterField { name, kind }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    /// `:`, the text contains the value.
+    Has,
+}
+
+impl Operator {
+    /// the SQL and SurrealQL operator of the comparisons other than `Has`.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Operator::Equal => "=",
+            Operator::NotEqual => "!=",
+            Operator::Less => "<",
+            Operator::LessOrEqual => "<=",
+            Operator::Greater => ">",
+            Operator::GreaterOrEqual => ">=",
+            Operator::Has => ":",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(String),
+    Time(DateTime<Utc>),
+    User(Ulid),
+}
+
+/// a parsed filter. an empty filter is `And` of nothing and matches every record.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+    Not(Box<Filter>),
+    Compare {
+        field: &'static str,
+        operator: Operator,
+        value: Value,
+    },
+}
+
+/// a field of a record, for backends that filter in process.
+pub enum FieldValue<'a> {
+    Text(&'a str),
+    Time(Option<DateTime<Utc>>),
+    User(Ulid),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterError(pub String);
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter::And(Vec::new())
+    }
+}
+
+impl Filter {
+    /// parses the AIP-160 subset of `filter`: comparisons of the `fields`, joined by `AND`,
+    /// `OR` (which binds tighter) or spaces, negated by `NOT` or `-`, grouped by parentheses.
+    pub fn parse(filter: &str, fields: &[FilterField]) -> Result<Self, FilterError> {
+        if filter.len() > MAX_FILTER_LEN {
+            return Err(error(format!("must be at most {} bytes.", MAX_FILTER_LEN)));
+        }
+
+        let tokens = tokenize(filter)?;
+        if tokens.is_empty() {
+            return Ok(Filter::default());
+        }
+
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+            fields,
+        };
+        let filter = parser.expression(0)?;
+
+        match parser.peek() {
+            None => Ok(filter),
+            Some(token) => Err(error(format!("unexpected {}.", token))),
+        }
+    }
+
+    /// whether the record whose fields `field` reads matches.
+    pub fn matches<'a>(&self, field: &impl Fn(&'static str) -> FieldValue<'a>) -> bool {
+        match self {
+            Filter::And(filters) => filters.iter().all(|filter| filter.matches(field)),
+            Filter::Or(filters) => filters.iter().any(|filter| filter.matches(field)),
+            Filter::Not(filter) => !filter.matches(field),
+            Filter::Compare {
+                field: name,
+                operator,
+                value,
+            } => match (field(name), value) {
+                (FieldValue::Text(text), Value::Text(value)) => match operator {
+                    Operator::Equal => text == value,
+                    Operator::NotEqual => text != value,
+                    Operator::Has => text.to_lowercase().contains(&value.to_lowercase()),
+                    _ => false,
+                },
+                // like SQL, a missing time matches no comparison.
+                (FieldValue::Time(Some(time)), Value::Time(value)) => match operator {
+                    Operator::Equal => time == *value,
+                    Operator::NotEqual => time != *value,
+                    Operator::Less => time < *value,
+                    Operator::LessOrEqual => time <= *value,
+                    Operator::Greater => time > *value,
+                    Operator::GreaterOrEqual => time >= *value,
+                    Operator::Has => false,
+                },
+                (FieldValue::User(id), Value::User(value)) => match operator {
+                    Operator::Equal => id == *value,
+                    Operator::NotEqual => id != *value,
+                    _ => false,
+                },
+                _ => false,
+            },
+        }
+    }
+}
+
+fn error(message: impl Into<String>) -> FilterError {
+    FilterError(message.into())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Minus,
+    Operator(Operator),
+    /// unquoted text, including the `AND`, `OR` and `NOT` keywords.
+    Word(String),
+    Quoted(String),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Open => write!(f, "`(`"),
+            Token::Close => write!(f, "`)`"),
+            Token::Minus => write!(f, "`-`"),
+            Token::Operator(operator) => write!(f, "`{}`", operator.symbol()),
+            Token::Word(word) => write!(f, "`{}`", word),
+            Token::Quoted(text) => write!(f, "\"{}\"", text),
+        }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && !matches!(c, '(' | ')' | '"' | '=' | '!' | '<' | '>' | ':')
+}
+
+fn tokenize(filter: &str) -> Result<Vec<Token>, FilterError> {
+    let mut tokens = Vec::new();
+    let mut chars = filter.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '-' => Token::Minus,
+            ':' => Token::Operator(Operator::Has),
+            '=' => Token::Operator(Operator::Equal),
+            '!' if chars.next_if_eq(&'=').is_some() => Token::Operator(Operator::NotEqual),
+            '<' if chars.next_if_eq(&'=').is_some() => Token::Operator(Operator::LessOrEqual),
+            '<' => Token::Operator(Operator::Less),
+            '>' if chars.next_if_eq(&'=').is_some() => Token::Operator(Operator::GreaterOrEqual),
+            '>' => Token::Operator(Operator::Greater),
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c) => text.push(c),
+                            None => return Err(error("unterminated string.")),
+                        },
+                        Some(c) => text.push(c),
+                        None => return Err(error("unterminated string.")),
+                    }
+                }
+                Token::Quoted(text)
+            }
+            c if is_word_char(c) => {
+                let mut word = c.to_string();
+                loop {
+                    if let Some(c) = chars.next_if(|c| is_word_char(*c)) {
+                        word.push(c);
+                        continue;
+                    }
+
+                    // the `:` of an unquoted time, `2026-01-01T09:00:00Z`, isn't the has operator.
+                    // field names don't start with a digit.
+                    let mut ahead = chars.clone();
+                    let is_time_colon = word.starts_with(|c: char| c.is_ascii_digit())
+                        && ahead.next() == Some(':')
+                        && ahead.next().is_some_and(|c| c.is_ascii_digit());
+                    if !is_time_colon {
+                        break;
+                    }
+
+                    word.push(':');
+                    chars.next();
+                }
+                Token::Word(word)
+            }
+            c => return Err(error(format!("unexpected `{}`.", c))),
+        };
+
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    fields: &'a [FilterField],
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(word)) if word == keyword)
+    }
+
+    /// sequences joined by `AND`.
+    fn expression(&mut self, depth: usize) -> Result<Filter, FilterError> {
+        if depth > MAX_DEPTH {
+            return Err(error("nests too deep."));
+        }
+
+        let mut filters = vec![self.sequence(depth)?];
+        while self.is_keyword("AND") {
+            self.next();
+            filters.push(self.sequence(depth)?);
+        }
+
+        Ok(join(filters, Filter::And))
+    }
+
+    /// factors next to each other, which all have to match.
+    fn sequence(&mut self, depth: usize) -> Result<Filter, FilterError> {
+        let mut filters = vec![self.factor(depth)?];
+        while !matches!(self.peek(), None | Some(Token::Close)) && !self.is_keyword("AND") {
+            filters.push(self.factor(depth)?);
+        }
+
+        Ok(join(filters, Filter::And))
+    }
+
+    /// terms joined by `OR`.
+    fn factor(&mut self, depth: usize) -> Result<Filter, FilterError> {
+        let mut filters = vec![self.term(depth)?];
+        while self.is_keyword("OR") {
+            self.next();
+            filters.push(self.term(depth)?);
+        }
+
+        Ok(join(filters, Filter::Or))
+    }
+
+    fn term(&mut self, depth: usize) -> Result<Filter, FilterError> {
+        if depth > MAX_DEPTH {
+            return Err(error("nests too deep."));
+        }
+
+        match self.next() {
+            Some(Token::Minus) => Ok(Filter::Not(Box::new(self.term(depth + 1)?))),
+            Some(Token::Word(word)) if word == "NOT" => {
+                Ok(Filter::Not(Box::new(self.term(depth + 1)?)))
+            }
+            Some(Token::Open) => {
+                let filter = self.expression(depth + 1)?;
+                match self.next() {
+                    Some(Token::Close) => Ok(filter),
+                    _ => Err(error("missing `)`.")),
+                }
+            }
+            Some(Token::Word(name)) => self.comparison(&name),
+            Some(token) => Err(error(format!("unexpected {}.", token))),
+            None => Err(error("ends too early.")),
+        }
+    }
+
+    fn comparison(&mut self, name: &str) -> Result<Filter, FilterError> {
+        let Some(field) = self.fields.iter().find(|field| field.name == name) else {
+            return Err(error(format!("unknown field `{}`.", name)));
+        };
+
+        let operator = match self.next() {
+            Some(Token::Operator(operator)) => operator,
+            _ => return Err(error(format!("`{}` must be compared to a value.", name))),
+        };
+
+        let value = match self.next() {
+            Some(Token::Word(value) | Token::Quoted(value)) => value,
+            _ => return Err(error(format!("`{}` must be compared to a value.", name))),
+        };
+
+        let is_supported = match field.kind {
+            FieldKind::Text => matches!(
+                operator,
+                Operator::Equal | Operator::NotEqual | Operator::Has
+            ),
+            FieldKind::Time => operator != Operator::Has,
+            FieldKind::User => matches!(operator, Operator::Equal | Operator::NotEqual),
+        };
+        if !is_supported {
+            return Err(error(format!(
+                "`{}` can't be compared with `{}`.",
+                name,
+                operator.symbol()
+            )));
+        }
+
+        let value =
+            match field.kind {
+                FieldKind::Text => Value::Text(value),
+                FieldKind::Time => Value::Time(parse_time(&value).ok_or_else(|| {
+                    error(format!("`{}` must be an RFC 3339 time or a date.", name))
+                })?),
+                FieldKind::User => Value::User(
+                    value
+                        .strip_prefix("users/")
+                        .and_then(|id| Ulid::from_string(id).ok())
+                        .ok_or_else(|| error(format!("`{}` must be a user name.", name)))?,
+                ),
+            };
+
+        Ok(Filter::Compare {
+            field: field.name,
+            operator,
+            value,
+        })
+    }
+}
+
+fn join(mut filters: Vec<Filter>, group: fn(Vec<Filter>) -> Filter) -> Filter {
+    if filters.len() == 1 {
+        filters.remove(0)
+    } else {
+        group(filters)
+    }
+}
+
+/// `2026-01-01T09:00:00Z`, or `2026-01-01` for the start of the day in UTC.
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Utc));
+    }
+
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|time| time.and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIELDS: &[FilterField] = &[
+        FilterField::new("content", FieldKind::Text),
+        FilterField::new("create_time", FieldKind::Time),
+        FilterField::new("pin_time", FieldKind::Time),
+        FilterField::new("author", FieldKind::User),
+    ];
+
+    const AUTHOR: &str = "01HGW2N5EQNG50CB2HBCN4A8GT";
+
+    fn parse(filter: &str) -> Filter {
+        Filter::parse(filter, FIELDS).unwrap()
+    }
+
+    fn error_of(filter: &str) -> String {
+        Filter::parse(filter, FIELDS).unwrap_err().0
+    }
+
+    fn has(text: &str) -> Filter {
+        Filter::Compare {
+            field: "content",
+            operator: Operator::Has,
+            value: Value::Text(text.to_string()),
+        }
+    }
+
+    fn time(value: &str) -> Value {
+        Value::Time(
+            DateTime::parse_from_rfc3339(value)
+                .unwrap()
+                .with_timezone(&Utc),
+        )
+    }
+
+    #[test]
+    fn empty_filter_matches_everything() {
+        assert_eq!(parse(""), Filter::And(vec![]));
+        assert_eq!(parse("  "), Filter::And(vec![]));
+        assert!(parse("").matches(&|_| FieldValue::Text("")));
+    }
+
+    #[test]
+    fn comparisons() {
+        assert_eq!(parse("content:hello"), has("hello"));
+        assert_eq!(
+            parse(r#"content = "say \"hi\" (now)""#),
+            Filter::Compare {
+                field: "content",
+                operator: Operator::Equal,
+                value: Value::Text(r#"say "hi" (now)"#.to_string()),
+            }
+        );
+        assert_eq!(
+            parse(&format!("author!=users/{}", AUTHOR)),
+            Filter::Compare {
+                field: "author",
+                operator: Operator::NotEqual,
+                value: Value::User(Ulid::from_string(AUTHOR).unwrap()),
+            }
+        );
+    }
+
+    #[test]
+    fn time_values() {
+        let after = |value: &str| match parse(&format!("create_time >= {}", value)) {
+            Filter::Compare { value, .. } => value,
+            filter => panic!("{:?}", filter),
+        };
+
+        assert_eq!(after("2024-01-01"), time("2024-01-01T00:00:00Z"));
+        assert_eq!(after("2024-01-01T00:00:00Z"), time("2024-01-01T00:00:00Z"));
+        assert_eq!(
+            after("\"2024-01-01T00:00:00Z\""),
+            time("2024-01-01T00:00:00Z")
+        );
+        assert_eq!(
+            after("2024-01-01T09:30:00.5+09:00"),
+            time("2024-01-01T00:30:00.5Z")
+        );
+        assert_eq!(
+            parse("create_time>2024-01-01T00:00:00Z content:a"),
+            Filter::And(vec![
+                Filter::Compare {
+                    field: "create_time",
+                    operator: Operator::Greater,
+                    value: time("2024-01-01T00:00:00Z"),
+                },
+                has("a"),
+            ])
+        );
+    }
+
+    #[test]
+    fn or_binds_tighter_than_and() {
+        assert_eq!(
+            parse("content:a AND content:b OR content:c"),
+            Filter::And(vec![has("a"), Filter::Or(vec![has("b"), has("c")])])
+        );
+        assert_eq!(
+            parse("content:a content:b OR content:c"),
+            Filter::And(vec![has("a"), Filter::Or(vec![has("b"), has("c")])])
+        );
+        assert_eq!(
+            parse("(content:a AND content:b) OR content:c"),
+            Filter::Or(vec![Filter::And(vec![has("a"), has("b")]), has("c")])
+        );
+    }
+
+    #[test]
+    fn negation() {
+        let not = |filter| Filter::Not(Box::new(filter));
+
+        assert_eq!(parse("NOT content:a"), not(has("a")));
+        assert_eq!(parse("-content:a"), not(has("a")));
+        assert_eq!(
+            parse("-(content:a OR content:b)"),
+            not(Filter::Or(vec![has("a"), has("b")]))
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(error_of("nope:a"), "unknown field `nope`.");
+        assert_eq!(
+            error_of("content<a"),
+            "`content` can't be compared with `<`."
+        );
+        assert_eq!(error_of("author:x"), "`author` can't be compared with `:`.");
+        assert_eq!(
+            error_of("create_time>yesterday"),
+            "`create_time` must be an RFC 3339 time or a date."
+        );
+        assert_eq!(error_of("author=users/x"), "`author` must be a user name.");
+        assert_eq!(
+            error_of("content"),
+            "`content` must be compared to a value."
+        );
+        assert_eq!(
+            error_of("content:"),
+            "`content` must be compared to a value."
+        );
+        assert_eq!(error_of("content:\"a"), "unterminated string.");
+        assert_eq!(error_of("(content:a"), "missing `)`.");
+        assert_eq!(error_of("content:a)"), "unexpected `)`.");
+        assert_eq!(error_of("NOT"), "ends too early.");
+        assert_eq!(error_of("content:a AND"), "ends too early.");
+        assert_eq!(error_of("content ! a"), "unexpected `!`.");
+        assert_eq!(error_of(&"(".repeat(MAX_DEPTH + 2)), "nests too deep.");
+        assert_eq!(
+            error_of(&"a".repeat(MAX_FILTER_LEN + 1)),
+            format!("must be at most {} bytes.", MAX_FILTER_LEN)
+        );
+    }
+
+    #[test]
+    fn matches_records() {
+        let author = Ulid::from_string(AUTHOR).unwrap();
+        let created = DateTime::parse_from_rfc3339("2024-06-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let field = |name| match name {
+            "content" => FieldValue::Text("Hello World"),
+            "create_time" => FieldValue::Time(Some(created)),
+            "pin_time" => FieldValue::Time(None),
+            _ => FieldValue::User(author),
+        };
+        let matches = |filter: &str| parse(filter).matches(&field);
+
+        assert!(matches("content:hello"));
+        assert!(!matches(r#"content="hello world""#));
+        assert!(matches(&format!("author=users/{}", AUTHOR)));
+        assert!(matches(
+            "create_time>2024-01-01 create_time<2024-12-31T00:00:00Z"
+        ));
+        assert!(!matches("create_time<=2024-01-01"));
+        // a missing time fails every comparison, and so its negation matches.
+        assert!(!matches("pin_time>2000-01-01"));
+        assert!(!matches("pin_time<2000-01-01"));
+        assert!(matches("NOT pin_time>2000-01-01"));
+    }
+}
@@ -0,0 +1,114 @@
+use tonic::async_trait;
+
+use super::{insert, MemoryDb};
+use crate::{
+    db::{error::RepositoryError, traits::attachment::AttachmentRepository},
+    models::{
+        attachment::{Attachment, AttachmentId},
+        user::UserId,
+    },
+};
+
+#[derive(Clone)]
+pub struct AttachmentRepositoryImpl {}
+
+impl AttachmentRepositoryImpl {
+    pub async fn new() -> Self {
+        AttachmentRepositoryImpl {}
+    }
+}
+
+fn is_attachment(attachment: &Option<Attachment>, id: &AttachmentId) -> bool {
+    attachment
+        .as_ref()
+        .is_some_and(|attachment| attachment.id == *id)
+}
+
+#[async_trait]
+impl AttachmentRepository<MemoryDb> for AttachmentRepositoryImpl {
+    async fn get_attachment(
+        &self,
+        db: &MemoryDb,
+        id: &AttachmentId,
+    ) -> Result<Option<Attachment>, RepositoryError> {
+        Ok(db.read()?.attachments.get(id).cloned())
+    }
+
+    async fn add_attachment(
+        &self,
+        db: &MemoryDb,
+        attachment: &Attachment,
+        uploader: &UserId,
+    ) -> Result<Option<Attachment>, RepositoryError> {
+        let mut tables = db.write()?;
+
+        let created = insert(&mut tables.attachments, attachment.id, attachment)?;
+        tables.attachment_uploaders.insert(attachment.id, *uploader);
+
+        Ok(Some(created))
+    }
+
+    async fn delete_attachment(
+        &self,
+        db: &MemoryDb,
+        id: &AttachmentId,
+    ) -> Result<u8, RepositoryError> {
+        let mut tables = db.write()?;
+
+        tables.attachment_uploaders.remove(id);
+        tables.attachments.remove(id);
+
+        Ok(1)
+    }
+
+    async fn get_attachments(
+        &self,
+        db: &MemoryDb,
+        ids: &[AttachmentId],
+    ) -> Result<Vec<Attachment>, RepositoryError> {
+        let tables = db.read()?;
+
+        Ok(ids
+            .iter()
+            .filter_map(|id| tables.attachments.get(id))
+            .cloned()
+            .collect())
+    }
+
+    async fn is_referenced(
+        &self,
+        db: &MemoryDb,
+        id: &AttachmentId,
+    ) -> Result<bool, RepositoryError> {
+        let tables = db.read()?;
+
+        Ok(tables
+            .users
+            .values()
+            .any(|user| is_attachment(&user.avatar, id))
+            || tables
+                .servers
+                .values()
+                .any(|server| is_attachment(&server.icon, id))
+            || tables
+                .channels
+                .values()
+                .any(|channel| is_attachment(&channel.icon, id))
+            || tables
+                .server_members
+                .values()
+                .any(|member| is_attachment(&member.avatar, id))
+            || tables
+                .messages
+                .values()
+                .any(|message| message.attachments.contains(id)))
+    }
+
+    async fn get_uploader(
+        &self,
+        db: &MemoryDb,
+        id: &AttachmentId,
+    ) -> Result<Option<UserId>, RepositoryError> {
+        Ok(db.read()?.attachment_uploaders.get(id).copied())
+    }
+}
@@ -0,0 +1,78 @@
+use tonic::async_trait;
+
+use super::{insert, MemoryDb, MemoryTransaction};
+use crate::{
+    db::{error::RepositoryError, traits::auth::AuthRepository},
+    models::{auth::DbAuth, user::UserId},
+};
+
+#[derive(Clone)]
+pub struct AuthRepositoryImpl {}
+
+impl AuthRepositoryImpl {
+    pub async fn new() -> Self {
+        AuthRepositoryImpl {}
+    }
+}
+
+#[async_trait]
+impl AuthRepository<MemoryDb> for AuthRepositoryImpl {
+    async fn get(&self, db: &MemoryDb, id: &UserId) -> Result<Option<DbAuth>, RepositoryError> {
+        Ok(db.read()?.auths.get(id).cloned())
+    }
+
+    async fn get_by_username(
+        &self,
+        db: &MemoryDb,
+        username: &str,
+    ) -> Result<Option<DbAuth>, RepositoryError> {
+        Ok(db
+            .read()?
+            .auths
+            .values()
+            .find(|auth| auth.username == username)
+            .cloned())
+    }
+
+    async fn add(&self, db: &MemoryDb, auth: &DbAuth) -> Result<Option<DbAuth>, RepositoryError> {
+        let mut tables = db.write()?;
+
+        // `authUsernameIndex`
+        if tables
+            .auths
+            .values()
+            .any(|other| other.username == auth.username)
+        {
+            return Err(RepositoryError::Conflict(format!(
+                "username `{}` already exists.",
+                auth.username
+            )));
+        }
+
+        insert(&mut tables.auths, auth.id, auth).map(Some)
+    }
+
+    async fn update(
+        &self,
+        db: &MemoryDb,
+        auth: &DbAuth,
+    ) -> Result<Option<DbAuth>, RepositoryError> {
+        db.write()?.auths.insert(auth.id, auth.clone());
+
+        Ok(Some(auth.clone()))
+    }
+
+    fn delete_in(
+        &self,
+        transaction: &mut MemoryTransaction,
+        id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        let id = *id;
+        transaction.push(move |tables| {
+            tables.auths.remove(&id);
+            Ok(())
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,293 @@
+use std::cmp::Reverse;
+
+use surrealdb::sql::Datetime;
+use tonic::async_trait;
+
+use super::{insert, MemoryDb, MemoryTransaction};
+use crate::{
+    db::{
+        error::RepositoryError,
+        filter::{FieldValue, Filter},
+        page::Page,
+        traits::channel::ChannelRepository,
+    },
+    models::channel::{ChannelId, ChannelPosition, ChannelType, DbChannel},
+    models::server::ServerId,
+    models::server_category::{CategoryPosition, ServerCategoryId},
+    models::user::UserId,
+};
+
+#[derive(Clone)]
+pub struct ChannelRepositoryImpl {}
+
+/// the `FILTER_FIELDS` of a channel.
+fn field<'a>(channel: &'a DbChannel, name: &str) -> FieldValue<'a> {
+    match name {
+        "display_name" => FieldValue::Text(&channel.display_name),
+        "description" => FieldValue::Text(&channel.description),
+        "last_message_time" => {
+            FieldValue::Time(channel.last_message_time.as_ref().map(|time| time.0))
+        }
+        "create_time" => FieldValue::Time(Some(channel.create_time.0)),
+        "update_time" => FieldValue::Time(channel.update_time.as_ref().map(|time| time.0)),
+        _ => FieldValue::Time(None),
+    }
+}
+
+impl ChannelRepositoryImpl {
+    pub async fn new() -> Self {
+        ChannelRepositoryImpl {}
+    }
+}
+
+fn is_in_server(channel: &DbChannel, server_id: &ServerId) -> bool {
+    matches!(channel.channel_type, ChannelType::Server { server } if server == *server_id)
+}
+
+fn is_direct_or_group(channel: &DbChannel) -> bool {
+    matches!(
+        channel.channel_type,
+        ChannelType::Direct | ChannelType::Group { .. }
+    )
+}
+
+#[async_trait]
+impl ChannelRepository<MemoryDb> for ChannelRepositoryImpl {
+    async fn get(
+        &self,
+        db: &MemoryDb,
+        id: &ChannelId,
+    ) -> Result<Option<DbChannel>, RepositoryError> {
+        Ok(db.read()?.channels.get(id).cloned())
+    }
+
+    async fn get_list_by_server_id(
+        &self,
+        db: &MemoryDb,
+        server_id: &ServerId,
+    ) -> Result<Vec<DbChannel>, RepositoryError> {
+        let tables = db.read()?;
+
+        let mut channels = tables
+            .channels
+            .values()
+            .filter(|channel| is_in_server(channel, server_id))
+            .cloned()
+            .collect::<Vec<DbChannel>>();
+
+        // uncategorized channels come first, as NONE sorts before numbers.
+        channels.sort_by_key(|channel| {
+            let category_order = channel
+                .category
+                .and_then(|category| tables.server_categories.get(&category))
+                .map(|category| category.order);
+
+            (category_order, channel.category, channel.order, channel.id)
+        });
+
+        Ok(channels)
+    }
+
+    async fn get_server_channels(
+        &self,
+        db: &MemoryDb,
+        server_id: &ServerId,
+        filter: &Filter,
+        page: Page,
+    ) -> Result<Vec<DbChannel>, RepositoryError> {
+        let mut channels = self.get_list_by_server_id(db, server_id).await?;
+        channels.retain(|channel| filter.matches(&|name| field(channel, name)));
+
+        Ok(page.cut(channels, |channel| channel.id))
+    }
+
+    async fn update_layout(
+        &self,
+        db: &MemoryDb,
+        categories: &[CategoryPosition],
+        channels: &[ChannelPosition],
+    ) -> Result<(), RepositoryError> {
+        // one write lock, so readers see the whole layout change or none of it.
+        let mut tables = db.write()?;
+
+        for position in categories {
+            if let Some(category) = tables.server_categories.get_mut(&position.id) {
+                category.order = position.order;
+                category.update_time = Some(Datetime::default());
+            }
+        }
+
+        for position in channels {
+            if let Some(channel) = tables.channels.get_mut(&position.id) {
+                channel.category = position.category;
+                channel.order = position.order;
+                channel.update_time = Some(Datetime::default());
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn get_channels_by_user_id(
+        &self,
+        db: &MemoryDb,
+        user_id: &UserId,
+    ) -> Result<Vec<DbChannel>, RepositoryError> {
+        let tables = db.read()?;
+
+        let servers = tables
+            .server_members
+            .values()
+            .filter(|member| member.user == *user_id)
+            .map(|member| member.server)
+            .collect::<Vec<ServerId>>();
+
+        Ok(tables
+            .channels
+            .values()
+            .filter(|channel| match channel.channel_type {
+                ChannelType::Saved { owner } => owner == *user_id,
+                ChannelType::Direct | ChannelType::Group { .. } => {
+                    channel.members.contains(user_id)
+                }
+                ChannelType::Server { server } => servers.contains(&server),
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn get_direct_channel(
+        &self,
+        db: &MemoryDb,
+        user_id: &UserId,
+        other_user_id: &UserId,
+    ) -> Result<Option<DbChannel>, RepositoryError> {
+        Ok(db
+            .read()?
+            .channels
+            .values()
+            .find(|channel| {
+                matches!(channel.channel_type, ChannelType::Direct)
+                    && channel.members.contains(user_id)
+                    && channel.members.contains(other_user_id)
+            })
+            .cloned())
+    }
+
+    async fn get_direct_channels_by_user_id(
+        &self,
+        db: &MemoryDb,
+        user_id: &UserId,
+    ) -> Result<Vec<DbChannel>, RepositoryError> {
+        let mut channels = db
+            .read()?
+            .channels
+            .values()
+            .filter(|channel| is_direct_or_group(channel) && channel.members.contains(user_id))
+            .cloned()
+            .collect::<Vec<DbChannel>>();
+
+        channels.sort_by_key(|channel| {
+            Reverse(
+                channel
+                    .last_message_time
+                    .clone()
+                    .unwrap_or_else(|| channel.create_time.clone()),
+            )
+        });
+
+        Ok(channels)
+    }
+
+    async fn update_last_message_time(
+        &self,
+        db: &MemoryDb,
+        id: &ChannelId,
+        last_message_time: &Datetime,
+    ) -> Result<(), RepositoryError> {
+        if let Some(channel) = db.write()?.channels.get_mut(id) {
+            channel.last_message_time = Some(last_message_time.clone());
+        }
+
+        Ok(())
+    }
+
+    async fn add(
+        &self,
+        db: &MemoryDb,
+        channel: &DbChannel,
+    ) -> Result<Option<DbChannel>, RepositoryError> {
+        insert(&mut db.write()?.channels, channel.id, channel).map(Some)
+    }
+
+    async fn update(
+        &self,
+        db: &MemoryDb,
+        channel: &DbChannel,
+    ) -> Result<Option<DbChannel>, RepositoryError> {
+        db.write()?.channels.insert(channel.id, channel.clone());
+
+        Ok(Some(channel.clone()))
+    }
+
+    async fn clear_category(
+        &self,
+        db: &MemoryDb,
+        category_id: &ServerCategoryId,
+    ) -> Result<(), RepositoryError> {
+        for channel in db.write()?.channels.values_mut() {
+            if channel.category == Some(*category_id) {
+                channel.category = None;
+                channel.update_time = Some(Datetime::default());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn delete_in(
+        &self,
+        transaction: &mut MemoryTransaction,
+        id: &ChannelId,
+    ) -> Result<(), RepositoryError> {
+        let id = *id;
+        transaction.push(move |tables| {
+            tables.channels.remove(&id);
+            Ok(())
+        });
+
+        Ok(())
+    }
+
+    fn delete_by_server_id_in(
+        &self,
+        transaction: &mut MemoryTransaction,
+        server_id: &ServerId,
+    ) -> Result<(), RepositoryError> {
+        let server_id = *server_id;
+        transaction.push(move |tables| {
+            tables
+                .channels
+                .retain(|_, channel| !is_in_server(channel, &server_id));
+            Ok(())
+        });
+
+        Ok(())
+    }
+
+    fn remove_member_in(
+        &self,
+        transaction: &mut MemoryTransaction,
+        user_id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        let user_id = *user_id;
+        transaction.push(move |tables| {
+            for channel in tables.channels.values_mut() {
+                channel.members.retain(|member| *member != user_id);
+            }
+            Ok(())
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use tonic::async_trait;
+
+use super::{read_page, MemoryDb, MemoryTransaction};
+use crate::{
+    db::{error::RepositoryError, page::Page, traits::mention::MentionRepository},
+    models::{channel::ChannelId, mention::DbMention, message::MessageId, user::UserId},
+};
+
+#[derive(Clone)]
+pub struct MentionRepositoryImpl {}
+
+impl MentionRepositoryImpl {
+    pub async fn new() -> Self {
+        MentionRepositoryImpl {}
+    }
+}
+
+#[async_trait]
+impl MentionRepository<MemoryDb> for MentionRepositoryImpl {
+    async fn add_mentions(
+        &self,
+        db: &MemoryDb,
+        mentions: &[DbMention],
+    ) -> Result<(), RepositoryError> {
+        let mut tables = db.write()?;
+
+        if let Some(mention) = mentions
+            .iter()
+            .find(|mention| tables.mentions.contains_key(&mention.id))
+        {
+            return Err(RepositoryError::Conflict(format!(
+                "mention `{}` already exists.",
+                mention.id
+            )));
+        }
+
+        for mention in mentions {
+            tables.mentions.insert(mention.id, mention.clone());
+        }
+
+        Ok(())
+    }
+
+    async fn get_list_by_user_id(
+        &self,
+        db: &MemoryDb,
+        user_id: &UserId,
+        page: Page,
+    ) -> Result<Vec<DbMention>, RepositoryError> {
+        let tables = db.read()?;
+
+        let mentions = tables
+            .mentions
+            .iter()
+            .filter(|(_, mention)| mention.user == *user_id);
+
+        Ok(read_page(mentions, page))
+    }
+
+    async fn count_by_user_id_and_channel_id(
+        &self,
+        db: &MemoryDb,
+        user_id: &UserId,
+        channel_id: &ChannelId,
+        after: Option<MessageId>,
+    ) -> Result<u64, RepositoryError> {
+        let count = db
+            .read()?
+            .mentions
+            .values()
+            .filter(|mention| {
+                mention.user == *user_id
+                    && mention.channel == *channel_id
+                    && after.is_none_or(|after| mention.message > after)
+            })
+            .count();
+
+        Ok(count as u64)
+    }
+
+    fn delete_by_channel_id_in(
+        &self,
+        transaction: &mut MemoryTransaction,
+        channel_id: &ChannelId,
+    ) -> Result<(), RepositoryError> {
+        let channel_id = *channel_id;
+        transaction.push(move |tables| {
+            tables
+                .mentions
+                .retain(|_, mention| mention.channel != channel_id);
+            Ok(())
+        });
+
+        Ok(())
+    }
+
+    fn delete_by_message_id_in(
+        &self,
+        transaction: &mut MemoryTransaction,
+        message_id: &MessageId,
+    ) -> Result<(), RepositoryError> {
+        let message_id = *message_id;
+        transaction.push(move |tables| {
+            tables
+                .mentions
+                .retain(|_, mention| mention.message != message_id);
+            Ok(())
+        });
+
+        Ok(())
+    }
+
+    fn delete_by_user_id_in(
+        &self,
+        transaction: &mut MemoryTransaction,
+        user_id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        let user_id = *user_id;
+        transaction.push(move |tables| {
+            tables.mentions.retain(|_, mention| mention.user != user_id);
+            Ok(())
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+use std::cmp::Reverse;
+
+use tonic::async_trait;
+
+use super::{insert, read_page, MemoryDb, MemoryTransaction};
+use crate::{
+    db::{
+        error::RepositoryError,
+        filter::{FieldValue, Filter},
+        page::Page,
+        traits::message::MessageRepository,
+    },
+    models::{
+        channel::ChannelId,
+        message::{DbMessage, MessageId},
+        user::UserId,
+    },
+};
+
+#[derive(Clone)]
+pub struct MessageRepositoryImpl {}
+
+/// the `FILTER_FIELDS` of a message.
+fn field<'a>(message: &'a DbMessage, name: &str) -> FieldValue<'a> {
+    match name {
+        "content" => FieldValue::Text(&message.content),
+        "author" => FieldValue::User(message.author),
+        "pin_time" => FieldValue::Time(message.pin_time.as_ref().map(|time| time.0)),
+        "create_time" => FieldValue::Time(Some(message.create_time.0)),
+        "update_time" => FieldValue::Time(message.update_time.as_ref().map(|time| time.0)),
+        _ => FieldValue::Time(None),
+    }
+}
+
+impl MessageRepositoryImpl {
+    pub async fn new() -> Self {
+        MessageRepositoryImpl {}
+    }
+}
+
+#[async_trait]
+impl MessageRepository<MemoryDb> for MessageRepositoryImpl {
+    async fn get(
+        &self,
+        db: &MemoryDb,
+        id: &MessageId,
+    ) -> Result<Option<DbMessage>, RepositoryError> {
+        Ok(db.read()?.messages.get(id).cloned())
+    }
+
+    async fn get_list_by_ids(
+        &self,
+        db: &MemoryDb,
+        ids: &[MessageId],
+    ) -> Result<Vec<DbMessage>, RepositoryError> {
+        let tables = db.read()?;
+
+        Ok(ids
+            .iter()
+            .filter_map(|id| tables.messages.get(id))
+            .cloned()
+            .collect())
+    }
+
+    async fn update(
+        &self,
+        db: &MemoryDb,
+        message: &DbMessage,
+    ) -> Result<Option<DbMessage>, RepositoryError> {
+        db.write()?.messages.insert(message.id, message.clone());
+
+        Ok(Some(message.clone()))
+    }
+
+    async fn add(
+        &self,
+        db: &MemoryDb,
+        message: &DbMessage,
+    ) -> Result<Option<DbMessage>, RepositoryError> {
+        insert(&mut db.write()?.messages, message.id, message).map(Some)
+    }
+
+    async fn get_list_by_chnanel_id(
+        &self,
+        db: &MemoryDb,
+        channel_id: &ChannelId,
+        filter: &Filter,
+        page: Page,
+    ) -> Result<Vec<DbMessage>, RepositoryError> {
+        let tables = db.read()?;
+
+        let messages = tables
+            .messages
+            .iter()
+            .filter(|(_, message)| message.channel == *channel_id)
+            .filter(|(_, message)| filter.matches(&|name| field(message, name)));
+
+        Ok(read_page(messages, page))
+    }
+
+    async fn get_pinned_list_by_channel_id(
+        &self,
+        db: &MemoryDb,
+        channel_id: &ChannelId,
+    ) -> Result<Vec<DbMessage>, RepositoryError> {
+        let mut messages = db
+            .read()?
+            .messages
+            .values()
+            .filter(|message| message.channel == *channel_id && message.pin_time.is_some())
+            .cloned()
+            .collect::<Vec<DbMessage>>();
+
+        messages.sort_by_key(|message| Reverse(message.pin_time.clone()));
+
+        Ok(messages)
+    }
+
+    async fn count_unread(
+        &self,
+        db: &MemoryDb,
+        channel_id: &ChannelId,
+        user_id: &UserId,
+        after: Option<MessageId>,
+    ) -> Result<u64, RepositoryError> {
+        let count = db
+            .read()?
+            .messages
+            .values()
+            .filter(|message| {
+                message.channel == *channel_id
+                    && message.author != *user_id
+                    && after.is_none_or(|after| message.id > after)
+            })
+            .count();
+
+        Ok(count as u64)
+    }
+
+    async fn get_list_by_channel_id_in_range(
+        &self,
+        db: &MemoryDb,
+        channel_id: &ChannelId,
+        start_id: &MessageId,
+        end_id: &MessageId,
+        limit: i32,
+    ) -> Result<Vec<DbMessage>, RepositoryError> {
+        if start_id > end_id {
+            return Ok(vec![]);
+        }
+
+        Ok(db
+            .read()?
+            .messages
+            .range(start_id..=end_id)
+            .rev()
+            .filter(|(_, message)| message.channel == *channel_id)
+            .take(usize::try_from(limit).unwrap_or(0))
+            .map(|(_, message)| message.clone())
+            .collect())
+    }
+
+    fn delete_in(
+        &self,
+        transaction: &mut MemoryTransaction,
+        id: &MessageId,
+    ) -> Result<(), RepositoryError> {
+        let id = *id;
+        transaction.push(move |tables| {
+            tables.messages.remove(&id);
+            Ok(())
+        });
+
+        Ok(())
+    }
+
+    fn delete_by_channel_id_in(
+        &self,
+        transaction: &mut MemoryTransaction,
+        channel_id: &ChannelId,
+    ) -> Result<(), RepositoryError> {
+        let channel_id = *channel_id;
+        transaction.push(move |tables| {
+            tables
+                .messages
+                .retain(|_, message| message.channel != channel_id);
+            Ok(())
+        });
+
+        Ok(())
+    }
+}
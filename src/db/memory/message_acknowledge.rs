@@ -0,0 +1,166 @@
+use tonic::async_trait;
+
+use super::{read_page, MemoryDb, MemoryTransaction, Tables};
+use crate::{
+    db::{
+        error::RepositoryError, page::Page,
+        traits::message_acknowledge::MessageAcknowledgeRepository,
+    },
+    models::{
+        channel::ChannelId, message::MessageId, message_acknowledge::DbMessageAcknowledge,
+        user::UserId,
+    },
+};
+
+#[derive(Clone)]
+pub struct MessageAcknowledgeRepositoryImpl {}
+
+impl MessageAcknowledgeRepositoryImpl {
+    pub async fn new() -> Self {
+        MessageAcknowledgeRepositoryImpl {}
+    }
+}
+
+/// `unique_message_acknowledge`
+fn check_unique(
+    tables: &Tables,
+    message_acknowledge: &DbMessageAcknowledge,
+) -> Result<(), RepositoryError> {
+    let exists = tables.message_acknowledges.values().any(|other| {
+        other.message_id == message_acknowledge.message_id
+            && other.user_id == message_acknowledge.user_id
+    });
+
+    if exists {
+        return Err(RepositoryError::Conflict(format!(
+            "message `{}` is already acknowledged by user `{}`.",
+            message_acknowledge.message_id, message_acknowledge.user_id
+        )));
+    }
+
+    Ok(())
+}
+
+#[async_trait]
+impl MessageAcknowledgeRepository<MemoryDb> for MessageAcknowledgeRepositoryImpl {
+    async fn get_list_by_message(
+        &self,
+        db: &MemoryDb,
+        message_id: &MessageId,
+        page: Page,
+    ) -> Result<Vec<DbMessageAcknowledge>, RepositoryError> {
+        let tables = db.read()?;
+
+        let acknowledges = tables
+            .message_acknowledges
+            .iter()
+            .filter(|(_, acknowledge)| acknowledge.message_id == *message_id);
+
+        Ok(read_page(acknowledges, page))
+    }
+
+    async fn add_list(
+        &self,
+        db: &MemoryDb,
+        message_acknowledges: &[DbMessageAcknowledge],
+    ) -> Result<(), RepositoryError> {
+        let mut tables = db.write()?;
+
+        // like a single `INSERT`, nothing is written when one of them fails.
+        for (index, message_acknowledge) in message_acknowledges.iter().enumerate() {
+            check_unique(&tables, message_acknowledge)?;
+
+            let is_duplicated = message_acknowledges[..index].iter().any(|other| {
+                other.id == message_acknowledge.id
+                    || (other.message_id == message_acknowledge.message_id
+                        && other.user_id == message_acknowledge.user_id)
+            });
+            if is_duplicated
+                || tables
+                    .message_acknowledges
+                    .contains_key(&message_acknowledge.id)
+            {
+                return Err(RepositoryError::Conflict(format!(
+                    "message acknowledge `{}` already exists.",
+                    message_acknowledge.id
+                )));
+            }
+        }
+
+        for message_acknowledge in message_acknowledges {
+            tables
+                .message_acknowledges
+                .insert(message_acknowledge.id, message_acknowledge.clone());
+        }
+
+        Ok(())
+    }
+
+    async fn get_list_by_user_and_messages(
+        &self,
+        db: &MemoryDb,
+        user_id: &UserId,
+        message_ids: &[MessageId],
+    ) -> Result<Vec<DbMessageAcknowledge>, RepositoryError> {
+        Ok(db
+            .read()?
+            .message_acknowledges
+            .values()
+            .filter(|acknowledge| {
+                acknowledge.user_id == *user_id && message_ids.contains(&acknowledge.message_id)
+            })
+            .cloned()
+            .collect())
+    }
+
+    fn delete_by_channel_id_in(
+        &self,
+        transaction: &mut MemoryTransaction,
+        channel_id: &ChannelId,
+    ) -> Result<(), RepositoryError> {
+        let channel_id = *channel_id;
+        transaction.push(move |tables| {
+            let messages = &tables.messages;
+            tables.message_acknowledges.retain(|_, acknowledge| {
+                messages
+                    .get(&acknowledge.message_id)
+                    .is_none_or(|message| message.channel != channel_id)
+            });
+            Ok(())
+        });
+
+        Ok(())
+    }
+
+    fn delete_by_message_id_in(
+        &self,
+        transaction: &mut MemoryTransaction,
+        message_id: &MessageId,
+    ) -> Result<(), RepositoryError> {
+        let message_id = *message_id;
+        transaction.push(move |tables| {
+            tables
+                .message_acknowledges
+                .retain(|_, acknowledge| acknowledge.message_id != message_id);
+            Ok(())
+        });
+
+        Ok(())
+    }
+
+    fn delete_by_user_id_in(
+        &self,
+        transaction: &mut MemoryTransaction,
+        user_id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        let user_id = *user_id;
+        transaction.push(move |tables| {
+            tables
+                .message_acknowledges
+                .retain(|_, acknowledge| acknowledge.user_id != user_id);
+            Ok(())
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use tonic::async_trait;
+
+use super::MemoryDb;
+use crate::{
+    db::{error::RepositoryError, page::Page, traits::message_search::MessageSearchIndex},
+    models::{
+        message::{DbMessage, MessageId},
+        message_search::{MessageSearchHit, MessageSearchQuery, HIGHLIGHT_END, HIGHLIGHT_START},
+    },
+};
+
+/// scans the messages on every search, words match by prefix like the `edgengram` filter of
+/// `message_analyzer` in `migrations/surreal`.
+#[derive(Clone)]
+pub struct MessageSearchIndexImpl {}
+
+impl MessageSearchIndexImpl {
+    pub async fn new() -> Self {
+        MessageSearchIndexImpl {}
+    }
+}
+
+fn terms(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(|term| term.to_lowercase())
+        .collect()
+}
+
+fn is_match(word: &str, terms: &[String]) -> bool {
+    let word = word.to_lowercase();
+
+    terms.iter().any(|term| word.starts_with(term.as_str()))
+}
+
+/// `content` with every word starting with one of `terms` highlighted, or `None` when a term
+/// isn't found.
+fn highlight(content: &str, terms: &[String]) -> Option<String> {
+    let words = self::terms(content);
+    let is_every_term_found = terms
+        .iter()
+        .all(|term| words.iter().any(|word| word.starts_with(term.as_str())));
+    if !is_every_term_found {
+        return None;
+    }
+
+    let mut highlighted = String::with_capacity(content.len());
+    let mut word = String::new();
+
+    let push_word = |highlighted: &mut String, word: &mut String| {
+        if is_match(word, terms) {
+            highlighted.push_str(HIGHLIGHT_START);
+            highlighted.push_str(word);
+            highlighted.push_str(HIGHLIGHT_END);
+        } else {
+            highlighted.push_str(word);
+        }
+        word.clear();
+    };
+
+    for c in content.chars() {
+        if c.is_alphanumeric() {
+            word.push(c);
+        } else {
+            push_word(&mut highlighted, &mut word);
+            highlighted.push(c);
+        }
+    }
+    push_word(&mut highlighted, &mut word);
+
+    Some(highlighted)
+}
+
+fn is_filtered(message: &DbMessage, query: &MessageSearchQuery) -> bool {
+    query.channels.contains(&message.channel)
+        && query.author.is_none_or(|author| message.author == author)
+        && query
+            .mentions
+            .is_none_or(|user| message.mentions.users.contains(&user))
+        && query
+            .has_attachment
+            .is_none_or(|has_attachment| message.attachments.is_empty() != has_attachment)
+        && query
+            .start_time
+            .as_ref()
+            .is_none_or(|start_time| message.create_time >= *start_time)
+        && query
+            .end_time
+            .as_ref()
+            .is_none_or(|end_time| message.create_time < *end_time)
+        && query
+            .pinned
+            .is_none_or(|pinned| message.pin_time.is_some() == pinned)
+}
+
+#[async_trait]
+impl MessageSearchIndex<MemoryDb> for MessageSearchIndexImpl {
+    async fn index_message(
+        &self,
+        _db: &MemoryDb,
+        _message: &DbMessage,
+    ) -> Result<(), RepositoryError> {
+        Ok(())
+    }
+
+    async fn remove_message(&self, _db: &MemoryDb, _id: &MessageId) -> Result<(), RepositoryError> {
+        Ok(())
+    }
+
+    async fn search(
+        &self,
+        db: &MemoryDb,
+        query: &MessageSearchQuery,
+        page: Page,
+    ) -> Result<Vec<MessageSearchHit>, RepositoryError> {
+        let terms = terms(&query.query);
+
+        let hits = db
+            .read()?
+            .messages
+            .values()
+            .filter(|message| page.includes(&message.id))
+            .filter(|message| is_filtered(message, query))
+            .filter_map(|message| {
+                highlight(&message.content, &terms).map(|highlighted| MessageSearchHit {
+                    id: message.id,
+                    highlighted,
+                })
+            })
+            .collect();
+
+        Ok(page.read(hits, |hit| hit.id))
+    }
+}
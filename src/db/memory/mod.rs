@@ -0,0 +1,181 @@
+pub mod attachment;
+pub mod auth;
+pub mod channel;
+pub mod mention;
+pub mod message;
+pub mod message_acknowledge;
+pub mod message_search;
+pub mod read_state;
+pub mod server;
+pub mod server_category;
+pub mod server_member;
+pub mod user;
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use tonic::async_trait;
+use ulid::Ulid;
+
+use crate::{
+    db::{error::RepositoryError, page::Page, Database, Repositories},
+    models::{
+        attachment::{Attachment, AttachmentId},
+        auth::DbAuth,
+        channel::{ChannelId, DbChannel},
+        mention::{DbMention, MentionId},
+        message::{DbMessage, MessageId},
+        message_acknowledge::{DbMessageAcknowledge, MessageAcknowledgeId},
+        read_state::{DbReadState, ReadStateId},
+        server::{DbServer, ServerId},
+        server_category::{DbServerCategory, ServerCategoryId},
+        server_member::{DbServerMember, ServerMemberId},
+        user::{DbUser, UserId},
+    },
+};
+
+pub type MemoryRepositories = Repositories<
+    auth::AuthRepositoryImpl,
+    user::UserRepositoryImpl,
+    server::ServerRepositoryImpl,
+    server_category::ServerCategoryRepositoryImpl,
+    server_member::ServerMemberRepositoryImpl,
+    channel::ChannelRepositoryImpl,
+    message::MessageRepositoryImpl,
+    message_acknowledge::MessageAcknowledgeRepositoryImpl,
+    attachment::AttachmentRepositoryImpl,
+    mention::MentionRepositoryImpl,
+    message_search::MessageSearchIndexImpl,
+    read_state::ReadStateRepositoryImpl,
+>;
+
+/// the repositories of a `MemoryDb`.
+pub async fn repositories() -> MemoryRepositories {
+    Repositories {
+        auth: auth::AuthRepositoryImpl::new().await,
+        user: user::UserRepositoryImpl::new().await,
+        server: server::ServerRepositoryImpl::new().await,
+        server_category: server_category::ServerCategoryRepositoryImpl::new().await,
+        server_member: server_member::ServerMemberRepositoryImpl::new().await,
+        channel: channel::ChannelRepositoryImpl::new().await,
+        message: message::MessageRepositoryImpl::new().await,
+        message_acknowledge: message_acknowledge::MessageAcknowledgeRepositoryImpl::new().await,
+        attachment: attachment::AttachmentRepositoryImpl::new().await,
+        mention: mention::MentionRepositoryImpl::new().await,
+        message_search: message_search::MessageSearchIndexImpl::new().await,
+        read_state: read_state::ReadStateRepositoryImpl::new().await,
+    }
+}
+
+/// a database kept in the process, for tests and demos that run without a SurrealDB server.
+/// clones share the same tables, nothing is kept after the process exits.
+#[derive(Clone, Default)]
+pub struct MemoryDb {
+    tables: Arc<RwLock<Tables>>,
+}
+
+/// records are keyed by their ulid, so iterating a table walks them in creation order like
+/// `ORDER BY id` does on SurrealDB.
+#[derive(Clone, Default)]
+struct Tables {
+    auths: BTreeMap<UserId, DbAuth>,
+    users: BTreeMap<UserId, DbUser>,
+    servers: BTreeMap<ServerId, DbServer>,
+    server_members: BTreeMap<ServerMemberId, DbServerMember>,
+    server_categories: BTreeMap<ServerCategoryId, DbServerCategory>,
+    channels: BTreeMap<ChannelId, DbChannel>,
+    messages: BTreeMap<MessageId, DbMessage>,
+    message_acknowledges: BTreeMap<MessageAcknowledgeId, DbMessageAcknowledge>,
+    mentions: BTreeMap<MentionId, DbMention>,
+    read_states: BTreeMap<ReadStateId, DbReadState>,
+    attachments: BTreeMap<AttachmentId, Attachment>,
+    /// `attachment_uploaded` edges, attachment to its uploader.
+    attachment_uploaders: BTreeMap<AttachmentId, UserId>,
+}
+
+impl MemoryDb {
+    pub fn new() -> Self {
+        MemoryDb::default()
+    }
+
+    // guards are never held across an await, repository futures stay `Send`.
+    fn read(&self) -> Result<RwLockReadGuard<'_, Tables>, RepositoryError> {
+        self.tables
+            .read()
+            .map_err(|_| RepositoryError::Internal("memory database is poisoned.".to_string()))
+    }
+
+    fn write(&self) -> Result<RwLockWriteGuard<'_, Tables>, RepositoryError> {
+        self.tables
+            .write()
+            .map_err(|_| RepositoryError::Internal("memory database is poisoned.".to_string()))
+    }
+}
+
+type Operation = Box<dyn FnOnce(&mut Tables) -> Result<(), RepositoryError> + Send>;
+
+/// operations applied under one write lock, the tables are restored when one fails.
+#[derive(Default)]
+pub struct MemoryTransaction {
+    operations: Vec<Operation>,
+}
+
+impl MemoryTransaction {
+    fn push<F>(&mut self, operation: F)
+    where
+        F: FnOnce(&mut Tables) -> Result<(), RepositoryError> + Send + 'static,
+    {
+        self.operations.push(Box::new(operation));
+    }
+}
+
+#[async_trait]
+impl Database for MemoryDb {
+    type Transaction = MemoryTransaction;
+
+    async fn commit(&self, transaction: MemoryTransaction) -> Result<(), RepositoryError> {
+        let mut tables = self.write()?;
+        let snapshot = tables.clone();
+
+        for operation in transaction.operations {
+            if let Err(err) = operation(&mut tables) {
+                *tables = snapshot;
+
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// inserts a new record, failing like `CREATE` does when the id is taken.
+fn insert<K, V>(table: &mut BTreeMap<K, V>, id: K, record: &V) -> Result<V, RepositoryError>
+where
+    K: Ord + ToString,
+    V: Clone,
+{
+    if table.contains_key(&id) {
+        return Err(RepositoryError::Conflict(format!(
+            "record `{}` already exists.",
+            id.to_string()
+        )));
+    }
+
+    table.insert(id, record.clone());
+
+    Ok(record.clone())
+}
+
+/// a page of a table or of records filtered from it, ordered by id like the database pages.
+fn read_page<'a, V>(records: impl Iterator<Item = (&'a Ulid, &'a V)>, page: Page) -> Vec<V>
+where
+    V: Clone + 'a,
+{
+    page.read(records.collect(), |(id, _)| **id)
+        .into_iter()
+        .map(|(_, record)| record.clone())
+        .collect()
+}
@@ -0,0 +1,102 @@
+use tonic::async_trait;
+
+use super::{MemoryDb, MemoryTransaction};
+use crate::{
+    db::{error::RepositoryError, traits::read_state::ReadStateRepository},
+    models::{channel::ChannelId, read_state::DbReadState, user::UserId},
+};
+
+#[derive(Clone)]
+pub struct ReadStateRepositoryImpl {}
+
+impl ReadStateRepositoryImpl {
+    pub async fn new() -> Self {
+        ReadStateRepositoryImpl {}
+    }
+}
+
+#[async_trait]
+impl ReadStateRepository<MemoryDb> for ReadStateRepositoryImpl {
+    async fn get(
+        &self,
+        db: &MemoryDb,
+        user_id: &UserId,
+        channel_id: &ChannelId,
+    ) -> Result<Option<DbReadState>, RepositoryError> {
+        Ok(db
+            .read()?
+            .read_states
+            .values()
+            .find(|read_state| read_state.user == *user_id && read_state.channel == *channel_id)
+            .cloned())
+    }
+
+    async fn get_list_by_user_id(
+        &self,
+        db: &MemoryDb,
+        user_id: &UserId,
+        channel_ids: &[ChannelId],
+    ) -> Result<Vec<DbReadState>, RepositoryError> {
+        Ok(db
+            .read()?
+            .read_states
+            .values()
+            .filter(|read_state| {
+                read_state.user == *user_id && channel_ids.contains(&read_state.channel)
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn save(&self, db: &MemoryDb, read_state: &DbReadState) -> Result<(), RepositoryError> {
+        let mut tables = db.write()?;
+
+        // `unique_read_state`
+        if tables.read_states.values().any(|other| {
+            other.id != read_state.id
+                && other.user == read_state.user
+                && other.channel == read_state.channel
+        }) {
+            return Err(RepositoryError::Conflict(format!(
+                "read state of user `{}` in channel `{}` already exists.",
+                read_state.user, read_state.channel
+            )));
+        }
+
+        tables.read_states.insert(read_state.id, read_state.clone());
+
+        Ok(())
+    }
+
+    fn delete_by_channel_id_in(
+        &self,
+        transaction: &mut MemoryTransaction,
+        channel_id: &ChannelId,
+    ) -> Result<(), RepositoryError> {
+        let channel_id = *channel_id;
+        transaction.push(move |tables| {
+            tables
+                .read_states
+                .retain(|_, read_state| read_state.channel != channel_id);
+            Ok(())
+        });
+
+        Ok(())
+    }
+
+    fn delete_by_user_id_in(
+        &self,
+        transaction: &mut MemoryTransaction,
+        user_id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        let user_id = *user_id;
+        transaction.push(move |tables| {
+            tables
+                .read_states
+                .retain(|_, read_state| read_state.user != user_id);
+            Ok(())
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+use tonic::async_trait;
+
+use super::{insert, read_page, MemoryDb, MemoryTransaction};
+use crate::{
+    db::{
+        error::RepositoryError,
+        filter::{FieldValue, Filter},
+        page::Page,
+        traits::server::ServerRepository,
+    },
+    models::{
+        server::{DbServer, ServerId},
+        user::UserId,
+    },
+};
+
+#[derive(Clone)]
+pub struct ServerRepositoryImpl {}
+
+/// the `FILTER_FIELDS` of a server.
+fn field<'a>(server: &'a DbServer, name: &str) -> FieldValue<'a> {
+    match name {
+        "display_name" => FieldValue::Text(&server.display_name),
+        "description" => FieldValue::Text(&server.description),
+        "owner" => FieldValue::User(server.owner),
+        "create_time" => FieldValue::Time(Some(server.create_time.0)),
+        "update_time" => FieldValue::Time(server.update_time.as_ref().map(|time| time.0)),
+        _ => FieldValue::Time(None),
+    }
+}
+
+impl ServerRepositoryImpl {
+    pub async fn new() -> Self {
+        ServerRepositoryImpl {}
+    }
+}
+
+#[async_trait]
+impl ServerRepository<MemoryDb> for ServerRepositoryImpl {
+    async fn get_server(
+        &self,
+        db: &MemoryDb,
+        id: &ServerId,
+    ) -> Result<Option<DbServer>, RepositoryError> {
+        Ok(db.read()?.servers.get(id).cloned())
+    }
+
+    async fn update_server(
+        &self,
+        db: &MemoryDb,
+        server: &DbServer,
+    ) -> Result<Option<DbServer>, RepositoryError> {
+        db.write()?.servers.insert(server.id, server.clone());
+
+        Ok(Some(server.clone()))
+    }
+
+    async fn get_servers(
+        &self,
+        db: &MemoryDb,
+        filter: &Filter,
+        page: Page,
+    ) -> Result<Vec<DbServer>, RepositoryError> {
+        let tables = db.read()?;
+
+        let servers = tables
+            .servers
+            .iter()
+            .filter(|(_, server)| filter.matches(&|name| field(server, name)));
+
+        Ok(read_page(servers, page))
+    }
+
+    async fn get_servers_by_owner(
+        &self,
+        db: &MemoryDb,
+        owner: &UserId,
+    ) -> Result<Vec<DbServer>, RepositoryError> {
+        Ok(db
+            .read()?
+            .servers
+            .values()
+            .filter(|server| server.owner == *owner)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_joined_servers(
+        &self,
+        db: &MemoryDb,
+        user_id: &UserId,
+        page: Page,
+    ) -> Result<Vec<DbServer>, RepositoryError> {
+        let tables = db.read()?;
+
+        let servers = tables.servers.iter().filter(|(id, _)| {
+            tables
+                .server_members
+                .values()
+                .any(|member| member.server == **id && member.user == *user_id)
+        });
+
+        Ok(read_page(servers, page))
+    }
+
+    fn add_server_in(
+        &self,
+        transaction: &mut MemoryTransaction,
+        server: &DbServer,
+    ) -> Result<(), RepositoryError> {
+        let server = server.clone();
+        transaction.push(move |tables| insert(&mut tables.servers, server.id, &server).map(drop));
+
+        Ok(())
+    }
+
+    fn update_server_in(
+        &self,
+        transaction: &mut MemoryTransaction,
+        server: &DbServer,
+    ) -> Result<(), RepositoryError> {
+        let server = server.clone();
+        transaction.push(move |tables| {
+            tables.servers.insert(server.id, server);
+            Ok(())
+        });
+
+        Ok(())
+    }
+
+    fn delete_server_in(
+        &self,
+        transaction: &mut MemoryTransaction,
+        id: &ServerId,
+    ) -> Result<(), RepositoryError> {
+        let id = *id;
+        transaction.push(move |tables| {
+            tables.servers.remove(&id);
+            Ok(())
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+use tonic::async_trait;
+
+use super::{insert, read_page, MemoryDb, MemoryTransaction};
+use crate::{
+    db::{error::RepositoryError, page::Page, traits::server_category::ServerCategoryRepository},
+    models::server::ServerId,
+    models::server_category::{DbServerCategory, ServerCategoryId},
+};
+
+#[derive(Clone)]
+pub struct ServerCategoryRepositoryImpl {}
+
+impl ServerCategoryRepositoryImpl {
+    pub async fn new() -> Self {
+        ServerCategoryRepositoryImpl {}
+    }
+}
+
+#[async_trait]
+impl ServerCategoryRepository<MemoryDb> for ServerCategoryRepositoryImpl {
+    async fn get(
+        &self,
+        db: &MemoryDb,
+        id: &ServerCategoryId,
+    ) -> Result<Option<DbServerCategory>, RepositoryError> {
+        Ok(db.read()?.server_categories.get(id).cloned())
+    }
+
+    async fn add(
+        &self,
+        db: &MemoryDb,
+        server_category: &DbServerCategory,
+    ) -> Result<Option<DbServerCategory>, RepositoryError> {
+        insert(
+            &mut db.write()?.server_categories,
+            server_category.id,
+            server_category,
+        )
+        .map(Some)
+    }
+
+    async fn update(
+        &self,
+        db: &MemoryDb,
+        server_category: &DbServerCategory,
+    ) -> Result<Option<DbServerCategory>, RepositoryError> {
+        db.write()?
+            .server_categories
+            .insert(server_category.id, server_category.clone());
+
+        Ok(Some(server_category.clone()))
+    }
+
+    async fn delete(&self, db: &MemoryDb, id: &ServerCategoryId) -> Result<u8, RepositoryError> {
+        db.write()?.server_categories.remove(id);
+
+        Ok(1)
+    }
+
+    async fn get_list_by_server_id(
+        &self,
+        db: &MemoryDb,
+        server_id: &ServerId,
+    ) -> Result<Vec<DbServerCategory>, RepositoryError> {
+        let mut categories = db
+            .read()?
+            .server_categories
+            .values()
+            .filter(|category| category.server == *server_id)
+            .cloned()
+            .collect::<Vec<DbServerCategory>>();
+
+        categories.sort_by_key(|category| (category.order, category.id));
+
+        Ok(categories)
+    }
+
+    async fn get_server_categories(
+        &self,
+        db: &MemoryDb,
+        server_id: &ServerId,
+        page: Page,
+    ) -> Result<Vec<DbServerCategory>, RepositoryError> {
+        let tables = db.read()?;
+
+        let categories = tables
+            .server_categories
+            .iter()
+            .filter(|(_, category)| category.server == *server_id);
+
+        Ok(read_page(categories, page))
+    }
+
+    fn delete_by_server_id_in(
+        &self,
+        transaction: &mut MemoryTransaction,
+        server_id: &ServerId,
+    ) -> Result<(), RepositoryError> {
+        let server_id = *server_id;
+        transaction.push(move |tables| {
+            tables
+                .server_categories
+                .retain(|_, category| category.server != server_id);
+            Ok(())
+        });
+
+        Ok(())
+    }
+}
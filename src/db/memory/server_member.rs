@@ -0,0 +1,194 @@
+use tonic::async_trait;
+
+use super::{insert, read_page, MemoryDb, MemoryTransaction, Tables};
+use crate::{
+    db::{
+        error::RepositoryError,
+        filter::{FieldValue, Filter},
+        page::Page,
+        traits::server_member::ServerMemberRepository,
+    },
+    models::server::ServerId,
+    models::server_member::{DbServerMember, ServerMemberId},
+    models::user::UserId,
+};
+
+#[derive(Clone)]
+pub struct ServerMemberRepositoryImpl {}
+
+/// the `FILTER_FIELDS` of a server member.
+fn field<'a>(member: &'a DbServerMember, name: &str) -> FieldValue<'a> {
+    match name {
+        "display_name" => FieldValue::Text(&member.display_name),
+        "description" => FieldValue::Text(&member.description),
+        "user" => FieldValue::User(member.user),
+        "create_time" => FieldValue::Time(Some(member.create_time.0)),
+        "update_time" => FieldValue::Time(member.update_time.as_ref().map(|time| time.0)),
+        _ => FieldValue::Time(None),
+    }
+}
+
+fn add(
+    tables: &mut Tables,
+    server_member: &DbServerMember,
+) -> Result<DbServerMember, RepositoryError> {
+    // `unique_member`
+    if tables
+        .server_members
+        .values()
+        .any(|other| other.server == server_member.server && other.user == server_member.user)
+    {
+        return Err(RepositoryError::Conflict(format!(
+            "user `{}` is already a member of server `{}`.",
+            server_member.user, server_member.server
+        )));
+    }
+
+    insert(&mut tables.server_members, server_member.id, server_member)
+}
+
+impl ServerMemberRepositoryImpl {
+    pub async fn new() -> Self {
+        ServerMemberRepositoryImpl {}
+    }
+}
+
+#[async_trait]
+impl ServerMemberRepository<MemoryDb> for ServerMemberRepositoryImpl {
+    async fn get_server_member(
+        &self,
+        db: &MemoryDb,
+        id: &ServerMemberId,
+    ) -> Result<Option<DbServerMember>, RepositoryError> {
+        Ok(db.read()?.server_members.get(id).cloned())
+    }
+
+    async fn add_server_member(
+        &self,
+        db: &MemoryDb,
+        server_member: &DbServerMember,
+    ) -> Result<Option<DbServerMember>, RepositoryError> {
+        add(&mut *db.write()?, server_member).map(Some)
+    }
+
+    async fn update_server_member(
+        &self,
+        db: &MemoryDb,
+        server_member: &DbServerMember,
+    ) -> Result<Option<DbServerMember>, RepositoryError> {
+        db.write()?
+            .server_members
+            .insert(server_member.id, server_member.clone());
+
+        Ok(Some(server_member.clone()))
+    }
+
+    async fn delete(&self, db: &MemoryDb, id: &ServerMemberId) -> Result<u8, RepositoryError> {
+        db.write()?.server_members.remove(id);
+
+        Ok(1)
+    }
+
+    async fn get_server_members(
+        &self,
+        db: &MemoryDb,
+        server_id: &ServerId,
+        filter: &Filter,
+        page: Page,
+    ) -> Result<Vec<DbServerMember>, RepositoryError> {
+        let tables = db.read()?;
+
+        let members = tables
+            .server_members
+            .iter()
+            .filter(|(_, member)| member.server == *server_id)
+            .filter(|(_, member)| filter.matches(&|name| field(member, name)));
+
+        Ok(read_page(members, page))
+    }
+
+    async fn get_server_members_by_server_id(
+        &self,
+        db: &MemoryDb,
+        server_id: &ServerId,
+    ) -> Result<Vec<DbServerMember>, RepositoryError> {
+        Ok(db
+            .read()?
+            .server_members
+            .values()
+            .filter(|member| member.server == *server_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_server_members_by_user_id(
+        &self,
+        db: &MemoryDb,
+        user_id: &UserId,
+    ) -> Result<Vec<DbServerMember>, RepositoryError> {
+        Ok(db
+            .read()?
+            .server_members
+            .values()
+            .filter(|member| member.user == *user_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_server_member_by_server_id_and_user_id(
+        &self,
+        db: &MemoryDb,
+        server_id: &ServerId,
+        user_id: &UserId,
+    ) -> Result<Option<DbServerMember>, RepositoryError> {
+        Ok(db
+            .read()?
+            .server_members
+            .values()
+            .find(|member| member.server == *server_id && member.user == *user_id)
+            .cloned())
+    }
+
+    fn add_server_member_in(
+        &self,
+        transaction: &mut MemoryTransaction,
+        server_member: &DbServerMember,
+    ) -> Result<(), RepositoryError> {
+        let server_member = server_member.clone();
+        transaction.push(move |tables| add(tables, &server_member).map(drop));
+
+        Ok(())
+    }
+
+    fn delete_by_server_id_in(
+        &self,
+        transaction: &mut MemoryTransaction,
+        server_id: &ServerId,
+    ) -> Result<(), RepositoryError> {
+        let server_id = *server_id;
+        transaction.push(move |tables| {
+            tables
+                .server_members
+                .retain(|_, member| member.server != server_id);
+            Ok(())
+        });
+
+        Ok(())
+    }
+
+    fn delete_by_user_id_in(
+        &self,
+        transaction: &mut MemoryTransaction,
+        user_id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        let user_id = *user_id;
+        transaction.push(move |tables| {
+            tables
+                .server_members
+                .retain(|_, member| member.user != user_id);
+            Ok(())
+        });
+
+        Ok(())
+    }
+}
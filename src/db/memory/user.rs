@@ -0,0 +1,97 @@
+use tonic::async_trait;
+
+use super::{insert, read_page, MemoryDb, MemoryTransaction};
+use crate::{
+    db::{
+        error::RepositoryError,
+        filter::{FieldValue, Filter},
+        page::Page,
+        traits::user::UserRepository,
+    },
+    models::user::{DbUser, UserId},
+};
+
+#[derive(Clone)]
+pub struct UserRepositoryImpl {}
+
+/// the `FILTER_FIELDS` of a user.
+fn field<'a>(user: &'a DbUser, name: &str) -> FieldValue<'a> {
+    match name {
+        "display_name" => FieldValue::Text(&user.display_name),
+        "description" => FieldValue::Text(&user.description),
+        "create_time" => FieldValue::Time(Some(user.create_time.0)),
+        "update_time" => FieldValue::Time(user.update_time.as_ref().map(|time| time.0)),
+        _ => FieldValue::Time(None),
+    }
+}
+
+impl UserRepositoryImpl {
+    pub async fn new() -> Self {
+        UserRepositoryImpl {}
+    }
+}
+
+#[async_trait]
+impl UserRepository<MemoryDb> for UserRepositoryImpl {
+    async fn get_user(
+        &self,
+        db: &MemoryDb,
+        id: &UserId,
+    ) -> Result<Option<DbUser>, RepositoryError> {
+        Ok(db.read()?.users.get(id).cloned())
+    }
+
+    async fn add_user(
+        &self,
+        db: &MemoryDb,
+        user: &DbUser,
+    ) -> Result<Option<DbUser>, RepositoryError> {
+        insert(&mut db.write()?.users, user.id, user).map(Some)
+    }
+
+    async fn update_user(
+        &self,
+        db: &MemoryDb,
+        user: &DbUser,
+    ) -> Result<Option<DbUser>, RepositoryError> {
+        db.write()?.users.insert(user.id, user.clone());
+
+        Ok(Some(user.clone()))
+    }
+
+    async fn delete_user(&self, db: &MemoryDb, id: &UserId) -> Result<u8, RepositoryError> {
+        db.write()?.users.remove(id);
+
+        Ok(1)
+    }
+
+    async fn get_users(
+        &self,
+        db: &MemoryDb,
+        filter: &Filter,
+        page: Page,
+    ) -> Result<Vec<DbUser>, RepositoryError> {
+        let tables = db.read()?;
+
+        let users = tables
+            .users
+            .iter()
+            .filter(|(_, user)| filter.matches(&|name| field(user, name)));
+
+        Ok(read_page(users, page))
+    }
+
+    fn delete_user_in(
+        &self,
+        transaction: &mut MemoryTransaction,
+        id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        let id = *id;
+        transaction.push(move |tables| {
+            tables.users.remove(&id);
+            Ok(())
+        });
+
+        Ok(())
+    }
+}
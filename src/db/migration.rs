@@ -0,0 +1,320 @@
+use std::fmt;
+
+use sha2::{Digest, Sha256};
+
+use crate::db::{error::RepositoryError, traits::schema_version::SchemaVersionRepository};
+
+/// a schema change, embedded from `migrations/{backend}/{version:04}_{name}.*`. applied
+/// migrations are never edited, every change gets a new version.
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    pub script: &'static str,
+}
+
+impl Migration {
+    pub const fn new(version: u32, name: &'static str, script: &'static str) -> Self {
+        Migration {
+            version,
+            name,
+            script,
+        }
+    }
+
+    /// sha256 of the script, compared with the one recorded when it was applied.
+    pub fn checksum(&self) -> String {
+        format!("{:x}", Sha256::digest(self.script.as_bytes()))
+    }
+}
+
+impl fmt::Display for Migration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}_{}", self.version, self.name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationMode {
+    /// applies the pending migrations.
+    Apply,
+    /// prints the pending migrations without applying them.
+    DryRun,
+    /// fails when a migration is pending, for servers started with `auto_migrate` off.
+    Check,
+}
+
+#[derive(Debug)]
+pub enum MigrationError {
+    /// the script of an applied migration changed since.
+    Modified {
+        migration: String,
+        applied: String,
+        current: String,
+    },
+    /// the database has a migration this build doesn't know, it was migrated by a newer one.
+    Unknown {
+        version: u32,
+        name: String,
+    },
+    Pending(Vec<String>),
+    Repository(RepositoryError),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::Modified {
+                migration,
+                applied,
+                current,
+            } => write!(
+                f,
+                "migration {} was edited after it was applied, checksum {} is now {}.",
+                migration, applied, current
+            ),
+            MigrationError::Unknown { version, name } => write!(
+                f,
+                "the database has migration {:04}_{} which this server doesn't know.",
+                version, name
+            ),
+            MigrationError::Pending(migrations) => write!(
+                f,
+                "migrations {} are pending, run `ycChat migrate` or enable database.auto_migrate.",
+                migrations.join(", ")
+            ),
+            MigrationError::Repository(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+impl From<RepositoryError> for MigrationError {
+    fn from(err: RepositoryError) -> Self {
+        MigrationError::Repository(err)
+    }
+}
+
+/// checks the applied migrations against `migrations` and applies the rest in order. every
+/// migration runs in its own transaction, a failure keeps the ones applied before it.
+pub async fn migrate<DB, R>(
+    db: &DB,
+    repository: &R,
+    migrations: &[Migration],
+    mode: MigrationMode,
+) -> Result<(), MigrationError>
+where
+    R: SchemaVersionRepository<DB>,
+{
+    let applied = repository.get_list(db).await?;
+
+    for schema_version in &applied {
+        let Some(migration) = migrations
+            .iter()
+            .find(|migration| migration.version == schema_version.version)
+        else {
+            return Err(MigrationError::Unknown {
+                version: schema_version.version,
+                name: schema_version.name.clone(),
+            });
+        };
+
+        let checksum = migration.checksum();
+        if checksum != schema_version.checksum {
+            return Err(MigrationError::Modified {
+                migration: migration.to_string(),
+                applied: schema_version.checksum.clone(),
+                current: checksum,
+            });
+        }
+    }
+
+    let pending = migrations
+        .iter()
+        .filter(|migration| {
+            !applied
+                .iter()
+                .any(|schema_version| schema_version.version == migration.version)
+        })
+        .collect::<Vec<&Migration>>();
+
+    match mode {
+        MigrationMode::Apply => {
+            for migration in pending {
+                repository.apply(db, migration).await?;
+
+                println!("applied migration {}", migration);
+            }
+        }
+        MigrationMode::DryRun => {
+            for migration in &pending {
+                println!(
+                    "-- pending migration {} (sha256 {})",
+                    migration,
+                    migration.checksum()
+                );
+                println!("{}", migration.script.trim_end());
+            }
+
+            println!("-- {} pending, nothing applied.", pending.len());
+        }
+        MigrationMode::Check if !pending.is_empty() => {
+            return Err(MigrationError::Pending(
+                pending.iter().map(ToString::to_string).collect(),
+            ));
+        }
+        MigrationMode::Check => {}
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use surrealdb::sql::Datetime;
+
+    use super::*;
+    use crate::{db, models::schema_version::DbSchemaVersion};
+
+    const MIGRATIONS: &[Migration] = &[
+        Migration::new(1, "initial", "CREATE a;"),
+        Migration::new(2, "second", "CREATE b;"),
+        Migration::new(3, "third", "CREATE c;"),
+    ];
+
+    /// the `schema_version` table, the connection is unused.
+    #[derive(Default)]
+    struct Versions(Mutex<Vec<DbSchemaVersion>>);
+
+    impl Versions {
+        fn applied(migrations: &[Migration]) -> Self {
+            let versions = Versions::default();
+            for migration in migrations {
+                versions.record(migration);
+            }
+
+            versions
+        }
+
+        fn record(&self, migration: &Migration) {
+            self.0.lock().unwrap().push(DbSchemaVersion {
+                version: migration.version,
+                name: migration.name.to_string(),
+                checksum: migration.checksum(),
+                apply_time: Datetime::default(),
+            });
+        }
+
+        fn versions(&self) -> Vec<u32> {
+            self.0
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|schema_version| schema_version.version)
+                .collect()
+        }
+    }
+
+    #[tonic::async_trait]
+    impl SchemaVersionRepository<()> for Versions {
+        async fn get_list(&self, _db: &()) -> Result<Vec<DbSchemaVersion>, RepositoryError> {
+            Ok(self.0.lock().unwrap().clone())
+        }
+
+        async fn apply(&self, _db: &(), migration: &Migration) -> Result<(), RepositoryError> {
+            self.record(migration);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn checksum_is_sha256_of_script() {
+        assert_eq!(
+            Migration::new(1, "initial", "").checksum(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_ne!(MIGRATIONS[0].checksum(), MIGRATIONS[1].checksum());
+    }
+
+    #[test]
+    fn backend_migrations_are_numbered_in_order() {
+        for migrations in [db::postgres::MIGRATIONS, db::surreal::MIGRATIONS] {
+            let versions = migrations
+                .iter()
+                .map(|migration| migration.version)
+                .collect::<Vec<u32>>();
+
+            assert_eq!(
+                versions,
+                (1..=migrations.len() as u32).collect::<Vec<u32>>()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn applies_pending_in_order() {
+        let versions = Versions::applied(&MIGRATIONS[..1]);
+
+        migrate(&(), &versions, MIGRATIONS, MigrationMode::Apply)
+            .await
+            .unwrap();
+        assert_eq!(versions.versions(), vec![1, 2, 3]);
+
+        migrate(&(), &versions, MIGRATIONS, MigrationMode::Apply)
+            .await
+            .unwrap();
+        assert_eq!(versions.versions(), vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn dry_run_and_check_apply_nothing() {
+        let versions = Versions::applied(&MIGRATIONS[..1]);
+
+        migrate(&(), &versions, MIGRATIONS, MigrationMode::DryRun)
+            .await
+            .unwrap();
+        let err = migrate(&(), &versions, MIGRATIONS, MigrationMode::Check)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            MigrationError::Pending(pending) if pending == ["0002_second", "0003_third"]
+        ));
+        assert_eq!(versions.versions(), vec![1]);
+
+        let versions = Versions::applied(MIGRATIONS);
+        migrate(&(), &versions, MIGRATIONS, MigrationMode::Check)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn edited_migration_is_rejected() {
+        let versions = Versions::applied(&[Migration::new(1, "initial", "CREATE z;")]);
+
+        let err = migrate(&(), &versions, MIGRATIONS, MigrationMode::Apply)
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(err, MigrationError::Modified { migration, .. } if migration == "0001_initial")
+        );
+        assert_eq!(versions.versions(), vec![1]);
+    }
+
+    #[tokio::test]
+    async fn unknown_migration_is_rejected() {
+        let versions = Versions::applied(MIGRATIONS);
+
+        let err = migrate(&(), &versions, &MIGRATIONS[..2], MigrationMode::Apply)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, MigrationError::Unknown { version: 3, .. }));
+    }
+}
@@ -1,2 +1,38 @@
+pub mod error;
+pub mod filter;
+pub mod memory;
+pub mod migration;
+pub mod page;
+pub mod postgres;
 pub mod surreal;
 pub mod traits;
+
+use crate::db::error::RepositoryError;
+
+/// the connection repositories run their queries on, shared by every service.
+#[tonic::async_trait]
+pub trait Database: Clone + Send + Sync + 'static {
+    /// writes queued by the `*_in` methods of the repositories, applied by `commit`.
+    type Transaction: Default + Send;
+
+    /// applies every queued write at once, or none of them when one fails. reads don't see
+    /// queued writes, read what the writes depend on before queueing them.
+    async fn commit(&self, transaction: Self::Transaction) -> Result<(), RepositoryError>;
+}
+
+/// one implementation of every repository, all running on the same kind of database.
+#[derive(Clone)]
+pub struct Repositories<AU, U, S, SC, SM, C, M, ACK, A, MN, SI, R> {
+    pub auth: AU,
+    pub user: U,
+    pub server: S,
+    pub server_category: SC,
+    pub server_member: SM,
+    pub channel: C,
+    pub message: M,
+    pub message_acknowledge: ACK,
+    pub attachment: A,
+    pub mention: MN,
+    pub message_search: SI,
+    pub read_state: R,
+}
@@ -0,0 +1,259 @@
+use ulid::Ulid;
+
+/// direction of a list along its sort key. ids are ulids, ordering by id orders by creation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    Ascending,
+    Descending,
+}
+
+/// what a list is sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    /// the id, and so the creation time.
+    Id,
+    /// the position in the channel layout, see `Page::cut`.
+    Layout,
+}
+
+/// the record a page starts from, excluded from the page. `After` continues the list,
+/// `Before` walks it back towards its start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cursor {
+    After(Ulid),
+    Before(Ulid),
+}
+
+/// the records a list request reads, up to `limit` of them next to `cursor`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Page {
+    pub limit: u32,
+    pub key: Key,
+    pub order: Order,
+    pub cursor: Option<Cursor>,
+}
+
+impl Page {
+    pub fn new(limit: u32, key: Key, order: Order, cursor: Option<Cursor>) -> Self {
+        Page {
+            limit,
+            key,
+            order,
+            cursor,
+        }
+    }
+
+    /// whether the records are read by descending id. a page before the cursor is read
+    /// backwards from it, `finish` puts it back in list order.
+    pub fn is_descending(&self) -> bool {
+        (self.order == Order::Descending) != self.is_before()
+    }
+
+    pub fn is_before(&self) -> bool {
+        matches!(self.cursor, Some(Cursor::Before(_)))
+    }
+
+    pub fn cursor_id(&self) -> Option<Ulid> {
+        match self.cursor {
+            Some(Cursor::After(id) | Cursor::Before(id)) => Some(id),
+            None => None,
+        }
+    }
+
+    /// how ids past the cursor compare to it, `<` or `>`.
+    pub fn operator(&self) -> &'static str {
+        if self.is_descending() {
+            "<"
+        } else {
+            ">"
+        }
+    }
+
+    /// `ASC` or `DESC`, the order the records are read in.
+    pub fn direction(&self) -> &'static str {
+        if self.is_descending() {
+            "DESC"
+        } else {
+            "ASC"
+        }
+    }
+
+    /// whether `id` is past the cursor in reading order.
+    pub fn includes(&self, id: &Ulid) -> bool {
+        match self.cursor_id() {
+            Some(cursor_id) if self.is_descending() => *id < cursor_id,
+            Some(cursor_id) => *id > cursor_id,
+            None => true,
+        }
+    }
+
+    /// the records as read, in list order.
+    pub fn finish<T>(&self, mut records: Vec<T>) -> Vec<T> {
+        if self.is_before() {
+            records.reverse();
+        }
+
+        records
+    }
+
+    /// the page of `records` sorted by ascending id, for backends that filter in process.
+    pub fn read<T>(&self, mut records: Vec<T>, id: impl Fn(&T) -> Ulid) -> Vec<T> {
+        if self.is_descending() {
+            records.reverse();
+        }
+
+        let records = records
+            .into_iter()
+            .filter(|record| self.includes(&id(record)))
+            .take(self.limit as usize)
+            .collect();
+
+        self.finish(records)
+    }
+
+    /// the page of a whole list without a sortable key, e.g. the channel layout, `records`
+    /// being in ascending order. the cursor is found by position, a removed one ends the list.
+    /// pages by id read the records sorted by id instead.
+    pub fn cut<T>(&self, mut records: Vec<T>, id: impl Fn(&T) -> Ulid) -> Vec<T> {
+        if self.key == Key::Id {
+            records.sort_by_key(&id);
+
+            return self.read(records, id);
+        }
+
+        if self.order == Order::Descending {
+            records.reverse();
+        }
+
+        let position = |cursor_id: Ulid| records.iter().position(|record| id(record) == cursor_id);
+        let limit = self.limit as usize;
+
+        let range = match self.cursor {
+            None => 0..records.len().min(limit),
+            Some(Cursor::After(cursor_id)) => match position(cursor_id) {
+                Some(index) => index + 1..records.len().min(index + 1 + limit),
+                None => 0..0,
+            },
+            Some(Cursor::Before(cursor_id)) => match position(cursor_id) {
+                Some(index) => index.saturating_sub(limit)..index,
+                None => 0..0,
+            },
+        };
+
+        records.drain(range).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(range: std::ops::RangeInclusive<u128>) -> Vec<Ulid> {
+        range.map(Ulid).collect()
+    }
+
+    fn page(limit: u32, key: Key, order: Order, cursor: Option<Cursor>) -> Page {
+        Page::new(limit, key, order, cursor)
+    }
+
+    #[test]
+    fn cut_layout_pages_by_position() {
+        let layout = vec![Ulid(3), Ulid(1), Ulid(5), Ulid(2), Ulid(4)];
+        let cut =
+            |cursor| page(2, Key::Layout, Order::Ascending, cursor).cut(layout.clone(), |id| *id);
+
+        assert_eq!(cut(None), vec![Ulid(3), Ulid(1)]);
+        assert_eq!(cut(Some(Cursor::After(Ulid(1)))), vec![Ulid(5), Ulid(2)]);
+        assert_eq!(cut(Some(Cursor::After(Ulid(2)))), vec![Ulid(4)]);
+        assert_eq!(cut(Some(Cursor::After(Ulid(4)))), vec![]);
+        assert_eq!(cut(Some(Cursor::Before(Ulid(2)))), vec![Ulid(1), Ulid(5)]);
+        assert_eq!(cut(Some(Cursor::Before(Ulid(1)))), vec![Ulid(3)]);
+        assert_eq!(cut(Some(Cursor::Before(Ulid(3)))), vec![]);
+    }
+
+    #[test]
+    fn cut_layout_descending_starts_at_the_bottom() {
+        let layout = vec![Ulid(3), Ulid(1), Ulid(5)];
+        let cut =
+            |cursor| page(2, Key::Layout, Order::Descending, cursor).cut(layout.clone(), |id| *id);
+
+        assert_eq!(cut(None), vec![Ulid(5), Ulid(1)]);
+        assert_eq!(cut(Some(Cursor::After(Ulid(1)))), vec![Ulid(3)]);
+        assert_eq!(cut(Some(Cursor::Before(Ulid(3)))), vec![Ulid(5), Ulid(1)]);
+    }
+
+    #[test]
+    fn cut_layout_removed_cursor_ends_the_list() {
+        let layout = ids(1..=3);
+
+        let after = page(
+            2,
+            Key::Layout,
+            Order::Ascending,
+            Some(Cursor::After(Ulid(9))),
+        );
+        let before = page(
+            2,
+            Key::Layout,
+            Order::Ascending,
+            Some(Cursor::Before(Ulid(9))),
+        );
+
+        assert_eq!(after.cut(layout.clone(), |id| *id), vec![]);
+        assert_eq!(before.cut(layout, |id| *id), vec![]);
+    }
+
+    #[test]
+    fn cut_by_id_sorts_the_records() {
+        let records = vec![Ulid(3), Ulid(1), Ulid(5), Ulid(2), Ulid(4)];
+
+        let first = page(2, Key::Id, Order::Ascending, None);
+        let next = page(2, Key::Id, Order::Ascending, Some(Cursor::After(Ulid(2))));
+
+        assert_eq!(first.cut(records.clone(), |id| *id), ids(1..=2));
+        assert_eq!(next.cut(records, |id| *id), ids(3..=4));
+    }
+
+    #[test]
+    fn read_ascending_round_trip() {
+        let records = ids(1..=5);
+        let read =
+            |cursor| page(2, Key::Id, Order::Ascending, cursor).read(records.clone(), |id| *id);
+
+        assert_eq!(read(None), ids(1..=2));
+        assert_eq!(read(Some(Cursor::After(Ulid(2)))), ids(3..=4));
+        assert_eq!(read(Some(Cursor::After(Ulid(4)))), ids(5..=5));
+        assert_eq!(read(Some(Cursor::After(Ulid(5)))), vec![]);
+        assert_eq!(read(Some(Cursor::Before(Ulid(5)))), ids(3..=4));
+        assert_eq!(read(Some(Cursor::Before(Ulid(3)))), ids(1..=2));
+        assert_eq!(read(Some(Cursor::Before(Ulid(1)))), vec![]);
+    }
+
+    #[test]
+    fn read_descending_round_trip() {
+        let records = ids(1..=5);
+        let read =
+            |cursor| page(2, Key::Id, Order::Descending, cursor).read(records.clone(), |id| *id);
+
+        assert_eq!(read(None), vec![Ulid(5), Ulid(4)]);
+        assert_eq!(read(Some(Cursor::After(Ulid(4)))), vec![Ulid(3), Ulid(2)]);
+        assert_eq!(read(Some(Cursor::After(Ulid(2)))), vec![Ulid(1)]);
+        assert_eq!(read(Some(Cursor::Before(Ulid(1)))), vec![Ulid(3), Ulid(2)]);
+        assert_eq!(read(Some(Cursor::Before(Ulid(3)))), vec![Ulid(5), Ulid(4)]);
+    }
+
+    #[test]
+    fn before_cursor_reads_backwards() {
+        let ascending = page(2, Key::Id, Order::Ascending, Some(Cursor::Before(Ulid(3))));
+        let descending = page(2, Key::Id, Order::Descending, Some(Cursor::Before(Ulid(3))));
+
+        assert!(ascending.is_descending());
+        assert_eq!((ascending.operator(), ascending.direction()), ("<", "DESC"));
+        assert!(!descending.is_descending());
+        assert_eq!(
+            (descending.operator(), descending.direction()),
+            (">", "ASC")
+        );
+        assert_eq!(ascending.finish(vec![Ulid(2), Ulid(1)]), ids(1..=2));
+    }
+}
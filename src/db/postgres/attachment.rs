@@ -0,0 +1,177 @@
+use deadpool_postgres::Pool;
+use tokio_postgres::{types::Json, Row};
+use tonic::async_trait;
+
+use super::{attachment_columns, client, to_strings, RowExt};
+use crate::{
+    db::{error::RepositoryError, traits::attachment::AttachmentRepository},
+    models::{
+        attachment::{Attachment, AttachmentId, AttachmentMetadata},
+        user::UserId,
+    },
+};
+
+#[derive(Clone)]
+pub struct AttachmentRepositoryImpl {}
+
+impl AttachmentRepositoryImpl {
+    pub async fn new() -> Self {
+        AttachmentRepositoryImpl {}
+    }
+}
+
+/// the attachment selected with `attachment_columns(_, prefix)`.
+pub(super) fn from_row(
+    row: &Row,
+    prefix: &str,
+    id: AttachmentId,
+) -> Result<Attachment, RepositoryError> {
+    let column = |name: &str| format!("{prefix}_{name}");
+
+    Ok(Attachment {
+        id,
+        url: row.try_get(column("url").as_str())?,
+        filename: row.try_get(column("filename").as_str())?,
+        mime_type: row.try_get(column("mime_type").as_str())?,
+        file_size: row.try_get(column("file_size").as_str())?,
+        metadata: row
+            .try_get::<_, Option<Json<AttachmentMetadata>>>(column("metadata").as_str())?
+            .map(|Json(metadata)| metadata),
+        create_time: row.datetime(&column("create_time"))?,
+    })
+}
+
+#[async_trait]
+impl AttachmentRepository<Pool> for AttachmentRepositoryImpl {
+    async fn get_attachment(
+        &self,
+        db: &Pool,
+        id: &AttachmentId,
+    ) -> Result<Option<Attachment>, RepositoryError> {
+        let row = client(db)
+            .await?
+            .query_opt(
+                &format!(
+                    "SELECT {} FROM attachment a WHERE a.id = $1",
+                    attachment_columns("a", "attachment")
+                ),
+                &[&id.to_string()],
+            )
+            .await?;
+
+        match row {
+            Some(row) => row.attachment("attachment"),
+            None => Ok(None),
+        }
+    }
+
+    async fn add_attachment(
+        &self,
+        db: &Pool,
+        attachment: &Attachment,
+        uploader: &UserId,
+    ) -> Result<Option<Attachment>, RepositoryError> {
+        let mut client = client(db).await?;
+        let transaction = client.transaction().await?;
+
+        transaction
+            .execute(
+                "INSERT INTO attachment (id, url, filename, mime_type, file_size, metadata, create_time)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7)",
+                &[
+                    &attachment.id.to_string(),
+                    &attachment.url,
+                    &attachment.filename,
+                    &attachment.mime_type,
+                    &attachment.file_size,
+                    &attachment.metadata.as_ref().map(Json),
+                    &attachment.create_time.0,
+                ],
+            )
+            .await?;
+        transaction
+            .execute(
+                "INSERT INTO attachment_uploaded (attachment_id, user_id) VALUES ($1, $2)
+                    ON CONFLICT (attachment_id) DO UPDATE SET user_id = excluded.user_id",
+                &[&attachment.id.to_string(), &uploader.to_string()],
+            )
+            .await?;
+        transaction.commit().await?;
+
+        Ok(Some(attachment.clone()))
+    }
+
+    async fn delete_attachment(&self, db: &Pool, id: &AttachmentId) -> Result<u8, RepositoryError> {
+        let mut client = client(db).await?;
+        let transaction = client.transaction().await?;
+
+        transaction
+            .execute(
+                "DELETE FROM attachment_uploaded WHERE attachment_id = $1",
+                &[&id.to_string()],
+            )
+            .await?;
+        transaction
+            .execute("DELETE FROM attachment WHERE id = $1", &[&id.to_string()])
+            .await?;
+        transaction.commit().await?;
+
+        Ok(1)
+    }
+
+    async fn get_attachments(
+        &self,
+        db: &Pool,
+        ids: &[AttachmentId],
+    ) -> Result<Vec<Attachment>, RepositoryError> {
+        let rows = client(db)
+            .await?
+            .query(
+                &format!(
+                    "SELECT {} FROM attachment a WHERE a.id = ANY($1)",
+                    attachment_columns("a", "attachment")
+                ),
+                &[&to_strings(ids)],
+            )
+            .await?;
+
+        rows.iter()
+            .filter_map(|row| row.attachment("attachment").transpose())
+            .collect()
+    }
+
+    async fn is_referenced(&self, db: &Pool, id: &AttachmentId) -> Result<bool, RepositoryError> {
+        let row = client(db)
+            .await?
+            .query_one(
+                r#"SELECT EXISTS (SELECT 1 FROM "user" WHERE avatar = $1)
+                    OR EXISTS (SELECT 1 FROM server WHERE icon = $1)
+                    OR EXISTS (SELECT 1 FROM channel WHERE icon = $1)
+                    OR EXISTS (SELECT 1 FROM server_member WHERE avatar = $1)
+                    OR EXISTS (SELECT 1 FROM message WHERE $1 = ANY(attachments))"#,
+                &[&id.to_string()],
+            )
+            .await?;
+
+        Ok(row.try_get(0)?)
+    }
+
+    async fn get_uploader(
+        &self,
+        db: &Pool,
+        id: &AttachmentId,
+    ) -> Result<Option<UserId>, RepositoryError> {
+        let row = client(db)
+            .await?
+            .query_opt(
+                "SELECT user_id FROM attachment_uploaded WHERE attachment_id = $1",
+                &[&id.to_string()],
+            )
+            .await?;
+
+        match row {
+            Some(row) => row.ulid("user_id").map(Some),
+            None => Ok(None),
+        }
+    }
+}
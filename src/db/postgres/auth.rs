@@ -0,0 +1,114 @@
+use deadpool_postgres::Pool;
+use tokio_postgres::Row;
+use tonic::async_trait;
+
+use super::{client, to_timestamp, PostgresTransaction, RowExt, Statement};
+use crate::{
+    db::{error::RepositoryError, traits::auth::AuthRepository},
+    models::{auth::DbAuth, user::UserId},
+};
+
+#[derive(Clone)]
+pub struct AuthRepositoryImpl {}
+
+impl AuthRepositoryImpl {
+    pub async fn new() -> Self {
+        AuthRepositoryImpl {}
+    }
+}
+
+fn from_row(row: &Row) -> Result<DbAuth, RepositoryError> {
+    Ok(DbAuth {
+        id: row.ulid("id")?,
+        username: row.try_get("username")?,
+        password: row.try_get("password")?,
+        email: row.try_get("email")?,
+        is_email_verified: row.try_get("is_email_verified")?,
+        create_time: row.datetime("create_time")?,
+        update_time: row.datetime_option("update_time")?,
+        last_login_time: row.datetime_option("last_login_time")?,
+    })
+}
+
+/// the stored auth, emails are lowercased like `string::lowercase` in `migrations/surreal`.
+async fn upsert(db: &Pool, auth: &DbAuth, is_update: bool) -> Result<DbAuth, RepositoryError> {
+    let on_conflict = if is_update {
+        "ON CONFLICT (id) DO UPDATE SET username = excluded.username,
+            password = excluded.password, email = excluded.email,
+            is_email_verified = excluded.is_email_verified, create_time = excluded.create_time,
+            update_time = excluded.update_time, last_login_time = excluded.last_login_time"
+    } else {
+        ""
+    };
+    let auth = DbAuth {
+        email: auth.email.to_lowercase(),
+        ..auth.clone()
+    };
+
+    client(db)
+        .await?
+        .execute(
+            &format!(
+                "INSERT INTO auth (id, username, password, email, is_email_verified, create_time, update_time, last_login_time)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8) {on_conflict}"
+            ),
+            &[
+                &auth.id.to_string(),
+                &auth.username,
+                &auth.password,
+                &auth.email,
+                &auth.is_email_verified,
+                &auth.create_time.0,
+                &to_timestamp(&auth.update_time),
+                &to_timestamp(&auth.last_login_time),
+            ],
+        )
+        .await?;
+
+    Ok(auth)
+}
+
+#[async_trait]
+impl AuthRepository<Pool> for AuthRepositoryImpl {
+    async fn get(&self, db: &Pool, id: &UserId) -> Result<Option<DbAuth>, RepositoryError> {
+        client(db)
+            .await?
+            .query_opt("SELECT * FROM auth WHERE id = $1", &[&id.to_string()])
+            .await?
+            .as_ref()
+            .map(from_row)
+            .transpose()
+    }
+
+    async fn get_by_username(
+        &self,
+        db: &Pool,
+        username: &str,
+    ) -> Result<Option<DbAuth>, RepositoryError> {
+        client(db)
+            .await?
+            .query_opt("SELECT * FROM auth WHERE username = $1", &[&username])
+            .await?
+            .as_ref()
+            .map(from_row)
+            .transpose()
+    }
+
+    async fn add(&self, db: &Pool, auth: &DbAuth) -> Result<Option<DbAuth>, RepositoryError> {
+        upsert(db, auth, false).await.map(Some)
+    }
+
+    async fn update(&self, db: &Pool, auth: &DbAuth) -> Result<Option<DbAuth>, RepositoryError> {
+        upsert(db, auth, true).await.map(Some)
+    }
+
+    fn delete_in(
+        &self,
+        transaction: &mut PostgresTransaction,
+        id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        transaction.push(Statement::new("DELETE FROM auth WHERE id = $1").param(id.to_string()));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,401 @@
+use deadpool_postgres::Pool;
+use surrealdb::sql::Datetime;
+use tokio_postgres::Row;
+use tonic::async_trait;
+
+use super::{
+    attachment_columns, client, to_strings, to_timestamp, PostgresTransaction, RowExt, Statement,
+};
+use crate::{
+    db::{error::RepositoryError, filter::Filter, page::Page, traits::channel::ChannelRepository},
+    models::channel::{ChannelId, ChannelPosition, ChannelType, DbChannel},
+    models::server::ServerId,
+    models::server_category::{CategoryPosition, ServerCategoryId},
+    models::user::UserId,
+};
+
+#[derive(Clone)]
+pub struct ChannelRepositoryImpl {}
+
+impl ChannelRepositoryImpl {
+    pub async fn new() -> Self {
+        ChannelRepositoryImpl {}
+    }
+}
+
+fn select() -> String {
+    format!(
+        "SELECT c.*, {} FROM channel c LEFT JOIN attachment a ON a.id = c.icon",
+        attachment_columns("a", "icon")
+    )
+}
+
+/// the server channels of the server bound to `server` matching `condition`, in layout order.
+fn select_layout(server: &str, condition: &str) -> String {
+    format!(
+        r#"{} LEFT JOIN server_category sc ON sc.id = c.category
+            WHERE c.channel_type = 'SERVER' AND c.server = {} AND {}
+            ORDER BY sc."order" NULLS FIRST, c.category NULLS FIRST, c."order", c.id"#,
+        select(),
+        server,
+        condition
+    )
+}
+
+/// `channel_type`, `owner` and `server` columns of the channel type.
+fn channel_type_columns(
+    channel_type: &ChannelType,
+) -> (&'static str, Option<String>, Option<String>) {
+    match channel_type {
+        ChannelType::Saved { owner } => ("SAVED", Some(owner.to_string()), None),
+        ChannelType::Direct => ("DIRECT", None, None),
+        ChannelType::Group { owner } => ("GROUP", Some(owner.to_string()), None),
+        ChannelType::Server { server } => ("SERVER", None, Some(server.to_string())),
+    }
+}
+
+fn from_row(row: &Row) -> Result<DbChannel, RepositoryError> {
+    let channel_type = match row.try_get::<_, &str>("channel_type")? {
+        "SAVED" => ChannelType::Saved {
+            owner: row.ulid("owner")?,
+        },
+        "DIRECT" => ChannelType::Direct,
+        "GROUP" => ChannelType::Group {
+            owner: row.ulid("owner")?,
+        },
+        "SERVER" => ChannelType::Server {
+            server: row.ulid("server")?,
+        },
+        channel_type => {
+            return Err(RepositoryError::Internal(format!(
+                "unknown channel type `{}`.",
+                channel_type
+            )))
+        }
+    };
+
+    Ok(DbChannel {
+        id: row.ulid("id")?,
+        channel_type,
+        display_name: row.try_get("display_name")?,
+        description: row.try_get("description")?,
+        category: row.ulid_option("category")?,
+        order: row.try_get::<_, i64>("order")? as u64,
+        icon: row.attachment("icon")?,
+        members: row.ulid_list("members")?,
+        last_message_time: row.datetime_option("last_message_time")?,
+        create_time: row.datetime("create_time")?,
+        update_time: row.datetime_option("update_time")?,
+    })
+}
+
+async fn upsert(db: &Pool, channel: &DbChannel, is_update: bool) -> Result<(), RepositoryError> {
+    let on_conflict = if is_update {
+        r#"ON CONFLICT (id) DO UPDATE SET channel_type = excluded.channel_type,
+            owner = excluded.owner, server = excluded.server,
+            display_name = excluded.display_name, description = excluded.description,
+            icon = excluded.icon, "order" = excluded."order", category = excluded.category,
+            members = excluded.members, last_message_time = excluded.last_message_time,
+            create_time = excluded.create_time, update_time = excluded.update_time"#
+    } else {
+        ""
+    };
+    let (channel_type, owner, server) = channel_type_columns(&channel.channel_type);
+
+    client(db)
+        .await?
+        .execute(
+            &format!(
+                r#"INSERT INTO channel (id, channel_type, owner, server, display_name, description, icon, "order", category, members, last_message_time, create_time, update_time)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13) {on_conflict}"#
+            ),
+            &[
+                &channel.id.to_string(),
+                &channel_type,
+                &owner,
+                &server,
+                &channel.display_name,
+                &channel.description,
+                &channel.icon.as_ref().map(|icon| icon.id.to_string()),
+                &(channel.order as i64),
+                &channel.category.map(|category| category.to_string()),
+                &to_strings(&channel.members),
+                &to_timestamp(&channel.last_message_time),
+                &channel.create_time.0,
+                &to_timestamp(&channel.update_time),
+            ],
+        )
+        .await?;
+
+    Ok(())
+}
+
+#[async_trait]
+impl ChannelRepository<Pool> for ChannelRepositoryImpl {
+    async fn get(&self, db: &Pool, id: &ChannelId) -> Result<Option<DbChannel>, RepositoryError> {
+        client(db)
+            .await?
+            .query_opt(&format!("{} WHERE c.id = $1", select()), &[&id.to_string()])
+            .await?
+            .as_ref()
+            .map(from_row)
+            .transpose()
+    }
+
+    async fn get_list_by_server_id(
+        &self,
+        db: &Pool,
+        server_id: &ServerId,
+    ) -> Result<Vec<DbChannel>, RepositoryError> {
+        client(db)
+            .await?
+            .query(&select_layout("$1", "TRUE"), &[&server_id.to_string()])
+            .await?
+            .iter()
+            .map(from_row)
+            .collect()
+    }
+
+    async fn get_server_channels(
+        &self,
+        db: &Pool,
+        server_id: &ServerId,
+        filter: &Filter,
+        page: Page,
+    ) -> Result<Vec<DbChannel>, RepositoryError> {
+        let mut statement = Statement::new(String::new());
+        let server = statement.bind(server_id.to_string());
+        let condition = statement.filter(filter, "c");
+        statement.sql = select_layout(&server, &condition);
+
+        let channels = statement
+            .query(db)
+            .await?
+            .iter()
+            .map(from_row)
+            .collect::<Result<_, _>>()?;
+
+        Ok(page.cut(channels, |channel| channel.id))
+    }
+
+    async fn update_layout(
+        &self,
+        db: &Pool,
+        categories: &[CategoryPosition],
+        channels: &[ChannelPosition],
+    ) -> Result<(), RepositoryError> {
+        let mut client = client(db).await?;
+        let transaction = client.transaction().await?;
+
+        for position in categories {
+            transaction
+                .execute(
+                    r#"UPDATE server_category SET "order" = $2, update_time = now() WHERE id = $1"#,
+                    &[&position.id.to_string(), &i64::from(position.order)],
+                )
+                .await?;
+        }
+
+        for position in channels {
+            transaction
+                .execute(
+                    r#"UPDATE channel SET category = $2, "order" = $3, update_time = now() WHERE id = $1"#,
+                    &[
+                        &position.id.to_string(),
+                        &position.category.map(|category| category.to_string()),
+                        &(position.order as i64),
+                    ],
+                )
+                .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn get_channels_by_user_id(
+        &self,
+        db: &Pool,
+        user_id: &UserId,
+    ) -> Result<Vec<DbChannel>, RepositoryError> {
+        client(db)
+            .await?
+            .query(
+                &format!(
+                    r#"{} WHERE (c.channel_type = 'SAVED' AND c.owner = $1)
+                        OR (c.channel_type IN ('DIRECT', 'GROUP') AND $1 = ANY(c.members))
+                        OR (c.channel_type = 'SERVER'
+                            AND c.server IN (SELECT server FROM server_member WHERE "user" = $1))"#,
+                    select()
+                ),
+                &[&user_id.to_string()],
+            )
+            .await?
+            .iter()
+            .map(from_row)
+            .collect()
+    }
+
+    async fn get_direct_channel(
+        &self,
+        db: &Pool,
+        user_id: &UserId,
+        other_user_id: &UserId,
+    ) -> Result<Option<DbChannel>, RepositoryError> {
+        client(db)
+            .await?
+            .query_opt(
+                &format!(
+                    "{} WHERE c.channel_type = 'DIRECT' AND $1 = ANY(c.members) AND $2 = ANY(c.members)
+                        LIMIT 1",
+                    select()
+                ),
+                &[&user_id.to_string(), &other_user_id.to_string()],
+            )
+            .await?
+            .as_ref()
+            .map(from_row)
+            .transpose()
+    }
+
+    async fn get_direct_channels_by_user_id(
+        &self,
+        db: &Pool,
+        user_id: &UserId,
+    ) -> Result<Vec<DbChannel>, RepositoryError> {
+        client(db)
+            .await?
+            .query(
+                &format!(
+                    "{} WHERE c.channel_type IN ('DIRECT', 'GROUP') AND $1 = ANY(c.members)
+                        ORDER BY COALESCE(c.last_message_time, c.create_time) DESC",
+                    select()
+                ),
+                &[&user_id.to_string()],
+            )
+            .await?
+            .iter()
+            .map(from_row)
+            .collect()
+    }
+
+    async fn update_last_message_time(
+        &self,
+        db: &Pool,
+        id: &ChannelId,
+        last_message_time: &Datetime,
+    ) -> Result<(), RepositoryError> {
+        client(db)
+            .await?
+            .execute(
+                "UPDATE channel SET last_message_time = $2 WHERE id = $1",
+                &[&id.to_string(), &last_message_time.0],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn add(
+        &self,
+        db: &Pool,
+        channel: &DbChannel,
+    ) -> Result<Option<DbChannel>, RepositoryError> {
+        upsert(db, channel, false).await?;
+
+        Ok(Some(channel.clone()))
+    }
+
+    async fn update(
+        &self,
+        db: &Pool,
+        channel: &DbChannel,
+    ) -> Result<Option<DbChannel>, RepositoryError> {
+        upsert(db, channel, true).await?;
+
+        Ok(Some(channel.clone()))
+    }
+
+    async fn clear_category(
+        &self,
+        db: &Pool,
+        category_id: &ServerCategoryId,
+    ) -> Result<(), RepositoryError> {
+        client(db)
+            .await?
+            .execute(
+                "UPDATE channel SET category = NULL, update_time = now() WHERE category = $1",
+                &[&category_id.to_string()],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    fn delete_in(
+        &self,
+        transaction: &mut PostgresTransaction,
+        id: &ChannelId,
+    ) -> Result<(), RepositoryError> {
+        transaction.push(Statement::new("DELETE FROM channel WHERE id = $1").param(id.to_string()));
+
+        Ok(())
+    }
+
+    fn delete_by_server_id_in(
+        &self,
+        transaction: &mut PostgresTransaction,
+        server_id: &ServerId,
+    ) -> Result<(), RepositoryError> {
+        transaction.push(
+            Statement::new("DELETE FROM channel WHERE server = $1").param(server_id.to_string()),
+        );
+
+        Ok(())
+    }
+
+    fn remove_member_in(
+        &self,
+        transaction: &mut PostgresTransaction,
+        user_id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        transaction.push(
+            Statement::new(
+                "UPDATE channel SET members = array_remove(members, $1) WHERE $1 = ANY(members)",
+            )
+            .param(user_id.to_string()),
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INITIAL: &str = include_str!("../../../migrations/postgres/0001_initial.sql");
+
+    #[test]
+    fn channel_types_are_stored_by_the_names_the_schema_allows() {
+        let check = INITIAL
+            .lines()
+            .find(|line| line.trim_start().starts_with("channel_type "))
+            .unwrap();
+
+        let (user, server) = (UserId::new(), ServerId::new());
+        for (channel_type, owner, parent) in [
+            (ChannelType::Saved { owner: user }, Some(user), None),
+            (ChannelType::Direct, None, None),
+            (ChannelType::Group { owner: user }, Some(user), None),
+            (ChannelType::Server { server }, None, Some(server)),
+        ] {
+            let (name, owner_column, server_column) = channel_type_columns(&channel_type);
+
+            assert!(check.contains(&format!("'{name}'")), "{name}");
+            assert_eq!(owner_column, owner.map(|owner| owner.to_string()));
+            assert_eq!(server_column, parent.map(|server| server.to_string()));
+        }
+    }
+}
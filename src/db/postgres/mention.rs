@@ -0,0 +1,137 @@
+use deadpool_postgres::Pool;
+use tokio_postgres::Row;
+use tonic::async_trait;
+
+use super::{client, cursor, limit, paged, PostgresTransaction, RowExt, Statement};
+use crate::{
+    db::{error::RepositoryError, page::Page, traits::mention::MentionRepository},
+    models::{channel::ChannelId, mention::DbMention, message::MessageId, user::UserId},
+};
+
+#[derive(Clone)]
+pub struct MentionRepositoryImpl {}
+
+impl MentionRepositoryImpl {
+    pub async fn new() -> Self {
+        MentionRepositoryImpl {}
+    }
+}
+
+fn from_row(row: &Row) -> Result<DbMention, RepositoryError> {
+    Ok(DbMention {
+        id: row.ulid("id")?,
+        user: row.ulid("user")?,
+        message: row.ulid("message")?,
+        channel: row.ulid("channel")?,
+        create_time: row.datetime("create_time")?,
+    })
+}
+
+#[async_trait]
+impl MentionRepository<Pool> for MentionRepositoryImpl {
+    async fn add_mentions(&self, db: &Pool, mentions: &[DbMention]) -> Result<(), RepositoryError> {
+        let mut client = client(db).await?;
+        let transaction = client.transaction().await?;
+
+        for mention in mentions {
+            transaction
+                .execute(
+                    r#"INSERT INTO mention (id, "user", message, channel, create_time)
+                        VALUES ($1, $2, $3, $4, $5)"#,
+                    &[
+                        &mention.id.to_string(),
+                        &mention.user.to_string(),
+                        &mention.message.to_string(),
+                        &mention.channel.to_string(),
+                        &mention.create_time.0,
+                    ],
+                )
+                .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn get_list_by_user_id(
+        &self,
+        db: &Pool,
+        user_id: &UserId,
+        page: Page,
+    ) -> Result<Vec<DbMention>, RepositoryError> {
+        client(db)
+            .await?
+            .query(
+                &format!(
+                    r#"SELECT * FROM mention WHERE "user" = $1 AND {}"#,
+                    paged("id", 2, &page)
+                ),
+                &[&user_id.to_string(), &cursor(&page), &limit(&page)],
+            )
+            .await?
+            .iter()
+            .map(from_row)
+            .collect::<Result<_, _>>()
+            .map(|records| page.finish(records))
+    }
+
+    async fn count_by_user_id_and_channel_id(
+        &self,
+        db: &Pool,
+        user_id: &UserId,
+        channel_id: &ChannelId,
+        after: Option<MessageId>,
+    ) -> Result<u64, RepositoryError> {
+        let row = client(db)
+            .await?
+            .query_one(
+                r#"SELECT count(*) FROM mention WHERE "user" = $1 AND channel = $2
+                    AND ($3::text IS NULL OR message > $3)"#,
+                &[
+                    &user_id.to_string(),
+                    &channel_id.to_string(),
+                    &after.map(|id| id.to_string()),
+                ],
+            )
+            .await?;
+
+        Ok(row.try_get::<_, i64>(0)? as u64)
+    }
+
+    fn delete_by_channel_id_in(
+        &self,
+        transaction: &mut PostgresTransaction,
+        channel_id: &ChannelId,
+    ) -> Result<(), RepositoryError> {
+        transaction.push(
+            Statement::new("DELETE FROM mention WHERE channel = $1").param(channel_id.to_string()),
+        );
+
+        Ok(())
+    }
+
+    fn delete_by_message_id_in(
+        &self,
+        transaction: &mut PostgresTransaction,
+        message_id: &MessageId,
+    ) -> Result<(), RepositoryError> {
+        transaction.push(
+            Statement::new("DELETE FROM mention WHERE message = $1").param(message_id.to_string()),
+        );
+
+        Ok(())
+    }
+
+    fn delete_by_user_id_in(
+        &self,
+        transaction: &mut PostgresTransaction,
+        user_id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        transaction.push(
+            Statement::new(r#"DELETE FROM mention WHERE "user" = $1"#).param(user_id.to_string()),
+        );
+
+        Ok(())
+    }
+}
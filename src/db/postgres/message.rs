@@ -0,0 +1,298 @@
+use deadpool_postgres::Pool;
+use tokio_postgres::Row;
+use tonic::async_trait;
+
+use super::{client, to_strings, to_timestamp, PostgresTransaction, RowExt, Statement};
+use crate::{
+    db::{error::RepositoryError, filter::Filter, page::Page, traits::message::MessageRepository},
+    models::{
+        channel::ChannelId,
+        mention::Mentions,
+        message::{DbMessage, MessageId, MessageType},
+        user::UserId,
+    },
+};
+
+#[derive(Clone)]
+pub struct MessageRepositoryImpl {}
+
+impl MessageRepositoryImpl {
+    pub async fn new() -> Self {
+        MessageRepositoryImpl {}
+    }
+}
+
+/// the serde name of the message type, `message_type` is stored like SurrealDB stores it.
+fn message_type_name(message_type: MessageType) -> Result<String, RepositoryError> {
+    match serde_json::to_value(message_type) {
+        Ok(serde_json::Value::String(name)) => Ok(name),
+        _ => Err(RepositoryError::Internal(format!(
+            "message type {:?} has no name.",
+            message_type
+        ))),
+    }
+}
+
+fn from_row(row: &Row) -> Result<DbMessage, RepositoryError> {
+    let message_type = row.try_get::<_, String>("message_type")?;
+
+    Ok(DbMessage {
+        id: row.ulid("id")?,
+        author: row.ulid("author")?,
+        channel: row.ulid("channel")?,
+        content: row.try_get("content")?,
+        message_type: serde_json::from_value(serde_json::Value::String(message_type))
+            .map_err(|err| RepositoryError::Internal(err.to_string()))?,
+        reference: row.ulid_option("reference")?,
+        attachments: row.ulid_list("attachments")?,
+        mentions: Mentions {
+            users: row.ulid_list("mention_users")?,
+            roles: row.ulid_list("mention_roles")?,
+            channels: row.ulid_list("mention_channels")?,
+            everyone: row.try_get("mention_everyone")?,
+            here: row.try_get("mention_here")?,
+        },
+        pinned_by: row.ulid_option("pinned_by")?,
+        pin_time: row.datetime_option("pin_time")?,
+        create_time: row.datetime("create_time")?,
+        update_time: row.datetime_option("update_time")?,
+    })
+}
+
+async fn upsert(db: &Pool, message: &DbMessage, is_update: bool) -> Result<(), RepositoryError> {
+    let on_conflict = if is_update {
+        "ON CONFLICT (id) DO UPDATE SET author = excluded.author, channel = excluded.channel,
+            content = excluded.content, message_type = excluded.message_type,
+            reference = excluded.reference, attachments = excluded.attachments,
+            mention_users = excluded.mention_users, mention_roles = excluded.mention_roles,
+            mention_channels = excluded.mention_channels,
+            mention_everyone = excluded.mention_everyone, mention_here = excluded.mention_here,
+            pinned_by = excluded.pinned_by, pin_time = excluded.pin_time,
+            create_time = excluded.create_time, update_time = excluded.update_time"
+    } else {
+        ""
+    };
+
+    client(db)
+        .await?
+        .execute(
+            &format!(
+                "INSERT INTO message (id, author, channel, content, message_type, reference, attachments, mention_users, mention_roles, mention_channels, mention_everyone, mention_here, pinned_by, pin_time, create_time, update_time)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16) {on_conflict}"
+            ),
+            &[
+                &message.id.to_string(),
+                &message.author.to_string(),
+                &message.channel.to_string(),
+                &message.content,
+                &message_type_name(message.message_type)?,
+                &message.reference.map(|reference| reference.to_string()),
+                &to_strings(&message.attachments),
+                &to_strings(&message.mentions.users),
+                &to_strings(&message.mentions.roles),
+                &to_strings(&message.mentions.channels),
+                &message.mentions.everyone,
+                &message.mentions.here,
+                &message.pinned_by.map(|pinned_by| pinned_by.to_string()),
+                &to_timestamp(&message.pin_time),
+                &message.create_time.0,
+                &to_timestamp(&message.update_time),
+            ],
+        )
+        .await?;
+
+    Ok(())
+}
+
+#[async_trait]
+impl MessageRepository<Pool> for MessageRepositoryImpl {
+    async fn get(&self, db: &Pool, id: &MessageId) -> Result<Option<DbMessage>, RepositoryError> {
+        client(db)
+            .await?
+            .query_opt("SELECT * FROM message WHERE id = $1", &[&id.to_string()])
+            .await?
+            .as_ref()
+            .map(from_row)
+            .transpose()
+    }
+
+    async fn add(
+        &self,
+        db: &Pool,
+        message: &DbMessage,
+    ) -> Result<Option<DbMessage>, RepositoryError> {
+        upsert(db, message, false).await?;
+
+        Ok(Some(message.clone()))
+    }
+
+    async fn get_list_by_ids(
+        &self,
+        db: &Pool,
+        ids: &[MessageId],
+    ) -> Result<Vec<DbMessage>, RepositoryError> {
+        client(db)
+            .await?
+            .query(
+                "SELECT * FROM message WHERE id = ANY($1)",
+                &[&to_strings(ids)],
+            )
+            .await?
+            .iter()
+            .map(from_row)
+            .collect()
+    }
+
+    async fn update(
+        &self,
+        db: &Pool,
+        message: &DbMessage,
+    ) -> Result<Option<DbMessage>, RepositoryError> {
+        upsert(db, message, true).await?;
+
+        Ok(Some(message.clone()))
+    }
+
+    async fn get_list_by_chnanel_id(
+        &self,
+        db: &Pool,
+        channel_id: &ChannelId,
+        filter: &Filter,
+        page: Page,
+    ) -> Result<Vec<DbMessage>, RepositoryError> {
+        let mut statement = Statement::new(String::new());
+        let channel = statement.bind(channel_id.to_string());
+        let condition = statement.filter(filter, "m");
+        let paged = statement.page("m.id", &page);
+        statement.sql = format!(
+            "SELECT * FROM message m WHERE m.channel = {} AND {} AND {}",
+            channel, condition, paged
+        );
+
+        statement
+            .query(db)
+            .await?
+            .iter()
+            .map(from_row)
+            .collect::<Result<_, _>>()
+            .map(|records| page.finish(records))
+    }
+
+    async fn get_pinned_list_by_channel_id(
+        &self,
+        db: &Pool,
+        channel_id: &ChannelId,
+    ) -> Result<Vec<DbMessage>, RepositoryError> {
+        client(db)
+            .await?
+            .query(
+                "SELECT * FROM message WHERE channel = $1 AND pin_time IS NOT NULL
+                    ORDER BY pin_time DESC",
+                &[&channel_id.to_string()],
+            )
+            .await?
+            .iter()
+            .map(from_row)
+            .collect()
+    }
+
+    async fn count_unread(
+        &self,
+        db: &Pool,
+        channel_id: &ChannelId,
+        user_id: &UserId,
+        after: Option<MessageId>,
+    ) -> Result<u64, RepositoryError> {
+        let row = client(db)
+            .await?
+            .query_one(
+                "SELECT count(*) FROM message WHERE channel = $1 AND author <> $2
+                    AND ($3::text IS NULL OR id > $3)",
+                &[
+                    &channel_id.to_string(),
+                    &user_id.to_string(),
+                    &after.map(|id| id.to_string()),
+                ],
+            )
+            .await?;
+
+        Ok(row.try_get::<_, i64>(0)? as u64)
+    }
+
+    async fn get_list_by_channel_id_in_range(
+        &self,
+        db: &Pool,
+        channel_id: &ChannelId,
+        start_id: &MessageId,
+        end_id: &MessageId,
+        limit: i32,
+    ) -> Result<Vec<DbMessage>, RepositoryError> {
+        client(db)
+            .await?
+            .query(
+                "SELECT * FROM message WHERE channel = $1 AND id >= $2 AND id <= $3
+                    ORDER BY id DESC LIMIT $4",
+                &[
+                    &channel_id.to_string(),
+                    &start_id.to_string(),
+                    &end_id.to_string(),
+                    &i64::from(limit.max(0)),
+                ],
+            )
+            .await?
+            .iter()
+            .map(from_row)
+            .collect()
+    }
+
+    fn delete_in(
+        &self,
+        transaction: &mut PostgresTransaction,
+        id: &MessageId,
+    ) -> Result<(), RepositoryError> {
+        transaction.push(Statement::new("DELETE FROM message WHERE id = $1").param(id.to_string()));
+
+        Ok(())
+    }
+
+    fn delete_by_channel_id_in(
+        &self,
+        transaction: &mut PostgresTransaction,
+        channel_id: &ChannelId,
+    ) -> Result<(), RepositoryError> {
+        transaction.push(
+            Statement::new("DELETE FROM message WHERE channel = $1").param(channel_id.to_string()),
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INITIAL: &str = include_str!("../../../migrations/postgres/0001_initial.sql");
+
+    #[test]
+    fn message_types_are_stored_by_the_names_the_schema_allows() {
+        let check = INITIAL
+            .lines()
+            .find(|line| line.trim_start().starts_with("message_type "))
+            .unwrap();
+
+        for message_type in [
+            MessageType::Default,
+            MessageType::ChannelPinnedMessage,
+            MessageType::GroupMemberAdded,
+            MessageType::GroupMemberRemoved,
+            MessageType::GroupMemberLeft,
+        ] {
+            let name = message_type_name(message_type).unwrap();
+            assert!(check.contains(&format!("'{name}'")), "{name}");
+
+            let read = serde_json::from_value::<MessageType>(serde_json::Value::String(name));
+            assert_eq!(read.unwrap(), message_type);
+        }
+    }
+}
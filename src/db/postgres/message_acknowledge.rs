@@ -0,0 +1,149 @@
+use deadpool_postgres::Pool;
+use tokio_postgres::Row;
+use tonic::async_trait;
+
+use super::{client, cursor, limit, paged, to_strings, PostgresTransaction, RowExt, Statement};
+use crate::{
+    db::{
+        error::RepositoryError, page::Page,
+        traits::message_acknowledge::MessageAcknowledgeRepository,
+    },
+    models::{
+        channel::ChannelId, message::MessageId, message_acknowledge::DbMessageAcknowledge,
+        user::UserId,
+    },
+};
+
+#[derive(Clone)]
+pub struct MessageAcknowledgeRepositoryImpl {}
+
+impl MessageAcknowledgeRepositoryImpl {
+    pub async fn new() -> Self {
+        MessageAcknowledgeRepositoryImpl {}
+    }
+}
+
+const INSERT: &str = "INSERT INTO message_acknowledge (id, message_id, user_id, create_time)
+    VALUES ($1, $2, $3, $4)";
+
+fn from_row(row: &Row) -> Result<DbMessageAcknowledge, RepositoryError> {
+    Ok(DbMessageAcknowledge {
+        id: row.ulid("id")?,
+        message_id: row.ulid("message_id")?,
+        user_id: row.ulid("user_id")?,
+        create_time: row.datetime("create_time")?,
+    })
+}
+
+#[async_trait]
+impl MessageAcknowledgeRepository<Pool> for MessageAcknowledgeRepositoryImpl {
+    async fn get_list_by_message(
+        &self,
+        db: &Pool,
+        message_id: &MessageId,
+        page: Page,
+    ) -> Result<Vec<DbMessageAcknowledge>, RepositoryError> {
+        client(db)
+            .await?
+            .query(
+                &format!(
+                    "SELECT * FROM message_acknowledge WHERE message_id = $1 AND {}",
+                    paged("id", 2, &page)
+                ),
+                &[&message_id.to_string(), &cursor(&page), &limit(&page)],
+            )
+            .await?
+            .iter()
+            .map(from_row)
+            .collect::<Result<_, _>>()
+            .map(|records| page.finish(records))
+    }
+
+    async fn add_list(
+        &self,
+        db: &Pool,
+        message_acknowledges: &[DbMessageAcknowledge],
+    ) -> Result<(), RepositoryError> {
+        // like a single `INSERT`, nothing is written when one of them fails.
+        let mut client = client(db).await?;
+        let transaction = client.transaction().await?;
+
+        for message_acknowledge in message_acknowledges {
+            transaction
+                .execute(
+                    INSERT,
+                    &[
+                        &message_acknowledge.id.to_string(),
+                        &message_acknowledge.message_id.to_string(),
+                        &message_acknowledge.user_id.to_string(),
+                        &message_acknowledge.create_time.0,
+                    ],
+                )
+                .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn get_list_by_user_and_messages(
+        &self,
+        db: &Pool,
+        user_id: &UserId,
+        message_ids: &[MessageId],
+    ) -> Result<Vec<DbMessageAcknowledge>, RepositoryError> {
+        client(db)
+            .await?
+            .query(
+                "SELECT * FROM message_acknowledge WHERE user_id = $1 AND message_id = ANY($2)",
+                &[&user_id.to_string(), &to_strings(message_ids)],
+            )
+            .await?
+            .iter()
+            .map(from_row)
+            .collect()
+    }
+
+    fn delete_by_channel_id_in(
+        &self,
+        transaction: &mut PostgresTransaction,
+        channel_id: &ChannelId,
+    ) -> Result<(), RepositoryError> {
+        transaction.push(
+            Statement::new(
+                "DELETE FROM message_acknowledge
+                WHERE message_id IN (SELECT id FROM message WHERE channel = $1)",
+            )
+            .param(channel_id.to_string()),
+        );
+
+        Ok(())
+    }
+
+    fn delete_by_message_id_in(
+        &self,
+        transaction: &mut PostgresTransaction,
+        message_id: &MessageId,
+    ) -> Result<(), RepositoryError> {
+        transaction.push(
+            Statement::new("DELETE FROM message_acknowledge WHERE message_id = $1")
+                .param(message_id.to_string()),
+        );
+
+        Ok(())
+    }
+
+    fn delete_by_user_id_in(
+        &self,
+        transaction: &mut PostgresTransaction,
+        user_id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        transaction.push(
+            Statement::new("DELETE FROM message_acknowledge WHERE user_id = $1")
+                .param(user_id.to_string()),
+        );
+
+        Ok(())
+    }
+}
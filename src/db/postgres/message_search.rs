@@ -0,0 +1,109 @@
+use deadpool_postgres::Pool;
+use tonic::async_trait;
+
+use super::{client, cursor, limit, paged, to_strings, to_timestamp, RowExt};
+use crate::{
+    db::{error::RepositoryError, page::Page, traits::message_search::MessageSearchIndex},
+    models::{
+        message::{DbMessage, MessageId},
+        message_search::{MessageSearchHit, MessageSearchQuery, HIGHLIGHT_END, HIGHLIGHT_START},
+    },
+};
+
+/// the `message_content_search` GIN index of `migrations/postgres`, kept up to date by
+/// PostgreSQL. words match by prefix like the `edgengram` filter of `message_analyzer` in
+/// `migrations/surreal`.
+#[derive(Clone)]
+pub struct MessageSearchIndexImpl {}
+
+impl MessageSearchIndexImpl {
+    pub async fn new() -> Self {
+        MessageSearchIndexImpl {}
+    }
+}
+
+/// `tsquery` matching messages with every word of `query` as a prefix, empty when there are no
+/// words. only letters and digits are kept so the words can't carry `tsquery` operators.
+fn ts_query(query: &str) -> String {
+    query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(|term| format!("{}:*", term.to_lowercase()))
+        .collect::<Vec<String>>()
+        .join(" & ")
+}
+
+#[async_trait]
+impl MessageSearchIndex<Pool> for MessageSearchIndexImpl {
+    async fn index_message(&self, _db: &Pool, _message: &DbMessage) -> Result<(), RepositoryError> {
+        Ok(())
+    }
+
+    async fn remove_message(&self, _db: &Pool, _id: &MessageId) -> Result<(), RepositoryError> {
+        Ok(())
+    }
+
+    async fn search(
+        &self,
+        db: &Pool,
+        query: &MessageSearchQuery,
+        page: Page,
+    ) -> Result<Vec<MessageSearchHit>, RepositoryError> {
+        let rows = client(db)
+            .await?
+            .query(
+                &format!(
+                    "SELECT id, CASE WHEN $1 = '' THEN content
+                        ELSE ts_headline('simple', content, to_tsquery('simple', $1),
+                            'StartSel={HIGHLIGHT_START}, StopSel={HIGHLIGHT_END}, HighlightAll=true')
+                        END AS highlighted
+                    FROM message
+                    WHERE channel = ANY($2)
+                        AND ($1 = '' OR to_tsvector('simple', content) @@ to_tsquery('simple', $1))
+                        AND ($3::text IS NULL OR author = $3)
+                        AND ($4::text IS NULL OR $4 = ANY(mention_users))
+                        AND ($5::bool IS NULL OR (cardinality(attachments) > 0) = $5)
+                        AND ($6::timestamptz IS NULL OR create_time >= $6)
+                        AND ($7::timestamptz IS NULL OR create_time < $7)
+                        AND ($8::bool IS NULL OR (pin_time IS NOT NULL) = $8)
+                        AND {}",
+                    paged("id", 9, &page)
+                ),
+                &[
+                    &ts_query(&query.query),
+                    &to_strings(&query.channels),
+                    &query.author.map(|author| author.to_string()),
+                    &query.mentions.map(|user| user.to_string()),
+                    &query.has_attachment,
+                    &to_timestamp(&query.start_time),
+                    &to_timestamp(&query.end_time),
+                    &query.pinned,
+                    &cursor(&page),
+                    &limit(&page),
+                ],
+            )
+            .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(MessageSearchHit {
+                    id: row.ulid("id")?,
+                    highlighted: row.try_get("highlighted")?,
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(|hits| page.finish(hits))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ts_query_keeps_only_words() {
+        assert_eq!(ts_query("Hello World"), "hello:* & world:*");
+        assert_eq!(ts_query("a & !b | c:* <-> (d)"), "a:* & b:* & c:* & d:*");
+        assert_eq!(ts_query("  !&| "), "");
+    }
+}
@@ -0,0 +1,442 @@
+pub mod attachment;
+pub mod auth;
+pub mod channel;
+pub mod mention;
+pub mod message;
+pub mod message_acknowledge;
+pub mod message_search;
+pub mod read_state;
+pub mod schema_version;
+pub mod server;
+pub mod server_category;
+pub mod server_member;
+pub mod user;
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use deadpool_postgres::{Object, Pool, PoolConfig, Runtime};
+use serde::Deserialize;
+use surrealdb::sql::Datetime;
+use tokio_postgres::{types::ToSql, NoTls, Row};
+use tonic::async_trait;
+use ulid::Ulid;
+
+use crate::{
+    db::{
+        error::RepositoryError,
+        filter::{Filter, Operator, Value},
+        migration::Migration,
+        page::Page,
+        Database, Repositories,
+    },
+    models::attachment::Attachment,
+};
+
+/// `migrations/postgres`, in order.
+pub const MIGRATIONS: &[Migration] = &[Migration::new(
+    1,
+    "initial",
+    include_str!("../../../migrations/postgres/0001_initial.sql"),
+)];
+
+/// delay before the first reconnect, doubled on every failed attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PostgresConfig {
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    pub password: String,
+    pub dbname: String,
+    /// connections kept open, every in-flight query holds one.
+    pub pool_size: usize,
+    /// attempts before startup gives up, 0 retries forever.
+    pub connect_attempts: u32,
+}
+
+impl Default for PostgresConfig {
+    fn default() -> Self {
+        PostgresConfig {
+            host: "127.0.0.1".to_string(),
+            port: 5432,
+            user: String::new(),
+            password: String::new(),
+            dbname: "ycchat".to_string(),
+            pool_size: 16,
+            connect_attempts: 10,
+        }
+    }
+}
+
+pub type PostgresRepositories = Repositories<
+    auth::AuthRepositoryImpl,
+    user::UserRepositoryImpl,
+    server::ServerRepositoryImpl,
+    server_category::ServerCategoryRepositoryImpl,
+    server_member::ServerMemberRepositoryImpl,
+    channel::ChannelRepositoryImpl,
+    message::MessageRepositoryImpl,
+    message_acknowledge::MessageAcknowledgeRepositoryImpl,
+    attachment::AttachmentRepositoryImpl,
+    mention::MentionRepositoryImpl,
+    message_search::MessageSearchIndexImpl,
+    read_state::ReadStateRepositoryImpl,
+>;
+
+/// the repositories of a PostgreSQL connection pool.
+pub async fn repositories() -> PostgresRepositories {
+    Repositories {
+        auth: auth::AuthRepositoryImpl::new().await,
+        user: user::UserRepositoryImpl::new().await,
+        server: server::ServerRepositoryImpl::new().await,
+        server_category: server_category::ServerCategoryRepositoryImpl::new().await,
+        server_member: server_member::ServerMemberRepositoryImpl::new().await,
+        channel: channel::ChannelRepositoryImpl::new().await,
+        message: message::MessageRepositoryImpl::new().await,
+        message_acknowledge: message_acknowledge::MessageAcknowledgeRepositoryImpl::new().await,
+        attachment: attachment::AttachmentRepositoryImpl::new().await,
+        mention: mention::MentionRepositoryImpl::new().await,
+        message_search: message_search::MessageSearchIndexImpl::new().await,
+        read_state: read_state::ReadStateRepositoryImpl::new().await,
+    }
+}
+
+/// opens the pool shared by every repository. connections are replaced when they break, so the
+/// pool is created once.
+pub async fn connect(config: &PostgresConfig) -> Result<Pool, RepositoryError> {
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 1;
+
+    loop {
+        match try_connect(config).await {
+            Ok(db) => return Ok(db),
+            Err(err) if config.connect_attempts == 0 || attempt < config.connect_attempts => {
+                eprintln!(
+                    "failed to connect to postgres at {}:{} (attempt {}), retrying in {:?}: {}",
+                    config.host, config.port, attempt, backoff, err
+                );
+
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+async fn try_connect(config: &PostgresConfig) -> Result<Pool, RepositoryError> {
+    let pool_config = deadpool_postgres::Config {
+        host: Some(config.host.clone()),
+        port: Some(config.port),
+        user: Some(config.user.clone()),
+        password: Some(config.password.clone()),
+        dbname: Some(config.dbname.clone()),
+        pool: Some(PoolConfig::new(config.pool_size)),
+        ..Default::default()
+    };
+
+    let pool = pool_config
+        .create_pool(Some(Runtime::Tokio1), NoTls)
+        .map_err(|err| RepositoryError::Internal(err.to_string()))?;
+
+    // connections are opened lazily, check the server is reachable before serving.
+    let _ = client(&pool).await?;
+
+    Ok(pool)
+}
+
+async fn client(db: &Pool) -> Result<Object, RepositoryError> {
+    db.get().await.map_err(RepositoryError::from)
+}
+
+/// a statement with owned parameters. writes are run right away or queued in a
+/// `PostgresTransaction`, reads may write their SQL as they bind its parameters.
+pub struct Statement {
+    sql: String,
+    params: Vec<Box<dyn ToSql + Sync + Send>>,
+}
+
+impl Statement {
+    fn new(sql: impl Into<String>) -> Self {
+        Statement {
+            sql: sql.into(),
+            params: Vec::new(),
+        }
+    }
+
+    fn param(mut self, param: impl ToSql + Sync + Send + 'static) -> Self {
+        self.params.push(Box::new(param));
+        self
+    }
+
+    fn params(&self) -> Vec<&(dyn ToSql + Sync)> {
+        self.params
+            .iter()
+            .map(|param| param.as_ref() as &(dyn ToSql + Sync))
+            .collect()
+    }
+
+    /// binds `param`, returning its placeholder.
+    fn bind(&mut self, param: impl ToSql + Sync + Send + 'static) -> String {
+        self.params.push(Box::new(param));
+        format!("${}", self.params.len())
+    }
+
+    /// `filter` as a condition on the columns of the table aliased `alias`, which are named
+    /// after the fields.
+    fn filter(&mut self, filter: &Filter, alias: &str) -> String {
+        match filter {
+            // `AND` of nothing holds, `OR` of nothing doesn't.
+            Filter::And(filters) if filters.is_empty() => "TRUE".to_string(),
+            Filter::Or(filters) if filters.is_empty() => "FALSE".to_string(),
+            Filter::And(filters) => self.join(filters, alias, " AND "),
+            Filter::Or(filters) => self.join(filters, alias, " OR "),
+            Filter::Not(filter) => format!("NOT ({})", self.filter(filter, alias)),
+            Filter::Compare {
+                field,
+                operator,
+                value,
+            } => {
+                let column = format!(r#"{alias}."{field}""#);
+                let value = match value {
+                    Value::Text(text) => self.bind(text.clone()),
+                    Value::Time(time) => self.bind(*time),
+                    Value::User(id) => self.bind(id.to_string()),
+                };
+
+                // a comparison with a missing value is false rather than NULL, so `NOT` of it
+                // holds, as the other backends filter.
+                match operator {
+                    Operator::Has => {
+                        format!("COALESCE(strpos(lower({column}), lower({value})) > 0, false)")
+                    }
+                    operator => format!("COALESCE({column} {} {value}, false)", operator.symbol()),
+                }
+            }
+        }
+    }
+
+    fn join(&mut self, filters: &[Filter], alias: &str, separator: &str) -> String {
+        filters
+            .iter()
+            .map(|filter| format!("({})", self.filter(filter, alias)))
+            .collect::<Vec<String>>()
+            .join(separator)
+    }
+
+    /// `paged` on `column`, binding the cursor and limit of `page`.
+    fn page(&mut self, column: &str, page: &Page) -> String {
+        let n = self.params.len() + 1;
+        self.params.push(Box::new(cursor(page)));
+        self.params.push(Box::new(limit(page)));
+
+        paged(column, n, page)
+    }
+
+    async fn execute(&self, db: &Pool) -> Result<u64, RepositoryError> {
+        Ok(client(db).await?.execute(&self.sql, &self.params()).await?)
+    }
+
+    async fn query(&self, db: &Pool) -> Result<Vec<Row>, RepositoryError> {
+        Ok(client(db).await?.query(&self.sql, &self.params()).await?)
+    }
+}
+
+/// statements run in order in one transaction.
+#[derive(Default)]
+pub struct PostgresTransaction {
+    statements: Vec<Statement>,
+}
+
+impl PostgresTransaction {
+    fn push(&mut self, statement: Statement) {
+        self.statements.push(statement);
+    }
+}
+
+#[async_trait]
+impl Database for Pool {
+    type Transaction = PostgresTransaction;
+
+    async fn commit(&self, transaction: PostgresTransaction) -> Result<(), RepositoryError> {
+        let mut client = client(self).await?;
+        let tx = client.transaction().await?;
+
+        for statement in &transaction.statements {
+            tx.execute(&statement.sql, &statement.params()).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
+
+fn to_ulid(id: &str) -> Result<Ulid, RepositoryError> {
+    Ulid::from_string(id).map_err(|err| RepositoryError::Internal(format!("id `{}`: {}", id, err)))
+}
+
+fn to_strings(ids: &[Ulid]) -> Vec<String> {
+    ids.iter().map(Ulid::to_string).collect()
+}
+
+fn to_timestamp(datetime: &Option<Datetime>) -> Option<DateTime<Utc>> {
+    datetime.as_ref().map(|datetime| datetime.0)
+}
+
+/// the keyset condition of `page` on `column`, closing a `WHERE`. `$n` binds `cursor(page)`
+/// and `$n + 1` binds `limit(page)`.
+fn paged(column: &str, n: usize, page: &Page) -> String {
+    format!(
+        "(${n}::text IS NULL OR {column} {} ${n}) ORDER BY {column} {} LIMIT ${}",
+        page.operator(),
+        page.direction(),
+        n + 1
+    )
+}
+
+fn cursor(page: &Page) -> Option<String> {
+    page.cursor_id().map(|id| id.to_string())
+}
+
+fn limit(page: &Page) -> i64 {
+    i64::from(page.limit)
+}
+
+/// `attachment` columns of the table aliased `alias`, renamed `{prefix}_*` so they can be
+/// joined next to the columns of the record that links them.
+fn attachment_columns(alias: &str, prefix: &str) -> String {
+    [
+        "id",
+        "url",
+        "filename",
+        "mime_type",
+        "file_size",
+        "metadata",
+        "create_time",
+    ]
+    .iter()
+    .map(|column| format!("{alias}.{column} AS {prefix}_{column}"))
+    .collect::<Vec<String>>()
+    .join(", ")
+}
+
+/// typed reads of the columns the models are stored in.
+trait RowExt {
+    fn ulid(&self, column: &str) -> Result<Ulid, RepositoryError>;
+    fn ulid_option(&self, column: &str) -> Result<Option<Ulid>, RepositoryError>;
+    fn ulid_list(&self, column: &str) -> Result<Vec<Ulid>, RepositoryError>;
+    fn datetime(&self, column: &str) -> Result<Datetime, RepositoryError>;
+    fn datetime_option(&self, column: &str) -> Result<Option<Datetime>, RepositoryError>;
+    /// the attachment joined with `attachment_columns` as `prefix`.
+    fn attachment(&self, prefix: &str) -> Result<Option<Attachment>, RepositoryError>;
+}
+
+impl RowExt for Row {
+    fn ulid(&self, column: &str) -> Result<Ulid, RepositoryError> {
+        to_ulid(self.try_get::<_, &str>(column)?)
+    }
+
+    fn ulid_option(&self, column: &str) -> Result<Option<Ulid>, RepositoryError> {
+        self.try_get::<_, Option<&str>>(column)?
+            .map(to_ulid)
+            .transpose()
+    }
+
+    fn ulid_list(&self, column: &str) -> Result<Vec<Ulid>, RepositoryError> {
+        self.try_get::<_, Vec<&str>>(column)?
+            .into_iter()
+            .map(to_ulid)
+            .collect()
+    }
+
+    fn datetime(&self, column: &str) -> Result<Datetime, RepositoryError> {
+        Ok(Datetime::from(self.try_get::<_, DateTime<Utc>>(column)?))
+    }
+
+    fn datetime_option(&self, column: &str) -> Result<Option<Datetime>, RepositoryError> {
+        Ok(self
+            .try_get::<_, Option<DateTime<Utc>>>(column)?
+            .map(Datetime::from))
+    }
+
+    fn attachment(&self, prefix: &str) -> Result<Option<Attachment>, RepositoryError> {
+        let Some(id) = self.ulid_option(&format!("{prefix}_id"))? else {
+            return Ok(None);
+        };
+
+        attachment::from_row(self, prefix, id).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::filter::{FieldKind, FilterField};
+
+    const FIELDS: &[FilterField] = &[
+        FilterField::new("content", FieldKind::Text),
+        FilterField::new("pin_time", FieldKind::Time),
+        FilterField::new("author", FieldKind::User),
+    ];
+
+    fn translate(filter: &str) -> (String, usize) {
+        let filter = Filter::parse(filter, FIELDS).unwrap();
+        let mut statement = Statement::new("");
+        let condition = statement.filter(&filter, "m");
+
+        (condition, statement.params.len())
+    }
+
+    #[test]
+    fn empty_filter() {
+        assert_eq!(translate(""), ("TRUE".to_string(), 0));
+    }
+
+    #[test]
+    fn comparisons_bind_their_values() {
+        assert_eq!(
+            translate("content:Hello"),
+            (
+                r#"COALESCE(strpos(lower(m."content"), lower($1)) > 0, false)"#.to_string(),
+                1
+            )
+        );
+        assert_eq!(
+            translate("author = users/01HGW2N5EQNG50CB2HBCN4A8GT"),
+            (r#"COALESCE(m."author" = $1, false)"#.to_string(), 1)
+        );
+    }
+
+    #[test]
+    fn groups_and_negation() {
+        assert_eq!(
+            translate("content:a AND (NOT pin_time > 2024-01-01T00:00:00Z OR content = b)"),
+            (
+                concat!(
+                    r#"(COALESCE(strpos(lower(m."content"), lower($1)) > 0, false)) AND "#,
+                    r#"((NOT (COALESCE(m."pin_time" > $2, false))) OR "#,
+                    r#"(COALESCE(m."content" = $3, false)))"#
+                )
+                .to_string(),
+                3
+            )
+        );
+    }
+
+    #[test]
+    fn placeholders_follow_bound_params() {
+        let filter = Filter::parse("content:a", FIELDS).unwrap();
+        let mut statement = Statement::new("").param("channel");
+
+        assert_eq!(
+            statement.filter(&filter, "m"),
+            r#"COALESCE(strpos(lower(m."content"), lower($2)) > 0, false)"#
+        );
+    }
+}
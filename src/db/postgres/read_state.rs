@@ -0,0 +1,115 @@
+use deadpool_postgres::Pool;
+use tokio_postgres::Row;
+use tonic::async_trait;
+
+use super::{client, to_strings, PostgresTransaction, RowExt, Statement};
+use crate::{
+    db::{error::RepositoryError, traits::read_state::ReadStateRepository},
+    models::{channel::ChannelId, read_state::DbReadState, user::UserId},
+};
+
+#[derive(Clone)]
+pub struct ReadStateRepositoryImpl {}
+
+impl ReadStateRepositoryImpl {
+    pub async fn new() -> Self {
+        ReadStateRepositoryImpl {}
+    }
+}
+
+fn from_row(row: &Row) -> Result<DbReadState, RepositoryError> {
+    Ok(DbReadState {
+        id: row.ulid("id")?,
+        user: row.ulid("user")?,
+        channel: row.ulid("channel")?,
+        last_read_message: row.ulid("last_read_message")?,
+        update_time: row.datetime("update_time")?,
+    })
+}
+
+#[async_trait]
+impl ReadStateRepository<Pool> for ReadStateRepositoryImpl {
+    async fn get(
+        &self,
+        db: &Pool,
+        user_id: &UserId,
+        channel_id: &ChannelId,
+    ) -> Result<Option<DbReadState>, RepositoryError> {
+        client(db)
+            .await?
+            .query_opt(
+                r#"SELECT * FROM read_state WHERE "user" = $1 AND channel = $2"#,
+                &[&user_id.to_string(), &channel_id.to_string()],
+            )
+            .await?
+            .as_ref()
+            .map(from_row)
+            .transpose()
+    }
+
+    async fn get_list_by_user_id(
+        &self,
+        db: &Pool,
+        user_id: &UserId,
+        channel_ids: &[ChannelId],
+    ) -> Result<Vec<DbReadState>, RepositoryError> {
+        client(db)
+            .await?
+            .query(
+                r#"SELECT * FROM read_state WHERE "user" = $1 AND channel = ANY($2)"#,
+                &[&user_id.to_string(), &to_strings(channel_ids)],
+            )
+            .await?
+            .iter()
+            .map(from_row)
+            .collect()
+    }
+
+    async fn save(&self, db: &Pool, read_state: &DbReadState) -> Result<(), RepositoryError> {
+        client(db)
+            .await?
+            .execute(
+                r#"INSERT INTO read_state (id, "user", channel, last_read_message, update_time)
+                    VALUES ($1, $2, $3, $4, $5)
+                    ON CONFLICT (id) DO UPDATE SET "user" = excluded."user",
+                        channel = excluded.channel, last_read_message = excluded.last_read_message,
+                        update_time = excluded.update_time"#,
+                &[
+                    &read_state.id.to_string(),
+                    &read_state.user.to_string(),
+                    &read_state.channel.to_string(),
+                    &read_state.last_read_message.to_string(),
+                    &read_state.update_time.0,
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    fn delete_by_channel_id_in(
+        &self,
+        transaction: &mut PostgresTransaction,
+        channel_id: &ChannelId,
+    ) -> Result<(), RepositoryError> {
+        transaction.push(
+            Statement::new("DELETE FROM read_state WHERE channel = $1")
+                .param(channel_id.to_string()),
+        );
+
+        Ok(())
+    }
+
+    fn delete_by_user_id_in(
+        &self,
+        transaction: &mut PostgresTransaction,
+        user_id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        transaction.push(
+            Statement::new(r#"DELETE FROM read_state WHERE "user" = $1"#)
+                .param(user_id.to_string()),
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+use deadpool_postgres::Pool;
+use tonic::async_trait;
+
+use super::{client, RowExt};
+use crate::{
+    db::{
+        error::RepositoryError, migration::Migration,
+        traits::schema_version::SchemaVersionRepository,
+    },
+    models::schema_version::DbSchemaVersion,
+};
+
+/// `pg_advisory_xact_lock` key held while a migration runs, so servers starting together
+/// apply it once.
+const MIGRATION_LOCK: i64 = 0x7963_6368_6174; // "ycchat"
+
+#[derive(Clone)]
+pub struct SchemaVersionRepositoryImpl {}
+
+impl SchemaVersionRepositoryImpl {
+    pub async fn new() -> Self {
+        SchemaVersionRepositoryImpl {}
+    }
+}
+
+#[async_trait]
+impl SchemaVersionRepository<Pool> for SchemaVersionRepositoryImpl {
+    async fn get_list(&self, db: &Pool) -> Result<Vec<DbSchemaVersion>, RepositoryError> {
+        let client = client(db).await?;
+
+        let is_created: bool = client
+            .query_one("SELECT to_regclass('schema_version') IS NOT NULL", &[])
+            .await?
+            .try_get(0)?;
+        if !is_created {
+            return Ok(vec![]);
+        }
+
+        client
+            .query(
+                "SELECT version, name, checksum, apply_time FROM schema_version ORDER BY version",
+                &[],
+            )
+            .await?
+            .iter()
+            .map(|row| {
+                Ok(DbSchemaVersion {
+                    version: row.try_get::<_, i64>("version")? as u32,
+                    name: row.try_get("name")?,
+                    checksum: row.try_get("checksum")?,
+                    apply_time: row.datetime("apply_time")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn apply(&self, db: &Pool, migration: &Migration) -> Result<(), RepositoryError> {
+        let mut client = client(db).await?;
+        let transaction = client.transaction().await?;
+
+        transaction
+            .execute("SELECT pg_advisory_xact_lock($1)", &[&MIGRATION_LOCK])
+            .await?;
+
+        transaction
+            .batch_execute(
+                "CREATE TABLE IF NOT EXISTS schema_version (
+                    version BIGINT PRIMARY KEY,
+                    name TEXT NOT NULL,
+                    checksum TEXT NOT NULL,
+                    apply_time TIMESTAMPTZ NOT NULL DEFAULT now()
+                )",
+            )
+            .await?;
+
+        // another server applied it while this one waited for the lock.
+        let is_applied = transaction
+            .query_opt(
+                "SELECT 1 FROM schema_version WHERE version = $1",
+                &[&i64::from(migration.version)],
+            )
+            .await?
+            .is_some();
+        if is_applied {
+            return Ok(());
+        }
+
+        transaction.batch_execute(migration.script).await?;
+        transaction
+            .execute(
+                "INSERT INTO schema_version (version, name, checksum) VALUES ($1, $2, $3)",
+                &[
+                    &i64::from(migration.version),
+                    &migration.name,
+                    &migration.checksum(),
+                ],
+            )
+            .await?;
+        transaction.commit().await?;
+
+        Ok(())
+    }
+}
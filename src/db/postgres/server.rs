@@ -0,0 +1,186 @@
+use deadpool_postgres::Pool;
+use tokio_postgres::Row;
+use tonic::async_trait;
+
+use super::{
+    attachment_columns, client, cursor, limit, paged, to_timestamp, PostgresTransaction, RowExt,
+    Statement,
+};
+use crate::{
+    db::{error::RepositoryError, filter::Filter, page::Page, traits::server::ServerRepository},
+    models::{
+        server::{DbServer, ServerId},
+        user::UserId,
+    },
+};
+
+#[derive(Clone)]
+pub struct ServerRepositoryImpl {}
+
+impl ServerRepositoryImpl {
+    pub async fn new() -> Self {
+        ServerRepositoryImpl {}
+    }
+}
+
+fn select() -> String {
+    format!(
+        "SELECT s.*, {} FROM server s LEFT JOIN attachment a ON a.id = s.icon",
+        attachment_columns("a", "icon")
+    )
+}
+
+fn from_row(row: &Row) -> Result<DbServer, RepositoryError> {
+    Ok(DbServer {
+        id: row.ulid("id")?,
+        display_name: row.try_get("display_name")?,
+        description: row.try_get("description")?,
+        owner: row.ulid("owner")?,
+        author: row.ulid("author")?,
+        icon: row.attachment("icon")?,
+        create_time: row.datetime("create_time")?,
+        update_time: row.datetime_option("update_time")?,
+    })
+}
+
+fn upsert(server: &DbServer, is_update: bool) -> Statement {
+    let on_conflict = if is_update {
+        "ON CONFLICT (id) DO UPDATE SET display_name = excluded.display_name,
+            description = excluded.description, owner = excluded.owner,
+            author = excluded.author, icon = excluded.icon,
+            create_time = excluded.create_time, update_time = excluded.update_time"
+    } else {
+        ""
+    };
+
+    Statement::new(format!(
+        "INSERT INTO server (id, display_name, description, owner, author, icon, create_time, update_time)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8) {on_conflict}"
+    ))
+    .param(server.id.to_string())
+    .param(server.display_name.clone())
+    .param(server.description.clone())
+    .param(server.owner.to_string())
+    .param(server.author.to_string())
+    .param(server.icon.as_ref().map(|icon| icon.id.to_string()))
+    .param(server.create_time.0)
+    .param(to_timestamp(&server.update_time))
+}
+
+#[async_trait]
+impl ServerRepository<Pool> for ServerRepositoryImpl {
+    async fn get_server(
+        &self,
+        db: &Pool,
+        id: &ServerId,
+    ) -> Result<Option<DbServer>, RepositoryError> {
+        client(db)
+            .await?
+            .query_opt(&format!("{} WHERE s.id = $1", select()), &[&id.to_string()])
+            .await?
+            .as_ref()
+            .map(from_row)
+            .transpose()
+    }
+
+    async fn update_server(
+        &self,
+        db: &Pool,
+        server: &DbServer,
+    ) -> Result<Option<DbServer>, RepositoryError> {
+        upsert(server, true).execute(db).await?;
+
+        Ok(Some(server.clone()))
+    }
+
+    async fn get_servers(
+        &self,
+        db: &Pool,
+        filter: &Filter,
+        page: Page,
+    ) -> Result<Vec<DbServer>, RepositoryError> {
+        let mut statement = Statement::new(String::new());
+        let condition = statement.filter(filter, "s");
+        let paged = statement.page("s.id", &page);
+        statement.sql = format!("{} WHERE {} AND {}", select(), condition, paged);
+
+        statement
+            .query(db)
+            .await?
+            .iter()
+            .map(from_row)
+            .collect::<Result<_, _>>()
+            .map(|records| page.finish(records))
+    }
+
+    async fn get_servers_by_owner(
+        &self,
+        db: &Pool,
+        owner: &UserId,
+    ) -> Result<Vec<DbServer>, RepositoryError> {
+        client(db)
+            .await?
+            .query(
+                &format!("{} WHERE s.owner = $1 ORDER BY s.id", select()),
+                &[&owner.to_string()],
+            )
+            .await?
+            .iter()
+            .map(from_row)
+            .collect()
+    }
+
+    async fn get_joined_servers(
+        &self,
+        db: &Pool,
+        user_id: &UserId,
+        page: Page,
+    ) -> Result<Vec<DbServer>, RepositoryError> {
+        client(db)
+            .await?
+            .query(
+                &format!(
+                    r#"{} WHERE s.id IN (SELECT server FROM server_member WHERE "user" = $1)
+                        AND {}"#,
+                    select(),
+                    paged("s.id", 2, &page)
+                ),
+                &[&user_id.to_string(), &cursor(&page), &limit(&page)],
+            )
+            .await?
+            .iter()
+            .map(from_row)
+            .collect::<Result<_, _>>()
+            .map(|records| page.finish(records))
+    }
+
+    fn add_server_in(
+        &self,
+        transaction: &mut PostgresTransaction,
+        server: &DbServer,
+    ) -> Result<(), RepositoryError> {
+        transaction.push(upsert(server, false));
+
+        Ok(())
+    }
+
+    fn update_server_in(
+        &self,
+        transaction: &mut PostgresTransaction,
+        server: &DbServer,
+    ) -> Result<(), RepositoryError> {
+        transaction.push(upsert(server, true));
+
+        Ok(())
+    }
+
+    fn delete_server_in(
+        &self,
+        transaction: &mut PostgresTransaction,
+        id: &ServerId,
+    ) -> Result<(), RepositoryError> {
+        transaction.push(Statement::new("DELETE FROM server WHERE id = $1").param(id.to_string()));
+
+        Ok(())
+    }
+}
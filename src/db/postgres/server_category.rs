@@ -0,0 +1,173 @@
+use deadpool_postgres::Pool;
+use tokio_postgres::Row;
+use tonic::async_trait;
+
+use super::{client, cursor, limit, paged, to_timestamp, PostgresTransaction, RowExt, Statement};
+use crate::{
+    db::{error::RepositoryError, page::Page, traits::server_category::ServerCategoryRepository},
+    models::server::ServerId,
+    models::server_category::{DbServerCategory, ServerCategoryId},
+};
+
+#[derive(Clone)]
+pub struct ServerCategoryRepositoryImpl {}
+
+impl ServerCategoryRepositoryImpl {
+    pub async fn new() -> Self {
+        ServerCategoryRepositoryImpl {}
+    }
+}
+
+fn from_row(row: &Row) -> Result<DbServerCategory, RepositoryError> {
+    Ok(DbServerCategory {
+        id: row.ulid("id")?,
+        display_name: row.try_get("display_name")?,
+        description: row.try_get("description")?,
+        server: row.ulid("server")?,
+        icon: row.ulid_option("icon")?,
+        order: row.try_get::<_, i64>("order")? as u32,
+        create_time: row.datetime("create_time")?,
+        update_time: row.datetime_option("update_time")?,
+    })
+}
+
+async fn upsert(
+    db: &Pool,
+    server_category: &DbServerCategory,
+    is_update: bool,
+) -> Result<(), RepositoryError> {
+    let on_conflict = if is_update {
+        r#"ON CONFLICT (id) DO UPDATE SET display_name = excluded.display_name,
+            description = excluded.description, server = excluded.server, icon = excluded.icon,
+            "order" = excluded."order", create_time = excluded.create_time,
+            update_time = excluded.update_time"#
+    } else {
+        ""
+    };
+
+    client(db)
+        .await?
+        .execute(
+            &format!(
+                r#"INSERT INTO server_category (id, display_name, description, server, icon, "order", create_time, update_time)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8) {on_conflict}"#
+            ),
+            &[
+                &server_category.id.to_string(),
+                &server_category.display_name,
+                &server_category.description,
+                &server_category.server.to_string(),
+                &server_category.icon.map(|icon| icon.to_string()),
+                &i64::from(server_category.order),
+                &server_category.create_time.0,
+                &to_timestamp(&server_category.update_time),
+            ],
+        )
+        .await?;
+
+    Ok(())
+}
+
+#[async_trait]
+impl ServerCategoryRepository<Pool> for ServerCategoryRepositoryImpl {
+    async fn get(
+        &self,
+        db: &Pool,
+        id: &ServerCategoryId,
+    ) -> Result<Option<DbServerCategory>, RepositoryError> {
+        client(db)
+            .await?
+            .query_opt(
+                "SELECT * FROM server_category WHERE id = $1",
+                &[&id.to_string()],
+            )
+            .await?
+            .as_ref()
+            .map(from_row)
+            .transpose()
+    }
+
+    async fn add(
+        &self,
+        db: &Pool,
+        server_category: &DbServerCategory,
+    ) -> Result<Option<DbServerCategory>, RepositoryError> {
+        upsert(db, server_category, false).await?;
+
+        Ok(Some(server_category.clone()))
+    }
+
+    async fn update(
+        &self,
+        db: &Pool,
+        server_category: &DbServerCategory,
+    ) -> Result<Option<DbServerCategory>, RepositoryError> {
+        upsert(db, server_category, true).await?;
+
+        Ok(Some(server_category.clone()))
+    }
+
+    async fn delete(&self, db: &Pool, id: &ServerCategoryId) -> Result<u8, RepositoryError> {
+        client(db)
+            .await?
+            .execute(
+                "DELETE FROM server_category WHERE id = $1",
+                &[&id.to_string()],
+            )
+            .await?;
+
+        Ok(1)
+    }
+
+    async fn get_list_by_server_id(
+        &self,
+        db: &Pool,
+        server_id: &ServerId,
+    ) -> Result<Vec<DbServerCategory>, RepositoryError> {
+        client(db)
+            .await?
+            .query(
+                r#"SELECT * FROM server_category WHERE server = $1 ORDER BY "order", id"#,
+                &[&server_id.to_string()],
+            )
+            .await?
+            .iter()
+            .map(from_row)
+            .collect()
+    }
+
+    async fn get_server_categories(
+        &self,
+        db: &Pool,
+        server_id: &ServerId,
+        page: Page,
+    ) -> Result<Vec<DbServerCategory>, RepositoryError> {
+        client(db)
+            .await?
+            .query(
+                &format!(
+                    "SELECT * FROM server_category WHERE server = $1 AND {}",
+                    paged("id", 2, &page)
+                ),
+                &[&server_id.to_string(), &cursor(&page), &limit(&page)],
+            )
+            .await?
+            .iter()
+            .map(from_row)
+            .collect::<Result<_, _>>()
+            .map(|records| page.finish(records))
+    }
+
+    fn delete_by_server_id_in(
+        &self,
+        transaction: &mut PostgresTransaction,
+        server_id: &ServerId,
+    ) -> Result<(), RepositoryError> {
+        transaction.push(
+            Statement::new("DELETE FROM server_category WHERE server = $1")
+                .param(server_id.to_string()),
+        );
+
+        Ok(())
+    }
+}
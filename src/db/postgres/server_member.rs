@@ -0,0 +1,237 @@
+use deadpool_postgres::Pool;
+use tokio_postgres::Row;
+use tonic::async_trait;
+
+use super::{attachment_columns, client, to_timestamp, PostgresTransaction, RowExt, Statement};
+use crate::{
+    db::{
+        error::RepositoryError, filter::Filter, page::Page,
+        traits::server_member::ServerMemberRepository,
+    },
+    models::server::ServerId,
+    models::server_member::{DbServerMember, ServerMemberId},
+    models::user::UserId,
+};
+
+#[derive(Clone)]
+pub struct ServerMemberRepositoryImpl {}
+
+impl ServerMemberRepositoryImpl {
+    pub async fn new() -> Self {
+        ServerMemberRepositoryImpl {}
+    }
+}
+
+fn select() -> String {
+    format!(
+        "SELECT m.*, {} FROM server_member m LEFT JOIN attachment a ON a.id = m.avatar",
+        attachment_columns("a", "avatar")
+    )
+}
+
+fn from_row(row: &Row) -> Result<DbServerMember, RepositoryError> {
+    Ok(DbServerMember {
+        id: row.ulid("id")?,
+        user: row.ulid("user")?,
+        server: row.ulid("server")?,
+        display_name: row.try_get("display_name")?,
+        description: row.try_get("description")?,
+        avatar: row.attachment("avatar")?,
+        create_time: row.datetime("create_time")?,
+        update_time: row.datetime_option("update_time")?,
+    })
+}
+
+fn upsert(server_member: &DbServerMember, is_update: bool) -> Statement {
+    let on_conflict = if is_update {
+        r#"ON CONFLICT (id) DO UPDATE SET "user" = excluded."user", server = excluded.server,
+            display_name = excluded.display_name, description = excluded.description,
+            avatar = excluded.avatar, create_time = excluded.create_time,
+            update_time = excluded.update_time"#
+    } else {
+        ""
+    };
+
+    Statement::new(format!(
+        r#"INSERT INTO server_member (id, "user", server, display_name, description, avatar, create_time, update_time)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8) {on_conflict}"#
+    ))
+    .param(server_member.id.to_string())
+    .param(server_member.user.to_string())
+    .param(server_member.server.to_string())
+    .param(server_member.display_name.clone())
+    .param(server_member.description.clone())
+    .param(
+        server_member
+            .avatar
+            .as_ref()
+            .map(|avatar| avatar.id.to_string()),
+    )
+    .param(server_member.create_time.0)
+    .param(to_timestamp(&server_member.update_time))
+}
+
+#[async_trait]
+impl ServerMemberRepository<Pool> for ServerMemberRepositoryImpl {
+    async fn get_server_member(
+        &self,
+        db: &Pool,
+        id: &ServerMemberId,
+    ) -> Result<Option<DbServerMember>, RepositoryError> {
+        client(db)
+            .await?
+            .query_opt(&format!("{} WHERE m.id = $1", select()), &[&id.to_string()])
+            .await?
+            .as_ref()
+            .map(from_row)
+            .transpose()
+    }
+
+    async fn get_server_member_by_server_id_and_user_id(
+        &self,
+        db: &Pool,
+        server_id: &ServerId,
+        user_id: &UserId,
+    ) -> Result<Option<DbServerMember>, RepositoryError> {
+        client(db)
+            .await?
+            .query_opt(
+                &format!(r#"{} WHERE m.server = $1 AND m."user" = $2"#, select()),
+                &[&server_id.to_string(), &user_id.to_string()],
+            )
+            .await?
+            .as_ref()
+            .map(from_row)
+            .transpose()
+    }
+
+    async fn add_server_member(
+        &self,
+        db: &Pool,
+        server_member: &DbServerMember,
+    ) -> Result<Option<DbServerMember>, RepositoryError> {
+        upsert(server_member, false).execute(db).await?;
+
+        Ok(Some(server_member.clone()))
+    }
+
+    async fn update_server_member(
+        &self,
+        db: &Pool,
+        server_member: &DbServerMember,
+    ) -> Result<Option<DbServerMember>, RepositoryError> {
+        upsert(server_member, true).execute(db).await?;
+
+        Ok(Some(server_member.clone()))
+    }
+
+    async fn delete(&self, db: &Pool, id: &ServerMemberId) -> Result<u8, RepositoryError> {
+        client(db)
+            .await?
+            .execute(
+                "DELETE FROM server_member WHERE id = $1",
+                &[&id.to_string()],
+            )
+            .await?;
+
+        Ok(1)
+    }
+
+    async fn get_server_members(
+        &self,
+        db: &Pool,
+        server_id: &ServerId,
+        filter: &Filter,
+        page: Page,
+    ) -> Result<Vec<DbServerMember>, RepositoryError> {
+        let mut statement = Statement::new(String::new());
+        let server = statement.bind(server_id.to_string());
+        let condition = statement.filter(filter, "m");
+        let paged = statement.page("m.id", &page);
+        statement.sql = format!(
+            "{} WHERE m.server = {} AND {} AND {}",
+            select(),
+            server,
+            condition,
+            paged
+        );
+
+        statement
+            .query(db)
+            .await?
+            .iter()
+            .map(from_row)
+            .collect::<Result<_, _>>()
+            .map(|records| page.finish(records))
+    }
+
+    async fn get_server_members_by_server_id(
+        &self,
+        db: &Pool,
+        server_id: &ServerId,
+    ) -> Result<Vec<DbServerMember>, RepositoryError> {
+        client(db)
+            .await?
+            .query(
+                &format!("{} WHERE m.server = $1", select()),
+                &[&server_id.to_string()],
+            )
+            .await?
+            .iter()
+            .map(from_row)
+            .collect()
+    }
+
+    async fn get_server_members_by_user_id(
+        &self,
+        db: &Pool,
+        user_id: &UserId,
+    ) -> Result<Vec<DbServerMember>, RepositoryError> {
+        client(db)
+            .await?
+            .query(
+                &format!(r#"{} WHERE m."user" = $1"#, select()),
+                &[&user_id.to_string()],
+            )
+            .await?
+            .iter()
+            .map(from_row)
+            .collect()
+    }
+
+    fn add_server_member_in(
+        &self,
+        transaction: &mut PostgresTransaction,
+        server_member: &DbServerMember,
+    ) -> Result<(), RepositoryError> {
+        transaction.push(upsert(server_member, false));
+
+        Ok(())
+    }
+
+    fn delete_by_server_id_in(
+        &self,
+        transaction: &mut PostgresTransaction,
+        server_id: &ServerId,
+    ) -> Result<(), RepositoryError> {
+        transaction.push(
+            Statement::new("DELETE FROM server_member WHERE server = $1")
+                .param(server_id.to_string()),
+        );
+
+        Ok(())
+    }
+
+    fn delete_by_user_id_in(
+        &self,
+        transaction: &mut PostgresTransaction,
+        user_id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        transaction.push(
+            Statement::new(r#"DELETE FROM server_member WHERE "user" = $1"#)
+                .param(user_id.to_string()),
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+use deadpool_postgres::Pool;
+use tokio_postgres::{types::Json, Row};
+use tonic::async_trait;
+
+use super::{attachment_columns, client, to_timestamp, PostgresTransaction, RowExt, Statement};
+use crate::{
+    db::{error::RepositoryError, filter::Filter, page::Page, traits::user::UserRepository},
+    models::user::{DbUser, UserId, UserSettings},
+};
+
+#[derive(Clone)]
+pub struct UserRepositoryImpl {}
+
+impl UserRepositoryImpl {
+    pub async fn new() -> Self {
+        UserRepositoryImpl {}
+    }
+}
+
+fn select() -> String {
+    format!(
+        r#"SELECT u.*, {} FROM "user" u LEFT JOIN attachment a ON a.id = u.avatar"#,
+        attachment_columns("a", "avatar")
+    )
+}
+
+fn from_row(row: &Row) -> Result<DbUser, RepositoryError> {
+    Ok(DbUser {
+        id: row.ulid("id")?,
+        display_name: row.try_get("display_name")?,
+        description: row.try_get("description")?,
+        avatar: row.attachment("avatar")?,
+        region_code: row.try_get("region_code")?,
+        language_code: row.try_get("language_code")?,
+        time_zone: row.try_get("time_zone")?,
+        settings: row.try_get::<_, Json<UserSettings>>("settings")?.0,
+        create_time: row.datetime("create_time")?,
+        update_time: row.datetime_option("update_time")?,
+    })
+}
+
+async fn upsert(db: &Pool, user: &DbUser, is_update: bool) -> Result<(), RepositoryError> {
+    let on_conflict = if is_update {
+        "ON CONFLICT (id) DO UPDATE SET display_name = excluded.display_name,
+            description = excluded.description, avatar = excluded.avatar,
+            region_code = excluded.region_code, language_code = excluded.language_code,
+            time_zone = excluded.time_zone, settings = excluded.settings,
+            create_time = excluded.create_time, update_time = excluded.update_time"
+    } else {
+        ""
+    };
+
+    client(db)
+        .await?
+        .execute(
+            &format!(
+                r#"INSERT INTO "user" (id, display_name, description, avatar, region_code, language_code, time_zone, settings, create_time, update_time)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) {on_conflict}"#
+            ),
+            &[
+                &user.id.to_string(),
+                &user.display_name,
+                &user.description,
+                &user.avatar.as_ref().map(|avatar| avatar.id.to_string()),
+                &user.region_code,
+                &user.language_code,
+                &user.time_zone,
+                &Json(&user.settings),
+                &user.create_time.0,
+                &to_timestamp(&user.update_time),
+            ],
+        )
+        .await?;
+
+    Ok(())
+}
+
+#[async_trait]
+impl UserRepository<Pool> for UserRepositoryImpl {
+    async fn get_user(&self, db: &Pool, id: &UserId) -> Result<Option<DbUser>, RepositoryError> {
+        client(db)
+            .await?
+            .query_opt(&format!("{} WHERE u.id = $1", select()), &[&id.to_string()])
+            .await?
+            .as_ref()
+            .map(from_row)
+            .transpose()
+    }
+
+    async fn add_user(&self, db: &Pool, user: &DbUser) -> Result<Option<DbUser>, RepositoryError> {
+        upsert(db, user, false).await?;
+
+        Ok(Some(user.clone()))
+    }
+
+    async fn update_user(
+        &self,
+        db: &Pool,
+        user: &DbUser,
+    ) -> Result<Option<DbUser>, RepositoryError> {
+        upsert(db, user, true).await?;
+
+        Ok(Some(user.clone()))
+    }
+
+    async fn delete_user(&self, db: &Pool, id: &UserId) -> Result<u8, RepositoryError> {
+        client(db)
+            .await?
+            .execute(r#"DELETE FROM "user" WHERE id = $1"#, &[&id.to_string()])
+            .await?;
+
+        Ok(1)
+    }
+
+    async fn get_users(
+        &self,
+        db: &Pool,
+        filter: &Filter,
+        page: Page,
+    ) -> Result<Vec<DbUser>, RepositoryError> {
+        let mut statement = Statement::new(String::new());
+        let condition = statement.filter(filter, "u");
+        let paged = statement.page("u.id", &page);
+        statement.sql = format!("{} WHERE {} AND {}", select(), condition, paged);
+
+        statement
+            .query(db)
+            .await?
+            .iter()
+            .map(from_row)
+            .collect::<Result<_, _>>()
+            .map(|records| page.finish(records))
+    }
+
+    fn delete_user_in(
+        &self,
+        transaction: &mut PostgresTransaction,
+        id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        transaction
+            .push(Statement::new(r#"DELETE FROM "user" WHERE id = $1"#).param(id.to_string()));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,225 @@
+use serde::{Serialize, Serializer};
+use surrealdb::{
+    engine::any::Any,
+    sql::{Id, Thing},
+    Surreal,
+};
+use tonic::async_trait;
+
+use super::{
+    channel::COLLECTION_NAME as CHANNEL_COLLECTION_NAME,
+    message::COLLECTION_NAME as MESSAGE_COLLECTION_NAME,
+    server::COLLECTION_NAME as SERVER_COLLECTION_NAME,
+    server_member::COLLECTION_NAME as SERVER_MEMBER_COLLECTION_NAME,
+    user::COLLECTION_NAME as USER_COLLECTION_NAME,
+};
+use crate::{
+    db::{error::RepositoryError, traits::attachment::AttachmentRepository},
+    models::{
+        attachment::{Attachment, AttachmentId, AttachmentUploaded},
+        user::UserId,
+    },
+};
+
+pub const COLLECTION_NAME: &str = "attachment";
+const UPLOADED_COLLECTION_NAME: &str = "attachment_uploaded";
+
+#[derive(Clone)]
+pub struct AttachmentRepositoryImpl {}
+
+impl AttachmentRepositoryImpl {
+    pub async fn new() -> Self {
+        AttachmentRepositoryImpl {}
+    }
+}
+
+#[async_trait]
+impl AttachmentRepository<Surreal<Any>> for AttachmentRepositoryImpl {
+    async fn get_attachment(
+        &self,
+        db: &Surreal<Any>,
+        id: &AttachmentId,
+    ) -> Result<Option<Attachment>, RepositoryError> {
+        let res = db.select((COLLECTION_NAME, id.to_string())).await;
+
+        match res {
+            Ok(res) => Ok(res),
+            Err(e) => Err(RepositoryError::from(e)),
+        }
+    }
+
+    async fn add_attachment(
+        &self,
+        db: &Surreal<Any>,
+        attachment: &Attachment,
+        uploader: &UserId,
+    ) -> Result<Option<Attachment>, RepositoryError> {
+        let created: Option<Attachment> = db
+            .create((COLLECTION_NAME, attachment.id.to_string()))
+            .content(attachment)
+            .await
+            .map_err(RepositoryError::from)?;
+
+        let user = Thing {
+            tb: USER_COLLECTION_NAME.to_string(),
+            id: Id::String(uploader.to_string()),
+        };
+
+        let attachment = Thing {
+            tb: COLLECTION_NAME.to_string(),
+            id: Id::String(attachment.id.to_string()),
+        };
+
+        db.query(format!(
+            "RELATE $user->{UPLOADED_COLLECTION_NAME}->$attachment"
+        ))
+        .bind(("user", user))
+        .bind(("attachment", attachment))
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(created)
+    }
+
+    async fn delete_attachment(
+        &self,
+        db: &Surreal<Any>,
+        id: &AttachmentId,
+    ) -> Result<u8, RepositoryError> {
+        let attachment = Thing {
+            tb: COLLECTION_NAME.to_string(),
+            id: Id::String(id.to_string()),
+        };
+
+        db.query(format!(
+            "DELETE {UPLOADED_COLLECTION_NAME} WHERE out == $attachment"
+        ))
+        .bind(("attachment", attachment))
+        .await
+        .map_err(RepositoryError::from)?;
+
+        db.delete::<Option<Attachment>>((COLLECTION_NAME, id.to_string()))
+            .await
+            .map_err(RepositoryError::from)?;
+
+        Ok(1)
+    }
+
+    async fn get_attachments(
+        &self,
+        db: &Surreal<Any>,
+        ids: &[AttachmentId],
+    ) -> Result<Vec<Attachment>, RepositoryError> {
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let ids = ids
+            .iter()
+            .map(|id| Thing {
+                tb: COLLECTION_NAME.to_string(),
+                id: Id::String(id.to_string()),
+            })
+            .collect::<Vec<Thing>>();
+
+        let res = db
+            .query(format!(
+                "SELECT * FROM {COLLECTION_NAME} WHERE id INSIDE $ids"
+            ))
+            .bind(("ids", ids))
+            .await
+            .map_err(RepositoryError::from)?
+            .take::<Vec<Attachment>>(0);
+
+        match res {
+            Ok(res) => Ok(res),
+            Err(e) => Err(RepositoryError::from(e)),
+        }
+    }
+
+    async fn is_referenced(
+        &self,
+        db: &Surreal<Any>,
+        id: &AttachmentId,
+    ) -> Result<bool, RepositoryError> {
+        let attachment = Thing {
+            tb: COLLECTION_NAME.to_string(),
+            id: Id::String(id.to_string()),
+        };
+
+        let res = db
+            .query(format!(
+                "RETURN array::len((SELECT id FROM {USER_COLLECTION_NAME} WHERE avatar == $attachment))
+                    + array::len((SELECT id FROM {SERVER_COLLECTION_NAME} WHERE icon == $attachment))
+                    + array::len((SELECT id FROM {CHANNEL_COLLECTION_NAME} WHERE icon == $attachment))
+                    + array::len((SELECT id FROM {SERVER_MEMBER_COLLECTION_NAME} WHERE avatar == $attachment))
+                    + array::len((SELECT id FROM {MESSAGE_COLLECTION_NAME} WHERE attachments CONTAINS $attachment))"
+            ))
+            .bind(("attachment", attachment))
+            .await
+            .map_err(RepositoryError::from)?
+            .take::<Option<i64>>(0);
+
+        match res {
+            Ok(count) => Ok(count.unwrap_or(0) > 0),
+            Err(e) => Err(RepositoryError::from(e)),
+        }
+    }
+
+    async fn get_uploader(
+        &self,
+        db: &Surreal<Any>,
+        id: &AttachmentId,
+    ) -> Result<Option<UserId>, RepositoryError> {
+        let attachment = Thing {
+            tb: COLLECTION_NAME.to_string(),
+            id: Id::String(id.to_string()),
+        };
+
+        let res = db
+            .query(format!(
+                "SELECT * FROM {UPLOADED_COLLECTION_NAME} WHERE out == $attachment"
+            ))
+            .bind(("attachment", attachment))
+            .await
+            .map_err(RepositoryError::from)?
+            .take::<Option<AttachmentUploaded>>(0);
+
+        match res {
+            Ok(res) => Ok(res.map(|uploaded| uploaded.user)),
+            Err(e) => Err(RepositoryError::from(e)),
+        }
+    }
+}
+
+pub fn serialize_id<S>(id: &AttachmentId, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let surreal_id = Thing::from((COLLECTION_NAME.to_string(), id.to_string()));
+    surreal_id.serialize(s)
+}
+
+/// attachments are embedded in models when read with `FETCH`, but stored as record links.
+pub fn serialize_option<S>(attachment: &Option<Attachment>, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let surreal_id = attachment
+        .as_ref()
+        .map(|attachment| Thing::from((COLLECTION_NAME.to_string(), attachment.id.to_string())));
+
+    surreal_id.serialize(s)
+}
+
+pub fn serialize_id_list<S>(ids: &[AttachmentId], s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let surreal_ids = ids
+        .iter()
+        .map(|id| Thing::from((COLLECTION_NAME.to_string(), id.to_string())))
+        .collect::<Vec<Thing>>();
+
+    surreal_ids.serialize(s)
+}
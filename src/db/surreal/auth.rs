@@ -1,8 +1,9 @@
 use serde::{Serialize, Serializer};
-use surrealdb::{engine::remote::ws::Client, sql::Thing, Surreal};
+use surrealdb::{engine::any::Any, sql::Thing, Surreal};
 
+use super::SurrealTransaction;
 use crate::{
-    db::traits::auth::AuthRepository,
+    db::{error::RepositoryError, traits::auth::AuthRepository},
     models::{auth::DbAuth, user::UserId},
 };
 
@@ -18,58 +19,69 @@ impl AuthRepositoryImpl {
 const COLLECTION_NAME: &str = "auth";
 
 #[tonic::async_trait]
-impl AuthRepository<Surreal<Client>> for AuthRepositoryImpl {
-    async fn get(&self, db: &Surreal<Client>, id: &UserId) -> Result<Option<DbAuth>, String> {
+impl AuthRepository<Surreal<Any>> for AuthRepositoryImpl {
+    async fn get(&self, db: &Surreal<Any>, id: &UserId) -> Result<Option<DbAuth>, RepositoryError> {
         let res = db.select((COLLECTION_NAME, id.to_string())).await;
 
         match res {
             Ok(res) => Ok(res),
-            Err(e) => Err(e.to_string()),
+            Err(e) => Err(RepositoryError::from(e)),
         }
     }
 
     async fn get_by_username(
         &self,
-        db: &Surreal<Client>,
+        db: &Surreal<Any>,
         username: &str,
-    ) -> Result<Option<DbAuth>, String> {
+    ) -> Result<Option<DbAuth>, RepositoryError> {
         let mut res = db
             .query(format!(
                 "SELECT * FROM {COLLECTION_NAME} WHERE username = $username"
             ))
             .bind(("username", username))
             .await
-            .unwrap();
+            .map_err(RepositoryError::from)?;
 
-        Ok(res.take::<Option<DbAuth>>(0).unwrap())
+        res.take::<Option<DbAuth>>(0).map_err(RepositoryError::from)
     }
 
-    async fn add(&self, db: &Surreal<Client>, auth: &DbAuth) -> Result<Option<DbAuth>, String> {
+    async fn add(
+        &self,
+        db: &Surreal<Any>,
+        auth: &DbAuth,
+    ) -> Result<Option<DbAuth>, RepositoryError> {
         let created = db
             .create((COLLECTION_NAME, auth.id.to_string()))
             .content(auth)
             .await
-            .unwrap();
+            .map_err(RepositoryError::from)?;
 
         Ok(created)
     }
 
-    async fn update(&self, db: &Surreal<Client>, auth: &DbAuth) -> Result<Option<DbAuth>, String> {
+    async fn update(
+        &self,
+        db: &Surreal<Any>,
+        auth: &DbAuth,
+    ) -> Result<Option<DbAuth>, RepositoryError> {
         let res: Option<DbAuth> = db
             .update((COLLECTION_NAME, auth.id.to_string()))
             .content(auth.clone())
             .await
-            .unwrap();
+            .map_err(RepositoryError::from)?;
 
         return Ok(res);
     }
 
-    async fn delete(&self, db: &Surreal<Client>, id: &UserId) -> Result<u8, String> {
-        db.delete::<Option<DbAuth>>((COLLECTION_NAME, id.to_string()))
-            .await
-            .unwrap();
+    fn delete_in(
+        &self,
+        transaction: &mut SurrealTransaction,
+        id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        let id = transaction.bind(Thing::from((COLLECTION_NAME.to_string(), id.to_string())))?;
+        transaction.push(format!("DELETE {}", id));
 
-        Ok(1)
+        Ok(())
     }
 }
 
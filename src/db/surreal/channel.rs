@@ -1,20 +1,37 @@
+use std::collections::BTreeMap;
+
 use serde::{Serialize, Serializer};
 use surrealdb::{
-    engine::remote::ws::Client,
-    sql::{Id, Thing},
+    engine::any::Any,
+    sql::{Datetime, Id, Thing},
     Surreal,
 };
 
+use super::{condition, SurrealTransaction};
 use crate::{
-    db::traits::channel::ChannelRepository,
-    models::channel::{ChannelId, DbChannel},
+    db::{error::RepositoryError, filter::Filter, page::Page, traits::channel::ChannelRepository},
+    models::channel::{ChannelId, ChannelPosition, DbChannel},
     models::server::ServerId,
+    models::server_category::{CategoryPosition, ServerCategoryId},
+    models::user::UserId,
 };
 
-use super::server::COLLECTION_NAME as SERVER_COLLECTION_NAME;
+use super::{
+    server::COLLECTION_NAME as SERVER_COLLECTION_NAME,
+    server_category::COLLECTION_NAME as SERVER_CATEGORY_COLLECTION_NAME,
+    server_member::COLLECTION_NAME as SERVER_MEMBER_COLLECTION_NAME,
+    user::COLLECTION_NAME as USER_COLLECTION_NAME,
+};
 
 pub const COLLECTION_NAME: &str = "channel";
 
+/// the channels of the server bound to `$server` matching `condition`, in layout order.
+fn select_layout(condition: &str) -> String {
+    format!(
+        "SELECT *, category.order AS category_order FROM {COLLECTION_NAME} WHERE server == $server AND {condition} ORDER BY category_order, category, order, id FETCH icon"
+    )
+}
+
 #[derive(Clone)]
 pub struct ChannelRepositoryImpl {}
 
@@ -25,87 +42,336 @@ impl ChannelRepositoryImpl {
 }
 
 #[tonic::async_trait]
-impl ChannelRepository<Surreal<Client>> for ChannelRepositoryImpl {
-    async fn get(&self, db: &Surreal<Client>, id: &ChannelId) -> Result<Option<DbChannel>, String> {
-        let res = db.select((COLLECTION_NAME, id.to_string())).await;
+impl ChannelRepository<Surreal<Any>> for ChannelRepositoryImpl {
+    async fn get(
+        &self,
+        db: &Surreal<Any>,
+        id: &ChannelId,
+    ) -> Result<Option<DbChannel>, RepositoryError> {
+        let id = Thing::from((COLLECTION_NAME.to_string(), id.to_string()));
+
+        let res = db
+            .query("SELECT * FROM $id FETCH icon")
+            .bind(("id", id))
+            .await
+            .map_err(RepositoryError::from)?
+            .take::<Option<DbChannel>>(0);
+
+        match res {
+            Ok(res) => Ok(res),
+            Err(e) => Err(RepositoryError::from(e)),
+        }
+    }
+
+    async fn get_list_by_server_id(
+        &self,
+        db: &Surreal<Any>,
+        server_id: &ServerId,
+    ) -> Result<Vec<DbChannel>, RepositoryError> {
+        let server = Thing {
+            tb: SERVER_COLLECTION_NAME.to_string(),
+            id: Id::String(server_id.to_string()),
+        };
+
+        let res = db
+            .query(select_layout("true"))
+            .bind(("server", server))
+            .await
+            .map_err(RepositoryError::from)?
+            .take::<Vec<DbChannel>>(0);
 
         match res {
             Ok(res) => Ok(res),
-            Err(e) => Err(e.to_string()),
+            Err(e) => Err(RepositoryError::from(e)),
         }
     }
 
     async fn get_server_channels(
         &self,
-        db: &Surreal<Client>,
+        db: &Surreal<Any>,
         server_id: &ServerId,
-        page_size: i32,
-        offset_id: Option<ChannelId>,
-    ) -> Result<Vec<DbChannel>, String> {
+        filter: &Filter,
+        page: Page,
+    ) -> Result<Vec<DbChannel>, RepositoryError> {
         let server = Thing {
             tb: SERVER_COLLECTION_NAME.to_string(),
             id: Id::String(server_id.to_string()),
         };
 
-        let query = match offset_id {
-            Some(offset_id) => db
+        let mut bindings = BTreeMap::new();
+        let condition = condition(filter, &[], &mut bindings);
+
+        // the layout order has no single sortable key, pages are cut from the whole layout.
+        let channels = db
+            .query(select_layout(&condition))
+            .bind(bindings)
+            .bind(("server", server))
+            .await
+            .map_err(RepositoryError::from)?
+            .take::<Vec<DbChannel>>(0)?;
+
+        Ok(page.cut(channels, |channel| channel.id))
+    }
+
+    async fn update_layout(
+        &self,
+        db: &Surreal<Any>,
+        categories: &[CategoryPosition],
+        channels: &[ChannelPosition],
+    ) -> Result<(), RepositoryError> {
+        let mut query = db.query("BEGIN TRANSACTION");
+
+        for (index, position) in categories.iter().enumerate() {
+            query = query
                 .query(format!(
-                    "SELECT * FROM {COLLECTION_NAME} WHERE server == $server AND id < $offset_id ORDER BY id DESC LIMIT $page_size"
+                    "UPDATE $category_{index} SET order = $category_order_{index}, update_time = time::now() RETURN NONE"
                 ))
-                .bind(("server", server))
-                .bind(("offset_id", offset_id))
-                .bind(("page_size", page_size)),
+                .bind((
+                    format!("category_{index}"),
+                    Thing::from((SERVER_CATEGORY_COLLECTION_NAME.to_string(), position.id.to_string())),
+                ))
+                .bind((format!("category_order_{index}"), position.order));
+        }
 
-            None => db
+        for (index, position) in channels.iter().enumerate() {
+            query = query
                 .query(format!(
-                    "SELECT * FROM {COLLECTION_NAME} WHERE server == $server ORDER BY id DESC LIMIT $page_size"
+                    "UPDATE $channel_{index} SET category = $channel_category_{index}, order = $channel_order_{index}, update_time = time::now() RETURN NONE"
+                ))
+                .bind((
+                    format!("channel_{index}"),
+                    Thing::from((COLLECTION_NAME.to_string(), position.id.to_string())),
                 ))
-                .bind(("server", server))
-                .bind(("page_size", page_size)),
+                .bind((
+                    format!("channel_category_{index}"),
+                    position.category.map(|category| {
+                        Thing::from((SERVER_CATEGORY_COLLECTION_NAME.to_string(), category.to_string()))
+                    }),
+                ))
+                .bind((format!("channel_order_{index}"), position.order));
+        }
+
+        query
+            .query("COMMIT TRANSACTION")
+            .await
+            .map_err(RepositoryError::from)?
+            .check()
+            .map_err(RepositoryError::from)?;
+
+        Ok(())
+    }
+
+    async fn get_channels_by_user_id(
+        &self,
+        db: &Surreal<Any>,
+        user_id: &UserId,
+    ) -> Result<Vec<DbChannel>, RepositoryError> {
+        let user = Thing {
+            tb: USER_COLLECTION_NAME.to_string(),
+            id: Id::String(user_id.to_string()),
         };
 
-        let res = query.await.unwrap().take::<Vec<DbChannel>>(0);
+        let res = db
+            .query(format!(
+                "SELECT * FROM {COLLECTION_NAME}
+                    WHERE (channel_type.type == 'Saved' AND channel_type.owner == $user_id)
+                        OR (channel_type.type INSIDE ['Direct', 'Group'] AND members CONTAINS $user)
+                        OR (channel_type.type == 'Server' AND channel_type.server INSIDE
+                            (SELECT VALUE meta::id(out) FROM {SERVER_MEMBER_COLLECTION_NAME} WHERE in == $user))
+                    FETCH icon"
+            ))
+            .bind(("user_id", user_id.to_string()))
+            .bind(("user", user))
+            .await
+            .map_err(RepositoryError::from)?
+            .take::<Vec<DbChannel>>(0);
 
         match res {
             Ok(res) => Ok(res),
-            Err(e) => Err(e.to_string()),
+            Err(e) => Err(RepositoryError::from(e)),
         }
     }
 
+    async fn get_direct_channel(
+        &self,
+        db: &Surreal<Any>,
+        user_id: &UserId,
+        other_user_id: &UserId,
+    ) -> Result<Option<DbChannel>, RepositoryError> {
+        let members = [user_id, other_user_id]
+            .iter()
+            .map(|id| Thing {
+                tb: USER_COLLECTION_NAME.to_string(),
+                id: Id::String(id.to_string()),
+            })
+            .collect::<Vec<Thing>>();
+
+        let res = db
+            .query(format!(
+                "SELECT * FROM {COLLECTION_NAME} WHERE channel_type.type == 'Direct' AND members CONTAINSALL $members LIMIT 1 FETCH icon"
+            ))
+            .bind(("members", members))
+            .await
+            .map_err(RepositoryError::from)?
+            .take::<Option<DbChannel>>(0);
+
+        match res {
+            Ok(res) => Ok(res),
+            Err(e) => Err(RepositoryError::from(e)),
+        }
+    }
+
+    async fn get_direct_channels_by_user_id(
+        &self,
+        db: &Surreal<Any>,
+        user_id: &UserId,
+    ) -> Result<Vec<DbChannel>, RepositoryError> {
+        let user = Thing {
+            tb: USER_COLLECTION_NAME.to_string(),
+            id: Id::String(user_id.to_string()),
+        };
+
+        let res = db
+            .query(format!(
+                "SELECT *, last_message_time ?? create_time AS activity_time FROM {COLLECTION_NAME}
+                    WHERE channel_type.type INSIDE ['Direct', 'Group'] AND members CONTAINS $user
+                    ORDER BY activity_time DESC FETCH icon"
+            ))
+            .bind(("user", user))
+            .await
+            .map_err(RepositoryError::from)?
+            .take::<Vec<DbChannel>>(0);
+
+        match res {
+            Ok(res) => Ok(res),
+            Err(e) => Err(RepositoryError::from(e)),
+        }
+    }
+
+    async fn update_last_message_time(
+        &self,
+        db: &Surreal<Any>,
+        id: &ChannelId,
+        last_message_time: &Datetime,
+    ) -> Result<(), RepositoryError> {
+        db.query("UPDATE $id SET last_message_time = $last_message_time RETURN NONE")
+            .bind((
+                "id",
+                Thing::from((COLLECTION_NAME.to_string(), id.to_string())),
+            ))
+            .bind(("last_message_time", last_message_time))
+            .await
+            .map_err(RepositoryError::from)?
+            .check()
+            .map_err(RepositoryError::from)?;
+
+        Ok(())
+    }
+
     async fn add(
         &self,
-        db: &Surreal<Client>,
+        db: &Surreal<Any>,
         channel: &DbChannel,
-    ) -> Result<Option<DbChannel>, String> {
-        let created: Option<DbChannel> = db
-            .create((COLLECTION_NAME, channel.id.to_string()))
-            .content(channel)
+    ) -> Result<Option<DbChannel>, RepositoryError> {
+        // icon is stored as a record link, read it back with the attachment fetched.
+        db.query("CREATE $id CONTENT $content RETURN NONE")
+            .bind((
+                "id",
+                Thing::from((COLLECTION_NAME.to_string(), channel.id.to_string())),
+            ))
+            .bind(("content", channel))
             .await
-            .unwrap();
+            .map_err(RepositoryError::from)?
+            .check()
+            .map_err(RepositoryError::from)?;
 
-        Ok(created)
+        self.get(db, &channel.id).await
     }
 
     async fn update(
         &self,
-        db: &Surreal<Client>,
+        db: &Surreal<Any>,
         channel: &DbChannel,
-    ) -> Result<Option<DbChannel>, String> {
-        let res: Option<DbChannel> = db
-            .update((COLLECTION_NAME, channel.id.to_string()))
-            .content(channel.clone())
+    ) -> Result<Option<DbChannel>, RepositoryError> {
+        db.query("UPDATE $id CONTENT $content RETURN NONE")
+            .bind((
+                "id",
+                Thing::from((COLLECTION_NAME.to_string(), channel.id.to_string())),
+            ))
+            .bind(("content", channel))
             .await
-            .unwrap();
+            .map_err(RepositoryError::from)?
+            .check()
+            .map_err(RepositoryError::from)?;
 
-        return Ok(res);
+        self.get(db, &channel.id).await
     }
 
-    async fn delete(&self, db: &Surreal<Client>, id: &ChannelId) -> Result<u8, String> {
-        db.delete::<Option<DbChannel>>((COLLECTION_NAME, id.to_string()))
-            .await
-            .unwrap();
+    async fn clear_category(
+        &self,
+        db: &Surreal<Any>,
+        category_id: &ServerCategoryId,
+    ) -> Result<(), RepositoryError> {
+        let category = Thing {
+            tb: SERVER_CATEGORY_COLLECTION_NAME.to_string(),
+            id: Id::String(category_id.to_string()),
+        };
+
+        db.query(format!(
+            "UPDATE {COLLECTION_NAME} SET category = NONE, update_time = time::now() WHERE category == $category RETURN NONE"
+        ))
+        .bind(("category", category))
+        .await
+        .map_err(RepositoryError::from)?
+        .check()
+        .map_err(RepositoryError::from)?;
+
+        Ok(())
+    }
+
+    fn delete_in(
+        &self,
+        transaction: &mut SurrealTransaction,
+        id: &ChannelId,
+    ) -> Result<(), RepositoryError> {
+        let id = transaction.bind(Thing::from((COLLECTION_NAME.to_string(), id.to_string())))?;
+        transaction.push(format!("DELETE {}", id));
+
+        Ok(())
+    }
+
+    fn delete_by_server_id_in(
+        &self,
+        transaction: &mut SurrealTransaction,
+        server_id: &ServerId,
+    ) -> Result<(), RepositoryError> {
+        let server_id = transaction.bind(Thing::from((
+            SERVER_COLLECTION_NAME.to_string(),
+            server_id.to_string(),
+        )))?;
+        transaction.push(format!(
+            "DELETE {COLLECTION_NAME} WHERE server = {}",
+            server_id
+        ));
 
-        Ok(1)
+        Ok(())
+    }
+
+    fn remove_member_in(
+        &self,
+        transaction: &mut SurrealTransaction,
+        user_id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        let user_id = transaction.bind(Thing::from((
+            USER_COLLECTION_NAME.to_string(),
+            user_id.to_string(),
+        )))?;
+        transaction.push(format!(
+            "UPDATE {COLLECTION_NAME} SET members -= {0} WHERE members CONTAINS {0} RETURN NONE",
+            user_id
+        ));
+
+        Ok(())
     }
 }
 
@@ -116,3 +382,15 @@ where
     let surreal_id = Thing::from((COLLECTION_NAME.to_string(), id.to_string()));
     surreal_id.serialize(s)
 }
+
+pub fn serialize_id_list<S>(ids: &[ChannelId], s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let surreal_ids = ids
+        .iter()
+        .map(|id| Thing::from((COLLECTION_NAME.to_string(), id.to_string())))
+        .collect::<Vec<Thing>>();
+
+    surreal_ids.serialize(s)
+}
@@ -0,0 +1,183 @@
+use super::{
+    channel::COLLECTION_NAME as CHANNEL_COLLECTION_NAME, cursor,
+    message::COLLECTION_NAME as MESSAGE_COLLECTION_NAME, paged,
+    user::COLLECTION_NAME as USER_COLLECTION_NAME, SurrealTransaction,
+};
+use crate::{
+    db::{error::RepositoryError, page::Page, traits::mention::MentionRepository},
+    models::{
+        channel::ChannelId,
+        mention::{DbMention, MentionId},
+        message::MessageId,
+        user::UserId,
+    },
+};
+use serde::{Serialize, Serializer};
+use surrealdb::{
+    engine::any::Any,
+    sql::{Id, Thing},
+    Surreal,
+};
+use tonic::async_trait;
+
+pub const COLLECTION_NAME: &str = "mention";
+
+#[derive(Clone)]
+pub struct MentionRepositoryImpl {}
+
+impl MentionRepositoryImpl {
+    pub async fn new() -> Self {
+        MentionRepositoryImpl {}
+    }
+}
+
+#[async_trait]
+impl MentionRepository<Surreal<Any>> for MentionRepositoryImpl {
+    async fn add_mentions(
+        &self,
+        db: &Surreal<Any>,
+        mentions: &[DbMention],
+    ) -> Result<(), RepositoryError> {
+        if mentions.is_empty() {
+            return Ok(());
+        }
+
+        db.query(format!(
+            "INSERT INTO {COLLECTION_NAME} $mentions RETURN NONE"
+        ))
+        .bind(("mentions", mentions))
+        .await
+        .map_err(RepositoryError::from)?
+        .check()
+        .map_err(RepositoryError::from)?;
+
+        Ok(())
+    }
+
+    async fn get_list_by_user_id(
+        &self,
+        db: &Surreal<Any>,
+        user_id: &UserId,
+        page: Page,
+    ) -> Result<Vec<DbMention>, RepositoryError> {
+        let user = Thing {
+            tb: USER_COLLECTION_NAME.to_string(),
+            id: Id::String(user_id.to_string()),
+        };
+
+        let res = db
+            .query(format!(
+                "SELECT * FROM {COLLECTION_NAME} WHERE user == $user AND {}",
+                paged(&page)
+            ))
+            .bind(("user", user))
+            .bind(("cursor", cursor(COLLECTION_NAME, &page)))
+            .bind(("limit", page.limit))
+            .await
+            .map_err(RepositoryError::from)?
+            .take::<Vec<DbMention>>(0);
+
+        match res {
+            Ok(res) => Ok(page.finish(res)),
+            Err(e) => Err(RepositoryError::from(e)),
+        }
+    }
+
+    async fn count_by_user_id_and_channel_id(
+        &self,
+        db: &Surreal<Any>,
+        user_id: &UserId,
+        channel_id: &ChannelId,
+        after: Option<MessageId>,
+    ) -> Result<u64, RepositoryError> {
+        let user = Thing {
+            tb: USER_COLLECTION_NAME.to_string(),
+            id: Id::String(user_id.to_string()),
+        };
+
+        let channel = Thing {
+            tb: CHANNEL_COLLECTION_NAME.to_string(),
+            id: Id::String(channel_id.to_string()),
+        };
+
+        let query = match after {
+            Some(after) => db.query(format!(
+                "SELECT count() FROM {COLLECTION_NAME} WHERE user == $user AND channel == $channel AND message > $after GROUP ALL"
+            ))
+                .bind(("user", user))
+                .bind(("channel", channel))
+                .bind(("after", Thing::from((MESSAGE_COLLECTION_NAME.to_string(), after.to_string())))),
+            None => db.query(format!(
+                "SELECT count() FROM {COLLECTION_NAME} WHERE user == $user AND channel == $channel GROUP ALL"
+            ))
+                .bind(("user", user))
+                .bind(("channel", channel)),
+        };
+
+        let res = query
+            .await
+            .map_err(RepositoryError::from)?
+            .take::<Option<u64>>((0, "count"));
+
+        match res {
+            Ok(res) => Ok(res.unwrap_or(0)),
+            Err(e) => Err(RepositoryError::from(e)),
+        }
+    }
+
+    fn delete_by_channel_id_in(
+        &self,
+        transaction: &mut SurrealTransaction,
+        channel_id: &ChannelId,
+    ) -> Result<(), RepositoryError> {
+        let channel_id = transaction.bind(Thing::from((
+            CHANNEL_COLLECTION_NAME.to_string(),
+            channel_id.to_string(),
+        )))?;
+        transaction.push(format!(
+            "DELETE {COLLECTION_NAME} WHERE channel = {}",
+            channel_id
+        ));
+
+        Ok(())
+    }
+
+    fn delete_by_message_id_in(
+        &self,
+        transaction: &mut SurrealTransaction,
+        message_id: &MessageId,
+    ) -> Result<(), RepositoryError> {
+        let message_id = transaction.bind(Thing::from((
+            MESSAGE_COLLECTION_NAME.to_string(),
+            message_id.to_string(),
+        )))?;
+        transaction.push(format!(
+            "DELETE {COLLECTION_NAME} WHERE message = {}",
+            message_id
+        ));
+
+        Ok(())
+    }
+
+    fn delete_by_user_id_in(
+        &self,
+        transaction: &mut SurrealTransaction,
+        user_id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        let user_id = transaction.bind(Thing::from((
+            USER_COLLECTION_NAME.to_string(),
+            user_id.to_string(),
+        )))?;
+        transaction.push(format!("DELETE {COLLECTION_NAME} WHERE user = {}", user_id));
+
+        Ok(())
+    }
+}
+
+pub fn serialize_id<S>(id: &MentionId, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let surreal_id = Thing::from((COLLECTION_NAME.to_string(), id.to_string()));
+    surreal_id.serialize(s)
+}
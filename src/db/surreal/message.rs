@@ -1,14 +1,20 @@
-use super::channel::COLLECTION_NAME as CHANNEL_COLLECTION_NAME;
+use super::{
+    channel::COLLECTION_NAME as CHANNEL_COLLECTION_NAME, condition, cursor, paged,
+    user::COLLECTION_NAME as USER_COLLECTION_NAME, SurrealTransaction,
+};
 use crate::{
-    db::traits::message::MessageRepository,
+    db::{error::RepositoryError, filter::Filter, page::Page, traits::message::MessageRepository},
     models::{
         channel::ChannelId,
         message::{DbMessage, MessageId},
+        user::UserId,
     },
 };
+use std::collections::BTreeMap;
+
 use serde::{Serialize, Serializer};
 use surrealdb::{
-    engine::remote::ws::Client,
+    engine::any::Any,
     sql::{Id, Thing},
     Surreal,
 };
@@ -26,71 +32,245 @@ impl MessageRepositoryImpl {
 }
 
 #[async_trait]
-impl MessageRepository<Surreal<Client>> for MessageRepositoryImpl {
-    async fn get(&self, db: &Surreal<Client>, id: &MessageId) -> Result<Option<DbMessage>, String> {
+impl MessageRepository<Surreal<Any>> for MessageRepositoryImpl {
+    async fn get(
+        &self,
+        db: &Surreal<Any>,
+        id: &MessageId,
+    ) -> Result<Option<DbMessage>, RepositoryError> {
         let res = db.select((COLLECTION_NAME, id.to_string())).await;
 
         match res {
             Ok(res) => Ok(res),
-            Err(e) => Err(e.to_string()),
+            Err(e) => Err(RepositoryError::from(e)),
         }
     }
 
-    async fn delete(&self, db: &Surreal<Client>, id: &MessageId) -> Result<u8, String> {
-        db.delete::<Option<DbMessage>>((COLLECTION_NAME, id.to_string()))
+    async fn get_list_by_ids(
+        &self,
+        db: &Surreal<Any>,
+        ids: &[MessageId],
+    ) -> Result<Vec<DbMessage>, RepositoryError> {
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let ids = ids
+            .iter()
+            .map(|id| Thing {
+                tb: COLLECTION_NAME.to_string(),
+                id: Id::String(id.to_string()),
+            })
+            .collect::<Vec<Thing>>();
+
+        let res = db
+            .query(format!(
+                "SELECT * FROM {COLLECTION_NAME} WHERE id INSIDE $ids"
+            ))
+            .bind(("ids", ids))
             .await
-            .unwrap();
+            .map_err(RepositoryError::from)?
+            .take::<Vec<DbMessage>>(0);
 
-        Ok(1)
+        match res {
+            Ok(res) => Ok(res),
+            Err(e) => Err(RepositoryError::from(e)),
+        }
+    }
+
+    async fn update(
+        &self,
+        db: &Surreal<Any>,
+        message: &DbMessage,
+    ) -> Result<Option<DbMessage>, RepositoryError> {
+        db.query("UPDATE $id CONTENT $content RETURN NONE")
+            .bind((
+                "id",
+                Thing::from((COLLECTION_NAME.to_string(), message.id.to_string())),
+            ))
+            .bind(("content", message))
+            .await
+            .map_err(RepositoryError::from)?
+            .check()
+            .map_err(RepositoryError::from)?;
+
+        self.get(db, &message.id).await
     }
 
     async fn add(
         &self,
-        db: &Surreal<Client>,
+        db: &Surreal<Any>,
         message: &DbMessage,
-    ) -> Result<Option<DbMessage>, String> {
+    ) -> Result<Option<DbMessage>, RepositoryError> {
         let created: Option<DbMessage> = db
             .create((COLLECTION_NAME, message.id.to_string()))
             .content(message)
             .await
-            .unwrap();
+            .map_err(RepositoryError::from)?;
 
         Ok(created)
     }
 
     async fn get_list_by_chnanel_id(
         &self,
-        db: &Surreal<Client>,
+        db: &Surreal<Any>,
+        channel_id: &ChannelId,
+        filter: &Filter,
+        page: Page,
+    ) -> Result<Vec<DbMessage>, RepositoryError> {
+        let channel = Thing {
+            tb: CHANNEL_COLLECTION_NAME.to_string(),
+            id: Id::String(channel_id.to_string()),
+        };
+
+        let mut bindings = BTreeMap::new();
+        let condition = condition(filter, &[], &mut bindings);
+
+        let res = db
+            .query(format!(
+                "SELECT * FROM {COLLECTION_NAME} WHERE channel == $channel AND {} AND {}",
+                condition,
+                paged(&page)
+            ))
+            .bind(bindings)
+            .bind(("channel", channel))
+            .bind(("cursor", cursor(COLLECTION_NAME, &page)))
+            .bind(("limit", page.limit))
+            .await
+            .map_err(RepositoryError::from)?
+            .take::<Vec<DbMessage>>(0);
+
+        match res {
+            Ok(res) => Ok(page.finish(res)),
+            Err(e) => Err(RepositoryError::from(e)),
+        }
+    }
+
+    async fn get_pinned_list_by_channel_id(
+        &self,
+        db: &Surreal<Any>,
         channel_id: &ChannelId,
-        page_size: i32,
-        offset_id: Option<MessageId>,
-    ) -> Result<Vec<DbMessage>, String> {
+    ) -> Result<Vec<DbMessage>, RepositoryError> {
         let channel = Thing {
             tb: CHANNEL_COLLECTION_NAME.to_string(),
             id: Id::String(channel_id.to_string()),
         };
 
-        let query = match offset_id {
-            Some(offset_id) => db.query(format!(
-            "SELECT * FROM {COLLECTION_NAME} WHERE channel == $channel AND id < $offset_id ORDER BY id DESC LIMIT $page_size"
-        ))
+        let res = db
+            .query(format!(
+                "SELECT * FROM {COLLECTION_NAME} WHERE channel == $channel AND pin_time != NONE ORDER BY pin_time DESC"
+            ))
+            .bind(("channel", channel))
+            .await
+            .map_err(RepositoryError::from)?
+            .take::<Vec<DbMessage>>(0);
+
+        match res {
+            Ok(res) => Ok(res),
+            Err(e) => Err(RepositoryError::from(e)),
+        }
+    }
+
+    async fn count_unread(
+        &self,
+        db: &Surreal<Any>,
+        channel_id: &ChannelId,
+        user_id: &UserId,
+        after: Option<MessageId>,
+    ) -> Result<u64, RepositoryError> {
+        let channel = Thing {
+            tb: CHANNEL_COLLECTION_NAME.to_string(),
+            id: Id::String(channel_id.to_string()),
+        };
+
+        let user = Thing {
+            tb: USER_COLLECTION_NAME.to_string(),
+            id: Id::String(user_id.to_string()),
+        };
+
+        let query = match after {
+            Some(after) => db.query(format!(
+                "SELECT count() FROM {COLLECTION_NAME} WHERE channel == $channel AND author != $user AND id > $after GROUP ALL"
+            ))
                 .bind(("channel", channel))
-                .bind(("offset_id", offset_id))
-                .bind(("page_size", page_size)),
+                .bind(("user", user))
+                .bind(("after", Thing::from((COLLECTION_NAME.to_string(), after.to_string())))),
             None => db.query(format!(
-                "SELECT * FROM {COLLECTION_NAME} WHERE channel == $channel ORDER BY id DESC LIMIT $page_size"
+                "SELECT count() FROM {COLLECTION_NAME} WHERE channel == $channel AND author != $user GROUP ALL"
             ))
                 .bind(("channel", channel))
-                .bind(("page_size", page_size)),
+                .bind(("user", user)),
         };
 
-        let res = query.await.unwrap().take::<Vec<DbMessage>>(0);
+        let res = query
+            .await
+            .map_err(RepositoryError::from)?
+            .take::<Option<u64>>((0, "count"));
+
+        match res {
+            Ok(res) => Ok(res.unwrap_or(0)),
+            Err(e) => Err(RepositoryError::from(e)),
+        }
+    }
+
+    async fn get_list_by_channel_id_in_range(
+        &self,
+        db: &Surreal<Any>,
+        channel_id: &ChannelId,
+        start_id: &MessageId,
+        end_id: &MessageId,
+        limit: i32,
+    ) -> Result<Vec<DbMessage>, RepositoryError> {
+        let channel = Thing {
+            tb: CHANNEL_COLLECTION_NAME.to_string(),
+            id: Id::String(channel_id.to_string()),
+        };
+
+        let res = db
+            .query(format!(
+                "SELECT * FROM {COLLECTION_NAME} WHERE channel == $channel AND id >= $start_id AND id <= $end_id ORDER BY id DESC LIMIT $limit"
+            ))
+            .bind(("channel", channel))
+            .bind(("start_id", Thing::from((COLLECTION_NAME.to_string(), start_id.to_string()))))
+            .bind(("end_id", Thing::from((COLLECTION_NAME.to_string(), end_id.to_string()))))
+            .bind(("limit", limit))
+            .await
+            .map_err(RepositoryError::from)?
+            .take::<Vec<DbMessage>>(0);
 
         match res {
             Ok(res) => Ok(res),
-            Err(e) => Err(e.to_string()),
+            Err(e) => Err(RepositoryError::from(e)),
         }
     }
+
+    fn delete_in(
+        &self,
+        transaction: &mut SurrealTransaction,
+        id: &MessageId,
+    ) -> Result<(), RepositoryError> {
+        let id = transaction.bind(Thing::from((COLLECTION_NAME.to_string(), id.to_string())))?;
+        transaction.push(format!("DELETE {}", id));
+
+        Ok(())
+    }
+
+    fn delete_by_channel_id_in(
+        &self,
+        transaction: &mut SurrealTransaction,
+        channel_id: &ChannelId,
+    ) -> Result<(), RepositoryError> {
+        let channel_id = transaction.bind(Thing::from((
+            CHANNEL_COLLECTION_NAME.to_string(),
+            channel_id.to_string(),
+        )))?;
+        transaction.push(format!(
+            "DELETE {COLLECTION_NAME} WHERE channel = {}",
+            channel_id
+        ));
+
+        Ok(())
+    }
 }
 
 pub fn serialize_id<S>(id: &MessageId, s: S) -> Result<S::Ok, S::Error>
@@ -1,10 +1,15 @@
 use super::{
-    message::COLLECTION_NAME as MESSAGE_COLLECTION_NAME,
-    user::COLLECTION_NAME as USER_COLLECTION_NAME,
+    channel::COLLECTION_NAME as CHANNEL_COLLECTION_NAME, cursor,
+    message::COLLECTION_NAME as MESSAGE_COLLECTION_NAME, paged,
+    user::COLLECTION_NAME as USER_COLLECTION_NAME, SurrealTransaction,
 };
 use crate::{
-    db::traits::message_acknowledge::MessageAcknowledgeRepository,
+    db::{
+        error::RepositoryError, page::Page,
+        traits::message_acknowledge::MessageAcknowledgeRepository,
+    },
     models::{
+        channel::ChannelId,
         message::MessageId,
         message_acknowledge::{DbMessageAcknowledge, MessageAcknowledgeId},
         user::UserId,
@@ -12,7 +17,7 @@ use crate::{
 };
 use serde::{Serialize, Serializer};
 use surrealdb::{
-    engine::remote::ws::Client,
+    engine::any::Any,
     sql::{Id, Thing},
     Surreal,
 };
@@ -30,100 +35,145 @@ impl MessageAcknowledgeRepositoryImpl {
 }
 
 #[async_trait]
-impl MessageAcknowledgeRepository<Surreal<Client>> for MessageAcknowledgeRepositoryImpl {
-    async fn get(
-        &self,
-        db: &Surreal<Client>,
-        id: MessageAcknowledgeId,
-    ) -> Result<Option<DbMessageAcknowledge>, String> {
-        let res = db.select((COLLECTION_NAME, id.to_string())).await;
-
-        match res {
-            Ok(res) => Ok(res),
-            Err(e) => Err(e.to_string()),
-        }
-    }
-
-    async fn get_by_message_and_user(
+impl MessageAcknowledgeRepository<Surreal<Any>> for MessageAcknowledgeRepositoryImpl {
+    async fn get_list_by_message(
         &self,
-        db: &Surreal<Client>,
+        db: &Surreal<Any>,
         message_id: &MessageId,
-        user_id: &UserId,
-    ) -> Result<Option<DbMessageAcknowledge>, String> {
+        page: Page,
+    ) -> Result<Vec<DbMessageAcknowledge>, RepositoryError> {
         let message = Thing {
             tb: MESSAGE_COLLECTION_NAME.to_string(),
             id: Id::String(message_id.to_string()),
         };
 
-        let user = Thing {
-            tb: USER_COLLECTION_NAME.to_string(),
-            id: Id::String(user_id.to_string()),
-        };
-
         let res = db
             .query(format!(
-                "SELECT * FROM {COLLECTION_NAME} WHERE message_id == $message_id AND user_id == $user_id"
+                "SELECT * FROM {COLLECTION_NAME} WHERE message_id == $message_id AND {}",
+                paged(&page)
             ))
             .bind(("message_id", message))
-            .bind(("user_id", user))
+            .bind(("cursor", cursor(COLLECTION_NAME, &page)))
+            .bind(("limit", page.limit))
             .await
-            .unwrap()
-            .take::<Option<DbMessageAcknowledge>>(0);
+            .map_err(RepositoryError::from)?
+            .take::<Vec<DbMessageAcknowledge>>(0);
 
         match res {
-            Ok(res) => Ok(res),
-            Err(e) => Err(e.to_string()),
+            Ok(res) => Ok(page.finish(res)),
+            Err(e) => Err(RepositoryError::from(e)),
         }
     }
 
-    async fn get_list_by_message(
+    async fn add_list(
         &self,
-        db: &Surreal<Client>,
-        message_id: &MessageId,
-        page_size: i32,
-        offset_id: Option<MessageAcknowledgeId>,
-    ) -> Result<Vec<DbMessageAcknowledge>, String> {
-        let message = Thing {
-            tb: MESSAGE_COLLECTION_NAME.to_string(),
-            id: Id::String(message_id.to_string()),
+        db: &Surreal<Any>,
+        message_acknowledges: &[DbMessageAcknowledge],
+    ) -> Result<(), RepositoryError> {
+        if message_acknowledges.is_empty() {
+            return Ok(());
+        }
+
+        db.query(format!(
+            "INSERT INTO {COLLECTION_NAME} $message_acknowledges RETURN NONE"
+        ))
+        .bind(("message_acknowledges", message_acknowledges))
+        .await
+        .map_err(RepositoryError::from)?
+        .check()
+        .map_err(RepositoryError::from)?;
+
+        Ok(())
+    }
+
+    async fn get_list_by_user_and_messages(
+        &self,
+        db: &Surreal<Any>,
+        user_id: &UserId,
+        message_ids: &[MessageId],
+    ) -> Result<Vec<DbMessageAcknowledge>, RepositoryError> {
+        if message_ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let user = Thing {
+            tb: USER_COLLECTION_NAME.to_string(),
+            id: Id::String(user_id.to_string()),
         };
 
-        let query = match offset_id {
-            Some(offset_id) => db
-            .query(format!(
-                "SELECT * FROM {COLLECTION_NAME} WHERE message_id == $message_id AND id < $offset_id ORDER BY id DESC LIMIT $page_size"
-            ))
-                .bind(("message_id", message))
-                .bind(("offset_id", offset_id))
-                .bind(("page_size", page_size)),
-            None => db
+        let messages = message_ids
+            .iter()
+            .map(|id| Thing {
+                tb: MESSAGE_COLLECTION_NAME.to_string(),
+                id: Id::String(id.to_string()),
+            })
+            .collect::<Vec<Thing>>();
+
+        let res = db
             .query(format!(
-                "SELECT * FROM {COLLECTION_NAME} WHERE message_id == $message_id ORDER BY id DESC LIMIT $page_size"
+                "SELECT * FROM {COLLECTION_NAME} WHERE user_id == $user_id AND message_id INSIDE $message_ids"
             ))
-                .bind(("message_id", message))
-                .bind(("page_size", page_size)),
-        };
-
-        let res = query.await.unwrap().take::<Vec<DbMessageAcknowledge>>(0);
+            .bind(("user_id", user))
+            .bind(("message_ids", messages))
+            .await
+            .map_err(RepositoryError::from)?
+            .take::<Vec<DbMessageAcknowledge>>(0);
 
         match res {
             Ok(res) => Ok(res),
-            Err(e) => Err(e.to_string()),
+            Err(e) => Err(RepositoryError::from(e)),
         }
     }
 
-    async fn add(
+    fn delete_by_channel_id_in(
         &self,
-        db: &Surreal<Client>,
-        message_acknowledge: &DbMessageAcknowledge,
-    ) -> Result<Option<DbMessageAcknowledge>, String> {
-        let created: Option<DbMessageAcknowledge> = db
-            .create((COLLECTION_NAME, message_acknowledge.id.to_string()))
-            .content(message_acknowledge)
-            .await
-            .unwrap();
+        transaction: &mut SurrealTransaction,
+        channel_id: &ChannelId,
+    ) -> Result<(), RepositoryError> {
+        let channel_id = transaction.bind(Thing::from((
+            CHANNEL_COLLECTION_NAME.to_string(),
+            channel_id.to_string(),
+        )))?;
+        transaction.push(format!(
+            "DELETE {COLLECTION_NAME} WHERE message_id.channel = {}",
+            channel_id
+        ));
+
+        Ok(())
+    }
 
-        Ok(created)
+    fn delete_by_message_id_in(
+        &self,
+        transaction: &mut SurrealTransaction,
+        message_id: &MessageId,
+    ) -> Result<(), RepositoryError> {
+        let message_id = transaction.bind(Thing::from((
+            MESSAGE_COLLECTION_NAME.to_string(),
+            message_id.to_string(),
+        )))?;
+        transaction.push(format!(
+            "DELETE {COLLECTION_NAME} WHERE message_id = {}",
+            message_id
+        ));
+
+        Ok(())
+    }
+
+    fn delete_by_user_id_in(
+        &self,
+        transaction: &mut SurrealTransaction,
+        user_id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        let user_id = transaction.bind(Thing::from((
+            USER_COLLECTION_NAME.to_string(),
+            user_id.to_string(),
+        )))?;
+        transaction.push(format!(
+            "DELETE {COLLECTION_NAME} WHERE user_id = {}",
+            user_id
+        ));
+
+        Ok(())
     }
 }
 
@@ -0,0 +1,132 @@
+use super::{
+    channel::COLLECTION_NAME as CHANNEL_COLLECTION_NAME, cursor,
+    message::COLLECTION_NAME as MESSAGE_COLLECTION_NAME, paged,
+    user::COLLECTION_NAME as USER_COLLECTION_NAME,
+};
+use crate::{
+    db::{error::RepositoryError, page::Page, traits::message_search::MessageSearchIndex},
+    models::{
+        message::{DbMessage, MessageId},
+        message_search::{MessageSearchHit, MessageSearchQuery, HIGHLIGHT_END, HIGHLIGHT_START},
+    },
+};
+use surrealdb::{
+    engine::any::Any,
+    sql::{Id, Thing},
+    Surreal,
+};
+use tonic::async_trait;
+
+/// backed by the `message_content_search` index defined in `migrations/surreal`, which
+/// SurrealDB keeps up to date on every write.
+#[derive(Clone)]
+pub struct MessageSearchIndexImpl {}
+
+impl MessageSearchIndexImpl {
+    pub async fn new() -> Self {
+        MessageSearchIndexImpl {}
+    }
+}
+
+#[async_trait]
+impl MessageSearchIndex<Surreal<Any>> for MessageSearchIndexImpl {
+    async fn index_message(
+        &self,
+        _db: &Surreal<Any>,
+        _message: &DbMessage,
+    ) -> Result<(), RepositoryError> {
+        Ok(())
+    }
+
+    async fn remove_message(
+        &self,
+        _db: &Surreal<Any>,
+        _id: &MessageId,
+    ) -> Result<(), RepositoryError> {
+        Ok(())
+    }
+
+    async fn search(
+        &self,
+        db: &Surreal<Any>,
+        query: &MessageSearchQuery,
+        page: Page,
+    ) -> Result<Vec<MessageSearchHit>, RepositoryError> {
+        if query.channels.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let channels = query
+            .channels
+            .iter()
+            .map(|id| Thing {
+                tb: CHANNEL_COLLECTION_NAME.to_string(),
+                id: Id::String(id.to_string()),
+            })
+            .collect::<Vec<Thing>>();
+
+        let (highlighted, mut conditions) = if query.query.is_empty() {
+            ("content".to_string(), vec!["channel INSIDE $channels"])
+        } else {
+            (
+                format!("search::highlight('{HIGHLIGHT_START}', '{HIGHLIGHT_END}', 1)"),
+                vec!["content @1@ $query", "channel INSIDE $channels"],
+            )
+        };
+
+        if query.author.is_some() {
+            conditions.push("author == $author");
+        }
+        if query.mentions.is_some() {
+            conditions.push("mentions.users CONTAINS $mentions");
+        }
+        match query.has_attachment {
+            Some(true) => conditions.push("array::len(attachments) > 0"),
+            Some(false) => conditions.push("array::len(attachments) == 0"),
+            None => {}
+        }
+        if query.start_time.is_some() {
+            conditions.push("create_time >= $start_time");
+        }
+        if query.end_time.is_some() {
+            conditions.push("create_time < $end_time");
+        }
+        match query.pinned {
+            Some(true) => conditions.push("pin_time != NONE"),
+            Some(false) => conditions.push("pin_time == NONE"),
+            None => {}
+        }
+        let res = db
+            .query(format!(
+                "SELECT id, {highlighted} AS highlighted FROM {MESSAGE_COLLECTION_NAME} WHERE {} AND {}",
+                conditions.join(" AND "),
+                paged(&page)
+            ))
+            .bind(("query", query.query.clone()))
+            .bind(("channels", channels))
+            .bind((
+                "author",
+                query
+                    .author
+                    .map(|id| Thing::from((USER_COLLECTION_NAME.to_string(), id.to_string()))),
+            ))
+            .bind((
+                "mentions",
+                query
+                    .mentions
+                    .map(|id| Thing::from((USER_COLLECTION_NAME.to_string(), id.to_string()))),
+            ))
+            .bind(("start_time", query.start_time.clone()))
+            .bind(("end_time", query.end_time.clone()))
+            .bind(("cursor", cursor(MESSAGE_COLLECTION_NAME, &page)))
+            .bind(("limit", page.limit))
+            .await
+            .map_err(RepositoryError::from)?
+            .take::<Vec<MessageSearchHit>>(0);
+
+        match res {
+            Ok(res) => Ok(page.finish(res)),
+            Err(e) => Err(RepositoryError::from(e)),
+        }
+    }
+}
@@ -1,52 +1,568 @@
-// pub mod attachment;
+pub mod attachment;
 pub mod auth;
 pub mod channel;
+pub mod mention;
 pub mod message;
 pub mod message_acknowledge;
+pub mod message_search;
+pub mod read_state;
+pub mod schema_version;
 pub mod server;
 pub mod server_category;
 pub mod server_member;
 pub mod user;
 
-use serde::{Deserialize, Deserializer};
+use std::{collections::BTreeMap, str::FromStr, time::Duration};
+
+use serde::{de, Deserialize, Deserializer, Serialize};
 use surrealdb::{
-    engine::remote::ws::{Client, Ws},
-    opt::auth::Root,
-    sql::Thing,
+    engine::any::{self, Any},
+    opt::auth::Database as Credentials,
+    sql::{self, Thing, Value},
     Surreal,
 };
+use tonic::async_trait;
 use ulid::Ulid;
 
-pub async fn conn() -> Surreal<Client> {
-    let db = Surreal::new::<Ws>("127.0.0.1:8000").await.unwrap();
+use crate::db::{
+    error::RepositoryError,
+    filter::{self, Filter, Operator},
+    migration::Migration,
+    page::Page,
+    Database, Repositories,
+};
+
+/// delay before the first reconnect, doubled on every failed attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// `migrations/surreal`, in order.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration::new(
+        1,
+        "initial",
+        include_str!("../../../migrations/surreal/0001_initial.surql"),
+    ),
+    Migration::new(
+        2,
+        "mentions",
+        include_str!("../../../migrations/surreal/0002_mentions.surql"),
+    ),
+    Migration::new(
+        3,
+        "pinned_messages",
+        include_str!("../../../migrations/surreal/0003_pinned_messages.surql"),
+    ),
+    Migration::new(
+        4,
+        "message_search",
+        include_str!("../../../migrations/surreal/0004_message_search.surql"),
+    ),
+    Migration::new(
+        5,
+        "direct_channels",
+        include_str!("../../../migrations/surreal/0005_direct_channels.surql"),
+    ),
+    Migration::new(
+        6,
+        "group_channels",
+        include_str!("../../../migrations/surreal/0006_group_channels.surql"),
+    ),
+    Migration::new(
+        7,
+        "read_states",
+        include_str!("../../../migrations/surreal/0007_read_states.surql"),
+    ),
+    Migration::new(
+        8,
+        "read_receipts",
+        include_str!("../../../migrations/surreal/0008_read_receipts.surql"),
+    ),
+    Migration::new(
+        9,
+        "server_categories",
+        include_str!("../../../migrations/surreal/0009_server_categories.surql"),
+    ),
+];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SurrealConfig {
+    pub engine: SurrealEngine,
+    /// `host:port` of the SurrealDB server, for the `remote` engine.
+    pub endpoint: String,
+    /// data directory of the `rocksdb` engine.
+    pub path: String,
+    pub namespace: String,
+    pub database: String,
+    /// a database level user, the server never signs in as root. unused by embedded engines,
+    /// which only the process itself can reach.
+    pub username: String,
+    pub password: String,
+    /// attempts before startup gives up, 0 retries forever.
+    pub connect_attempts: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SurrealEngine {
+    /// a SurrealDB server over WebSocket.
+    #[default]
+    Remote,
+    /// embedded, kept in memory until the process exits.
+    Memory,
+    /// embedded, stored in `path`. needs the `kv-rocksdb` feature.
+    RocksDb,
+}
+
+impl SurrealEngine {
+    pub fn is_embedded(&self) -> bool {
+        *self != SurrealEngine::Remote
+    }
+}
+
+impl FromStr for SurrealEngine {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "remote" => Ok(SurrealEngine::Remote),
+            "memory" => Ok(SurrealEngine::Memory),
+            "rocksdb" => Ok(SurrealEngine::RocksDb),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Default for SurrealConfig {
+    fn default() -> Self {
+        SurrealConfig {
+            engine: SurrealEngine::Remote,
+            endpoint: "127.0.0.1:8000".to_string(),
+            path: "./data/surreal".to_string(),
+            namespace: "ycchat".to_string(),
+            database: "ycchat".to_string(),
+            username: String::new(),
+            password: String::new(),
+            connect_attempts: 10,
+        }
+    }
+}
+
+impl SurrealConfig {
+    /// the address `surrealdb::engine::any` picks the engine from.
+    fn address(&self) -> String {
+        match self.engine {
+            SurrealEngine::Remote => format!("ws://{}", self.endpoint),
+            SurrealEngine::Memory => "mem://".to_string(),
+            SurrealEngine::RocksDb => format!("rocksdb://{}", self.path),
+        }
+    }
+}
+
+pub type SurrealRepositories = Repositories<
+    auth::AuthRepositoryImpl,
+    user::UserRepositoryImpl,
+    server::ServerRepositoryImpl,
+    server_category::ServerCategoryRepositoryImpl,
+    server_member::ServerMemberRepositoryImpl,
+    channel::ChannelRepositoryImpl,
+    message::MessageRepositoryImpl,
+    message_acknowledge::MessageAcknowledgeRepositoryImpl,
+    attachment::AttachmentRepositoryImpl,
+    mention::MentionRepositoryImpl,
+    message_search::MessageSearchIndexImpl,
+    read_state::ReadStateRepositoryImpl,
+>;
+
+/// the repositories of a SurrealDB server.
+pub async fn repositories() -> SurrealRepositories {
+    Repositories {
+        auth: auth::AuthRepositoryImpl::new().await,
+        user: user::UserRepositoryImpl::new().await,
+        server: server::ServerRepositoryImpl::new().await,
+        server_category: server_category::ServerCategoryRepositoryImpl::new().await,
+        server_member: server_member::ServerMemberRepositoryImpl::new().await,
+        channel: channel::ChannelRepositoryImpl::new().await,
+        message: message::MessageRepositoryImpl::new().await,
+        message_acknowledge: message_acknowledge::MessageAcknowledgeRepositoryImpl::new().await,
+        attachment: attachment::AttachmentRepositoryImpl::new().await,
+        mention: mention::MentionRepositoryImpl::new().await,
+        message_search: message_search::MessageSearchIndexImpl::new().await,
+        read_state: read_state::ReadStateRepositoryImpl::new().await,
+    }
+}
+
+/// opens the client shared by every repository. the client reconnects on its own when the
+/// server restarts and replays the sign in and namespace selection, so it's created once.
+pub async fn connect(config: &SurrealConfig) -> Result<Surreal<Any>, surrealdb::Error> {
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 1;
+
+    loop {
+        match try_connect(config).await {
+            Ok(db) => return Ok(db),
+            // an embedded engine failing to open won't get better by waiting.
+            Err(err)
+                if !config.engine.is_embedded()
+                    && (config.connect_attempts == 0 || attempt < config.connect_attempts) =>
+            {
+                eprintln!(
+                    "failed to connect to surrealdb at {} (attempt {}), retrying in {:?}: {}",
+                    config.address(),
+                    attempt,
+                    backoff,
+                    err
+                );
+
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+async fn try_connect(config: &SurrealConfig) -> Result<Surreal<Any>, surrealdb::Error> {
+    let db = any::connect(config.address()).await?;
+
+    if !config.engine.is_embedded() {
+        db.signin(Credentials {
+            namespace: &config.namespace,
+            database: &config.database,
+            username: &config.username,
+            password: &config.password,
+        })
+        .await?;
+    }
+
+    db.use_ns(&config.namespace)
+        .use_db(&config.database)
+        .await?;
+
+    Ok(db)
+}
+
+/// statements run as one `BEGIN TRANSACTION; ... COMMIT TRANSACTION;` query, a SurrealDB
+/// transaction can't span several queries.
+#[derive(Default)]
+pub struct SurrealTransaction {
+    statements: Vec<String>,
+    bindings: BTreeMap<String, Value>,
+}
+
+impl SurrealTransaction {
+    /// binds `value` to a parameter of its own and returns its `$name`.
+    fn bind(&mut self, value: impl Serialize) -> Result<String, RepositoryError> {
+        let name = format!("p{}", self.bindings.len());
+        let value = sql::to_value(value).map_err(surrealdb::Error::from)?;
+
+        self.bindings.insert(name.clone(), value);
+
+        Ok(format!("${}", name))
+    }
+
+    fn push(&mut self, statement: String) {
+        self.statements.push(statement);
+    }
+}
+
+#[async_trait]
+impl Database for Surreal<Any> {
+    type Transaction = SurrealTransaction;
+
+    async fn commit(&self, transaction: SurrealTransaction) -> Result<(), RepositoryError> {
+        if transaction.statements.is_empty() {
+            return Ok(());
+        }
+
+        let mut query = "BEGIN TRANSACTION;\n".to_string();
+        for statement in &transaction.statements {
+            query.push_str(statement);
+            query.push_str(";\n");
+        }
+        query.push_str("COMMIT TRANSACTION;");
+
+        let mut errors = self
+            .query(query)
+            .bind(transaction.bindings)
+            .await?
+            .take_errors()
+            .into_iter()
+            .collect::<Vec<_>>();
+        errors.sort_by_key(|(index, _)| *index);
+
+        if errors.is_empty() {
+            return Ok(());
+        }
 
-    db.signin(Root {
-        username: "root",
-        password: "root",
-    })
-    .await
-    .unwrap();
+        // every statement of a failed transaction errors, report the one that failed it.
+        let failed = errors
+            .iter()
+            .position(|(_, err)| !err.to_string().contains("not executed due to a failed"))
+            .unwrap_or(0);
 
-    db.use_ns("ycchat").use_db("ycchat").await.unwrap();
+        Err(RepositoryError::from(errors.swap_remove(failed).1))
+    }
+}
+
+/// the keyset condition of `page`, closing a `WHERE`. binds `$cursor` to `cursor(table, page)`
+/// and `$limit` to the limit of the page.
+fn paged(page: &Page) -> String {
+    format!(
+        "($cursor == NONE OR id {} $cursor) ORDER BY id {} LIMIT $limit",
+        page.operator(),
+        page.direction()
+    )
+}
 
-    db
+fn cursor(table: &str, page: &Page) -> Option<Thing> {
+    page.cursor_id()
+        .map(|id| Thing::from((table.to_string(), id.to_string())))
 }
 
-pub fn deserialize_id<'de, D>(deserializer: D) -> Result<String, D::Error>
+/// `filter` as a condition on the fields of a record, stored under their own names unless
+/// `columns` renames them. binds the values to `bindings` as `$f0`, `$f1`, ...
+fn condition(
+    filter: &Filter,
+    columns: &[(&str, &str)],
+    bindings: &mut BTreeMap<String, Value>,
+) -> String {
+    let mut join = |filters: &[Filter], separator: &str| {
+        filters
+            .iter()
+            .map(|filter| format!("({})", condition(filter, columns, bindings)))
+            .collect::<Vec<String>>()
+            .join(separator)
+    };
+
+    match filter {
+        // `AND` of nothing holds, `OR` of nothing doesn't.
+        Filter::And(filters) if filters.is_empty() => "true".to_string(),
+        Filter::Or(filters) if filters.is_empty() => "false".to_string(),
+        Filter::And(filters) => join(filters, " AND "),
+        Filter::Or(filters) => join(filters, " OR "),
+        Filter::Not(filter) => format!("!({})", condition(filter, columns, bindings)),
+        Filter::Compare {
+            field,
+            operator,
+            value,
+        } => {
+            let column = columns
+                .iter()
+                .find(|(name, _)| name == field)
+                .map_or(*field, |(_, column)| column);
+
+            let name = format!("f{}", bindings.len());
+            let value = match value {
+                filter::Value::Text(text) if *operator == Operator::Has => {
+                    Value::from(text.to_lowercase())
+                }
+                filter::Value::Text(text) => Value::from(text.clone()),
+                filter::Value::Time(time) => Value::from(sql::Datetime::from(*time)),
+                filter::Value::User(id) => Value::from(Thing::from((
+                    user::COLLECTION_NAME.to_string(),
+                    id.to_string(),
+                ))),
+            };
+            let is_time = matches!(value, Value::Datetime(_));
+            bindings.insert(name.clone(), value);
+
+            match operator {
+                Operator::Has => {
+                    format!("string::contains(string::lowercase({column}), ${name})")
+                }
+                // like in SQL, a missing time matches no comparison.
+                operator if is_time => format!(
+                    "({column} != NONE AND {column} {} ${name})",
+                    operator.symbol()
+                ),
+                operator => format!("{column} {} ${name}", operator.symbol()),
+            }
+        }
+    }
+}
+
+pub fn deserialize_ulid_id<'de, D>(deserializer: D) -> Result<Ulid, D::Error>
 where
     D: Deserializer<'de>,
 {
     let id = Thing::deserialize(deserializer)?;
 
-    Ok(id.id.to_string())
+    to_ulid(&id)
 }
 
-pub fn deserialize_ulid_id<'de, D>(deserializer: D) -> Result<Ulid, D::Error>
+pub fn deserialize_ulid_id_option<'de, D>(deserializer: D) -> Result<Option<Ulid>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let id = Thing::deserialize(deserializer)?;
-    let id = Ulid::from_string(&id.id.to_string()).unwrap();
+    let id = Option::<Thing>::deserialize(deserializer)?;
+
+    id.as_ref().map(to_ulid).transpose()
+}
+
+pub fn deserialize_ulid_id_list<'de, D>(deserializer: D) -> Result<Vec<Ulid>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let ids = Vec::<Thing>::deserialize(deserializer)?;
+
+    ids.iter().map(to_ulid).collect()
+}
+
+/// the ULID a record id holds, a record stored with another id fails to deserialize.
+fn to_ulid<E: de::Error>(id: &Thing) -> Result<Ulid, E> {
+    Ulid::from_string(&id.id.to_string())
+        .map_err(|err| E::custom(format!("record id `{}`: {}", id, err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::filter::{FieldKind, FilterField};
+
+    const FIELDS: &[FilterField] = &[
+        FilterField::new("content", FieldKind::Text),
+        FilterField::new("pin_time", FieldKind::Time),
+        FilterField::new("user", FieldKind::User),
+    ];
+
+    fn translate(filter: &str) -> (String, BTreeMap<String, Value>) {
+        let filter = Filter::parse(filter, FIELDS).unwrap();
+        let mut bindings = BTreeMap::new();
+        let condition = condition(&filter, &[("user", "in")], &mut bindings);
+
+        (condition, bindings)
+    }
+
+    #[test]
+    fn empty_filter() {
+        assert_eq!(translate("").0, "true");
+    }
+
+    #[test]
+    fn has_compares_lowercase() {
+        let (condition, bindings) = translate("content:Hello");
+
+        assert_eq!(
+            condition,
+            "string::contains(string::lowercase(content), $f0)"
+        );
+        assert_eq!(bindings["f0"], Value::from("hello"));
+    }
+
+    #[test]
+    fn users_are_record_ids_of_renamed_fields() {
+        let (condition, bindings) = translate("user != users/01HGW2N5EQNG50CB2HBCN4A8GT");
+
+        assert_eq!(condition, "in != $f0");
+        assert_eq!(
+            bindings["f0"],
+            Value::from(Thing::from((
+                user::COLLECTION_NAME.to_string(),
+                "01HGW2N5EQNG50CB2HBCN4A8GT".to_string(),
+            )))
+        );
+    }
+
+    #[test]
+    fn times_must_be_set() {
+        let (condition, bindings) =
+            translate("NOT pin_time >= 2024-01-01T00:00:00Z OR content = a");
+
+        assert_eq!(
+            condition,
+            "(!((pin_time != NONE AND pin_time >= $f0))) OR (content = $f1)"
+        );
+        assert!(matches!(bindings["f0"], Value::Datetime(_)));
+        assert_eq!(bindings["f1"], Value::from("a"));
+    }
+
+    #[tokio::test]
+    async fn connect_selects_the_configured_namespace_and_database() {
+        let config = SurrealConfig {
+            engine: SurrealEngine::Memory,
+            namespace: "chat".to_string(),
+            database: "team".to_string(),
+            ..Default::default()
+        };
+
+        let db = connect(&config).await.unwrap();
+        let selected: Vec<String> = db
+            .query("RETURN [session::ns(), session::db()]")
+            .await
+            .unwrap()
+            .take(0)
+            .unwrap();
+
+        assert_eq!(selected, vec!["chat", "team"]);
+    }
+
+    #[tokio::test]
+    async fn connect_gives_up_after_the_configured_attempts() {
+        // nothing listens on port 1.
+        let config = SurrealConfig {
+            endpoint: "127.0.0.1:1".to_string(),
+            connect_attempts: 2,
+            ..Default::default()
+        };
+
+        let started = std::time::Instant::now();
+        assert!(connect(&config).await.is_err());
+        // one retry, after the initial backoff.
+        assert!(started.elapsed() >= INITIAL_BACKOFF);
+        assert!(started.elapsed() < INITIAL_BACKOFF * 3);
+    }
+
+    #[tokio::test]
+    async fn embedded_memory_database_is_migrated_and_stores_records() {
+        use crate::{
+            db::{
+                migration::{migrate, MigrationMode},
+                traits::server::ServerRepository,
+            },
+            models::{server::DbServer, user::UserId},
+            services::ycchat::v1::models::Server,
+        };
+
+        let config = SurrealConfig {
+            engine: SurrealEngine::Memory,
+            ..Default::default()
+        };
+        let db = connect(&config).await.unwrap();
+
+        let schema_version_repository = schema_version::SchemaVersionRepositoryImpl::new().await;
+        migrate(
+            &db,
+            &schema_version_repository,
+            MIGRATIONS,
+            MigrationMode::Apply,
+        )
+        .await
+        .unwrap();
+
+        let server = DbServer::new(
+            UserId::new(),
+            Server {
+                display_name: "server".to_string(),
+                ..Default::default()
+            },
+        );
+        let repositories = repositories().await;
+        let mut transaction = SurrealTransaction::default();
+        repositories
+            .server
+            .add_server_in(&mut transaction, &server)
+            .unwrap();
+        db.commit(transaction).await.unwrap();
 
-    Ok(id)
+        let stored = repositories
+            .server
+            .get_server(&db, &server.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.display_name, "server");
+    }
 }
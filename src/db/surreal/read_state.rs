@@ -0,0 +1,162 @@
+use super::{
+    channel::COLLECTION_NAME as CHANNEL_COLLECTION_NAME,
+    user::COLLECTION_NAME as USER_COLLECTION_NAME, SurrealTransaction,
+};
+use crate::{
+    db::{error::RepositoryError, traits::read_state::ReadStateRepository},
+    models::{
+        channel::ChannelId,
+        read_state::{DbReadState, ReadStateId},
+        user::UserId,
+    },
+};
+use serde::{Serialize, Serializer};
+use surrealdb::{
+    engine::any::Any,
+    sql::{Id, Thing},
+    Surreal,
+};
+use tonic::async_trait;
+
+pub const COLLECTION_NAME: &str = "read_state";
+
+#[derive(Clone)]
+pub struct ReadStateRepositoryImpl {}
+
+impl ReadStateRepositoryImpl {
+    pub async fn new() -> Self {
+        ReadStateRepositoryImpl {}
+    }
+}
+
+#[async_trait]
+impl ReadStateRepository<Surreal<Any>> for ReadStateRepositoryImpl {
+    async fn get(
+        &self,
+        db: &Surreal<Any>,
+        user_id: &UserId,
+        channel_id: &ChannelId,
+    ) -> Result<Option<DbReadState>, RepositoryError> {
+        let user = Thing {
+            tb: USER_COLLECTION_NAME.to_string(),
+            id: Id::String(user_id.to_string()),
+        };
+
+        let channel = Thing {
+            tb: CHANNEL_COLLECTION_NAME.to_string(),
+            id: Id::String(channel_id.to_string()),
+        };
+
+        let res = db
+            .query(format!(
+                "SELECT * FROM {COLLECTION_NAME} WHERE user == $user AND channel == $channel LIMIT 1"
+            ))
+            .bind(("user", user))
+            .bind(("channel", channel))
+            .await
+            .map_err(RepositoryError::from)?
+            .take::<Option<DbReadState>>(0);
+
+        match res {
+            Ok(res) => Ok(res),
+            Err(e) => Err(RepositoryError::from(e)),
+        }
+    }
+
+    async fn get_list_by_user_id(
+        &self,
+        db: &Surreal<Any>,
+        user_id: &UserId,
+        channel_ids: &[ChannelId],
+    ) -> Result<Vec<DbReadState>, RepositoryError> {
+        if channel_ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let user = Thing {
+            tb: USER_COLLECTION_NAME.to_string(),
+            id: Id::String(user_id.to_string()),
+        };
+
+        let channels = channel_ids
+            .iter()
+            .map(|id| Thing {
+                tb: CHANNEL_COLLECTION_NAME.to_string(),
+                id: Id::String(id.to_string()),
+            })
+            .collect::<Vec<Thing>>();
+
+        let res = db
+            .query(format!(
+                "SELECT * FROM {COLLECTION_NAME} WHERE user == $user AND channel INSIDE $channels"
+            ))
+            .bind(("user", user))
+            .bind(("channels", channels))
+            .await
+            .map_err(RepositoryError::from)?
+            .take::<Vec<DbReadState>>(0);
+
+        match res {
+            Ok(res) => Ok(res),
+            Err(e) => Err(RepositoryError::from(e)),
+        }
+    }
+
+    async fn save(
+        &self,
+        db: &Surreal<Any>,
+        read_state: &DbReadState,
+    ) -> Result<(), RepositoryError> {
+        db.query("UPDATE $id CONTENT $content RETURN NONE")
+            .bind((
+                "id",
+                Thing::from((COLLECTION_NAME.to_string(), read_state.id.to_string())),
+            ))
+            .bind(("content", read_state))
+            .await
+            .map_err(RepositoryError::from)?
+            .check()
+            .map_err(RepositoryError::from)?;
+
+        Ok(())
+    }
+
+    fn delete_by_channel_id_in(
+        &self,
+        transaction: &mut SurrealTransaction,
+        channel_id: &ChannelId,
+    ) -> Result<(), RepositoryError> {
+        let channel_id = transaction.bind(Thing::from((
+            CHANNEL_COLLECTION_NAME.to_string(),
+            channel_id.to_string(),
+        )))?;
+        transaction.push(format!(
+            "DELETE {COLLECTION_NAME} WHERE channel = {}",
+            channel_id
+        ));
+
+        Ok(())
+    }
+
+    fn delete_by_user_id_in(
+        &self,
+        transaction: &mut SurrealTransaction,
+        user_id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        let user_id = transaction.bind(Thing::from((
+            USER_COLLECTION_NAME.to_string(),
+            user_id.to_string(),
+        )))?;
+        transaction.push(format!("DELETE {COLLECTION_NAME} WHERE user = {}", user_id));
+
+        Ok(())
+    }
+}
+
+pub fn serialize_id<S>(id: &ReadStateId, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let surreal_id = Thing::from((COLLECTION_NAME.to_string(), id.to_string()));
+    surreal_id.serialize(s)
+}
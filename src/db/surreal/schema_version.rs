@@ -0,0 +1,187 @@
+use surrealdb::{engine::any::Any, Surreal};
+use tonic::async_trait;
+
+use crate::{
+    db::{
+        error::RepositoryError, migration::Migration,
+        traits::schema_version::SchemaVersionRepository,
+    },
+    models::schema_version::DbSchemaVersion,
+};
+
+pub const COLLECTION_NAME: &str = "schema_version";
+
+#[derive(Clone)]
+pub struct SchemaVersionRepositoryImpl {}
+
+impl SchemaVersionRepositoryImpl {
+    pub async fn new() -> Self {
+        SchemaVersionRepositoryImpl {}
+    }
+}
+
+#[async_trait]
+impl SchemaVersionRepository<Surreal<Any>> for SchemaVersionRepositoryImpl {
+    async fn get_list(&self, db: &Surreal<Any>) -> Result<Vec<DbSchemaVersion>, RepositoryError> {
+        let mut res = db
+            .query(format!(
+                "SELECT version, name, checksum, apply_time FROM {COLLECTION_NAME} ORDER BY version;"
+            ))
+            .await
+            .map_err(RepositoryError::from)?;
+
+        res.take::<Vec<DbSchemaVersion>>(0)
+            .map_err(RepositoryError::from)
+    }
+
+    /// SurrealDB has no advisory lock, migrations are applied by one runner at a time: `ycChat
+    /// migrate` before the servers start, or a single server with `auto_migrate`. a runner
+    /// racing another fails on creating the version record and its transaction is cancelled.
+    async fn apply(&self, db: &Surreal<Any>, migration: &Migration) -> Result<(), RepositoryError> {
+        db.query(format!(
+            "BEGIN TRANSACTION;
+            DEFINE TABLE {COLLECTION_NAME} SCHEMAFULL;
+            DEFINE FIELD version ON {COLLECTION_NAME} TYPE int;
+            DEFINE FIELD name ON {COLLECTION_NAME} TYPE string;
+            DEFINE FIELD checksum ON {COLLECTION_NAME} TYPE string;
+            DEFINE FIELD apply_time ON {COLLECTION_NAME} TYPE datetime;
+            {}
+            CREATE type::thing('{COLLECTION_NAME}', $version) CONTENT {{
+                version: $version,
+                name: $name,
+                checksum: $checksum,
+                apply_time: time::now(),
+            }};
+            COMMIT TRANSACTION;",
+            migration.script
+        ))
+        .bind(("version", migration.version))
+        .bind(("name", migration.name))
+        .bind(("checksum", migration.checksum()))
+        .await
+        .map_err(RepositoryError::from)?
+        .check()
+        .map_err(RepositoryError::from)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use surrealdb::engine::any;
+    use ulid::Ulid;
+
+    use super::*;
+    use crate::{
+        db::{
+            migration::{migrate, MigrationError, MigrationMode},
+            surreal::{repositories, MIGRATIONS},
+            traits::{message::MessageRepository, user::UserRepository},
+        },
+        models::message::MessageType,
+    };
+
+    async fn connect() -> Surreal<Any> {
+        let db = any::connect("mem://").await.unwrap();
+        db.use_ns("ycchat").use_db("ycchat").await.unwrap();
+
+        db
+    }
+
+    #[tokio::test]
+    async fn dry_run_and_check_leave_a_new_database_alone() {
+        let db = connect().await;
+        let repository = SchemaVersionRepositoryImpl::new().await;
+
+        migrate(&db, &repository, MIGRATIONS, MigrationMode::DryRun)
+            .await
+            .unwrap();
+        let err = migrate(&db, &repository, MIGRATIONS, MigrationMode::Check)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, MigrationError::Pending(pending) if pending.len() == MIGRATIONS.len())
+        );
+
+        let mut res = db.query("INFO FOR DB").await.unwrap();
+        let tables: Option<serde_json::Value> = res.take((0, "tables")).unwrap();
+        assert_eq!(tables, Some(serde_json::json!({})));
+    }
+
+    #[tokio::test]
+    async fn migrations_upgrade_v1_records() {
+        let db = connect().await;
+        let repository = SchemaVersionRepositoryImpl::new().await;
+        repository.apply(&db, &MIGRATIONS[0]).await.unwrap();
+
+        // a user and a message as v1.0.0 servers wrote them.
+        let (user_id, message_id) = (Ulid::new(), Ulid::new());
+        db.query(
+            "CREATE type::thing('user', $user) CONTENT {
+                display_name: 'user',
+                description: '',
+                region_code: 'KR',
+                language_code: 'ko',
+                time_zone: 'Asia/Seoul',
+            };
+            CREATE type::thing('message', $message) CONTENT {
+                author: type::thing('user', $user),
+                channel: type::thing('channel', $channel),
+                content: 'hello',
+                message_type: 'FIXME',
+                attachments: [],
+            };",
+        )
+        .bind(("user", user_id.to_string()))
+        .bind(("message", message_id.to_string()))
+        .bind(("channel", Ulid::new().to_string()))
+        .await
+        .unwrap()
+        .check()
+        .unwrap();
+
+        migrate(&db, &repository, MIGRATIONS, MigrationMode::Apply)
+            .await
+            .unwrap();
+
+        // filled in on the records, not only defaulted when read.
+        let mut res = db
+            .query("SELECT message_type, mentions.everyone AS everyone FROM type::thing('message', $message)")
+            .query("SELECT VALUE settings.read_receipts FROM type::thing('user', $user)")
+            .bind(("message", message_id.to_string()))
+            .bind(("user", user_id.to_string()))
+            .await
+            .unwrap();
+        let message_type: Option<String> = res.take((0, "message_type")).unwrap();
+        let everyone: Option<bool> = res.take((0, "everyone")).unwrap();
+        let read_receipts: Option<bool> = res.take(1).unwrap();
+        assert_eq!(message_type.as_deref(), Some("DEFAULT"));
+        assert_eq!(everyone, Some(false));
+        assert_eq!(read_receipts, Some(true));
+
+        let repositories = repositories().await;
+        let mut message = repositories
+            .message
+            .get(&db, &message_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(message.message_type, MessageType::Default);
+        assert!(message.mentions.users.is_empty());
+
+        // the records pass the assertions the later migrations added.
+        message.pinned_by = Some(user_id);
+        message.pin_time = Some(Default::default());
+        repositories.message.update(&db, &message).await.unwrap();
+
+        let user = repositories
+            .user
+            .get_user(&db, &user_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(user.settings.read_receipts);
+        repositories.user.update_user(&db, &user).await.unwrap();
+    }
+}
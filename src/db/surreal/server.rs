@@ -1,9 +1,15 @@
+use std::collections::BTreeMap;
+
 use serde::{Serialize, Serializer};
-use surrealdb::{engine::remote::ws::Client, sql::Thing, Surreal};
+use surrealdb::{engine::any::Any, sql::Thing, Surreal};
 use tonic::async_trait;
 
+use super::{
+    condition, cursor, paged, server_member::COLLECTION_NAME as SERVER_MEMBER_COLLECTION_NAME,
+    user::COLLECTION_NAME as USER_COLLECTION_NAME, SurrealTransaction,
+};
 use crate::{
-    db::traits::server::ServerRepository,
+    db::{error::RepositoryError, filter::Filter, page::Page, traits::server::ServerRepository},
     models::{
         server::{DbServer, ServerId},
         user::UserId,
@@ -22,94 +28,155 @@ impl ServerRepositoryImpl {
 pub const COLLECTION_NAME: &str = "server";
 
 #[async_trait]
-impl ServerRepository<Surreal<Client>> for ServerRepositoryImpl {
+impl ServerRepository<Surreal<Any>> for ServerRepositoryImpl {
     async fn get_server(
         &self,
-        db: &Surreal<Client>,
+        db: &Surreal<Any>,
         id: &ServerId,
-    ) -> Result<Option<DbServer>, String> {
+    ) -> Result<Option<DbServer>, RepositoryError> {
+        let id = Thing::from((COLLECTION_NAME.to_string(), id.to_string()));
+
         let res = db
-            .select::<Option<DbServer>>((COLLECTION_NAME, id.to_string()))
-            .await;
+            .query("SELECT * FROM $id FETCH icon")
+            .bind(("id", id))
+            .await
+            .map_err(RepositoryError::from)?
+            .take::<Option<DbServer>>(0);
 
         match res {
             Ok(res) => Ok(res),
-            Err(e) => Err(e.to_string()),
+            Err(e) => Err(RepositoryError::from(e)),
         }
     }
 
-    async fn add_server(
+    async fn update_server(
         &self,
-        db: &Surreal<Client>,
+        db: &Surreal<Any>,
         server: &DbServer,
-    ) -> Result<Option<DbServer>, String> {
-        let created: Option<DbServer> = db
-            .create((COLLECTION_NAME, server.id.to_string()))
-            .content(server)
+    ) -> Result<Option<DbServer>, RepositoryError> {
+        db.query("UPDATE $id CONTENT $content RETURN NONE")
+            .bind((
+                "id",
+                Thing::from((COLLECTION_NAME.to_string(), server.id.to_string())),
+            ))
+            .bind(("content", server))
             .await
-            .unwrap();
-
-        dbg!(&created);
+            .map_err(RepositoryError::from)?
+            .check()
+            .map_err(RepositoryError::from)?;
 
-        Ok(created)
+        self.get_server(db, &server.id).await
     }
 
-    async fn update_server(
+    async fn get_servers(
         &self,
-        db: &Surreal<Client>,
-        server: &DbServer,
-    ) -> Result<Option<DbServer>, String> {
-        let res: Option<DbServer> = db
-            .update((COLLECTION_NAME, server.id.to_string()))
-            .content(server.clone())
+        db: &Surreal<Any>,
+        filter: &Filter,
+        page: Page,
+    ) -> Result<Vec<DbServer>, RepositoryError> {
+        let mut bindings = BTreeMap::new();
+        let condition = condition(filter, &[], &mut bindings);
+
+        let res = db
+            .query(format!(
+                "SELECT * FROM {COLLECTION_NAME} WHERE {} AND {} FETCH icon",
+                condition,
+                paged(&page)
+            ))
+            .bind(bindings)
+            .bind(("cursor", cursor(COLLECTION_NAME, &page)))
+            .bind(("limit", page.limit))
             .await
-            .unwrap();
+            .map_err(RepositoryError::from)?
+            .take::<Vec<DbServer>>(0);
 
-        return Ok(res);
+        match res {
+            Ok(res) => Ok(page.finish(res)),
+            Err(e) => Err(RepositoryError::from(e)),
+        }
     }
 
-    async fn delete_server(&self, db: &Surreal<Client>, id: &ServerId) -> Result<u8, String> {
-        db.delete::<Option<DbServer>>((COLLECTION_NAME, id.to_string()))
-            .await
-            .unwrap();
+    async fn get_servers_by_owner(
+        &self,
+        db: &Surreal<Any>,
+        owner: &UserId,
+    ) -> Result<Vec<DbServer>, RepositoryError> {
+        let owner = Thing::from((USER_COLLECTION_NAME.to_string(), owner.to_string()));
+
+        db.query(format!(
+            "SELECT * FROM {COLLECTION_NAME} WHERE owner == $owner ORDER BY id FETCH icon"
+        ))
+        .bind(("owner", owner))
+        .await
+        .map_err(RepositoryError::from)?
+        .take::<Vec<DbServer>>(0)
+        .map_err(RepositoryError::from)
+    }
 
-        Ok(1)
+    async fn get_joined_servers(
+        &self,
+        db: &Surreal<Any>,
+        user_id: &UserId,
+        page: Page,
+    ) -> Result<Vec<DbServer>, RepositoryError> {
+        let user = Thing::from((USER_COLLECTION_NAME.to_string(), user_id.to_string()));
+
+        // members relate their user `in` to their server `out`.
+        db.query(format!(
+            "SELECT * FROM {COLLECTION_NAME} WHERE id INSIDE \
+                (SELECT VALUE out FROM {SERVER_MEMBER_COLLECTION_NAME} WHERE in == $user) \
+                AND {} FETCH icon",
+            paged(&page)
+        ))
+        .bind(("user", user))
+        .bind(("cursor", cursor(COLLECTION_NAME, &page)))
+        .bind(("limit", page.limit))
+        .await
+        .map_err(RepositoryError::from)?
+        .take::<Vec<DbServer>>(0)
+        .map(|records| page.finish(records))
+        .map_err(RepositoryError::from)
     }
 
-    async fn get_servers(
+    fn add_server_in(
         &self,
-        db: &Surreal<Client>,
-        page_size: i32,
-        offset_id: Option<ServerId>,
-    ) -> Result<Vec<DbServer>, String> {
-        let query = match offset_id {
-            Some(offset_id) => db
-                .query(format!(
-                    "SELECT * FROM {COLLECTION_NAME} WHERE id < $offset_id ORDER BY id DESC LIMIT $page_size"
-                ))
-                .bind(("offset_id", offset_id))
-                .bind(("page_size", page_size)),
-
-            None => db.query(format!("SELECT * FROM {COLLECTION_NAME} ORDER BY id DESC LIMIT $page_size"))
-                .bind(("page_size", page_size)),
-        };
-
-        let res = query.await.unwrap().take::<Vec<DbServer>>(0);
+        transaction: &mut SurrealTransaction,
+        server: &DbServer,
+    ) -> Result<(), RepositoryError> {
+        let id = transaction.bind(Thing::from((
+            COLLECTION_NAME.to_string(),
+            server.id.to_string(),
+        )))?;
+        let content = transaction.bind(server)?;
+        transaction.push(format!("CREATE {} CONTENT {} RETURN NONE", id, content));
+
+        Ok(())
+    }
 
-        match res {
-            Ok(res) => Ok(res),
-            Err(e) => Err(e.to_string()),
-        }
+    fn update_server_in(
+        &self,
+        transaction: &mut SurrealTransaction,
+        server: &DbServer,
+    ) -> Result<(), RepositoryError> {
+        let id = transaction.bind(Thing::from((
+            COLLECTION_NAME.to_string(),
+            server.id.to_string(),
+        )))?;
+        let content = transaction.bind(server)?;
+        transaction.push(format!("UPDATE {} CONTENT {} RETURN NONE", id, content));
+
+        Ok(())
     }
 
-    async fn get_joined_servers(
+    fn delete_server_in(
         &self,
-        db: &Surreal<Client>,
-        user_id: &UserId,
-        page_size: i32,
-        offset_id: Option<ServerId>,
-    ) -> Result<Vec<DbServer>, String> {
-        todo!()
+        transaction: &mut SurrealTransaction,
+        id: &ServerId,
+    ) -> Result<(), RepositoryError> {
+        let id = transaction.bind(Thing::from((COLLECTION_NAME.to_string(), id.to_string())))?;
+        transaction.push(format!("DELETE {}", id));
+
+        Ok(())
     }
 }
 
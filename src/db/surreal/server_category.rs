@@ -1,12 +1,13 @@
 use serde::{Serialize, Serializer};
 use surrealdb::{
-    engine::remote::ws::Client,
+    engine::any::Any,
     sql::{Id, Thing},
     Surreal,
 };
 
+use super::{cursor, paged, SurrealTransaction};
 use crate::{
-    db::traits::server_category::ServerCategoryRepository,
+    db::{error::RepositoryError, page::Page, traits::server_category::ServerCategoryRepository},
     models::server::ServerId,
     models::server_category::{DbServerCategory, ServerCategoryId},
 };
@@ -25,92 +26,131 @@ impl ServerCategoryRepositoryImpl {
 }
 
 #[tonic::async_trait]
-impl ServerCategoryRepository<Surreal<Client>> for ServerCategoryRepositoryImpl {
+impl ServerCategoryRepository<Surreal<Any>> for ServerCategoryRepositoryImpl {
     async fn get(
         &self,
-        db: &Surreal<Client>,
+        db: &Surreal<Any>,
         id: &ServerCategoryId,
-    ) -> Result<Option<DbServerCategory>, String> {
+    ) -> Result<Option<DbServerCategory>, RepositoryError> {
         let res = db.select((COLLECTION_NAME, id.to_string())).await;
 
         match res {
-            Ok(res) => Ok(res.unwrap()),
-            Err(e) => Err(e.to_string()),
+            Ok(res) => Ok(res),
+            Err(e) => Err(RepositoryError::from(e)),
         }
     }
 
     async fn add(
         &self,
-        db: &Surreal<Client>,
+        db: &Surreal<Any>,
         server_category: &DbServerCategory,
-    ) -> Result<Option<DbServerCategory>, String> {
+    ) -> Result<Option<DbServerCategory>, RepositoryError> {
         let created: Option<DbServerCategory> = db
             .create((COLLECTION_NAME, server_category.id.to_string()))
             .content(server_category)
             .await
-            .unwrap();
+            .map_err(RepositoryError::from)?;
 
         Ok(created)
     }
 
     async fn update(
         &self,
-        db: &Surreal<Client>,
+        db: &Surreal<Any>,
         server_category: &DbServerCategory,
-    ) -> Result<Option<DbServerCategory>, String> {
+    ) -> Result<Option<DbServerCategory>, RepositoryError> {
         let res: Option<DbServerCategory> = db
             .update((COLLECTION_NAME, server_category.id.to_string()))
             .content(server_category.clone())
             .await
-            .unwrap();
+            .map_err(RepositoryError::from)?;
 
         return Ok(res);
     }
 
-    async fn delete(&self, db: &Surreal<Client>, id: &ServerCategoryId) -> Result<u8, String> {
+    async fn delete(
+        &self,
+        db: &Surreal<Any>,
+        id: &ServerCategoryId,
+    ) -> Result<u8, RepositoryError> {
         db.delete::<Option<DbServerCategory>>((COLLECTION_NAME, id.to_string()))
             .await
-            .unwrap();
+            .map_err(RepositoryError::from)?;
 
         Ok(1)
     }
 
-    // TODO: paging
-    async fn get_server_categories(
+    async fn get_list_by_server_id(
         &self,
-        db: &Surreal<Client>,
+        db: &Surreal<Any>,
         server_id: &ServerId,
-        page_size: i32,
-        offset_id: Option<ServerCategoryId>,
-    ) -> Result<Vec<DbServerCategory>, String> {
+    ) -> Result<Vec<DbServerCategory>, RepositoryError> {
         let server = Thing {
             tb: SERVER_COLLECTION_NAME.to_string(),
             id: Id::String(server_id.to_string()),
         };
 
-        let query = match offset_id {
-            Some(offset_id) => db
+        let res = db
             .query(format!(
-                "SELECT * FROM {COLLECTION_NAME} WHERE server_id == $server AND id < $offset_id ORDER BY id DESC LIMIT $page_size"
+                "SELECT * FROM {COLLECTION_NAME} WHERE server == $server ORDER BY order, id"
             ))
-                .bind(("server", server))
-                .bind(("offset_id", offset_id))
-                .bind(("page_size", page_size)),
-            None => db
-                .query(format!(
-                    "SELECT * FROM {COLLECTION_NAME} WHERE server_id == $server ORDER BY id DESC LIMIT $page_size"
-                ))
-                .bind(("server", server))
-                .bind(("page_size", page_size)),
+            .bind(("server", server))
+            .await
+            .map_err(RepositoryError::from)?
+            .take::<Vec<DbServerCategory>>(0);
+
+        match res {
+            Ok(res) => Ok(res),
+            Err(e) => Err(RepositoryError::from(e)),
+        }
+    }
+
+    // TODO: paging
+    async fn get_server_categories(
+        &self,
+        db: &Surreal<Any>,
+        server_id: &ServerId,
+        page: Page,
+    ) -> Result<Vec<DbServerCategory>, RepositoryError> {
+        let server = Thing {
+            tb: SERVER_COLLECTION_NAME.to_string(),
+            id: Id::String(server_id.to_string()),
         };
 
-        let res = query.await.unwrap().take::<Vec<DbServerCategory>>(0);
+        let res = db
+            .query(format!(
+                "SELECT * FROM {COLLECTION_NAME} WHERE server == $server AND {}",
+                paged(&page)
+            ))
+            .bind(("server", server))
+            .bind(("cursor", cursor(COLLECTION_NAME, &page)))
+            .bind(("limit", page.limit))
+            .await
+            .map_err(RepositoryError::from)?
+            .take::<Vec<DbServerCategory>>(0);
 
         match res {
-            Ok(res) => Ok(res),
-            Err(e) => Err(e.to_string()),
+            Ok(res) => Ok(page.finish(res)),
+            Err(e) => Err(RepositoryError::from(e)),
         }
     }
+
+    fn delete_by_server_id_in(
+        &self,
+        transaction: &mut SurrealTransaction,
+        server_id: &ServerId,
+    ) -> Result<(), RepositoryError> {
+        let server_id = transaction.bind(Thing::from((
+            SERVER_COLLECTION_NAME.to_string(),
+            server_id.to_string(),
+        )))?;
+        transaction.push(format!(
+            "DELETE {COLLECTION_NAME} WHERE server = {}",
+            server_id
+        ));
+
+        Ok(())
+    }
 }
 
 pub fn serialize_id<S>(id: &ServerCategoryId, s: S) -> Result<S::Ok, S::Error>
@@ -120,3 +160,14 @@ where
     let surreal_id = Thing::from((COLLECTION_NAME.to_string(), id.to_string()));
     surreal_id.serialize(s)
 }
+
+pub fn serialize_option<S>(id: &Option<ServerCategoryId>, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let surreal_id = id
+        .as_ref()
+        .map(|id| Thing::from((COLLECTION_NAME.to_string(), id.to_string())));
+
+    surreal_id.serialize(s)
+}
@@ -1,14 +1,20 @@
+use std::collections::BTreeMap;
+
 use serde::{Serialize, Serializer};
 use surrealdb::{
-    engine::remote::ws::Client,
+    engine::any::Any,
     sql::{Id, Thing},
     Surreal,
 };
 
 use super::server::COLLECTION_NAME as SERVER_COLLECTION_NAME;
 use super::user::COLLECTION_NAME as USER_COLLECTION_NAME;
+use super::{condition, cursor, paged, SurrealTransaction};
 use crate::{
-    db::traits::server_member::ServerMemberRepository,
+    db::{
+        error::RepositoryError, filter::Filter, page::Page,
+        traits::server_member::ServerMemberRepository,
+    },
     models::server::ServerId,
     models::server_member::{DbServerMember, ServerMemberId},
     models::user::UserId,
@@ -23,100 +29,118 @@ impl ServerMemberRepositoryImpl {
     }
 }
 
-const COLLECTION_NAME: &str = "server_member";
+pub const COLLECTION_NAME: &str = "server_member";
 
 #[tonic::async_trait]
-impl ServerMemberRepository<Surreal<Client>> for ServerMemberRepositoryImpl {
+impl ServerMemberRepository<Surreal<Any>> for ServerMemberRepositoryImpl {
     async fn get_server_member(
         &self,
-        db: &Surreal<Client>,
+        db: &Surreal<Any>,
         id: &ServerMemberId,
-    ) -> Result<Option<DbServerMember>, String> {
+    ) -> Result<Option<DbServerMember>, RepositoryError> {
+        let id = Thing::from((COLLECTION_NAME.to_string(), id.to_string()));
+
         let res = db
-            .select::<Option<DbServerMember>>((COLLECTION_NAME, id.to_string()))
-            .await;
+            .query("SELECT * FROM $id FETCH avatar")
+            .bind(("id", id))
+            .await
+            .map_err(RepositoryError::from)?
+            .take::<Option<DbServerMember>>(0);
 
         match res {
             Ok(res) => Ok(res),
-            Err(e) => Err(e.to_string()),
+            Err(e) => Err(RepositoryError::from(e)),
         }
     }
 
     async fn add_server_member(
         &self,
-        db: &Surreal<Client>,
+        db: &Surreal<Any>,
         server_member: &DbServerMember,
-    ) -> Result<Option<DbServerMember>, String> {
-        let created: Option<DbServerMember> = db
-            .create((COLLECTION_NAME, server_member.id.to_string()))
-            .content(server_member)
+    ) -> Result<Option<DbServerMember>, RepositoryError> {
+        // avatar is stored as a record link, read it back with the attachment fetched.
+        db.query("CREATE $id CONTENT $content RETURN NONE")
+            .bind((
+                "id",
+                Thing::from((COLLECTION_NAME.to_string(), server_member.id.to_string())),
+            ))
+            .bind(("content", server_member))
             .await
-            .unwrap();
+            .map_err(RepositoryError::from)?
+            .check()
+            .map_err(RepositoryError::from)?;
 
-        Ok(created)
+        self.get_server_member(db, &server_member.id).await
     }
 
     async fn update_server_member(
         &self,
-        db: &Surreal<Client>,
+        db: &Surreal<Any>,
         server_member: &DbServerMember,
-    ) -> Result<Option<DbServerMember>, String> {
-        let res: Option<DbServerMember> = db
-            .update((COLLECTION_NAME, server_member.id.to_string()))
-            .content(server_member.clone())
+    ) -> Result<Option<DbServerMember>, RepositoryError> {
+        db.query("UPDATE $id CONTENT $content RETURN NONE")
+            .bind((
+                "id",
+                Thing::from((COLLECTION_NAME.to_string(), server_member.id.to_string())),
+            ))
+            .bind(("content", server_member))
             .await
-            .unwrap();
+            .map_err(RepositoryError::from)?
+            .check()
+            .map_err(RepositoryError::from)?;
 
-        return Ok(res);
+        self.get_server_member(db, &server_member.id).await
     }
 
-    async fn delete(&self, db: &Surreal<Client>, id: &ServerMemberId) -> Result<u8, String> {
+    async fn delete(&self, db: &Surreal<Any>, id: &ServerMemberId) -> Result<u8, RepositoryError> {
         db.delete::<Option<DbServerMember>>((COLLECTION_NAME, id.to_string()))
             .await
-            .unwrap();
+            .map_err(RepositoryError::from)?;
 
         Ok(1)
     }
 
     async fn get_server_members(
         &self,
-        db: &Surreal<Client>,
+        db: &Surreal<Any>,
         server_id: &ServerId,
-        page_size: i32,
-        offset_id: Option<ServerMemberId>,
-    ) -> Result<Vec<DbServerMember>, String> {
+        filter: &Filter,
+        page: Page,
+    ) -> Result<Vec<DbServerMember>, RepositoryError> {
         let server = Thing {
             tb: SERVER_COLLECTION_NAME.to_string(),
             id: Id::String(server_id.to_string()),
         };
 
-        let query = match offset_id {
-            Some(offset_id) => db.query(format!(
-                "SELECT * FROM {COLLECTION_NAME} WHERE server_id == $server AND id < $offset_id ORDER BY id DESC LIMIT $page_size"
-            ))
-                .bind(("server", server))
-                .bind(("offset_id", offset_id))
-                .bind(("page_size", page_size)),
-            None => db.query(format!(
-                "SELECT * FROM {COLLECTION_NAME} WHERE server_id == $server ORDER BY id DESC LIMIT $page_size"
-            ))
-                .bind(("server", server))
-                .bind(("page_size", page_size)),
-        };
+        // members relate their user `in` to their server.
+        let mut bindings = BTreeMap::new();
+        let condition = condition(filter, &[("user", "in")], &mut bindings);
 
-        let res = query.await.unwrap().take::<Vec<DbServerMember>>(0);
+        let res = db
+            .query(format!(
+                "SELECT * FROM {COLLECTION_NAME} WHERE server == $server AND {} AND {} FETCH avatar",
+                condition,
+                paged(&page)
+            ))
+            .bind(bindings)
+            .bind(("server", server))
+            .bind(("cursor", cursor(COLLECTION_NAME, &page)))
+            .bind(("limit", page.limit))
+            .await
+            .map_err(RepositoryError::from)?
+            .take::<Vec<DbServerMember>>(0);
 
         match res {
-            Ok(res) => Ok(res),
-            Err(e) => Err(e.to_string()),
+            Ok(res) => Ok(page.finish(res)),
+            Err(e) => Err(RepositoryError::from(e)),
         }
     }
 
     async fn get_server_members_by_server_id(
         &self,
-        db: &Surreal<Client>,
+        db: &Surreal<Any>,
         server_id: &ServerId,
-    ) -> Result<Vec<DbServerMember>, String> {
+    ) -> Result<Vec<DbServerMember>, RepositoryError> {
         let server = Thing {
             tb: SERVER_COLLECTION_NAME.to_string(),
             id: Id::String(server_id.to_string()),
@@ -124,25 +148,50 @@ impl ServerMemberRepository<Surreal<Client>> for ServerMemberRepositoryImpl {
 
         let res = db
             .query(format!(
-                "SELECT * FROM {COLLECTION_NAME} WHERE server == $server"
+                "SELECT * FROM {COLLECTION_NAME} WHERE server == $server FETCH avatar"
             ))
             .bind(("server", server))
             .await
-            .unwrap()
+            .map_err(RepositoryError::from)?
+            .take::<Vec<DbServerMember>>(0);
+
+        match res {
+            Ok(res) => Ok(res),
+            Err(e) => Err(RepositoryError::from(e)),
+        }
+    }
+
+    async fn get_server_members_by_user_id(
+        &self,
+        db: &Surreal<Any>,
+        user_id: &UserId,
+    ) -> Result<Vec<DbServerMember>, RepositoryError> {
+        let user = Thing {
+            tb: USER_COLLECTION_NAME.to_string(),
+            id: Id::String(user_id.to_string()),
+        };
+
+        let res = db
+            .query(format!(
+                "SELECT * FROM {COLLECTION_NAME} WHERE user == $user FETCH avatar"
+            ))
+            .bind(("user", user))
+            .await
+            .map_err(RepositoryError::from)?
             .take::<Vec<DbServerMember>>(0);
 
         match res {
             Ok(res) => Ok(res),
-            Err(e) => Err(e.to_string()),
+            Err(e) => Err(RepositoryError::from(e)),
         }
     }
 
     async fn get_server_member_by_server_id_and_user_id(
         &self,
-        db: &Surreal<Client>,
+        db: &Surreal<Any>,
         server_id: &ServerId,
         user_id: &UserId,
-    ) -> Result<Option<DbServerMember>, String> {
+    ) -> Result<Option<DbServerMember>, RepositoryError> {
         let server = Thing {
             tb: SERVER_COLLECTION_NAME.to_string(),
             id: Id::String(server_id.to_string()),
@@ -155,19 +204,65 @@ impl ServerMemberRepository<Surreal<Client>> for ServerMemberRepositoryImpl {
 
         let res = db
             .query(format!(
-                "SELECT * FROM {COLLECTION_NAME} WHERE server == $server AND user == $user"
+                "SELECT * FROM {COLLECTION_NAME} WHERE server == $server AND user == $user FETCH avatar"
             ))
             .bind(("server", server))
             .bind(("user", user))
             .await
-            .unwrap()
+            .map_err(RepositoryError::from)?
             .take::<Option<DbServerMember>>(0);
 
         match res {
             Ok(res) => Ok(res),
-            Err(e) => Err(e.to_string()),
+            Err(e) => Err(RepositoryError::from(e)),
         }
     }
+
+    fn add_server_member_in(
+        &self,
+        transaction: &mut SurrealTransaction,
+        server_member: &DbServerMember,
+    ) -> Result<(), RepositoryError> {
+        let id = transaction.bind(Thing::from((
+            COLLECTION_NAME.to_string(),
+            server_member.id.to_string(),
+        )))?;
+        let content = transaction.bind(server_member)?;
+        transaction.push(format!("CREATE {} CONTENT {} RETURN NONE", id, content));
+
+        Ok(())
+    }
+
+    fn delete_by_server_id_in(
+        &self,
+        transaction: &mut SurrealTransaction,
+        server_id: &ServerId,
+    ) -> Result<(), RepositoryError> {
+        let server_id = transaction.bind(Thing::from((
+            SERVER_COLLECTION_NAME.to_string(),
+            server_id.to_string(),
+        )))?;
+        transaction.push(format!(
+            "DELETE {COLLECTION_NAME} WHERE server = {}",
+            server_id
+        ));
+
+        Ok(())
+    }
+
+    fn delete_by_user_id_in(
+        &self,
+        transaction: &mut SurrealTransaction,
+        user_id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        let user_id = transaction.bind(Thing::from((
+            USER_COLLECTION_NAME.to_string(),
+            user_id.to_string(),
+        )))?;
+        transaction.push(format!("DELETE {COLLECTION_NAME} WHERE user = {}", user_id));
+
+        Ok(())
+    }
 }
 
 pub fn serialize_id<S>(id: &ServerMemberId, s: S) -> Result<S::Ok, S::Error>
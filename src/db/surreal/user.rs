@@ -1,8 +1,12 @@
+use std::collections::BTreeMap;
+
 use serde::{Serialize, Serializer};
-use surrealdb::{engine::remote::ws::Client, sql::Thing, Surreal};
+use surrealdb::{engine::any::Any, sql::Thing, Surreal};
 use tonic::async_trait;
 
 use super::super::traits::user::UserRepository;
+use super::{condition, cursor, paged, SurrealTransaction};
+use crate::db::{error::RepositoryError, filter::Filter, page::Page};
 use crate::models::user::{DbUser, UserId};
 
 #[derive(Clone)]
@@ -17,79 +21,112 @@ impl UserRepositoryImpl {
 pub const COLLECTION_NAME: &str = "user";
 
 #[async_trait]
-impl UserRepository<Surreal<Client>> for UserRepositoryImpl {
-    async fn get_user(&self, db: &Surreal<Client>, id: &UserId) -> Result<Option<DbUser>, String> {
+impl UserRepository<Surreal<Any>> for UserRepositoryImpl {
+    async fn get_user(
+        &self,
+        db: &Surreal<Any>,
+        id: &UserId,
+    ) -> Result<Option<DbUser>, RepositoryError> {
+        let id = Thing::from((COLLECTION_NAME.to_string(), id.to_string()));
+
         let res = db
-            .select::<Option<DbUser>>((COLLECTION_NAME, id.to_string()))
-            .await;
+            .query("SELECT * FROM $id FETCH avatar")
+            .bind(("id", id))
+            .await
+            .map_err(RepositoryError::from)?
+            .take::<Option<DbUser>>(0);
 
         match res {
             Ok(res) => Ok(res),
-            Err(e) => Err(e.to_string()),
+            Err(e) => Err(RepositoryError::from(e)),
         }
     }
 
     async fn add_user(
         &self,
-        db: &Surreal<Client>,
+        db: &Surreal<Any>,
         user: &DbUser,
-    ) -> Result<Option<DbUser>, String> {
-        let created: Option<DbUser> = db
-            .create((COLLECTION_NAME, user.id.to_string()))
-            .content(user)
+    ) -> Result<Option<DbUser>, RepositoryError> {
+        // avatar is stored as a record link, read it back with the attachment fetched.
+        db.query("CREATE $id CONTENT $content RETURN NONE")
+            .bind((
+                "id",
+                Thing::from((COLLECTION_NAME.to_string(), user.id.to_string())),
+            ))
+            .bind(("content", user))
             .await
-            .unwrap();
+            .map_err(RepositoryError::from)?
+            .check()
+            .map_err(RepositoryError::from)?;
 
-        Ok(created)
+        self.get_user(db, &user.id).await
     }
 
     async fn update_user(
         &self,
-        db: &Surreal<Client>,
+        db: &Surreal<Any>,
         user: &DbUser,
-    ) -> Result<Option<DbUser>, String> {
-        let res: Option<DbUser> = db
-            .update((COLLECTION_NAME, user.id.to_string()))
-            .content(user.clone())
+    ) -> Result<Option<DbUser>, RepositoryError> {
+        db.query("UPDATE $id CONTENT $content RETURN NONE")
+            .bind((
+                "id",
+                Thing::from((COLLECTION_NAME.to_string(), user.id.to_string())),
+            ))
+            .bind(("content", user))
             .await
-            .unwrap();
+            .map_err(RepositoryError::from)?
+            .check()
+            .map_err(RepositoryError::from)?;
 
-        return Ok(res);
+        self.get_user(db, &user.id).await
     }
 
-    async fn delete_user(&self, db: &Surreal<Client>, id: &UserId) -> Result<u8, String> {
+    async fn delete_user(&self, db: &Surreal<Any>, id: &UserId) -> Result<u8, RepositoryError> {
         db.delete::<Option<DbUser>>((COLLECTION_NAME, id.to_string()))
             .await
-            .unwrap();
+            .map_err(RepositoryError::from)?;
 
         Ok(1)
     }
 
     async fn get_users(
         &self,
-        db: &Surreal<Client>,
-        page_size: i32,
-        offset_id: Option<UserId>,
-    ) -> Result<Vec<DbUser>, String> {
-        let query = match offset_id {
-            Some(offset_id) => db.query(format!(
-                "SELECT * FROM {COLLECTION_NAME} WHERE id < $offset_id ORDER BY id DESC LIMIT $page_size"
-            ))
-                .bind(("offset_id", offset_id))
-                .bind(("page_size", page_size)),
-            None => db.query(format!(
-                "SELECT * FROM {COLLECTION_NAME} ORDER BY id DESC LIMIT $page_size"
-            ))
-                .bind(("page_size", page_size)),
-        };
+        db: &Surreal<Any>,
+        filter: &Filter,
+        page: Page,
+    ) -> Result<Vec<DbUser>, RepositoryError> {
+        let mut bindings = BTreeMap::new();
+        let condition = condition(filter, &[], &mut bindings);
 
-        let res = query.await.unwrap().take::<Vec<DbUser>>(0);
+        let res = db
+            .query(format!(
+                "SELECT * FROM {COLLECTION_NAME} WHERE {} AND {} FETCH avatar",
+                condition,
+                paged(&page)
+            ))
+            .bind(bindings)
+            .bind(("cursor", cursor(COLLECTION_NAME, &page)))
+            .bind(("limit", page.limit))
+            .await
+            .map_err(RepositoryError::from)?
+            .take::<Vec<DbUser>>(0);
 
         match res {
-            Ok(res) => Ok(res),
-            Err(e) => Err(e.to_string()),
+            Ok(res) => Ok(page.finish(res)),
+            Err(e) => Err(RepositoryError::from(e)),
         }
     }
+
+    fn delete_user_in(
+        &self,
+        transaction: &mut SurrealTransaction,
+        id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        let id = transaction.bind(Thing::from((COLLECTION_NAME.to_string(), id.to_string())))?;
+        transaction.push(format!("DELETE {}", id));
+
+        Ok(())
+    }
 }
 
 pub fn serialize_id<S>(id: &UserId, s: S) -> Result<S::Ok, S::Error>
@@ -99,3 +136,15 @@ where
     let surreal_id = Thing::from((COLLECTION_NAME.to_string(), id.to_string()));
     surreal_id.serialize(s)
 }
+
+pub fn serialize_id_list<S>(ids: &[UserId], s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let surreal_ids = ids
+        .iter()
+        .map(|id| Thing::from((COLLECTION_NAME.to_string(), id.to_string())))
+        .collect::<Vec<Thing>>();
+
+    surreal_ids.serialize(s)
+}
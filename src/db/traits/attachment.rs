@@ -1,15 +1,40 @@
+use crate::db::error::RepositoryError;
 use tonic::async_trait;
 
-use crate::models::attachment::{Attachment, AttachmentId};
+use crate::models::{
+    attachment::{Attachment, AttachmentId},
+    user::UserId,
+};
 
 #[async_trait]
-pub trait AttachmentRepository<C> {
-    async fn get_attachment(&self, db: &C, id: &AttachmentId)
-        -> Result<Option<Attachment>, String>;
+pub trait AttachmentRepository<C>: Sync + Send {
+    async fn get_attachment(
+        &self,
+        db: &C,
+        id: &AttachmentId,
+    ) -> Result<Option<Attachment>, RepositoryError>;
 
-    async fn add_attachment(&self, db: &C, attachment: Attachment) -> Result<Attachment, String>;
+    async fn add_attachment(
+        &self,
+        db: &C,
+        attachment: &Attachment,
+        uploader: &UserId,
+    ) -> Result<Option<Attachment>, RepositoryError>;
 
-    async fn delete_attachment(&self, db: &C, id: &AttachmentId) -> Result<u8, String>;
+    async fn delete_attachment(&self, db: &C, id: &AttachmentId) -> Result<u8, RepositoryError>;
 
-    async fn get_attachments(&self, db: &C) -> Result<Vec<Attachment>, String>;
+    async fn get_attachments(
+        &self,
+        db: &C,
+        ids: &[AttachmentId],
+    ) -> Result<Vec<Attachment>, RepositoryError>;
+
+    /// whether any user, server, channel, server member or message still refers to the attachment.
+    async fn is_referenced(&self, db: &C, id: &AttachmentId) -> Result<bool, RepositoryError>;
+
+    async fn get_uploader(
+        &self,
+        db: &C,
+        id: &AttachmentId,
+    ) -> Result<Option<UserId>, RepositoryError>;
 }
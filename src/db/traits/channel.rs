@@ -1,23 +1,103 @@
+use crate::db::{error::RepositoryError, filter::Filter, page::Page, Database};
+use surrealdb::sql::Datetime;
+
 use crate::models::{
-    channel::{ChannelId, DbChannel},
+    channel::{ChannelId, ChannelPosition, DbChannel},
     server::ServerId,
+    server_category::{CategoryPosition, ServerCategoryId},
+    user::UserId,
 };
 
 #[tonic::async_trait]
-pub trait ChannelRepository<C>: Sync + Send {
-    async fn get(&self, db: &C, id: &ChannelId) -> Result<Option<DbChannel>, String>;
+pub trait ChannelRepository<C: Database>: Sync + Send {
+    async fn get(&self, db: &C, id: &ChannelId) -> Result<Option<DbChannel>, RepositoryError>;
+
+    /// every channel of the server, uncategorized channels first then by category and order.
+    async fn get_list_by_server_id(
+        &self,
+        db: &C,
+        server_id: &ServerId,
+    ) -> Result<Vec<DbChannel>, RepositoryError>;
 
+    /// a page of the channels of `get_list_by_server_id` matching `filter`, cut from the
+    /// layout order.
     async fn get_server_channels(
         &self,
         db: &C,
         server_id: &ServerId,
-        page_size: i32,
-        offset_id: Option<ChannelId>,
-    ) -> Result<Vec<DbChannel>, String>;
+        filter: &Filter,
+        page: Page,
+    ) -> Result<Vec<DbChannel>, RepositoryError>;
+
+    /// applies the server layout at once, either every position is updated or none.
+    async fn update_layout(
+        &self,
+        db: &C,
+        categories: &[CategoryPosition],
+        channels: &[ChannelPosition],
+    ) -> Result<(), RepositoryError>;
 
-    async fn add(&self, db: &C, channel: &DbChannel) -> Result<Option<DbChannel>, String>;
+    /// every channel `user_id` can read.
+    async fn get_channels_by_user_id(
+        &self,
+        db: &C,
+        user_id: &UserId,
+    ) -> Result<Vec<DbChannel>, RepositoryError>;
+
+    /// the 1:1 channel between `user_id` and `other_user_id`.
+    async fn get_direct_channel(
+        &self,
+        db: &C,
+        user_id: &UserId,
+        other_user_id: &UserId,
+    ) -> Result<Option<DbChannel>, RepositoryError>;
 
-    async fn update(&self, db: &C, channel: &DbChannel) -> Result<Option<DbChannel>, String>;
+    /// direct and group channels of `user_id`, most recently active first.
+    async fn get_direct_channels_by_user_id(
+        &self,
+        db: &C,
+        user_id: &UserId,
+    ) -> Result<Vec<DbChannel>, RepositoryError>;
 
-    async fn delete(&self, db: &C, id: &ChannelId) -> Result<u8, String>;
+    async fn update_last_message_time(
+        &self,
+        db: &C,
+        id: &ChannelId,
+        last_message_time: &Datetime,
+    ) -> Result<(), RepositoryError>;
+
+    async fn add(&self, db: &C, channel: &DbChannel) -> Result<Option<DbChannel>, RepositoryError>;
+
+    async fn update(
+        &self,
+        db: &C,
+        channel: &DbChannel,
+    ) -> Result<Option<DbChannel>, RepositoryError>;
+
+    /// moves the channels of the category out of it, they keep their order.
+    async fn clear_category(
+        &self,
+        db: &C,
+        category_id: &ServerCategoryId,
+    ) -> Result<(), RepositoryError>;
+
+    /// deletes the channel in `transaction`, its messages are deleted apart.
+    fn delete_in(
+        &self,
+        transaction: &mut C::Transaction,
+        id: &ChannelId,
+    ) -> Result<(), RepositoryError>;
+
+    fn delete_by_server_id_in(
+        &self,
+        transaction: &mut C::Transaction,
+        server_id: &ServerId,
+    ) -> Result<(), RepositoryError>;
+
+    /// takes `user_id` out of the members of every direct and group channel.
+    fn remove_member_in(
+        &self,
+        transaction: &mut C::Transaction,
+        user_id: &UserId,
+    ) -> Result<(), RepositoryError>;
 }
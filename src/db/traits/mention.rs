@@ -0,0 +1,41 @@
+use crate::db::{error::RepositoryError, page::Page, Database};
+use crate::models::{channel::ChannelId, mention::DbMention, message::MessageId, user::UserId};
+
+#[tonic::async_trait]
+pub trait MentionRepository<C: Database>: Sync + Send {
+    async fn add_mentions(&self, db: &C, mentions: &[DbMention]) -> Result<(), RepositoryError>;
+
+    async fn get_list_by_user_id(
+        &self,
+        db: &C,
+        user_id: &UserId,
+        page: Page,
+    ) -> Result<Vec<DbMention>, RepositoryError>;
+
+    /// mentions of the user in the channel after `after`.
+    async fn count_by_user_id_and_channel_id(
+        &self,
+        db: &C,
+        user_id: &UserId,
+        channel_id: &ChannelId,
+        after: Option<MessageId>,
+    ) -> Result<u64, RepositoryError>;
+
+    fn delete_by_channel_id_in(
+        &self,
+        transaction: &mut C::Transaction,
+        channel_id: &ChannelId,
+    ) -> Result<(), RepositoryError>;
+
+    fn delete_by_message_id_in(
+        &self,
+        transaction: &mut C::Transaction,
+        message_id: &MessageId,
+    ) -> Result<(), RepositoryError>;
+
+    fn delete_by_user_id_in(
+        &self,
+        transaction: &mut C::Transaction,
+        user_id: &UserId,
+    ) -> Result<(), RepositoryError>;
+}
@@ -1,21 +1,71 @@
+use crate::db::{error::RepositoryError, filter::Filter, page::Page, Database};
 use crate::models::{
     channel::ChannelId,
     message::{DbMessage, MessageId},
+    user::UserId,
 };
 
 #[tonic::async_trait]
-pub trait MessageRepository<C>: Sync + Send {
-    async fn get(&self, db: &C, id: &MessageId) -> Result<Option<DbMessage>, String>;
+pub trait MessageRepository<C: Database>: Sync + Send {
+    async fn get(&self, db: &C, id: &MessageId) -> Result<Option<DbMessage>, RepositoryError>;
 
-    async fn add(&self, db: &C, message: &DbMessage) -> Result<Option<DbMessage>, String>;
+    async fn add(&self, db: &C, message: &DbMessage) -> Result<Option<DbMessage>, RepositoryError>;
 
-    async fn delete(&self, db: &C, id: &MessageId) -> Result<u8, String>;
+    async fn get_list_by_ids(
+        &self,
+        db: &C,
+        ids: &[MessageId],
+    ) -> Result<Vec<DbMessage>, RepositoryError>;
+
+    async fn update(
+        &self,
+        db: &C,
+        message: &DbMessage,
+    ) -> Result<Option<DbMessage>, RepositoryError>;
 
     async fn get_list_by_chnanel_id(
         &self,
         db: &C,
         channel_id: &ChannelId,
-        page_size: i32,
-        offset_id: Option<MessageId>,
-    ) -> Result<Vec<DbMessage>, String>;
+        filter: &Filter,
+        page: Page,
+    ) -> Result<Vec<DbMessage>, RepositoryError>;
+
+    /// pinned messages of the channel, most recently pinned first.
+    async fn get_pinned_list_by_channel_id(
+        &self,
+        db: &C,
+        channel_id: &ChannelId,
+    ) -> Result<Vec<DbMessage>, RepositoryError>;
+
+    /// messages of the channel after `after` which were not written by the user.
+    async fn count_unread(
+        &self,
+        db: &C,
+        channel_id: &ChannelId,
+        user_id: &UserId,
+        after: Option<MessageId>,
+    ) -> Result<u64, RepositoryError>;
+
+    /// messages of the channel from `start_id` to `end_id` inclusive, newest first.
+    async fn get_list_by_channel_id_in_range(
+        &self,
+        db: &C,
+        channel_id: &ChannelId,
+        start_id: &MessageId,
+        end_id: &MessageId,
+        limit: i32,
+    ) -> Result<Vec<DbMessage>, RepositoryError>;
+
+    fn delete_in(
+        &self,
+        transaction: &mut C::Transaction,
+        id: &MessageId,
+    ) -> Result<(), RepositoryError>;
+
+    fn delete_by_channel_id_in(
+        &self,
+        transaction: &mut C::Transaction,
+        channel_id: &ChannelId,
+    ) -> Result<(), RepositoryError>;
 }
@@ -1,35 +1,48 @@
+use crate::db::{error::RepositoryError, page::Page, Database};
 use crate::models::{
-    message::MessageId,
-    message_acknowledge::{DbMessageAcknowledge, MessageAcknowledgeId},
-    user::UserId,
+    channel::ChannelId, message::MessageId, message_acknowledge::DbMessageAcknowledge, user::UserId,
 };
 
 #[tonic::async_trait]
-pub trait MessageAcknowledgeRepository<C>: Sync + Send {
-    async fn get(
+pub trait MessageAcknowledgeRepository<C: Database>: Sync + Send {
+    async fn get_list_by_message(
         &self,
         db: &C,
-        id: MessageAcknowledgeId,
-    ) -> Result<Option<DbMessageAcknowledge>, String>;
+        message_id: &MessageId,
+        page: Page,
+    ) -> Result<Vec<DbMessageAcknowledge>, RepositoryError>;
 
-    async fn get_by_message_and_user(
+    async fn add_list(
         &self,
         db: &C,
-        message_id: &MessageId,
-        user_id: &UserId,
-    ) -> Result<Option<DbMessageAcknowledge>, String>;
+        message_acknowledges: &[DbMessageAcknowledge],
+    ) -> Result<(), RepositoryError>;
 
-    async fn get_list_by_message(
+    /// acknowledges of `user_id` among `message_ids`.
+    async fn get_list_by_user_and_messages(
         &self,
         db: &C,
+        user_id: &UserId,
+        message_ids: &[MessageId],
+    ) -> Result<Vec<DbMessageAcknowledge>, RepositoryError>;
+
+    /// acknowledges of the messages in the channel, queue it before deleting the messages.
+    fn delete_by_channel_id_in(
+        &self,
+        transaction: &mut C::Transaction,
+        channel_id: &ChannelId,
+    ) -> Result<(), RepositoryError>;
+
+    /// acknowledges of the message, queue it before deleting the message.
+    fn delete_by_message_id_in(
+        &self,
+        transaction: &mut C::Transaction,
         message_id: &MessageId,
-        page_size: i32,
-        offset_id: Option<MessageAcknowledgeId>,
-    ) -> Result<Vec<DbMessageAcknowledge>, String>;
+    ) -> Result<(), RepositoryError>;
 
-    async fn add(
+    fn delete_by_user_id_in(
         &self,
-        db: &C,
-        message_acknowledge: &DbMessageAcknowledge,
-    ) -> Result<Option<DbMessageAcknowledge>, String>;
+        transaction: &mut C::Transaction,
+        user_id: &UserId,
+    ) -> Result<(), RepositoryError>;
 }
@@ -0,0 +1,21 @@
+use crate::db::{error::RepositoryError, page::Page};
+use crate::models::{
+    message::{DbMessage, MessageId},
+    message_search::{MessageSearchHit, MessageSearchQuery},
+};
+
+/// full-text index over message content.
+#[tonic::async_trait]
+pub trait MessageSearchIndex<C>: Sync + Send {
+    async fn index_message(&self, db: &C, message: &DbMessage) -> Result<(), RepositoryError>;
+
+    async fn remove_message(&self, db: &C, id: &MessageId) -> Result<(), RepositoryError>;
+
+    /// matching messages, newest first.
+    async fn search(
+        &self,
+        db: &C,
+        query: &MessageSearchQuery,
+        page: Page,
+    ) -> Result<Vec<MessageSearchHit>, RepositoryError>;
+}
@@ -1,8 +1,12 @@
 pub mod attachment;
 pub mod auth;
 pub mod channel;
+pub mod mention;
 pub mod message;
 pub mod message_acknowledge;
+pub mod message_search;
+pub mod read_state;
+pub mod schema_version;
 pub mod server;
 pub mod server_category;
 pub mod server_member;
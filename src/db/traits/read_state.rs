@@ -0,0 +1,34 @@
+use crate::db::{error::RepositoryError, Database};
+use crate::models::{channel::ChannelId, read_state::DbReadState, user::UserId};
+
+#[tonic::async_trait]
+pub trait ReadStateRepository<C: Database>: Sync + Send {
+    async fn get(
+        &self,
+        db: &C,
+        user_id: &UserId,
+        channel_id: &ChannelId,
+    ) -> Result<Option<DbReadState>, RepositoryError>;
+
+    async fn get_list_by_user_id(
+        &self,
+        db: &C,
+        user_id: &UserId,
+        channel_ids: &[ChannelId],
+    ) -> Result<Vec<DbReadState>, RepositoryError>;
+
+    /// creates or replaces the read state.
+    async fn save(&self, db: &C, read_state: &DbReadState) -> Result<(), RepositoryError>;
+
+    fn delete_by_channel_id_in(
+        &self,
+        transaction: &mut C::Transaction,
+        channel_id: &ChannelId,
+    ) -> Result<(), RepositoryError>;
+
+    fn delete_by_user_id_in(
+        &self,
+        transaction: &mut C::Transaction,
+        user_id: &UserId,
+    ) -> Result<(), RepositoryError>;
+}
@@ -0,0 +1,13 @@
+use crate::db::{error::RepositoryError, migration::Migration};
+use crate::models::schema_version::DbSchemaVersion;
+
+#[tonic::async_trait]
+pub trait SchemaVersionRepository<C>: Sync + Send {
+    /// every applied migration, oldest first. read only, so dry runs and checks change nothing:
+    /// without a `schema_version` table no migration is applied yet.
+    async fn get_list(&self, db: &C) -> Result<Vec<DbSchemaVersion>, RepositoryError>;
+
+    /// runs the script and records it in one transaction, nothing is left behind when it fails.
+    /// creates the `schema_version` table when it's missing.
+    async fn apply(&self, db: &C, migration: &Migration) -> Result<(), RepositoryError>;
+}
@@ -1,26 +1,55 @@
+use crate::db::{error::RepositoryError, filter::Filter, page::Page, Database};
 use crate::models::{
     server::{DbServer, ServerId},
     user::UserId,
 };
 
 #[tonic::async_trait]
-pub trait ServerRepository<C>: Sync + Send {
-    async fn get_server(&self, db: &C, id: &ServerId) -> Result<Option<DbServer>, String>;
-    async fn add_server(&self, db: &C, server: &DbServer) -> Result<Option<DbServer>, String>;
-    async fn update_server(&self, db: &C, server: &DbServer) -> Result<Option<DbServer>, String>;
-    async fn delete_server(&self, db: &C, id: &ServerId) -> Result<u8, String>;
+pub trait ServerRepository<C: Database>: Sync + Send {
+    async fn get_server(&self, db: &C, id: &ServerId) -> Result<Option<DbServer>, RepositoryError>;
+    async fn update_server(
+        &self,
+        db: &C,
+        server: &DbServer,
+    ) -> Result<Option<DbServer>, RepositoryError>;
     async fn get_servers(
         &self,
         db: &C,
-        page_size: i32,
-        offset_id: Option<ServerId>,
-    ) -> Result<Vec<DbServer>, String>;
+        filter: &Filter,
+        page: Page,
+    ) -> Result<Vec<DbServer>, RepositoryError>;
+
+    /// every server `owner` owns.
+    async fn get_servers_by_owner(
+        &self,
+        db: &C,
+        owner: &UserId,
+    ) -> Result<Vec<DbServer>, RepositoryError>;
 
     async fn get_joined_servers(
         &self,
         db: &C,
         user_id: &UserId,
-        page_size: i32,
-        offset_id: Option<ServerId>,
-    ) -> Result<Vec<DbServer>, String>;
+        page: Page,
+    ) -> Result<Vec<DbServer>, RepositoryError>;
+
+    /// inserts `server` in `transaction`.
+    fn add_server_in(
+        &self,
+        transaction: &mut C::Transaction,
+        server: &DbServer,
+    ) -> Result<(), RepositoryError>;
+
+    /// queues `update_server` in `transaction`.
+    fn update_server_in(
+        &self,
+        transaction: &mut C::Transaction,
+        server: &DbServer,
+    ) -> Result<(), RepositoryError>;
+
+    fn delete_server_in(
+        &self,
+        transaction: &mut C::Transaction,
+        id: &ServerId,
+    ) -> Result<(), RepositoryError>;
 }
@@ -1,31 +1,48 @@
+use crate::db::{error::RepositoryError, page::Page, Database};
 use crate::models::{
     server::ServerId,
     server_category::{DbServerCategory, ServerCategoryId},
 };
 
 #[tonic::async_trait]
-pub trait ServerCategoryRepository<C>: Sync + Send {
-    async fn get(&self, db: &C, id: &ServerCategoryId) -> Result<Option<DbServerCategory>, String>;
+pub trait ServerCategoryRepository<C: Database>: Sync + Send {
+    async fn get(
+        &self,
+        db: &C,
+        id: &ServerCategoryId,
+    ) -> Result<Option<DbServerCategory>, RepositoryError>;
 
     async fn add(
         &self,
         db: &C,
         server_category: &DbServerCategory,
-    ) -> Result<Option<DbServerCategory>, String>;
+    ) -> Result<Option<DbServerCategory>, RepositoryError>;
 
     async fn update(
         &self,
         db: &C,
         server_category: &DbServerCategory,
-    ) -> Result<Option<DbServerCategory>, String>;
+    ) -> Result<Option<DbServerCategory>, RepositoryError>;
 
-    async fn delete(&self, db: &C, id: &ServerCategoryId) -> Result<u8, String>;
+    async fn delete(&self, db: &C, id: &ServerCategoryId) -> Result<u8, RepositoryError>;
+
+    /// every category of the server, in display order.
+    async fn get_list_by_server_id(
+        &self,
+        db: &C,
+        server_id: &ServerId,
+    ) -> Result<Vec<DbServerCategory>, RepositoryError>;
 
     async fn get_server_categories(
         &self,
         db: &C,
         server_id: &ServerId,
-        page_size: i32,
-        offset_id: Option<ServerCategoryId>,
-    ) -> Result<Vec<DbServerCategory>, String>; // FIXME
+        page: Page,
+    ) -> Result<Vec<DbServerCategory>, RepositoryError>; // FIXME
+
+    fn delete_by_server_id_in(
+        &self,
+        transaction: &mut C::Transaction,
+        server_id: &ServerId,
+    ) -> Result<(), RepositoryError>;
 }
@@ -1,3 +1,4 @@
+use crate::db::{error::RepositoryError, filter::Filter, page::Page, Database};
 use crate::models::{
     server::ServerId,
     server_member::{DbServerMember, ServerMemberId},
@@ -5,45 +6,71 @@ use crate::models::{
 };
 
 #[tonic::async_trait]
-pub trait ServerMemberRepository<C>: Sync + Send {
+pub trait ServerMemberRepository<C: Database>: Sync + Send {
     async fn get_server_member(
         &self,
         db: &C,
         id: &ServerMemberId,
-    ) -> Result<Option<DbServerMember>, String>;
+    ) -> Result<Option<DbServerMember>, RepositoryError>;
 
     async fn get_server_member_by_server_id_and_user_id(
         &self,
         db: &C,
         server_id: &ServerId,
         user_id: &UserId,
-    ) -> Result<Option<DbServerMember>, String>;
+    ) -> Result<Option<DbServerMember>, RepositoryError>;
 
     async fn add_server_member(
         &self,
         db: &C,
         server_member: &DbServerMember,
-    ) -> Result<Option<DbServerMember>, String>;
+    ) -> Result<Option<DbServerMember>, RepositoryError>;
 
     async fn update_server_member(
         &self,
         db: &C,
         server_member: &DbServerMember,
-    ) -> Result<Option<DbServerMember>, String>;
+    ) -> Result<Option<DbServerMember>, RepositoryError>;
 
-    async fn delete(&self, db: &C, id: &ServerMemberId) -> Result<u8, String>;
+    async fn delete(&self, db: &C, id: &ServerMemberId) -> Result<u8, RepositoryError>;
 
     async fn get_server_members(
         &self,
         db: &C,
         server_id: &ServerId,
-        page_size: i32,
-        offset_id: Option<ServerMemberId>,
-    ) -> Result<Vec<DbServerMember>, String>;
+        filter: &Filter,
+        page: Page,
+    ) -> Result<Vec<DbServerMember>, RepositoryError>;
 
     async fn get_server_members_by_server_id(
         &self,
         db: &C,
         server_id: &ServerId,
-    ) -> Result<Vec<DbServerMember>, String>;
+    ) -> Result<Vec<DbServerMember>, RepositoryError>;
+
+    /// every membership of `user_id`, in any server.
+    async fn get_server_members_by_user_id(
+        &self,
+        db: &C,
+        user_id: &UserId,
+    ) -> Result<Vec<DbServerMember>, RepositoryError>;
+
+    /// queues `add_server_member` in `transaction`.
+    fn add_server_member_in(
+        &self,
+        transaction: &mut C::Transaction,
+        server_member: &DbServerMember,
+    ) -> Result<(), RepositoryError>;
+
+    fn delete_by_server_id_in(
+        &self,
+        transaction: &mut C::Transaction,
+        server_id: &ServerId,
+    ) -> Result<(), RepositoryError>;
+
+    fn delete_by_user_id_in(
+        &self,
+        transaction: &mut C::Transaction,
+        user_id: &UserId,
+    ) -> Result<(), RepositoryError>;
 }
@@ -1,17 +1,25 @@
+use crate::db::{error::RepositoryError, filter::Filter, page::Page, Database};
 use tonic::async_trait;
 
 use crate::models::user::{DbUser, UserId};
 
 #[async_trait]
-pub trait UserRepository<C>: Sync + Send {
-    async fn get_user(&self, db: &C, id: &UserId) -> Result<Option<DbUser>, String>;
-    async fn add_user(&self, db: &C, user: &DbUser) -> Result<Option<DbUser>, String>;
-    async fn update_user(&self, db: &C, user: &DbUser) -> Result<Option<DbUser>, String>;
-    async fn delete_user(&self, db: &C, id: &UserId) -> Result<u8, String>;
+pub trait UserRepository<C: Database>: Sync + Send {
+    async fn get_user(&self, db: &C, id: &UserId) -> Result<Option<DbUser>, RepositoryError>;
+    async fn add_user(&self, db: &C, user: &DbUser) -> Result<Option<DbUser>, RepositoryError>;
+    async fn update_user(&self, db: &C, user: &DbUser) -> Result<Option<DbUser>, RepositoryError>;
+    async fn delete_user(&self, db: &C, id: &UserId) -> Result<u8, RepositoryError>;
     async fn get_users(
         &self,
         db: &C,
-        page_size: i32,
-        offset_id: Option<UserId>,
-    ) -> Result<Vec<DbUser>, String>;
+        filter: &Filter,
+        page: Page,
+    ) -> Result<Vec<DbUser>, RepositoryError>;
+
+    /// queues `delete_user` in `transaction`.
+    fn delete_user_in(
+        &self,
+        transaction: &mut C::Transaction,
+        id: &UserId,
+    ) -> Result<(), RepositoryError>;
 }
@@ -1,23 +1,21 @@
-use serde::{Deserialize, Serialize};
 use tonic::{metadata::AsciiMetadataValue, Request, Status};
 
 use crate::auth::jwt::decode;
+use crate::config::AuthConfig;
+use crate::services::error::ServiceError;
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Claims {
-    sub: String,
-}
-
-pub fn check_auth(mut req: Request<()>) -> Result<Request<()>, Status> {
+// tonic interceptors return `Status`.
+#[allow(clippy::result_large_err)]
+pub fn check_auth(config: &AuthConfig, mut req: Request<()>) -> Result<Request<()>, Status> {
     if let Some(t) = req.metadata().get("authorization") {
         let b = t.as_bytes().to_vec();
         let token = String::from_utf8(b).unwrap();
         let token = token.split(' ').collect::<Vec<&str>>()[1];
 
-        let token_data = match decode(token) {
+        let token_data = match decode(config, token) {
             Ok(res) => res,
             Err(err) => {
-                return Err(Status::unauthenticated(err.to_string()));
+                return Err(ServiceError::unauthenticated(err.to_string()).into());
             }
         };
 
@@ -25,13 +23,13 @@ pub fn check_auth(mut req: Request<()>) -> Result<Request<()>, Status> {
 
         let val: AsciiMetadataValue = match AsciiMetadataValue::try_from(aud.to_string()) {
             Ok(val) => val,
-            Err(err) => return Err(Status::unauthenticated(err.to_string())),
+            Err(err) => return Err(ServiceError::unauthenticated(err.to_string()).into()),
         };
 
         req.metadata_mut().append("user_id", val);
 
         Ok(req)
     } else {
-        Err(Status::unauthenticated("No valid auth token"))
+        Err(ServiceError::unauthenticated("No valid auth token").into())
     }
 }
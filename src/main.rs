@@ -2,23 +2,35 @@ use futures::lock::Mutex;
 use std::sync::Arc;
 
 use chat::broadcaster::Broadcaster;
-use db::surreal::{
-    auth::AuthRepositoryImpl, channel::ChannelRepositoryImpl, message::MessageRepositoryImpl,
-    message_acknowledge::MessageAcknowledgeRepositoryImpl, server::ServerRepositoryImpl,
-    server_category::ServerCategoryRepositoryImpl, server_member::ServerMemberRepositoryImpl,
-    user::UserRepositoryImpl,
+use config::{Config, DatabaseBackend};
+use db::{
+    migration::{migrate, MigrationMode},
+    traits::{
+        attachment::AttachmentRepository, auth::AuthRepository, channel::ChannelRepository,
+        mention::MentionRepository, message::MessageRepository,
+        message_acknowledge::MessageAcknowledgeRepository, message_search::MessageSearchIndex,
+        read_state::ReadStateRepository, server::ServerRepository,
+        server_category::ServerCategoryRepository, server_member::ServerMemberRepository,
+        user::UserRepository,
+    },
+    Database, Repositories,
 };
 use services::{
     account::AccountService,
+    attachment::AttachmentService,
     auth::AuthService,
     connect::ConnectService,
     message::MessageService,
     ycchat::v1::services::{
         account::account_service_server,
+        attachment::attachment_service_server,
         auth::auth_service_server,
         channel::channel_service_server,
         connect::connect_service_server,
-        me::{server::me_server_service_server, user::me_user_service_server},
+        me::{
+            channel::me_channel_service_server, mention::me_mention_service_server,
+            server::me_server_service_server, user::me_user_service_server,
+        },
         message::message_service_server,
         server::member::server_member_service_server,
         server::{category::category_service_server, server_service_server},
@@ -26,120 +38,364 @@ use services::{
     },
 };
 // use services::server::member::server_member_server::ServerMember as ServerMemberServer;
+use storage::local::LocalBlobStore;
 use tonic::transport::Server;
+use util::pager::Pager;
 
 mod auth;
 mod chat;
+mod config;
 mod db;
 mod interceptor;
+mod media;
 mod models;
 // mod redis;
 mod services;
+mod storage;
 mod util;
 
-#[macro_use]
-extern crate lazy_static;
+const USAGE: &str = "usage: ycChat [migrate [--dry-run]]";
+
+/// what the process was started for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Serve,
+    /// applies the pending migrations, or prints them with `--dry-run`, and exits.
+    Migrate {
+        dry_run: bool,
+    },
+}
+
+impl Command {
+    fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let args = args.collect::<Vec<String>>();
+
+        match args.iter().map(String::as_str).collect::<Vec<&str>>()[..] {
+            [] => Ok(Command::Serve),
+            ["migrate"] => Ok(Command::Migrate { dry_run: false }),
+            ["migrate", "--dry-run"] => Ok(Command::Migrate { dry_run: true }),
+            _ => Err(format!("unknown arguments `{}`.", args.join(" "))),
+        }
+    }
+
+    fn migration_mode(&self, auto_migrate: bool) -> MigrationMode {
+        match self {
+            Command::Migrate { dry_run: true } => MigrationMode::DryRun,
+            Command::Migrate { dry_run: false } => MigrationMode::Apply,
+            Command::Serve if auto_migrate => MigrationMode::Apply,
+            Command::Serve => MigrationMode::Check,
+        }
+    }
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     dotenv::dotenv().ok();
 
-    let addr = "0.0.0.0:50051".parse().unwrap();
+    let command = match Command::parse(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(err) => {
+            eprintln!("{}\n{}", err, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("invalid configuration: {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let migration_mode = command.migration_mode(config.database.auto_migrate);
+
+    match config.database.backend {
+        DatabaseBackend::Surreal => {
+            let db = db::surreal::connect(&config.database.surreal).await?;
 
-    let auth_repository = AuthRepositoryImpl::new().await;
-    let user_repository = UserRepositoryImpl::new().await;
-    let server_repository = ServerRepositoryImpl::new().await;
-    let server_category_repository = ServerCategoryRepositoryImpl::new().await;
-    let server_member_repository = ServerMemberRepositoryImpl::new().await;
-    let channel_repository = ChannelRepositoryImpl::new().await;
-    let message_repository = MessageRepositoryImpl::new().await;
-    let message_acknowledge_repository = MessageAcknowledgeRepositoryImpl::new().await;
+            let schema_version_repository =
+                db::surreal::schema_version::SchemaVersionRepositoryImpl::new().await;
+            let migrated = migrate(
+                &db,
+                &schema_version_repository,
+                db::surreal::MIGRATIONS,
+                migration_mode,
+            )
+            .await;
+            if let Err(err) = migrated {
+                eprintln!("failed to migrate the database: {}", err);
+                std::process::exit(1);
+            }
+
+            if command != Command::Serve {
+                return Ok(());
+            }
+            serve(config, db, db::surreal::repositories().await).await
+        }
+        DatabaseBackend::Postgres => {
+            let db = db::postgres::connect(&config.database.postgres).await?;
+
+            let schema_version_repository =
+                db::postgres::schema_version::SchemaVersionRepositoryImpl::new().await;
+            let migrated = migrate(
+                &db,
+                &schema_version_repository,
+                db::postgres::MIGRATIONS,
+                migration_mode,
+            )
+            .await;
+            if let Err(err) = migrated {
+                eprintln!("failed to migrate the database: {}", err);
+                std::process::exit(1);
+            }
+
+            if command != Command::Serve {
+                return Ok(());
+            }
+            serve(config, db, db::postgres::repositories().await).await
+        }
+        DatabaseBackend::Memory => {
+            if command != Command::Serve {
+                println!("the memory backend has no schema, nothing to migrate.");
+                return Ok(());
+            }
+
+            let db = db::memory::MemoryDb::new();
+            serve(config, db, db::memory::repositories().await).await
+        }
+    }
+}
+
+/// runs every service on `db`, whichever backend it is.
+async fn serve<DB, AU, U, S, SC, SM, C, M, ACK, A, MN, SI, R>(
+    config: Config,
+    db: DB,
+    repositories: Repositories<AU, U, S, SC, SM, C, M, ACK, A, MN, SI, R>,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    DB: Database,
+    AU: AuthRepository<DB> + Clone + 'static,
+    U: UserRepository<DB> + Clone + 'static,
+    S: ServerRepository<DB> + Clone + 'static,
+    SC: ServerCategoryRepository<DB> + Clone + 'static,
+    SM: ServerMemberRepository<DB> + Clone + 'static,
+    C: ChannelRepository<DB> + Clone + 'static,
+    M: MessageRepository<DB> + Clone + 'static,
+    ACK: MessageAcknowledgeRepository<DB> + Clone + 'static,
+    A: AttachmentRepository<DB> + Clone + 'static,
+    MN: MentionRepository<DB> + Clone + 'static,
+    SI: MessageSearchIndex<DB> + Clone + 'static,
+    R: ReadStateRepository<DB> + Clone + 'static,
+{
+    let auth_config = config.auth.clone();
+    #[allow(clippy::result_large_err)]
+    let check_auth = move |req| interceptor::auth::check_auth(&auth_config, req);
+
+    let Repositories {
+        auth: auth_repository,
+        user: user_repository,
+        server: server_repository,
+        server_category: server_category_repository,
+        server_member: server_member_repository,
+        channel: channel_repository,
+        message: message_repository,
+        message_acknowledge: message_acknowledge_repository,
+        attachment: attachment_repository,
+        mention: mention_repository,
+        message_search: message_search_index,
+        read_state: read_state_repository,
+    } = repositories;
+
+    let blob_store = LocalBlobStore::new(
+        &config.storage.attachment_dir,
+        &config.storage.attachment_base_url,
+    );
+
+    let pager = Pager::new(&config.paging);
 
     let broadcaster = Broadcaster::new();
     let broadcaster_arc: Arc<Mutex<Broadcaster>> = Arc::new(Mutex::new(broadcaster));
 
-    let auth_service_server =
-        auth_service_server::AuthServiceServer::new(AuthService::new(auth_repository.clone()));
+    let auth_service_server = auth_service_server::AuthServiceServer::new(AuthService::new(
+        db.clone(),
+        auth_repository.clone(),
+        config.auth.clone(),
+    ));
 
     let message_service_server = message_service_server::MessageServiceServer::with_interceptor(
         MessageService::new(
+            db.clone(),
             message_repository.clone(),
-            message_acknowledge_repository,
+            message_acknowledge_repository.clone(),
+            mention_repository.clone(),
             server_member_repository.clone(),
             channel_repository.clone(),
+            attachment_repository.clone(),
+            server_repository.clone(),
+            message_search_index.clone(),
+            user_repository.clone(),
+            broadcaster_arc.clone(),
+            config.limits.clone(),
+            pager.clone(),
         ),
-        interceptor::auth::check_auth,
+        check_auth.clone(),
     );
 
+    let attachment_service_server =
+        attachment_service_server::AttachmentServiceServer::with_interceptor(
+            AttachmentService::new(
+                db.clone(),
+                attachment_repository.clone(),
+                blob_store.clone(),
+                config.limits.max_file_size,
+            ),
+            check_auth.clone(),
+        );
+
     let connect_service_server = connect_service_server::ConnectServiceServer::with_interceptor(
         ConnectService::new(broadcaster_arc.clone()),
-        interceptor::auth::check_auth,
+        check_auth.clone(),
     );
 
     let account_service_server = account_service_server::AccountServiceServer::with_interceptor(
-        AccountService::new(auth_repository),
-        interceptor::auth::check_auth,
+        AccountService::new(
+            db.clone(),
+            auth_repository,
+            user_repository.clone(),
+            server_repository.clone(),
+            server_category_repository.clone(),
+            server_member_repository.clone(),
+            channel_repository.clone(),
+            message_repository.clone(),
+            message_acknowledge_repository.clone(),
+            mention_repository.clone(),
+            read_state_repository.clone(),
+            attachment_repository.clone(),
+            blob_store.clone(),
+        ),
+        check_auth.clone(),
     );
 
     // // let chat_service_service_server = chat::get_chat_service_service_server();
     let user_service_server = user_service_server::UserServiceServer::with_interceptor(
-        services::user::UserService::new(user_repository.clone()).await,
-        interceptor::auth::check_auth,
+        services::user::UserService::new(db.clone(), user_repository.clone(), pager.clone()).await,
+        check_auth.clone(),
     );
 
     let me_user_service_server = me_user_service_server::MeUserServiceServer::with_interceptor(
-        services::me_user::MeUserService::new(user_repository).await,
-        interceptor::auth::check_auth,
+        services::me_user::MeUserService::new(
+            db.clone(),
+            user_repository.clone(),
+            attachment_repository.clone(),
+            blob_store.clone(),
+        )
+        .await,
+        check_auth.clone(),
     );
 
+    let me_channel_service_server =
+        me_channel_service_server::MeChannelServiceServer::with_interceptor(
+            services::me_channel::MeChannelService::new(
+                db.clone(),
+                channel_repository.clone(),
+                read_state_repository.clone(),
+                message_repository.clone(),
+                mention_repository.clone(),
+            ),
+            check_auth.clone(),
+        );
+
+    let me_mention_service_server =
+        me_mention_service_server::MeMentionServiceServer::with_interceptor(
+            services::me_mention::MeMentionService::new(
+                db.clone(),
+                mention_repository.clone(),
+                message_repository.clone(),
+                attachment_repository.clone(),
+                pager.clone(),
+            ),
+            check_auth.clone(),
+        );
+
     let me_server_service_server =
         me_server_service_server::MeServerServiceServer::with_interceptor(
             services::me_server::MeServerService::new(
+                db.clone(),
                 server_repository.clone(),
-                server_member_repository.clone(),
+                pager.clone(),
             ),
-            interceptor::auth::check_auth,
+            check_auth.clone(),
         );
 
     let server_service_server = server_service_server::ServerServiceServer::with_interceptor(
         services::server::ServerService::new(
+            db.clone(),
             server_repository.clone(),
             server_member_repository.clone(),
+            attachment_repository.clone(),
+            blob_store.clone(),
+            server_category_repository.clone(),
+            channel_repository.clone(),
+            message_repository.clone(),
+            message_acknowledge_repository.clone(),
+            mention_repository.clone(),
+            read_state_repository.clone(),
+            pager.clone(),
         ),
-        interceptor::auth::check_auth,
+        check_auth.clone(),
     );
 
     let server_category_service_server =
         category_service_server::CategoryServiceServer::with_interceptor(
             services::server_category::ServerCategoryService::new(
+                db.clone(),
                 server_repository.clone(),
                 server_category_repository.clone(),
+                channel_repository.clone(),
+                pager.clone(),
             ),
-            interceptor::auth::check_auth,
+            check_auth.clone(),
         );
 
     let server_member_service_server =
         server_member_service_server::ServerMemberServiceServer::with_interceptor(
-            services::server_member::ServerMemberService::new(server_member_repository.clone()),
-            interceptor::auth::check_auth,
+            services::server_member::ServerMemberService::new(
+                db.clone(),
+                server_member_repository.clone(),
+                attachment_repository.clone(),
+                blob_store.clone(),
+                pager.clone(),
+            ),
+            check_auth.clone(),
         );
 
     let channel_service_server = channel_service_server::ChannelServiceServer::with_interceptor(
         services::channel::ChannelService::new(
+            db.clone(),
             server_member_repository,
             message_repository,
             channel_repository,
             server_repository,
             server_category_repository,
+            attachment_repository,
+            blob_store,
+            mention_repository,
+            message_search_index,
+            user_repository,
+            read_state_repository,
+            message_acknowledge_repository,
             broadcaster_arc.clone(),
+            pager,
         ),
-        interceptor::auth::check_auth,
+        check_auth.clone(),
     );
 
     Server::builder()
         .add_service(connect_service_server)
         .add_service(auth_service_server)
         .add_service(account_service_server)
+        .add_service(attachment_service_server)
         .add_service(user_service_server)
         .add_service(server_service_server)
         .add_service(server_category_service_server)
@@ -147,14 +403,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .add_service(channel_service_server)
         .add_service(message_service_server)
         .add_service(me_user_service_server)
+        .add_service(me_channel_service_server)
+        .add_service(me_mention_service_server)
         .add_service(me_server_service_server)
-        .serve(addr)
+        .serve(config.server.addr)
         .await?;
 
     // println!("Start Server...");
     // Server::builder()
     //     // .add_service(chat_service_service_server)
-    //     .serve(addr)
+    //     .serve(config.server.addr)
     //     .await?;
 
     Ok(())
@@ -0,0 +1,242 @@
+use std::io::Cursor;
+
+use exif::{In, Reader, Tag};
+use image::{imageops::FilterType, DynamicImage, ImageFormat, ImageOutputFormat};
+
+use super::{ProcessedMedia, Thumbnail, THUMBNAIL_SIZES};
+
+const BLURHASH_COMPONENTS_X: u32 = 4;
+const BLURHASH_COMPONENTS_Y: u32 = 3;
+const BLURHASH_SOURCE_SIZE: u32 = 32;
+const THUMBNAIL_QUALITY: u8 = 80;
+const REENCODE_QUALITY: u8 = 90;
+
+/// Reads dimensions, builds thumbnails and a blurhash and strips EXIF data (GPS location
+/// included) from the image. Images carrying EXIF data are re-encoded with the EXIF orientation
+/// applied, so they are still displayed upright.
+pub fn process(data: &[u8]) -> Result<ProcessedMedia, String> {
+    let format = image::guess_format(data).map_err(|err| err.to_string())?;
+
+    let exif = Reader::new()
+        .read_from_container(&mut Cursor::new(data))
+        .ok();
+
+    let mut img =
+        image::load_from_memory_with_format(data, format).map_err(|err| err.to_string())?;
+
+    let sanitized = match exif {
+        Some(exif) => {
+            let orientation = exif
+                .get_field(Tag::Orientation, In::PRIMARY)
+                .and_then(|field| field.value.get_uint(0))
+                .unwrap_or(1);
+
+            img = apply_orientation(img, orientation);
+
+            Some(encode(&img, format)?)
+        }
+        None => None,
+    };
+
+    let mut processed = describe(&img)?;
+    processed.data = sanitized;
+
+    Ok(processed)
+}
+
+/// mime type of the image `data` starts with, when `process` can strip its metadata. the type
+/// a client declares is not trusted, an image uploaded as another type still carries its EXIF.
+pub fn sniff_mime_type(data: &[u8]) -> Option<&'static str> {
+    match image::guess_format(data).ok()? {
+        ImageFormat::Jpeg => Some("image/jpeg"),
+        ImageFormat::Png => Some("image/png"),
+        ImageFormat::Gif => Some("image/gif"),
+        ImageFormat::WebP => Some("image/webp"),
+        _ => None,
+    }
+}
+
+/// Dimensions, blurhash and thumbnails of an already decoded image.
+pub fn describe(img: &DynamicImage) -> Result<ProcessedMedia, String> {
+    let (width, height) = (img.width(), img.height());
+
+    let mut thumbnails = vec![];
+    for size in THUMBNAIL_SIZES {
+        if size >= width.max(height) {
+            break;
+        }
+
+        let thumbnail = img.thumbnail(size, size);
+
+        let mut data = Cursor::new(vec![]);
+        DynamicImage::ImageRgb8(thumbnail.to_rgb8())
+            .write_to(&mut data, ImageOutputFormat::Jpeg(THUMBNAIL_QUALITY))
+            .map_err(|err| err.to_string())?;
+
+        thumbnails.push(Thumbnail {
+            size,
+            width: thumbnail.width(),
+            height: thumbnail.height(),
+            data: data.into_inner(),
+        });
+    }
+
+    let source = img
+        .thumbnail(BLURHASH_SOURCE_SIZE, BLURHASH_SOURCE_SIZE)
+        .to_rgba8();
+    let blurhash = blurhash::encode(
+        BLURHASH_COMPONENTS_X,
+        BLURHASH_COMPONENTS_Y,
+        source.width(),
+        source.height(),
+        source.as_raw(),
+    )
+    .ok();
+
+    Ok(ProcessedMedia {
+        data: None,
+        width: Some(width),
+        height: Some(height),
+        duration: None,
+        blurhash,
+        thumbnails,
+    })
+}
+
+/// Crops the centered square of the image and scales it down to `max_size` when larger.
+/// Returns the encoded image and its mime type, png when the image has transparency and jpeg
+/// otherwise.
+pub fn crop_square(data: &[u8], max_size: u32) -> Result<(Vec<u8>, &'static str), String> {
+    let img = image::load_from_memory(data).map_err(|err| err.to_string())?;
+
+    let side = img.width().min(img.height());
+    let x = (img.width() - side) / 2;
+    let y = (img.height() - side) / 2;
+
+    let mut cropped = img.crop_imm(x, y, side, side);
+    if side > max_size {
+        cropped = cropped.resize_exact(max_size, max_size, FilterType::Lanczos3);
+    }
+
+    if cropped.color().has_alpha() {
+        Ok((encode(&cropped, ImageFormat::Png)?, "image/png"))
+    } else {
+        Ok((encode(&cropped, ImageFormat::Jpeg)?, "image/jpeg"))
+    }
+}
+
+/// `img` in `format`, without the metadata of its source. webp is written lossless, the only
+/// webp encoding available. gif carries no EXIF data and is never re-encoded.
+fn encode(img: &DynamicImage, format: ImageFormat) -> Result<Vec<u8>, String> {
+    let output_format = match format {
+        ImageFormat::Jpeg => ImageOutputFormat::Jpeg(REENCODE_QUALITY),
+        ImageFormat::Png => ImageOutputFormat::Png,
+        ImageFormat::WebP => ImageOutputFormat::WebP,
+        _ => return Err(format!("can not remove metadata from {format:?} image")),
+    };
+
+    let img = match format {
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(img.to_rgb8()),
+        // the webp encoder takes 8 bit rgb(a) only.
+        ImageFormat::WebP if img.color().has_alpha() => DynamicImage::ImageRgba8(img.to_rgba8()),
+        ImageFormat::WebP => DynamicImage::ImageRgb8(img.to_rgb8()),
+        _ => img.clone(),
+    };
+
+    let mut data = Cursor::new(vec![]);
+    img.write_to(&mut data, output_format)
+        .map_err(|err| err.to_string())?;
+
+    Ok(data.into_inner())
+}
+
+// https://www.cipa.jp/std/documents/e/DC-008-2012_E.pdf (Orientation tag)
+fn apply_orientation(img: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => img.fliph(),
+        3 => img.rotate180(),
+        4 => img.flipv(),
+        5 => img.rotate90().fliph(),
+        6 => img.rotate90(),
+        7 => img.rotate270().fliph(),
+        8 => img.rotate270(),
+        _ => img,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a little-endian TIFF header with one IFD entry, Orientation `orientation`.
+    fn exif(orientation: u8) -> Vec<u8> {
+        let mut exif = b"II*\0\x08\0\0\0\x01\0".to_vec();
+        exif.extend_from_slice(&[0x12, 0x01, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00]);
+        exif.extend_from_slice(&[orientation, 0, 0, 0, 0, 0, 0, 0]);
+        exif
+    }
+
+    fn chunk(fourcc: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = fourcc.to_vec();
+        chunk.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    /// a lossless `width`x`height` webp in the extended format, with an EXIF chunk.
+    fn webp_with_exif(width: u32, height: u32, orientation: u8) -> Vec<u8> {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::new(width, height));
+        let simple = encode(&img, ImageFormat::WebP).unwrap();
+        // RIFF header, then the VP8L chunk.
+        let vp8l = &simple[12..];
+
+        let mut vp8x = vec![0x08, 0, 0, 0];
+        vp8x.extend_from_slice(&(width - 1).to_le_bytes()[..3]);
+        vp8x.extend_from_slice(&(height - 1).to_le_bytes()[..3]);
+
+        let mut body = b"WEBP".to_vec();
+        body.extend(chunk(b"VP8X", &vp8x));
+        body.extend_from_slice(vp8l);
+        body.extend(chunk(b"EXIF", &exif(orientation)));
+
+        chunk(b"RIFF", &body)
+    }
+
+    #[test]
+    fn webp_with_exif_is_reencoded_upright() {
+        let data = webp_with_exif(4, 2, 6);
+        assert!(Reader::new()
+            .read_from_container(&mut Cursor::new(&data))
+            .is_ok());
+
+        let processed = process(&data).unwrap();
+        assert_eq!((processed.width, processed.height), (Some(2), Some(4)));
+
+        let sanitized = processed.data.unwrap();
+        assert_eq!(image::guess_format(&sanitized).unwrap(), ImageFormat::WebP);
+        assert!(Reader::new()
+            .read_from_container(&mut Cursor::new(&sanitized))
+            .is_err());
+
+        let img = image::load_from_memory(&sanitized).unwrap();
+        assert_eq!((img.width(), img.height()), (2, 4));
+    }
+
+    #[test]
+    fn image_without_exif_is_kept() {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::new(4, 2));
+        for format in [ImageFormat::Png, ImageFormat::WebP] {
+            let processed = process(&encode(&img, format).unwrap()).unwrap();
+
+            assert!(processed.data.is_none());
+            assert_eq!((processed.width, processed.height), (Some(4), Some(2)));
+        }
+
+        let mut gif = Cursor::new(vec![]);
+        img.write_to(&mut gif, ImageOutputFormat::Gif).unwrap();
+        assert!(process(&gif.into_inner()).unwrap().data.is_none());
+    }
+}
@@ -0,0 +1,22 @@
+pub mod images;
+pub mod videos;
+
+/// longest edge of the generated thumbnails, in pixels.
+pub const THUMBNAIL_SIZES: [u32; 3] = [64, 256, 1024];
+
+pub struct Thumbnail {
+    pub size: u32,
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>, // jpeg
+}
+
+#[derive(Default)]
+pub struct ProcessedMedia {
+    pub data: Option<Vec<u8>>, // sanitized file, None when the uploaded file is kept as is.
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub duration: Option<f64>, // unit: seconds
+    pub blurhash: Option<String>,
+    pub thumbnails: Vec<Thumbnail>,
+}
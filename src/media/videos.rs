@@ -0,0 +1,94 @@
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use tokio::{fs, process::Command};
+use ulid::Ulid;
+
+use super::{images, ProcessedMedia};
+
+#[derive(Deserialize)]
+struct Probe {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+    format: Option<ProbeFormat>,
+}
+
+#[derive(Deserialize)]
+struct ProbeStream {
+    codec_type: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ProbeFormat {
+    duration: Option<String>,
+}
+
+/// Reads dimensions and duration with `ffprobe` and builds thumbnails and a blurhash from the
+/// first frame with `ffmpeg`. Both binaries must be available in `PATH`.
+pub async fn process(data: &[u8]) -> Result<ProcessedMedia, String> {
+    let path: PathBuf = std::env::temp_dir().join(format!("ycchat-{}", Ulid::new()));
+
+    fs::write(&path, data)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let res = process_file(&path).await;
+
+    let _ = fs::remove_file(&path).await;
+
+    res
+}
+
+async fn process_file(path: &PathBuf) -> Result<ProcessedMedia, String> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-print_format",
+            "json",
+            "-show_streams",
+            "-show_format",
+        ])
+        .arg(path)
+        .output()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).to_string());
+    }
+
+    let probe: Probe = serde_json::from_slice(&output.stdout).map_err(|err| err.to_string())?;
+
+    let video = probe
+        .streams
+        .iter()
+        .find(|stream| stream.codec_type.as_deref() == Some("video"));
+
+    let duration = probe
+        .format
+        .and_then(|format| format.duration)
+        .and_then(|duration| duration.parse::<f64>().ok());
+
+    let output = Command::new("ffmpeg")
+        .args(["-v", "error", "-i"])
+        .arg(path)
+        .args(["-frames:v", "1", "-f", "image2pipe", "-vcodec", "png", "-"])
+        .output()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let mut processed = match image::load_from_memory(&output.stdout) {
+        Ok(frame) if output.status.success() => images::describe(&frame)?,
+        _ => ProcessedMedia::default(),
+    };
+
+    // the decoded frame is already rotated, so prefer its dimensions over the coded ones.
+    processed.width = processed.width.or(video.and_then(|video| video.width));
+    processed.height = processed.height.or(video.and_then(|video| video.height));
+    processed.duration = duration;
+
+    Ok(processed)
+}
@@ -1,22 +1,137 @@
+use chrono::Timelike;
+use prost_types::Timestamp;
 use serde::{Deserialize, Serialize};
 use surrealdb::sql::Datetime;
 
 use super::user::UserId;
+use crate::db::surreal::{
+    attachment::serialize_id, deserialize_ulid_id, user::serialize_id as user_serialize_id,
+};
+use crate::services::ycchat::v1::models::{
+    attachment::{Metadata as AttachmentMetadataMessage, Thumbnail as AttachmentThumbnailMessage},
+    Attachment as AttachmentMessage,
+};
+use crate::util::resource_name::AttachmentName;
 
 pub type AttachmentId = ulid::Ulid;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Attachment {
+    #[serde(
+        serialize_with = "serialize_id",
+        deserialize_with = "deserialize_ulid_id"
+    )]
     pub id: AttachmentId,
     pub url: String,
     pub filename: String,
     pub mime_type: String,
     pub file_size: i64,
-    // pub metadata: Option<Map<String, String>>,
+    #[serde(default)]
+    pub metadata: Option<AttachmentMetadata>,
     pub create_time: Datetime,
 }
 
-pub struct AttachmentUplaoded {
-    pub user: UserId,             // surreal relate 'in'
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AttachmentMetadata {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub duration: Option<f64>, // unit: seconds
+    pub blurhash: Option<String>,
+    #[serde(default)]
+    pub thumbnails: Vec<AttachmentThumbnail>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AttachmentThumbnail {
+    pub url: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Attachment {
+    pub fn new(
+        id: AttachmentId,
+        url: String,
+        filename: String,
+        mime_type: String,
+        file_size: i64,
+    ) -> Self {
+        Attachment {
+            id,
+            url,
+            filename,
+            mime_type,
+            file_size,
+            metadata: None,
+            create_time: Datetime::default(),
+        }
+    }
+
+    /// keep only url and path safe characters, the original filename is stored in `filename`.
+    pub fn blob_key(id: &AttachmentId, filename: &str) -> String {
+        let filename = filename
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+                _ => '_',
+            })
+            .collect::<String>();
+
+        format!("{id}/{filename}")
+    }
+
+    pub fn thumbnail_blob_key(id: &AttachmentId, size: u32) -> String {
+        format!("{id}/thumbnails/{size}.jpg")
+    }
+
+    pub fn to_message(&self) -> AttachmentMessage {
+        AttachmentMessage {
+            name: AttachmentName(self.id).to_string(),
+            url: self.url.clone(),
+            filename: self.filename.clone(),
+            mime_type: self.mime_type.clone(),
+            file_size: self.file_size,
+            metadata: self.metadata.as_ref().map(|metadata| metadata.to_message()),
+            create_time: Some(Timestamp {
+                seconds: self.create_time.timestamp(),
+                nanos: self.create_time.nanosecond() as i32,
+            }),
+        }
+    }
+}
+
+impl AttachmentMetadata {
+    pub fn to_message(&self) -> AttachmentMetadataMessage {
+        AttachmentMetadataMessage {
+            width: self.width,
+            height: self.height,
+            duration: self.duration,
+            blurhash: self.blurhash.clone(),
+            thumbnails: self
+                .thumbnails
+                .iter()
+                .map(|thumbnail| AttachmentThumbnailMessage {
+                    url: thumbnail.url.clone(),
+                    width: thumbnail.width,
+                    height: thumbnail.height,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AttachmentUploaded {
+    #[serde(
+        rename = "in",
+        serialize_with = "user_serialize_id",
+        deserialize_with = "deserialize_ulid_id"
+    )]
+    pub user: UserId, // surreal relate 'in'
+    #[serde(
+        rename = "out",
+        serialize_with = "serialize_id",
+        deserialize_with = "deserialize_ulid_id"
+    )]
     pub attachment: AttachmentId, // surreal relate 'out'
 }
@@ -1,27 +1,43 @@
+use crate::db::filter::{FieldKind, FilterField};
 use crate::{
-    services::ycchat::v1::models::{channel::ChannelType as ChannelTypeMessage, Channel},
-    util::pager::PageItem,
+    services::{
+        error::ServiceError,
+        ycchat::v1::models::{channel::ChannelType as ChannelTypeMessage, Channel},
+    },
+    util::{
+        field_mask::UpdateMask,
+        pager::PageItem,
+        resource_name::{CategoryName, ChannelName, UserName},
+    },
 };
 use chrono::Timelike;
-use prost_types::Timestamp;
+use prost_types::{FieldMask, Timestamp};
 use serde::{Deserialize, Serialize};
-use surrealdb::sql::{Datetime, Id, Thing};
+use surrealdb::sql::Datetime;
 use ulid::Ulid;
 
 use crate::db::surreal::{
-    channel::serialize_id, deserialize_ulid_id, server::COLLECTION_NAME as SERVER_COLLECTION_NAME,
-    server_category::COLLECTION_NAME as SERVER_CATEGORY_COLLECTION_NAME,
+    attachment::serialize_option as attachment_serialize_option, channel::serialize_id,
+    deserialize_ulid_id, deserialize_ulid_id_list, deserialize_ulid_id_option,
+    server_category::serialize_option as server_category_serialize_option,
+    user::serialize_id_list as user_serialize_id_list,
 };
 
 use super::{
-    attachment::Attachment,
-    server::{DbServer, ServerId},
-    server_category::DbServerCategory,
-    user::UserId,
+    attachment::Attachment, server::ServerId, server_category::ServerCategoryId, user::UserId,
 };
 
 pub type ChannelId = Ulid;
 
+/// fields the `filter` of `ListServerChannels` may compare.
+pub const FILTER_FIELDS: &[FilterField] = &[
+    FilterField::new("display_name", FieldKind::Text),
+    FilterField::new("description", FieldKind::Text),
+    FilterField::new("last_message_time", FieldKind::Time),
+    FilterField::new("create_time", FieldKind::Time),
+    FilterField::new("update_time", FieldKind::Time),
+];
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DbChannel {
     #[serde(
@@ -32,8 +48,23 @@ pub struct DbChannel {
     pub channel_type: ChannelType,
     pub display_name: String,
     pub description: String,
+    #[serde(
+        default,
+        serialize_with = "server_category_serialize_option",
+        deserialize_with = "deserialize_ulid_id_option"
+    )]
+    pub category: Option<ServerCategoryId>,
     pub order: u64,
+    #[serde(default, serialize_with = "attachment_serialize_option")]
     pub icon: Option<Attachment>,
+    // participants of channels which don't belong to a server.
+    #[serde(
+        default,
+        serialize_with = "user_serialize_id_list",
+        deserialize_with = "deserialize_ulid_id_list"
+    )]
+    pub members: Vec<UserId>,
+    pub last_message_time: Option<Datetime>,
     pub create_time: Datetime,
     pub update_time: Option<Datetime>,
 }
@@ -43,19 +74,23 @@ pub struct DbChannel {
 pub enum ChannelType {
     Saved { owner: UserId }, // self
     Direct,                  // 1:1 direct message
+    Group { owner: UserId }, // direct message between up to `MAX_GROUP_MEMBERS` users
     Server { server: ServerId },
 }
 
+pub const MAX_GROUP_MEMBERS: usize = 10;
+
 impl DbChannel {
-    pub fn new(owner: UserId, message: Channel, server: Option<ServerId>) -> Self {
-        let channel_type = ChannelTypeMessage::from_i32(message.channel_type).unwrap();
-
-        let channel_type = match channel_type {
-            ChannelTypeMessage::Saved => ChannelType::Saved { owner },
-            ChannelTypeMessage::Direct => ChannelType::Direct,
-            ChannelTypeMessage::Server => ChannelType::Server {
-                server: server.unwrap(),
-            },
+    /// `channel_type` is resolved by the caller, the type of `message` isn't read.
+    pub fn new(
+        channel_type: ChannelType,
+        message: Channel,
+        category: Option<ServerCategoryId>,
+        order: u64,
+    ) -> Self {
+        let members = match channel_type {
+            ChannelType::Group { owner } => vec![owner],
+            _ => vec![],
         };
 
         DbChannel {
@@ -63,66 +98,135 @@ impl DbChannel {
             channel_type,
             display_name: message.display_name,
             description: message.description,
+            category,
+            order,
+            icon: None,
+            members,
+            last_message_time: None,
+            create_time: Datetime::default(),
+            update_time: None,
+        }
+    }
+
+    pub fn new_direct(user: UserId, other: UserId) -> Self {
+        DbChannel {
+            id: ChannelId::new(),
+            channel_type: ChannelType::Direct,
+            display_name: String::new(),
+            description: String::new(),
+            category: None,
             order: 0,
             icon: None,
+            members: vec![user, other],
+            last_message_time: None,
             create_time: Datetime::default(),
             update_time: None,
         }
     }
 
-    pub fn to_message(self) -> Channel {
+    pub fn to_message(&self) -> Channel {
         Channel {
-            name: format!("channels/{}", self.id),
-            display_name: self.display_name,
-            description: self.description,
-            icon: None,
+            name: ChannelName(self.id).to_string(),
+            display_name: self.display_name.clone(),
+            description: self.description.clone(),
+            icon: self.icon.as_ref().map(|icon| icon.to_message()),
             channel_type: self.channel_type.to_message() as i32,
-            unread_message_count: 0, // FIXME
+            // counts depend on the reader, the channel service fills them in.
+            unread_message_count: 0,
+            mention_count: 0,
+            category: match (&self.channel_type, self.category) {
+                (ChannelType::Server { server }, Some(category)) => Some(
+                    CategoryName {
+                        server: *server,
+                        category,
+                    }
+                    .to_string(),
+                ),
+                _ => None,
+            },
             order: self.order,
+            owner: match self.channel_type {
+                ChannelType::Saved { owner } | ChannelType::Group { owner } => {
+                    Some(UserName(owner).to_string())
+                }
+                _ => None,
+            },
+            members: self
+                .members
+                .iter()
+                .map(|member| UserName(*member).to_string())
+                .collect(),
+            last_message_time: self
+                .last_message_time
+                .as_ref()
+                .map(|last_message_time| Timestamp {
+                    seconds: last_message_time.timestamp(),
+                    nanos: last_message_time.nanosecond() as i32,
+                }),
             create_time: Some(Timestamp {
                 seconds: self.create_time.timestamp(),
                 nanos: self.create_time.nanosecond() as i32,
             }),
-            update_time: self.update_time.map(|update_time| Timestamp {
+            update_time: self.update_time.as_ref().map(|update_time| Timestamp {
                 seconds: update_time.timestamp(),
                 nanos: update_time.nanosecond() as i32,
             }),
         }
     }
 
-    pub fn update(&mut self, message: Channel) {
-        self.display_name = message.display_name;
-        self.description = message.description;
-        self.order = message.order;
-        // self.icon = message.icon;
-        self.update_time = Some(Datetime::default())
-    }
-}
-
-impl ChannelType {
-    pub fn new_saved(&self, owner: UserId) -> ChannelType {
-        ChannelType::Saved { owner }
-    }
+    /// applies the fields of `message` in `update_mask`. `category` has to be resolved
+    /// against the server, the caller moves the channel when the returned mask contains it.
+    pub fn update(
+        &mut self,
+        message: Channel,
+        update_mask: Option<FieldMask>,
+    ) -> Result<UpdateMask, ServiceError> {
+        let update_mask = UpdateMask::new(
+            update_mask,
+            &[
+                ("display_name", !message.display_name.is_empty()),
+                ("description", !message.description.is_empty()),
+                ("order", message.order != 0),
+                ("category", message.category.is_some()),
+            ],
+        )?;
 
-    pub fn new_direct(&self) -> ChannelType {
-        ChannelType::Direct
-    }
+        if update_mask.contains("display_name") {
+            self.display_name = message.display_name;
+        }
+        if update_mask.contains("description") {
+            self.description = message.description;
+        }
+        if update_mask.contains("order") {
+            self.order = message.order;
+        }
+        self.update_time = Some(Datetime::default());
 
-    pub fn new_server(&self, server: ServerId) -> ChannelType {
-        ChannelType::Server { server }
+        Ok(update_mask)
     }
+}
 
+impl ChannelType {
     pub fn to_message(&self) -> ChannelTypeMessage {
         match self {
-            ChannelType::Saved { owner } => ChannelTypeMessage::Saved,
+            ChannelType::Saved { .. } => ChannelTypeMessage::Saved,
             Self::Direct => ChannelTypeMessage::Direct,
-            ChannelType::Server { server } => ChannelTypeMessage::Server,
+            ChannelType::Group { .. } => ChannelTypeMessage::Group,
+            ChannelType::Server { .. } => ChannelTypeMessage::Server,
         }
     }
 }
 
+/// where a channel goes in the server layout.
+#[derive(Debug, Clone)]
+pub struct ChannelPosition {
+    pub id: ChannelId,
+    pub category: Option<ServerCategoryId>,
+    pub order: u64,
+}
+
 impl PageItem for DbChannel {
-    fn get_item_id(&self) -> String {
-        self.id.to_string()
+    fn get_item_id(&self) -> ChannelId {
+        self.id
     }
 }
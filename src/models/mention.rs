@@ -0,0 +1,274 @@
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Datetime;
+use ulid::Ulid;
+
+use super::{channel::ChannelId, message::MessageId, user::UserId};
+use crate::{
+    db::surreal::{
+        channel::{
+            serialize_id as channel_serialize_id, serialize_id_list as channel_serialize_id_list,
+        },
+        deserialize_ulid_id, deserialize_ulid_id_list,
+        mention::serialize_id,
+        message::serialize_id as message_serialize_id,
+        user::{serialize_id as user_serialize_id, serialize_id_list as user_serialize_id_list},
+    },
+    services::ycchat::v1::models::MessageMentions,
+    util::{
+        pager::PageItem,
+        resource_name::{ChannelName, UserName},
+    },
+};
+
+pub type MentionId = Ulid;
+
+/// roles are not modeled yet, role mentions are kept as written until they are.
+pub type RoleId = Ulid;
+
+/// mentions written in a message.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Mentions {
+    #[serde(
+        default,
+        serialize_with = "user_serialize_id_list",
+        deserialize_with = "deserialize_ulid_id_list"
+    )]
+    pub users: Vec<UserId>,
+    #[serde(default)]
+    pub roles: Vec<RoleId>,
+    #[serde(
+        default,
+        serialize_with = "channel_serialize_id_list",
+        deserialize_with = "deserialize_ulid_id_list"
+    )]
+    pub channels: Vec<ChannelId>,
+    #[serde(default)]
+    pub everyone: bool,
+    #[serde(default)]
+    pub here: bool,
+}
+
+impl Mentions {
+    /// parses `<@user>`, `<@&role>`, `<#channel>`, `@everyone` and `@here` tokens out of
+    /// `content`. malformed tokens are left as plain text.
+    pub fn parse(content: &str) -> Self {
+        let mut mentions = Mentions::default();
+        let mut index = 0;
+
+        while let Some(c) = content[index..].chars().next() {
+            let rest = &content[index..];
+
+            if c == '<' {
+                if let Some(len) = mentions.parse_tag(rest) {
+                    index += len;
+                    continue;
+                }
+            }
+
+            if c == '@' && !content[..index].ends_with(is_word_char) {
+                if let Some(len) = mentions.parse_keyword(rest) {
+                    index += len;
+                    continue;
+                }
+            }
+
+            index += c.len_utf8();
+        }
+
+        mentions
+    }
+
+    /// `<@user>`, `<@&role>` or `<#channel>` at the start of `rest`, returns the token length.
+    fn parse_tag(&mut self, rest: &str) -> Option<usize> {
+        let end = rest.find('>')?;
+        let tag = &rest[1..end];
+
+        if let Some(id) = tag.strip_prefix("@&") {
+            push_unique(&mut self.roles, Ulid::from_string(id).ok()?);
+        } else if let Some(id) = tag.strip_prefix('@') {
+            push_unique(&mut self.users, Ulid::from_string(id).ok()?);
+        } else if let Some(id) = tag.strip_prefix('#') {
+            push_unique(&mut self.channels, Ulid::from_string(id).ok()?);
+        } else {
+            return None;
+        }
+
+        Some(end + 1)
+    }
+
+    /// `@everyone` or `@here` at the start of `rest`, returns the token length.
+    fn parse_keyword(&mut self, rest: &str) -> Option<usize> {
+        let (keyword, flag) = if rest.starts_with("@everyone") {
+            ("@everyone", &mut self.everyone)
+        } else if rest.starts_with("@here") {
+            ("@here", &mut self.here)
+        } else {
+            return None;
+        };
+
+        if rest[keyword.len()..].starts_with(is_word_char) {
+            return None;
+        }
+
+        *flag = true;
+
+        Some(keyword.len())
+    }
+
+    pub fn is_mention_everyone(&self) -> bool {
+        self.everyone || self.here
+    }
+
+    pub fn to_message(&self) -> MessageMentions {
+        MessageMentions {
+            users: self
+                .users
+                .iter()
+                .map(|id| UserName(*id).to_string())
+                .collect(),
+            roles: self.roles.iter().map(|id| id.to_string()).collect(),
+            channels: self
+                .channels
+                .iter()
+                .map(|id| ChannelName(*id).to_string())
+                .collect(),
+            everyone: self.everyone,
+            here: self.here,
+        }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn push_unique(ids: &mut Vec<Ulid>, id: Ulid) {
+    if !ids.contains(&id) {
+        ids.push(id);
+    }
+}
+
+/// an entry of the user's mentions inbox.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DbMention {
+    #[serde(
+        serialize_with = "serialize_id",
+        deserialize_with = "deserialize_ulid_id"
+    )]
+    pub id: MentionId,
+    #[serde(
+        serialize_with = "user_serialize_id",
+        deserialize_with = "deserialize_ulid_id"
+    )]
+    pub user: UserId,
+    #[serde(
+        serialize_with = "message_serialize_id",
+        deserialize_with = "deserialize_ulid_id"
+    )]
+    pub message: MessageId,
+    #[serde(
+        serialize_with = "channel_serialize_id",
+        deserialize_with = "deserialize_ulid_id"
+    )]
+    pub channel: ChannelId,
+    pub create_time: Datetime,
+}
+
+impl DbMention {
+    pub fn new(user: UserId, message: MessageId, channel: ChannelId) -> Self {
+        DbMention {
+            id: MentionId::new(),
+            user,
+            message,
+            channel,
+            create_time: Datetime::default(),
+        }
+    }
+}
+
+impl PageItem for DbMention {
+    fn get_item_id(&self) -> MentionId {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER: &str = "01HGW2N5EQNG50CB2HBCN4A8GT";
+    const ROLE: &str = "01HGW2PDN3C9W6K9M0Q4CZ8Y7V";
+    const CHANNEL: &str = "01HGW2Q8V1X0D5Z3N6T7R2K4MB";
+
+    fn id(id: &str) -> Ulid {
+        Ulid::from_string(id).unwrap()
+    }
+
+    #[test]
+    fn parses_every_kind() {
+        let content = format!(
+            "hi <@{}>, <@&{}> see <#{}> @everyone @here",
+            USER, ROLE, CHANNEL
+        );
+        let mentions = Mentions::parse(&content);
+
+        assert_eq!(mentions.users, vec![id(USER)]);
+        assert_eq!(mentions.roles, vec![id(ROLE)]);
+        assert_eq!(mentions.channels, vec![id(CHANNEL)]);
+        assert!(mentions.everyone);
+        assert!(mentions.here);
+    }
+
+    #[test]
+    fn duplicates_are_kept_once() {
+        let mentions = Mentions::parse(&format!("<@{}><@{}> <@{}>", USER, USER, USER));
+
+        assert_eq!(mentions.users, vec![id(USER)]);
+    }
+
+    #[test]
+    fn malformed_tokens_are_plain_text() {
+        for content in [
+            format!("<@{}", USER),
+            format!("<{}>", USER),
+            format!("<!{}>", USER),
+            "<@not-an-id>".to_string(),
+            format!("<@ {}>", USER),
+            "<#>".to_string(),
+            "mail@everyone.com".to_string(),
+            "@everyones @hereafter".to_string(),
+            "@Everyone".to_string(),
+        ] {
+            let mentions = Mentions::parse(&content);
+
+            assert!(mentions.users.is_empty(), "{:?}", content);
+            assert!(mentions.roles.is_empty(), "{:?}", content);
+            assert!(mentions.channels.is_empty(), "{:?}", content);
+            assert!(!mentions.is_mention_everyone(), "{:?}", content);
+        }
+    }
+
+    #[test]
+    fn keywords_end_at_punctuation() {
+        let mentions = Mentions::parse("(@here), @everyone!");
+
+        assert!(mentions.everyone);
+        assert!(mentions.here);
+    }
+
+    #[test]
+    fn malformed_tag_does_not_hide_the_next_token() {
+        let mentions = Mentions::parse(&format!("<<@{}> <x <#{}>", USER, CHANNEL));
+
+        assert_eq!(mentions.users, vec![id(USER)]);
+        assert_eq!(mentions.channels, vec![id(CHANNEL)]);
+    }
+
+    #[test]
+    fn to_message_uses_resource_names() {
+        let message = Mentions::parse(&format!("<@{}> <#{}>", USER, CHANNEL)).to_message();
+
+        assert_eq!(message.users, vec![format!("users/{}", USER)]);
+        assert_eq!(message.channels, vec![format!("channels/{}", CHANNEL)]);
+    }
+}
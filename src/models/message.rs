@@ -1,11 +1,21 @@
-use super::{channel::ChannelId, user::UserId};
+use super::{
+    attachment::{Attachment, AttachmentId},
+    channel::ChannelId,
+    mention::Mentions,
+    user::UserId,
+};
+use crate::db::filter::{FieldKind, FilterField};
 use crate::{
     db::surreal::{
-        channel::serialize_id as channel_serialize_id, deserialize_ulid_id, message::serialize_id,
-        user::serialize_id as user_serialize_id,
+        attachment::serialize_id_list as attachment_serialize_id_list,
+        channel::serialize_id as channel_serialize_id, deserialize_ulid_id,
+        deserialize_ulid_id_list, message::serialize_id, user::serialize_id as user_serialize_id,
+    },
+    services::ycchat::v1::models::{message::MessageType as MessageTypeMessage, Message},
+    util::{
+        pager::PageItem,
+        resource_name::{MessageName, UserName},
     },
-    services::ycchat::v1::models::Message,
-    util::pager::PageItem,
 };
 use chrono::Timelike;
 use prost_types::Timestamp;
@@ -16,6 +26,39 @@ use ulid::Ulid;
 
 pub type MessageId = Ulid;
 
+/// fields the `filter` of `ListMessages` may compare.
+pub const FILTER_FIELDS: &[FilterField] = &[
+    FilterField::new("content", FieldKind::Text),
+    FilterField::new("author", FieldKind::User),
+    FilterField::new("pin_time", FieldKind::Time),
+    FilterField::new("create_time", FieldKind::Time),
+    FilterField::new("update_time", FieldKind::Time),
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MessageType {
+    #[default]
+    #[serde(alias = "FIXME")]
+    Default,
+    ChannelPinnedMessage, // system message, `reference` is the pinned message.
+    GroupMemberAdded,     // system message, `mentions` has the added user.
+    GroupMemberRemoved,   // system message, `mentions` has the removed user.
+    GroupMemberLeft,      // system message, the author left.
+}
+
+impl MessageType {
+    pub fn to_message(self) -> MessageTypeMessage {
+        match self {
+            MessageType::Default => MessageTypeMessage::Default,
+            MessageType::ChannelPinnedMessage => MessageTypeMessage::ChannelPinnedMessage,
+            MessageType::GroupMemberAdded => MessageTypeMessage::GroupMemberAdded,
+            MessageType::GroupMemberRemoved => MessageTypeMessage::GroupMemberRemoved,
+            MessageType::GroupMemberLeft => MessageTypeMessage::GroupMemberLeft,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DbMessage {
     #[serde(
@@ -37,41 +80,111 @@ pub struct DbMessage {
 
     pub content: String,
 
-    pub message_type: String,
-    // pub attachments: Vec<AttachmentId>,
+    pub message_type: MessageType,
+
+    #[serde(default)]
+    pub reference: Option<MessageId>,
+
+    #[serde(
+        default,
+        serialize_with = "attachment_serialize_id_list",
+        deserialize_with = "deserialize_ulid_id_list"
+    )]
+    pub attachments: Vec<AttachmentId>,
+    #[serde(default)]
+    pub mentions: Mentions,
+    #[serde(default)]
+    pub pinned_by: Option<UserId>,
+    #[serde(default)]
+    pub pin_time: Option<Datetime>,
     pub create_time: Datetime,
     pub update_time: Option<Datetime>,
 }
 
 impl DbMessage {
-    pub fn new(author: UserId, channel: ChannelId, content: String) -> Self {
+    pub fn new(
+        author: UserId,
+        channel: ChannelId,
+        content: String,
+        attachments: Vec<AttachmentId>,
+        mentions: Mentions,
+    ) -> Self {
         DbMessage {
             id: MessageId::new(),
             author,
             channel,
             content,
-            message_type: "FIXME".to_string(),
+            message_type: MessageType::Default,
+            reference: None,
+            attachments,
+            mentions,
+            pinned_by: None,
+            pin_time: None,
             create_time: Datetime::default(),
             update_time: None,
         }
     }
 
-    pub fn to_message(self) -> Message {
+    pub fn new_system(
+        author: UserId,
+        channel: ChannelId,
+        message_type: MessageType,
+        reference: Option<MessageId>,
+    ) -> Self {
+        DbMessage {
+            id: MessageId::new(),
+            author,
+            channel,
+            content: String::new(),
+            message_type,
+            reference,
+            attachments: vec![],
+            mentions: Mentions::default(),
+            pinned_by: None,
+            pin_time: None,
+            create_time: Datetime::default(),
+            update_time: None,
+        }
+    }
+
+    /// `attachments` may hold attachments of other messages too, only the ones referenced by
+    /// this message are used.
+    pub fn to_message(&self, attachments: &[Attachment]) -> Message {
+        let attachments = self
+            .attachments
+            .iter()
+            .filter_map(|id| attachments.iter().find(|attachment| attachment.id == *id))
+            .map(|attachment| attachment.to_message())
+            .collect();
+
         Message {
-            name: format!(
-                "channels/{}/messages/{}",
-                self.channel.to_string(),
-                self.id.to_string()
-            ),
-            author: self.author.to_string(),
-            content: self.content,
+            name: MessageName {
+                channel: self.channel,
+                message: self.id,
+            }
+            .to_string(),
+            author: UserName(self.author).to_string(),
+            content: self.content.clone(),
             reactions: HashMap::new(), // FIXME
-            attachments: vec![],
+            attachments,
+            mentions: Some(self.mentions.to_message()),
+            message_type: self.message_type.to_message() as i32,
+            reference: self.reference.map(|reference| {
+                MessageName {
+                    channel: self.channel,
+                    message: reference,
+                }
+                .to_string()
+            }),
+            pin_time: self.pin_time.as_ref().map(|pin_time| Timestamp {
+                seconds: pin_time.timestamp(),
+                nanos: pin_time.nanosecond() as i32,
+            }),
             create_time: Some(Timestamp {
                 seconds: self.create_time.timestamp(),
                 nanos: self.create_time.nanosecond() as i32,
             }),
-            update_time: self.update_time.map(|update_time| Timestamp {
+            update_time: self.update_time.as_ref().map(|update_time| Timestamp {
                 seconds: update_time.timestamp(),
                 nanos: update_time.nanosecond() as i32,
             }),
@@ -80,7 +193,7 @@ impl DbMessage {
 }
 
 impl PageItem for DbMessage {
-    fn get_item_id(&self) -> String {
-        self.id.to_string()
+    fn get_item_id(&self) -> MessageId {
+        self.id
     }
 }
@@ -1,7 +1,13 @@
 use crate::{
-    db::surreal::{deserialize_ulid_id, message_acknowledge::serialize_id},
-    util::pager::PageItem,
+    db::surreal::{
+        deserialize_ulid_id, message::serialize_id as message_serialize_id,
+        message_acknowledge::serialize_id, user::serialize_id as user_serialize_id,
+    },
+    services::ycchat::v1::services::message::MessageReader,
+    util::{pager::PageItem, resource_name::UserName},
 };
+use chrono::Timelike;
+use prost_types::Timestamp;
 use serde::{Deserialize, Serialize};
 use surrealdb::sql::Datetime;
 use ulid::Ulid;
@@ -18,8 +24,16 @@ pub struct DbMessageAcknowledge {
     )]
     pub id: MessageAcknowledgeId,
 
+    #[serde(
+        serialize_with = "message_serialize_id",
+        deserialize_with = "deserialize_ulid_id"
+    )]
     pub message_id: MessageId,
 
+    #[serde(
+        serialize_with = "user_serialize_id",
+        deserialize_with = "deserialize_ulid_id"
+    )]
     pub user_id: UserId,
 
     pub create_time: Datetime,
@@ -34,10 +48,20 @@ impl DbMessageAcknowledge {
             create_time: Datetime::default(),
         }
     }
+
+    pub fn to_message(&self) -> MessageReader {
+        MessageReader {
+            user: UserName(self.user_id).to_string(),
+            read_time: Some(Timestamp {
+                seconds: self.create_time.timestamp(),
+                nanos: self.create_time.nanosecond() as i32,
+            }),
+        }
+    }
 }
 
 impl PageItem for DbMessageAcknowledge {
-    fn get_item_id(&self) -> String {
-        self.id.to_string()
+    fn get_item_id(&self) -> MessageAcknowledgeId {
+        self.id
     }
 }
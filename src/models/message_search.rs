@@ -0,0 +1,133 @@
+use chrono::SecondsFormat;
+use serde::Deserialize;
+use surrealdb::sql::Datetime;
+
+use super::{channel::ChannelId, message::MessageId, user::UserId};
+use crate::{db::surreal::deserialize_ulid_id, util::pager::PageItem};
+
+pub const HIGHLIGHT_START: &str = "<em>";
+pub const HIGHLIGHT_END: &str = "</em>";
+
+const SNIPPET_WORDS: usize = 32;
+const SNIPPET_WORDS_BEFORE_HIGHLIGHT: usize = 8;
+
+#[derive(Debug, Clone, Default)]
+pub struct MessageSearchQuery {
+    /// full-text query, matches every message when empty.
+    pub query: String,
+    /// channels to search in, resolved from what the caller can read.
+    pub channels: Vec<ChannelId>,
+    pub author: Option<UserId>,
+    pub mentions: Option<UserId>,
+    pub has_attachment: Option<bool>,
+    pub start_time: Option<Datetime>,
+    pub end_time: Option<Datetime>,
+    pub pinned: Option<bool>,
+}
+
+impl MessageSearchQuery {
+    /// the criteria as the filter a page token is bound to, one `field=value` per criterion
+    /// in a fixed order. the channels come from the parent, which the token is bound to apart.
+    pub fn to_filter(&self) -> String {
+        let id = |id: &Option<UserId>| id.map(|id| id.to_string()).unwrap_or_default();
+        let flag = |flag: &Option<bool>| flag.map(|flag| flag.to_string()).unwrap_or_default();
+        let time = |time: &Option<Datetime>| {
+            time.as_ref()
+                .map(|time| time.to_rfc3339_opts(SecondsFormat::Nanos, true))
+                .unwrap_or_default()
+        };
+
+        format!(
+            "query={:?} author={} mentions={} has_attachment={} start_time={} end_time={} pinned={}",
+            self.query,
+            id(&self.author),
+            id(&self.mentions),
+            flag(&self.has_attachment),
+            time(&self.start_time),
+            time(&self.end_time),
+            flag(&self.pinned),
+        )
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct MessageSearchHit {
+    #[serde(deserialize_with = "deserialize_ulid_id")]
+    pub id: MessageId,
+    /// message content with matched terms wrapped in `HIGHLIGHT_START` and `HIGHLIGHT_END`.
+    pub highlighted: String,
+}
+
+impl MessageSearchHit {
+    /// the words around the first highlighted term.
+    pub fn snippet(&self) -> String {
+        let words = self.highlighted.split_whitespace().collect::<Vec<&str>>();
+
+        let first_highlight = words
+            .iter()
+            .position(|word| word.contains(HIGHLIGHT_START))
+            .unwrap_or(0);
+
+        let start = first_highlight.saturating_sub(SNIPPET_WORDS_BEFORE_HIGHLIGHT);
+        let end = (start + SNIPPET_WORDS).min(words.len());
+
+        let mut snippet = words[start..end].join(" ");
+        if start > 0 {
+            snippet = format!("…{}", snippet);
+        }
+        if end < words.len() {
+            snippet = format!("{}…", snippet);
+        }
+
+        snippet
+    }
+}
+
+impl PageItem for MessageSearchHit {
+    fn get_item_id(&self) -> MessageId {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_keeps_criteria_apart() {
+        let author = UserId::from_string("01HGW2N5EQNG50CB2HBCN4A8GT").unwrap();
+
+        let by_author = MessageSearchQuery {
+            query: "hello".to_string(),
+            author: Some(author),
+            ..Default::default()
+        };
+        let in_query = MessageSearchQuery {
+            query: format!("hello\" author={}", author),
+            ..Default::default()
+        };
+        let mentioned = MessageSearchQuery {
+            query: "hello".to_string(),
+            mentions: Some(author),
+            ..Default::default()
+        };
+
+        assert_ne!(by_author.to_filter(), in_query.to_filter());
+        assert_ne!(by_author.to_filter(), mentioned.to_filter());
+        assert_eq!(by_author.to_filter(), by_author.clone().to_filter());
+    }
+
+    #[test]
+    fn filter_ignores_channels() {
+        let query = MessageSearchQuery {
+            query: "hello".to_string(),
+            ..Default::default()
+        };
+        let in_channel = MessageSearchQuery {
+            channels: vec![ChannelId::new()],
+            ..query.clone()
+        };
+
+        assert_eq!(query.to_filter(), in_channel.to_filter());
+    }
+}
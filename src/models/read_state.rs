@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Datetime;
+use ulid::Ulid;
+
+use super::{channel::ChannelId, message::MessageId, user::UserId};
+use crate::db::surreal::{
+    channel::serialize_id as channel_serialize_id, deserialize_ulid_id,
+    message::serialize_id as message_serialize_id, read_state::serialize_id,
+    user::serialize_id as user_serialize_id,
+};
+
+pub type ReadStateId = Ulid;
+
+/// the last message a user has read in a channel.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DbReadState {
+    #[serde(
+        serialize_with = "serialize_id",
+        deserialize_with = "deserialize_ulid_id"
+    )]
+    pub id: ReadStateId,
+    #[serde(
+        serialize_with = "user_serialize_id",
+        deserialize_with = "deserialize_ulid_id"
+    )]
+    pub user: UserId,
+    #[serde(
+        serialize_with = "channel_serialize_id",
+        deserialize_with = "deserialize_ulid_id"
+    )]
+    pub channel: ChannelId,
+    #[serde(
+        serialize_with = "message_serialize_id",
+        deserialize_with = "deserialize_ulid_id"
+    )]
+    pub last_read_message: MessageId,
+    pub update_time: Datetime,
+}
+
+impl DbReadState {
+    pub fn new(user: UserId, channel: ChannelId, last_read_message: MessageId) -> Self {
+        DbReadState {
+            id: ReadStateId::new(),
+            user,
+            channel,
+            last_read_message,
+            update_time: Datetime::default(),
+        }
+    }
+}
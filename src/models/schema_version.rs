@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Datetime;
+
+/// a migration applied to the database, see `db::migration`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DbSchemaVersion {
+    pub version: u32,
+    pub name: String,
+    /// sha256 of the script when it was applied.
+    pub checksum: String,
+    pub apply_time: Datetime,
+}
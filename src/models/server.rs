@@ -1,19 +1,30 @@
+use crate::db::filter::{FieldKind, FilterField};
 use chrono::Timelike;
-use prost_types::Timestamp;
+use prost_types::{FieldMask, Timestamp};
 use serde::{Deserialize, Serialize};
 use surrealdb::sql::Datetime;
 
-use crate::{services::ycchat::v1::models::Server, util::pager::PageItem};
-
-use super::{
-    attachment::{Attachment, AttachmentId},
-    user::UserId,
+use crate::{
+    services::{error::ServiceError, ycchat::v1::models::Server},
+    util::{field_mask::UpdateMask, pager::PageItem, resource_name::ServerName},
 };
 
+use super::{attachment::Attachment, user::UserId};
+
 pub type ServerId = ulid::Ulid;
 
+/// fields the `filter` of `ListServers` may compare.
+pub const FILTER_FIELDS: &[FilterField] = &[
+    FilterField::new("display_name", FieldKind::Text),
+    FilterField::new("description", FieldKind::Text),
+    FilterField::new("owner", FieldKind::User),
+    FilterField::new("create_time", FieldKind::Time),
+    FilterField::new("update_time", FieldKind::Time),
+];
+
 use crate::db::surreal::{
-    deserialize_ulid_id, server::serialize_id, user::serialize_id as user_serialize_id,
+    attachment::serialize_option as attachment_serialize_option, deserialize_ulid_id,
+    server::serialize_id, user::serialize_id as user_serialize_id,
 };
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -35,7 +46,8 @@ pub struct DbServer {
         deserialize_with = "deserialize_ulid_id"
     )]
     pub author: UserId,
-    pub icon: Option<AttachmentId>,
+    #[serde(default, serialize_with = "attachment_serialize_option")]
+    pub icon: Option<Attachment>,
     pub create_time: Datetime,
     pub update_time: Option<Datetime>,
     // pub managers: Vec<UserId>,
@@ -55,32 +67,43 @@ impl DbServer {
         }
     }
 
-    pub fn from(message: Server) -> Self {
-        DbServer {
-            id: ServerId::from_string(message.name.split('/').collect::<Vec<&str>>()[1]).unwrap(),
-            display_name: message.display_name,
-            description: message.description,
-            owner: UserId::new(),  // FIXME
-            author: UserId::new(), // FIXME
-            icon: None,
-            create_time: Datetime::default(),
-            update_time: Some(Datetime::default()),
+    pub fn update(
+        &mut self,
+        message: Server,
+        update_mask: Option<FieldMask>,
+    ) -> Result<(), ServiceError> {
+        let update_mask = UpdateMask::new(
+            update_mask,
+            &[
+                ("display_name", !message.display_name.is_empty()),
+                ("description", !message.description.is_empty()),
+            ],
+        )?;
+
+        if update_mask.contains("display_name") {
+            self.display_name = message.display_name;
         }
+        if update_mask.contains("description") {
+            self.description = message.description;
+        }
+        self.update_time = Some(Datetime::default());
+
+        Ok(())
     }
 
-    pub fn to_message(self) -> Server {
+    pub fn to_message(&self) -> Server {
         Server {
-            name: format!("servers/{}", self.id.to_string()),
-            display_name: self.display_name,
-            description: self.description,
-            icon: None,         // FIXME
+            name: ServerName(self.id).to_string(),
+            display_name: self.display_name.clone(),
+            description: self.description.clone(),
+            icon: self.icon.as_ref().map(|icon| icon.to_message()),
             categories: vec![], // FIXME
             channels: vec![],
             create_time: Some(Timestamp {
                 seconds: self.create_time.timestamp(),
                 nanos: self.create_time.nanosecond() as i32,
             }),
-            update_time: self.update_time.map(|update_time| Timestamp {
+            update_time: self.update_time.as_ref().map(|update_time| Timestamp {
                 seconds: update_time.timestamp(),
                 nanos: update_time.nanosecond() as i32,
             }),
@@ -89,7 +112,7 @@ impl DbServer {
 }
 
 impl PageItem for DbServer {
-    fn get_item_id(&self) -> String {
-        self.id.to_string()
+    fn get_item_id(&self) -> ServerId {
+        self.id
     }
 }
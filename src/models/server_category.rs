@@ -1,5 +1,5 @@
 use chrono::Timelike;
-use prost_types::Timestamp;
+use prost_types::{FieldMask, Timestamp};
 use serde::{Deserialize, Serialize};
 use surrealdb::sql::Datetime;
 use ulid::Ulid;
@@ -9,8 +9,8 @@ use crate::{
         deserialize_ulid_id, server::serialize_id as server_serialize_id,
         server_category::serialize_id,
     },
-    services::ycchat::v1::models::Category,
-    util::pager::PageItem,
+    services::{error::ServiceError, ycchat::v1::models::Category},
+    util::{field_mask::UpdateMask, pager::PageItem, resource_name::CategoryName},
 };
 
 use super::{
@@ -57,34 +57,66 @@ impl DbServerCategory {
         }
     }
 
-    pub fn to_message(self) -> Category {
+    pub fn to_message(&self) -> Category {
         Category {
-            name: format!("servers/{}/categories/{}", self.server, self.id),
-            display_name: self.display_name,
-            description: self.description,
+            name: CategoryName {
+                server: self.server,
+                category: self.id,
+            }
+            .to_string(),
+            display_name: self.display_name.clone(),
+            description: self.description.clone(),
             order: self.order,
             icon: None,
             create_time: Some(Timestamp {
                 seconds: self.create_time.timestamp(),
                 nanos: self.create_time.nanosecond() as i32,
             }),
-            update_time: self.update_time.map(|update_time| Timestamp {
+            update_time: self.update_time.as_ref().map(|update_time| Timestamp {
                 seconds: update_time.timestamp(),
                 nanos: update_time.nanosecond() as i32,
             }),
         }
     }
 
-    pub fn update(&mut self, message: Category) {
-        self.display_name = message.display_name;
-        self.description = message.description;
-        self.order = message.order;
+    pub fn update(
+        &mut self,
+        message: Category,
+        update_mask: Option<FieldMask>,
+    ) -> Result<(), ServiceError> {
+        let update_mask = UpdateMask::new(
+            update_mask,
+            &[
+                ("display_name", !message.display_name.is_empty()),
+                ("description", !message.description.is_empty()),
+                ("order", message.order != 0),
+            ],
+        )?;
+
+        if update_mask.contains("display_name") {
+            self.display_name = message.display_name;
+        }
+        if update_mask.contains("description") {
+            self.description = message.description;
+        }
+        if update_mask.contains("order") {
+            self.order = message.order;
+        }
         self.update_time = Some(Datetime::default());
+
+        Ok(())
     }
 }
 
+/// where a category goes in the server layout.
+#[derive(Debug, Clone)]
+pub struct CategoryPosition {
+    pub id: ServerCategoryId,
+    pub order: u32,
+}
+
 impl PageItem for DbServerCategory {
-    fn get_item_id(&self) -> String {
-        self.id.to_string()
+    fn get_item_id(&self) -> ServerCategoryId {
+        self.id
     }
 }
@@ -1,17 +1,33 @@
 use super::{attachment::Attachment, server::ServerId, user::UserId};
+use crate::db::filter::{FieldKind, FilterField};
 use crate::db::surreal::{
-    deserialize_ulid_id, server::serialize_id as server_serialize_id, server_member::serialize_id,
+    attachment::serialize_option as attachment_serialize_option, deserialize_ulid_id,
+    server::serialize_id as server_serialize_id, server_member::serialize_id,
     user::serialize_id as user_serialize_id,
 };
+use crate::services::error::ServiceError;
 use crate::services::ycchat::v1::models::ServerMember;
-use crate::util::pager::PageItem;
+use crate::util::{
+    field_mask::UpdateMask,
+    pager::PageItem,
+    resource_name::{ServerMemberName, UserName},
+};
 use chrono::Timelike;
-use prost_types::Timestamp;
+use prost_types::{FieldMask, Timestamp};
 use serde::{Deserialize, Serialize};
 use surrealdb::sql::Datetime;
 
 pub type ServerMemberId = ulid::Ulid;
 
+/// fields the `filter` of `ListServerMembers` may compare.
+pub const FILTER_FIELDS: &[FilterField] = &[
+    FilterField::new("display_name", FieldKind::Text),
+    FilterField::new("description", FieldKind::Text),
+    FilterField::new("user", FieldKind::User),
+    FilterField::new("create_time", FieldKind::Time),
+    FilterField::new("update_time", FieldKind::Time),
+];
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DbServerMember {
     #[serde(
@@ -33,6 +49,7 @@ pub struct DbServerMember {
     pub server: ServerId,
     pub display_name: String,
     pub description: String,
+    #[serde(default, serialize_with = "attachment_serialize_option")]
     pub avatar: Option<Attachment>,
     pub create_time: Datetime,
     pub update_time: Option<Datetime>,
@@ -52,18 +69,46 @@ impl DbServerMember {
         }
     }
 
-    pub fn to_message(self) -> ServerMember {
+    pub fn update(
+        &mut self,
+        message: ServerMember,
+        update_mask: Option<FieldMask>,
+    ) -> Result<(), ServiceError> {
+        let update_mask = UpdateMask::new(
+            update_mask,
+            &[
+                ("display_name", !message.display_name.is_empty()),
+                ("description", !message.description.is_empty()),
+            ],
+        )?;
+
+        if update_mask.contains("display_name") {
+            self.display_name = message.display_name;
+        }
+        if update_mask.contains("description") {
+            self.description = message.description;
+        }
+        self.update_time = Some(Datetime::default());
+
+        Ok(())
+    }
+
+    pub fn to_message(&self) -> ServerMember {
         ServerMember {
-            name: format!("servers/{}/membmers/{}", self.server, self.id),
-            user: self.user.to_string(),
-            display_name: self.display_name,
-            description: self.description,
-            avartar: None, // FIXME
+            name: ServerMemberName {
+                server: self.server,
+                member: self.id,
+            }
+            .to_string(),
+            user: UserName(self.user).to_string(),
+            display_name: self.display_name.clone(),
+            description: self.description.clone(),
+            avartar: self.avatar.as_ref().map(|avatar| avatar.to_message()),
             create_time: Some(Timestamp {
                 seconds: self.create_time.timestamp(),
                 nanos: self.create_time.nanosecond() as i32,
             }),
-            update_time: self.update_time.map(|update_time| Timestamp {
+            update_time: self.update_time.as_ref().map(|update_time| Timestamp {
                 seconds: update_time.timestamp(),
                 nanos: update_time.nanosecond() as i32,
             }),
@@ -72,7 +117,7 @@ impl DbServerMember {
 }
 
 impl PageItem for DbServerMember {
-    fn get_item_id(&self) -> String {
-        self.id.to_string()
+    fn get_item_id(&self) -> ServerMemberId {
+        self.id
     }
 }
@@ -1,7 +1,6 @@
-use std::str::FromStr;
-
+use crate::db::filter::{FieldKind, FilterField};
 use chrono::Timelike;
-use prost_types::Timestamp;
+use prost_types::{FieldMask, Timestamp};
 use serde::{Deserialize, Serialize};
 use surrealdb::sql::Datetime;
 use ulid::Ulid;
@@ -10,10 +9,58 @@ use super::attachment::Attachment;
 
 pub type UserId = Ulid;
 
+/// fields the `filter` of `ListUsers` may compare.
+pub const FILTER_FIELDS: &[FilterField] = &[
+    FilterField::new("display_name", FieldKind::Text),
+    FilterField::new("description", FieldKind::Text),
+    FilterField::new("create_time", FieldKind::Time),
+    FilterField::new("update_time", FieldKind::Time),
+];
+
+use crate::services::error::ServiceError;
 use crate::services::ycchat::v1::models::User as UserMessage;
+use crate::services::ycchat::v1::services::me::user::UserSettings as UserSettingsMessage;
 
-use crate::db::surreal::{deserialize_ulid_id, user::serialize_id};
-use crate::util::pager::PageItem;
+use crate::db::surreal::{
+    attachment::serialize_option as attachment_serialize_option, deserialize_ulid_id,
+    user::serialize_id,
+};
+use crate::util::{field_mask::UpdateMask, pager::PageItem, resource_name::UserName};
+
+/// settings only the user can see.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UserSettings {
+    /// whether others see when the user read their messages. users who turn it off can't
+    /// see who read theirs either.
+    #[serde(default = "default_true")]
+    pub read_receipts: bool,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        UserSettings {
+            read_receipts: true,
+        }
+    }
+}
+
+impl UserSettings {
+    pub fn from(message: UserSettingsMessage) -> Self {
+        UserSettings {
+            read_receipts: message.read_receipts,
+        }
+    }
+
+    pub fn to_message(&self) -> UserSettingsMessage {
+        UserSettingsMessage {
+            read_receipts: self.read_receipts,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DbUser {
@@ -24,10 +71,13 @@ pub struct DbUser {
     pub id: UserId,
     pub display_name: String,
     pub description: String,
+    #[serde(default, serialize_with = "attachment_serialize_option")]
     pub avatar: Option<Attachment>,
     pub region_code: Option<String>,
     pub language_code: Option<String>,
     pub time_zone: Option<String>,
+    #[serde(default)]
+    pub settings: UserSettings,
     pub create_time: Datetime,
     pub update_time: Option<Datetime>,
 }
@@ -42,39 +92,62 @@ impl DbUser {
             region_code: message.region_code,
             language_code: message.language_code,
             time_zone: message.time_zone,
+            settings: UserSettings::default(),
             create_time: Datetime::default(),
             update_time: None,
         }
     }
 
-    pub fn from(message: UserMessage) -> Self {
-        DbUser {
-            id: Ulid::from_str(message.name.split('/').collect::<Vec<&str>>()[1]).unwrap(),
-            display_name: message.display_name,
-            description: message.description,
-            avatar: None,
-            region_code: message.region_code,
-            language_code: message.language_code,
-            time_zone: message.time_zone,
-            create_time: Datetime::default(),
-            update_time: None,
+    pub fn update(
+        &mut self,
+        message: UserMessage,
+        update_mask: Option<FieldMask>,
+    ) -> Result<(), ServiceError> {
+        let update_mask = UpdateMask::new(
+            update_mask,
+            &[
+                ("display_name", !message.display_name.is_empty()),
+                ("description", !message.description.is_empty()),
+                ("region_code", message.region_code.is_some()),
+                ("language_code", message.language_code.is_some()),
+                ("time_zone", message.time_zone.is_some()),
+            ],
+        )?;
+
+        if update_mask.contains("display_name") {
+            self.display_name = message.display_name;
+        }
+        if update_mask.contains("description") {
+            self.description = message.description;
         }
+        if update_mask.contains("region_code") {
+            self.region_code = message.region_code;
+        }
+        if update_mask.contains("language_code") {
+            self.language_code = message.language_code;
+        }
+        if update_mask.contains("time_zone") {
+            self.time_zone = message.time_zone;
+        }
+        self.update_time = Some(Datetime::default());
+
+        Ok(())
     }
 
-    pub fn to_message(self) -> UserMessage {
+    pub fn to_message(&self) -> UserMessage {
         UserMessage {
-            name: format!("users/{}", self.id),
-            display_name: self.display_name,
-            description: self.description,
-            avatar: None, // FIXME
-            region_code: self.region_code,
-            language_code: self.language_code,
-            time_zone: self.time_zone,
+            name: UserName(self.id).to_string(),
+            display_name: self.display_name.clone(),
+            description: self.description.clone(),
+            avatar: self.avatar.as_ref().map(|avatar| avatar.to_message()),
+            region_code: self.region_code.clone(),
+            language_code: self.language_code.clone(),
+            time_zone: self.time_zone.clone(),
             create_time: Some(Timestamp {
                 seconds: self.create_time.timestamp(),
                 nanos: self.create_time.nanosecond() as i32,
             }),
-            update_time: self.update_time.map(|update_time| Timestamp {
+            update_time: self.update_time.as_ref().map(|update_time| Timestamp {
                 seconds: update_time.timestamp(),
                 nanos: update_time.nanosecond() as i32,
             }),
@@ -83,7 +156,7 @@ impl DbUser {
 }
 
 impl PageItem for DbUser {
-    fn get_item_id(&self) -> String {
-        self.id.to_string()
+    fn get_item_id(&self) -> UserId {
+        self.id
     }
 }
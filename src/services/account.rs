@@ -2,44 +2,197 @@ use argon2::{
     password_hash::{rand_core::OsRng, SaltString},
     Argon2, PasswordHash, PasswordHasher, PasswordVerifier,
 };
-use surrealdb::{engine::remote::ws::Client, Surreal};
+use surrealdb::sql::Datetime;
 use tonic::{Request, Response, Status};
 
 use crate::{
-    db::{surreal::conn, traits::auth::AuthRepository},
-    models::user::UserId,
+    db::{
+        traits::{
+            attachment::AttachmentRepository, auth::AuthRepository, channel::ChannelRepository,
+            mention::MentionRepository, message::MessageRepository,
+            message_acknowledge::MessageAcknowledgeRepository, read_state::ReadStateRepository,
+            server::ServerRepository, server_category::ServerCategoryRepository,
+            server_member::ServerMemberRepository, user::UserRepository,
+        },
+        Database,
+    },
+    models::{attachment::Attachment, user::UserId},
+    storage::BlobStore,
 };
 
+use super::attachment::release_attachment;
+use super::channel::delete_channel_contents_in;
+use super::error::ServiceError;
 use super::ycchat::v1::services::account::{
     account_service_server::AccountService as Account, DeleteAccountRequest, UpdatePasswordRequest,
 };
 
-pub struct AccountService<U>
+pub struct AccountService<DB, U, US, S, SC, SM, C, M, ACK, MN, R, A, B>
 where
-    U: AuthRepository<Surreal<Client>>,
+    DB: Database,
+    U: AuthRepository<DB>,
+    US: UserRepository<DB>,
+    S: ServerRepository<DB>,
+    SC: ServerCategoryRepository<DB>,
+    SM: ServerMemberRepository<DB>,
+    C: ChannelRepository<DB>,
+    M: MessageRepository<DB>,
+    ACK: MessageAcknowledgeRepository<DB>,
+    MN: MentionRepository<DB>,
+    R: ReadStateRepository<DB>,
+    A: AttachmentRepository<DB>,
+    B: BlobStore,
 {
+    db: DB,
     auth_repository: U,
+    user_repository: US,
+    server_repository: S,
+    server_category_repository: SC,
+    server_member_repository: SM,
+    channel_repository: C,
+    message_repository: M,
+    message_acknowledge_repository: ACK,
+    mention_repository: MN,
+    read_state_repository: R,
+    attachment_repository: A,
+    blob_store: B,
 }
 
-impl<U> AccountService<U>
+impl<DB, U, US, S, SC, SM, C, M, ACK, MN, R, A, B>
+    AccountService<DB, U, US, S, SC, SM, C, M, ACK, MN, R, A, B>
 where
-    U: AuthRepository<Surreal<Client>>,
+    DB: Database,
+    U: AuthRepository<DB>,
+    US: UserRepository<DB>,
+    S: ServerRepository<DB>,
+    SC: ServerCategoryRepository<DB>,
+    SM: ServerMemberRepository<DB>,
+    C: ChannelRepository<DB>,
+    M: MessageRepository<DB>,
+    ACK: MessageAcknowledgeRepository<DB>,
+    MN: MentionRepository<DB>,
+    R: ReadStateRepository<DB>,
+    A: AttachmentRepository<DB>,
+    B: BlobStore,
 {
-    pub fn new(auth_repository: U) -> Self {
-        AccountService { auth_repository }
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        db: DB,
+        auth_repository: U,
+        user_repository: US,
+        server_repository: S,
+        server_category_repository: SC,
+        server_member_repository: SM,
+        channel_repository: C,
+        message_repository: M,
+        message_acknowledge_repository: ACK,
+        mention_repository: MN,
+        read_state_repository: R,
+        attachment_repository: A,
+        blob_store: B,
+    ) -> Self {
+        AccountService {
+            db,
+            auth_repository,
+            user_repository,
+            server_repository,
+            server_category_repository,
+            server_member_repository,
+            channel_repository,
+            message_repository,
+            message_acknowledge_repository,
+            mention_repository,
+            read_state_repository,
+            attachment_repository,
+            blob_store,
+        }
+    }
+
+    /// queues handing the servers of `user_id` to the member who joined them first after it,
+    /// servers nobody else is in are deleted with everything in them. Returns the icons of
+    /// the deleted servers and their channels.
+    async fn release_servers_in(
+        &self,
+        db: &DB,
+        transaction: &mut DB::Transaction,
+        user_id: &UserId,
+    ) -> Result<Vec<Attachment>, Status> {
+        let servers = self
+            .server_repository
+            .get_servers_by_owner(db, user_id)
+            .await?;
+
+        let mut icons = vec![];
+        for mut server in servers {
+            let successor = self
+                .server_member_repository
+                .get_server_members_by_server_id(db, &server.id)
+                .await?
+                .into_iter()
+                .filter(|member| member.user != *user_id)
+                .min_by_key(|member| member.id);
+
+            if let Some(successor) = successor {
+                server.owner = successor.user;
+                server.update_time = Some(Datetime::default());
+                self.server_repository
+                    .update_server_in(transaction, &server)?;
+                continue;
+            }
+
+            let channels = self
+                .channel_repository
+                .get_list_by_server_id(db, &server.id)
+                .await?;
+            for channel in &channels {
+                delete_channel_contents_in(
+                    transaction,
+                    &self.message_repository,
+                    &self.message_acknowledge_repository,
+                    &self.mention_repository,
+                    &self.read_state_repository,
+                    &channel.id,
+                )?;
+            }
+            icons.extend(channels.into_iter().filter_map(|channel| channel.icon));
+            icons.extend(server.icon.take());
+            self.channel_repository
+                .delete_by_server_id_in(transaction, &server.id)?;
+            self.server_category_repository
+                .delete_by_server_id_in(transaction, &server.id)?;
+            self.server_member_repository
+                .delete_by_server_id_in(transaction, &server.id)?;
+            self.server_repository
+                .delete_server_in(transaction, &server.id)?;
+        }
+
+        Ok(icons)
     }
 }
 
 #[tonic::async_trait]
-impl<U> Account for AccountService<U>
+impl<DB, U, US, S, SC, SM, C, M, ACK, MN, R, A, B> Account
+    for AccountService<DB, U, US, S, SC, SM, C, M, ACK, MN, R, A, B>
 where
-    U: AuthRepository<Surreal<Client>> + 'static,
+    DB: Database,
+    U: AuthRepository<DB> + 'static,
+    US: UserRepository<DB> + 'static,
+    S: ServerRepository<DB> + 'static,
+    SC: ServerCategoryRepository<DB> + 'static,
+    SM: ServerMemberRepository<DB> + 'static,
+    C: ChannelRepository<DB> + 'static,
+    M: MessageRepository<DB> + 'static,
+    ACK: MessageAcknowledgeRepository<DB> + 'static,
+    MN: MentionRepository<DB> + 'static,
+    R: ReadStateRepository<DB> + 'static,
+    A: AttachmentRepository<DB> + 'static,
+    B: BlobStore + 'static,
 {
     async fn update_password(
         &self,
         request: Request<UpdatePasswordRequest>,
     ) -> Result<Response<()>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
 
         let user_id = request
             .metadata()
@@ -56,11 +209,11 @@ where
         let current_password = request.current_password;
         let new_password = request.new_password;
 
-        let exist = self.auth_repository.get(&db, &user_id).await.unwrap();
+        let exist = self.auth_repository.get(&db, &user_id).await?;
 
         let mut exist = match exist {
             Some(exist) => exist,
-            None => return Err(Status::not_found("not found.")),
+            None => return Err(ServiceError::not_found("not found.").into()),
         };
 
         let parsed_hash = PasswordHash::new(&exist.password).unwrap();
@@ -75,12 +228,12 @@ where
 
         let hashed_new_password = match argon2.hash_password(new_password.as_bytes(), &salt) {
             Ok(res) => res.to_string(),
-            Err(err) => return Err(Status::unauthenticated(err.to_string())),
+            Err(err) => return Err(ServiceError::unauthenticated(err.to_string()).into()),
         };
 
         exist.password = hashed_new_password;
 
-        self.auth_repository.update(&db, &exist).await.unwrap();
+        self.auth_repository.update(&db, &exist).await?;
 
         Ok(Response::new(()))
     }
@@ -89,7 +242,7 @@ where
         &self,
         request: Request<DeleteAccountRequest>,
     ) -> Result<Response<()>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
 
         let user_id = request
             .metadata()
@@ -101,8 +254,198 @@ where
 
         let user_id = UserId::from_string(&user_id).unwrap();
 
-        self.auth_repository.delete(&db, &user_id).await.unwrap();
+        let mut attachments: Vec<_> = self
+            .user_repository
+            .get_user(&db, &user_id)
+            .await?
+            .and_then(|user| user.avatar)
+            .into_iter()
+            .collect();
+        attachments.extend(
+            self.server_member_repository
+                .get_server_members_by_user_id(&db, &user_id)
+                .await?
+                .into_iter()
+                .filter_map(|member| member.avatar),
+        );
+
+        // messages stay in their channels, like a member leaving them.
+        let mut transaction = DB::Transaction::default();
+        attachments.extend(
+            self.release_servers_in(&db, &mut transaction, &user_id)
+                .await?,
+        );
+        self.channel_repository
+            .remove_member_in(&mut transaction, &user_id)?;
+        self.message_acknowledge_repository
+            .delete_by_user_id_in(&mut transaction, &user_id)?;
+        self.server_member_repository
+            .delete_by_user_id_in(&mut transaction, &user_id)?;
+        self.mention_repository
+            .delete_by_user_id_in(&mut transaction, &user_id)?;
+        self.read_state_repository
+            .delete_by_user_id_in(&mut transaction, &user_id)?;
+        self.user_repository
+            .delete_user_in(&mut transaction, &user_id)?;
+        self.auth_repository.delete_in(&mut transaction, &user_id)?;
+        db.commit(transaction).await?;
+
+        for attachment in &attachments {
+            release_attachment(
+                &db,
+                &self.attachment_repository,
+                &self.blob_store,
+                attachment,
+            )
+            .await
+            .unwrap_or_else(|err| err.log());
+        }
 
         Ok(Response::new(()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::memory::{self, MemoryDb},
+        services::testing::{
+            add_image, add_server, add_server_member, add_user, request, MemoryBlobStore,
+        },
+        util::resource_name::AttachmentName,
+    };
+
+    async fn service(db: &MemoryDb, blob_store: &MemoryBlobStore) -> impl Account {
+        let repositories = memory::repositories().await;
+
+        AccountService::new(
+            db.clone(),
+            repositories.auth,
+            repositories.user,
+            repositories.server,
+            repositories.server_category,
+            repositories.server_member,
+            repositories.channel,
+            repositories.message,
+            repositories.message_acknowledge,
+            repositories.mention,
+            repositories.read_state,
+            repositories.attachment,
+            blob_store.clone(),
+        )
+    }
+
+    /// an image uploaded by `uploader`, as the avatars and icons refer to it.
+    async fn image(db: &MemoryDb, blob_store: &MemoryBlobStore, uploader: UserId) -> Attachment {
+        let name = add_image(db, blob_store, uploader, 100).await;
+        let id = AttachmentName::parse(&name).unwrap().0;
+
+        memory::repositories()
+            .await
+            .attachment
+            .get_attachment(db, &id)
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    fn blob_key(attachment: &Attachment) -> String {
+        Attachment::blob_key(&attachment.id, &attachment.filename)
+    }
+
+    async fn delete_account(service: &impl Account, user: UserId) {
+        service
+            .delete_account(request(user, DeleteAccountRequest::default()))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn deleting_an_account_releases_its_avatars_and_memberships() {
+        let db = MemoryDb::new();
+        let blob_store = MemoryBlobStore::default();
+        let service = service(&db, &blob_store).await;
+        let repositories = memory::repositories().await;
+
+        let mut user = add_user(&db).await;
+        let other = add_user(&db).await;
+        let avatar = image(&db, &blob_store, user.id).await;
+        user.avatar = Some(avatar.clone());
+        repositories.user.update_user(&db, &user).await.unwrap();
+
+        let joined = add_server(&db, other.id).await;
+        let mut member = add_server_member(&db, &joined, user.id).await;
+        let member_avatar = image(&db, &blob_store, user.id).await;
+        member.avatar = Some(member_avatar.clone());
+        repositories
+            .server_member
+            .update_server_member(&db, &member)
+            .await
+            .unwrap();
+
+        let owned = add_server(&db, user.id).await;
+        add_server_member(&db, &owned, other.id).await;
+
+        delete_account(&service, user.id).await;
+
+        for attachment in [&avatar, &member_avatar] {
+            assert!(!blob_store.contains(&blob_key(attachment)));
+            let got = repositories
+                .attachment
+                .get_attachment(&db, &attachment.id)
+                .await
+                .unwrap();
+            assert!(got.is_none());
+        }
+
+        assert!(repositories
+            .user
+            .get_user(&db, &user.id)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(repositories
+            .server_member
+            .get_server_members_by_user_id(&db, &user.id)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let owned = repositories
+            .server
+            .get_server(&db, &owned.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(owned.owner, other.id);
+    }
+
+    #[tokio::test]
+    async fn deleting_the_last_member_releases_the_server_icon() {
+        let db = MemoryDb::new();
+        let blob_store = MemoryBlobStore::default();
+        let service = service(&db, &blob_store).await;
+        let repositories = memory::repositories().await;
+
+        let user = add_user(&db).await;
+        let mut server = add_server(&db, user.id).await;
+        let icon = image(&db, &blob_store, user.id).await;
+        server.icon = Some(icon.clone());
+        repositories
+            .server
+            .update_server(&db, &server)
+            .await
+            .unwrap();
+
+        delete_account(&service, user.id).await;
+
+        assert!(repositories
+            .server
+            .get_server(&db, &server.id)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(!blob_store.contains(&blob_key(&icon)));
+    }
+}
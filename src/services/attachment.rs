@@ -0,0 +1,585 @@
+use tonic::{Request, Response, Status, Streaming};
+
+use crate::{
+    db::{traits::attachment::AttachmentRepository, Database},
+    media::{self, ProcessedMedia, THUMBNAIL_SIZES},
+    models::{
+        attachment::{Attachment, AttachmentId, AttachmentMetadata, AttachmentThumbnail},
+        user::UserId,
+    },
+    storage::BlobStore,
+    util::resource_name::AttachmentName,
+};
+
+use super::error::ServiceError;
+use super::ycchat::v1::models::Attachment as AttachmentModel;
+use super::ycchat::v1::services::attachment::{
+    attachment_service_server::AttachmentService as ProtoAttachmentService,
+    upload_attachment_request::Data, AttachmentInfo, UploadAttachmentRequest,
+};
+
+const MAX_FILENAME_LENGTH: usize = 255;
+
+// avatars and icons
+const MAX_SQUARE_IMAGE_FILE_SIZE: i64 = 8 * 1024 * 1024; // 8MB
+const MIN_SQUARE_IMAGE_SIZE: u32 = 64; // px
+const SQUARE_IMAGE_SIZE: u32 = 512; // px
+
+/// bytes kept from the start of an upload to sniff its type, the longest magic number is webp's.
+const SNIFF_LENGTH: usize = 16;
+
+const ALLOWED_MIME_TYPE_PREFIXES: [&str; 2] = ["video/", "audio/"];
+// only images which metadata can be read and stripped.
+const ALLOWED_IMAGE_MIME_TYPES: [&str; 4] = ["image/jpeg", "image/png", "image/gif", "image/webp"];
+const ALLOWED_MIME_TYPES: [&str; 5] = [
+    "text/plain",
+    "application/pdf",
+    "application/zip",
+    "application/json",
+    "application/octet-stream",
+];
+
+pub struct AttachmentService<DB, A, B>
+where
+    DB: Database,
+    A: AttachmentRepository<DB>,
+    B: BlobStore,
+{
+    db: DB,
+    attachment_repository: A,
+    blob_store: B,
+    max_file_size: i64,
+}
+
+impl<DB, A, B> AttachmentService<DB, A, B>
+where
+    DB: Database,
+    A: AttachmentRepository<DB>,
+    B: BlobStore,
+{
+    pub fn new(db: DB, attachment_repository: A, blob_store: B, max_file_size: i64) -> Self {
+        AttachmentService {
+            db,
+            attachment_repository,
+            blob_store,
+            max_file_size,
+        }
+    }
+
+    /// appends the chunks of `stream` to `key`, returns the first `SNIFF_LENGTH` bytes.
+    async fn receive_chunks(
+        &self,
+        stream: &mut Streaming<UploadAttachmentRequest>,
+        key: &str,
+        file_size: i64,
+    ) -> Result<Vec<u8>, Status> {
+        let mut received: i64 = 0;
+        let mut head = Vec::with_capacity(SNIFF_LENGTH);
+
+        while let Some(req) = stream.message().await? {
+            let chunk = match req.data {
+                Some(Data::Chunk(chunk)) => chunk,
+                _ => return Err(ServiceError::invalid_argument("expected file chunk.").into()),
+            };
+
+            received += chunk.len() as i64;
+            if received > file_size {
+                return Err(
+                    ServiceError::invalid_argument("file is larger than file_size.").into(),
+                );
+            }
+
+            let missing = SNIFF_LENGTH - head.len();
+            head.extend_from_slice(&chunk[..missing.min(chunk.len())]);
+
+            if let Err(err) = self.blob_store.append(key, &chunk).await {
+                return Err(ServiceError::internal(err).into());
+            }
+        }
+
+        if received != file_size {
+            return Err(ServiceError::invalid_argument("file is smaller than file_size.").into());
+        }
+
+        Ok(head)
+    }
+}
+
+#[tonic::async_trait]
+impl<DB, A, B> ProtoAttachmentService for AttachmentService<DB, A, B>
+where
+    DB: Database,
+    A: AttachmentRepository<DB> + 'static,
+    B: BlobStore + 'static,
+{
+    async fn upload_attachment(
+        &self,
+        request: Request<Streaming<UploadAttachmentRequest>>,
+    ) -> Result<Response<AttachmentModel>, Status> {
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
+
+        let mut stream = request.into_inner();
+
+        // first message must describe the file, every following message carries a chunk of it.
+        let info = match stream.message().await? {
+            Some(UploadAttachmentRequest {
+                data: Some(Data::Info(info)),
+            }) => info,
+            _ => return Err(ServiceError::invalid_argument("expected attachment info.").into()),
+        };
+
+        let AttachmentInfo {
+            filename,
+            mime_type,
+            file_size,
+        } = info;
+
+        let filename = sanitize_filename(&filename)?;
+        let mime_type = mime_type.to_lowercase();
+
+        if !is_allowed_mime_type(&mime_type) {
+            return Err(ServiceError::invalid_field("mime_type", "not allowed.").into());
+        }
+
+        if file_size <= 0 || file_size > self.max_file_size {
+            return Err(ServiceError::invalid_field("file_size", "out of range.").into());
+        }
+
+        let id = AttachmentId::new();
+        let key = Attachment::blob_key(&id, &filename);
+
+        let head = match self.receive_chunks(&mut stream, &key, file_size).await {
+            Ok(head) => head,
+            Err(status) => {
+                let _ = self.blob_store.delete(&key).await;
+
+                return Err(status);
+            }
+        };
+
+        let mut attachment = Attachment::new(
+            id,
+            self.blob_store.url(&key),
+            filename,
+            mime_type,
+            file_size,
+        );
+
+        let mut written = vec![key.clone()];
+
+        if let Err(status) =
+            process_media(&self.blob_store, &mut attachment, &key, &head, &mut written).await
+        {
+            delete_blobs(&self.blob_store, &written).await;
+
+            return Err(status);
+        }
+
+        let db = self.db.clone();
+
+        let added = self
+            .attachment_repository
+            .add_attachment(&db, &attachment, &user_id)
+            .await;
+
+        match added {
+            Ok(Some(attachment)) => Ok(Response::new(attachment.to_message())),
+            _ => {
+                delete_blobs(&self.blob_store, &written).await;
+
+                Err(ServiceError::internal("internal error").into())
+            }
+        }
+    }
+}
+
+fn sanitize_filename(filename: &str) -> Result<String, ServiceError> {
+    let filename = filename
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .trim();
+
+    if filename.is_empty() || filename == "." || filename == ".." {
+        return Err(ServiceError::invalid_field("filename", "invalid."));
+    }
+
+    if filename.chars().count() > MAX_FILENAME_LENGTH {
+        return Err(ServiceError::invalid_field("filename", "too long."));
+    }
+
+    Ok(filename.to_string())
+}
+
+fn is_allowed_mime_type(mime_type: &str) -> bool {
+    ALLOWED_MIME_TYPES.contains(&mime_type)
+        || ALLOWED_IMAGE_MIME_TYPES.contains(&mime_type)
+        || ALLOWED_MIME_TYPE_PREFIXES
+            .iter()
+            .any(|prefix| mime_type.starts_with(prefix))
+}
+
+pub fn parse_attachment_name(name: &str) -> Result<AttachmentId, ServiceError> {
+    Ok(AttachmentName::parse(name)?.0)
+}
+
+/// Validates the image attachment `name` uploaded by `user_id` and stores its centered square,
+/// scaled down to `SQUARE_IMAGE_SIZE`, as a new attachment to be used as an avatar or an icon.
+pub async fn create_square_image<DB, A, B>(
+    db: &DB,
+    attachment_repository: &A,
+    blob_store: &B,
+    user_id: &UserId,
+    name: &str,
+) -> Result<Attachment, Status>
+where
+    DB: Database,
+    A: AttachmentRepository<DB>,
+    B: BlobStore,
+{
+    let attachment_id = parse_attachment_name(name)?;
+
+    let uploader = attachment_repository
+        .get_uploader(db, &attachment_id)
+        .await?;
+
+    if uploader != Some(*user_id) {
+        return Err(ServiceError::not_found("attachment not found.").into());
+    }
+
+    let source = match attachment_repository
+        .get_attachment(db, &attachment_id)
+        .await?
+    {
+        Some(source) => source,
+        None => return Err(ServiceError::not_found("attachment not found.").into()),
+    };
+
+    if !ALLOWED_IMAGE_MIME_TYPES.contains(&source.mime_type.as_str()) {
+        return Err(ServiceError::invalid_argument("attachment is not an image.").into());
+    }
+
+    if source.file_size > MAX_SQUARE_IMAGE_FILE_SIZE {
+        return Err(ServiceError::invalid_argument("image is too large.").into());
+    }
+
+    let (width, height) = source
+        .metadata
+        .as_ref()
+        .map(|metadata| (metadata.width.unwrap_or(0), metadata.height.unwrap_or(0)))
+        .unwrap_or((0, 0));
+
+    if width.min(height) < MIN_SQUARE_IMAGE_SIZE {
+        return Err(ServiceError::invalid_argument("image is too small.").into());
+    }
+
+    let data = match blob_store
+        .get(&Attachment::blob_key(&source.id, &source.filename))
+        .await
+    {
+        Ok(Some(data)) => data,
+        Ok(None) => return Err(ServiceError::not_found("attachment not found.").into()),
+        Err(err) => return Err(ServiceError::internal(err).into()),
+    };
+
+    let (data, processed, mime_type) = tokio::task::spawn_blocking(move || {
+        let (data, mime_type) = media::images::crop_square(&data, SQUARE_IMAGE_SIZE)?;
+        let processed = media::images::process(&data)?;
+
+        Ok::<_, String>((data, processed, mime_type))
+    })
+    .await
+    .map_err(|err| ServiceError::internal(err.to_string()))?
+    .map_err(|err| ServiceError::invalid_argument(format!("invalid image: {err}")))?;
+
+    let extension = if mime_type == "image/png" {
+        "png"
+    } else {
+        "jpg"
+    };
+    let filename = std::path::Path::new(&source.filename)
+        .with_extension(extension)
+        .to_string_lossy()
+        .to_string();
+
+    let id = AttachmentId::new();
+    let key = Attachment::blob_key(&id, &filename);
+
+    let mut attachment = Attachment::new(
+        id,
+        blob_store.url(&key),
+        filename,
+        mime_type.to_string(),
+        data.len() as i64,
+    );
+
+    let mut written = vec![key.clone()];
+
+    if let Err(err) = blob_store.put(&key, &data).await {
+        delete_blobs(blob_store, &written).await;
+
+        return Err(ServiceError::internal(err).into());
+    }
+
+    if let Err(status) =
+        store_processed_media(blob_store, &mut attachment, processed, &mut written).await
+    {
+        delete_blobs(blob_store, &written).await;
+
+        return Err(status);
+    }
+
+    match attachment_repository
+        .add_attachment(db, &attachment, user_id)
+        .await
+    {
+        Ok(Some(attachment)) => Ok(attachment),
+        _ => {
+            delete_blobs(blob_store, &written).await;
+
+            Err(ServiceError::internal("internal error").into())
+        }
+    }
+}
+
+/// Deletes a replaced avatar or icon, with its files, once nothing refers to it anymore.
+/// The update that replaced it has already succeeded, callers log the error rather than
+/// return it.
+pub async fn release_attachment<DB, A, B>(
+    db: &DB,
+    attachment_repository: &A,
+    blob_store: &B,
+    attachment: &Attachment,
+) -> Result<(), ServiceError>
+where
+    DB: Database,
+    A: AttachmentRepository<DB>,
+    B: BlobStore,
+{
+    if attachment_repository
+        .is_referenced(db, &attachment.id)
+        .await?
+    {
+        return Ok(());
+    }
+
+    attachment_repository
+        .delete_attachment(db, &attachment.id)
+        .await?;
+
+    let mut keys = vec![Attachment::blob_key(&attachment.id, &attachment.filename)];
+    keys.extend(
+        THUMBNAIL_SIZES
+            .iter()
+            .map(|size| Attachment::thumbnail_blob_key(&attachment.id, *size)),
+    );
+
+    delete_blobs(blob_store, &keys).await;
+
+    Ok(())
+}
+
+/// Strips image metadata and records dimensions, duration, blurhash and thumbnails of image
+/// and video attachments. `head` is the start of the file, an image is processed as one and gets
+/// its actual mime type whatever type was declared. Every written blob key is pushed to
+/// `written`.
+async fn process_media<B: BlobStore>(
+    blob_store: &B,
+    attachment: &mut Attachment,
+    key: &str,
+    head: &[u8],
+    written: &mut Vec<String>,
+) -> Result<(), Status> {
+    if let Some(mime_type) = media::images::sniff_mime_type(head) {
+        attachment.mime_type = mime_type.to_string();
+    }
+
+    let is_image = attachment.mime_type.starts_with("image/");
+    let is_video = attachment.mime_type.starts_with("video/");
+
+    if !is_image && !is_video {
+        return Ok(());
+    }
+
+    let data = match blob_store.get(key).await {
+        Ok(Some(data)) => data,
+        Ok(None) => return Err(ServiceError::internal("uploaded file not found.").into()),
+        Err(err) => return Err(ServiceError::internal(err).into()),
+    };
+
+    let processed = if is_image {
+        let processed = tokio::task::spawn_blocking(move || media::images::process(&data))
+            .await
+            .map_err(|err| ServiceError::internal(err.to_string()))?;
+
+        match processed {
+            Ok(processed) => processed,
+            Err(err) => {
+                return Err(ServiceError::invalid_argument(format!("invalid image: {err}")).into())
+            }
+        }
+    } else {
+        match media::videos::process(&data).await {
+            Ok(processed) => processed,
+            Err(err) => {
+                // keep the video, clients fall back to a generic player without metadata.
+                ServiceError::internal(format!(
+                    "failed to process video {}: {}",
+                    attachment.id, err
+                ))
+                .log();
+                return Ok(());
+            }
+        }
+    };
+
+    if let Some(data) = &processed.data {
+        blob_store
+            .put(key, data)
+            .await
+            .map_err(ServiceError::internal)?;
+        attachment.file_size = data.len() as i64;
+    }
+
+    store_processed_media(blob_store, attachment, processed, written).await
+}
+
+async fn store_processed_media<B: BlobStore>(
+    blob_store: &B,
+    attachment: &mut Attachment,
+    processed: ProcessedMedia,
+    written: &mut Vec<String>,
+) -> Result<(), Status> {
+    let mut thumbnails = vec![];
+    for thumbnail in processed.thumbnails {
+        let thumbnail_key = Attachment::thumbnail_blob_key(&attachment.id, thumbnail.size);
+
+        written.push(thumbnail_key.clone());
+        blob_store
+            .put(&thumbnail_key, &thumbnail.data)
+            .await
+            .map_err(ServiceError::internal)?;
+
+        thumbnails.push(AttachmentThumbnail {
+            url: blob_store.url(&thumbnail_key),
+            width: thumbnail.width,
+            height: thumbnail.height,
+        });
+    }
+
+    attachment.metadata = Some(AttachmentMetadata {
+        width: processed.width,
+        height: processed.height,
+        duration: processed.duration,
+        blurhash: processed.blurhash,
+        thumbnails,
+    });
+
+    Ok(())
+}
+
+async fn delete_blobs<B: BlobStore>(blob_store: &B, keys: &[String]) {
+    for key in keys {
+        let _ = blob_store.delete(key).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use exif::Reader;
+    use image::{DynamicImage, ImageOutputFormat, RgbImage};
+
+    use super::*;
+    use crate::services::testing::MemoryBlobStore;
+
+    /// a jpeg with an APP1 segment holding a little-endian TIFF header and one IFD entry.
+    fn jpeg_with_exif() -> Vec<u8> {
+        let mut jpeg = vec![];
+        DynamicImage::ImageRgb8(RgbImage::new(4, 2))
+            .write_to(&mut Cursor::new(&mut jpeg), ImageOutputFormat::Jpeg(90))
+            .unwrap();
+
+        let mut exif = b"Exif\0\0II*\0\x08\0\0\0\x01\0".to_vec();
+        exif.extend_from_slice(&[0x12, 0x01, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00]);
+        exif.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0]);
+
+        let mut app1 = vec![0xff, 0xe1];
+        app1.extend_from_slice(&(exif.len() as u16 + 2).to_be_bytes());
+        app1.extend(exif);
+
+        // right after the SOI marker.
+        jpeg.splice(2..2, app1);
+        jpeg
+    }
+
+    #[tokio::test]
+    async fn images_are_sniffed_whatever_type_was_declared() {
+        let blob_store = MemoryBlobStore::default();
+        let data = jpeg_with_exif();
+        assert!(Reader::new()
+            .read_from_container(&mut Cursor::new(&data))
+            .is_ok());
+
+        let id = AttachmentId::new();
+        let key = Attachment::blob_key(&id, "photo");
+        blob_store.put(&key, &data).await.unwrap();
+
+        let mut attachment = Attachment::new(
+            id,
+            blob_store.url(&key),
+            "photo".to_string(),
+            "application/octet-stream".to_string(),
+            data.len() as i64,
+        );
+        let mut written = vec![];
+        process_media(
+            &blob_store,
+            &mut attachment,
+            &key,
+            &data[..SNIFF_LENGTH],
+            &mut written,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(attachment.mime_type, "image/jpeg");
+        let stored = blob_store.get(&key).await.unwrap().unwrap();
+        assert!(Reader::new()
+            .read_from_container(&mut Cursor::new(&stored))
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn other_files_keep_the_declared_type() {
+        let blob_store = MemoryBlobStore::default();
+        let data = b"just some text, long enough to sniff.";
+
+        let id = AttachmentId::new();
+        let key = Attachment::blob_key(&id, "notes");
+        blob_store.put(&key, data).await.unwrap();
+
+        let mut attachment = Attachment::new(
+            id,
+            blob_store.url(&key),
+            "notes".to_string(),
+            "application/octet-stream".to_string(),
+            data.len() as i64,
+        );
+        let mut written = vec![];
+        process_media(
+            &blob_store,
+            &mut attachment,
+            &key,
+            &data[..SNIFF_LENGTH],
+            &mut written,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(attachment.mime_type, "application/octet-stream");
+        assert!(attachment.metadata.is_none());
+    }
+}
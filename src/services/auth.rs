@@ -1,51 +1,57 @@
 use argon2::password_hash::rand_core::OsRng;
 use argon2::password_hash::SaltString;
 use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
-use surrealdb::engine::remote::ws::Client;
 use surrealdb::sql::Datetime;
-use surrealdb::Surreal;
 use tonic::{Request, Response, Status};
 use ulid::Ulid;
 
 use crate::auth::jwt::{decode, generate_access_token, generate_refresh_token};
-use crate::db::surreal::conn;
+use crate::config::AuthConfig;
 use crate::db::traits::auth::AuthRepository;
+use crate::db::Database;
 use crate::models::auth::DbAuth;
 use crate::models::user::UserId;
 // use crate::redis::RedisClient;
 
+use super::error::ServiceError;
 use super::ycchat::v1::services::auth::auth_service_server::AuthService as Auth;
 use super::ycchat::v1::services::auth::{
     RefreshTokenRequest, RefreshTokenResponse, RevokeRefreshTokenRequest, SignInRequest,
     SignInResponse, SignUpRequest, SignUpResponse,
 };
 
-pub struct AuthService<U>
+pub struct AuthService<DB, U>
 where
-    U: AuthRepository<Surreal<Client>>,
+    DB: Database,
+    U: AuthRepository<DB>,
 {
+    db: DB,
     // redis_client: RedisClient,
     auth_repository: U,
+    auth_config: AuthConfig,
 }
 
-impl<U> AuthService<U>
+impl<DB, U> AuthService<DB, U>
 where
-    U: AuthRepository<Surreal<Client>>,
+    DB: Database,
+    U: AuthRepository<DB>,
 {
-    pub fn new(auth_repository: U) -> Self {
+    pub fn new(db: DB, auth_repository: U, auth_config: AuthConfig) -> Self {
         // let redis_client = RedisClient::new();
 
         AuthService {
+            db,
             // redis_client,
             auth_repository,
+            auth_config,
         }
     }
 
-    fn get_user_id(&self, refresh_token: &str) -> Result<UserId, Status> {
-        let token_data = match decode(refresh_token) {
+    fn get_user_id(&self, refresh_token: &str) -> Result<UserId, ServiceError> {
+        let token_data = match decode(&self.auth_config, refresh_token) {
             Ok(res) => res,
             Err(err) => {
-                return Err(Status::unauthenticated(err.to_string()));
+                return Err(ServiceError::unauthenticated(err.to_string()));
             }
         };
 
@@ -54,27 +60,31 @@ where
 }
 
 #[tonic::async_trait]
-impl<U> Auth for AuthService<U>
+impl<DB, U> Auth for AuthService<DB, U>
 where
-    U: AuthRepository<Surreal<Client>> + 'static,
+    DB: Database,
+    U: AuthRepository<DB> + 'static,
 {
     async fn sign_up(
         &self,
         request: Request<SignUpRequest>,
     ) -> Result<Response<SignUpResponse>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
 
         let req = request.into_inner();
         let email = req.email;
 
+        if !is_valid_username(&req.username) {
+            return Err(ServiceError::invalid_field("username", "invalid.").into());
+        }
+
         let exist = self
             .auth_repository
             .get_by_username(&db, &req.username)
-            .await
-            .unwrap();
+            .await?;
 
         if exist.is_some() {
-            return Err(Status::already_exists("username already exist."));
+            return Err(ServiceError::already_exists("username already exist.").into());
         }
 
         let salt = SaltString::generate(&mut OsRng);
@@ -83,7 +93,7 @@ where
 
         let hashed_password = match argon2.hash_password(req.password.as_bytes(), &salt) {
             Ok(res) => res.to_string(),
-            Err(err) => return Err(Status::unauthenticated(err.to_string())),
+            Err(err) => return Err(ServiceError::unauthenticated(err.to_string()).into()),
         };
 
         let user_id = Ulid::new();
@@ -103,12 +113,11 @@ where
                     last_login_time: None,
                 },
             )
-            .await
-            .unwrap();
+            .await?;
 
-        let access_token = generate_access_token(&user_id).unwrap();
+        let access_token = generate_access_token(&self.auth_config, &user_id).unwrap();
 
-        let refresh_token = generate_refresh_token(&user_id).unwrap();
+        let refresh_token = generate_refresh_token(&self.auth_config, &user_id).unwrap();
         // self.redis_client.set_refresh_token(&refresh_token).unwrap();
 
         match res {
@@ -117,9 +126,9 @@ where
                 user_id: res.id.to_string(),
                 access_token,
                 refresh_token,
-                expires_in: 3600,
+                expires_in: self.auth_config.access_token_ttl as i64,
             })),
-            None => Err(Status::internal("internal error")),
+            None => Err(ServiceError::internal("internal error").into()),
         }
     }
 
@@ -127,20 +136,17 @@ where
         &self,
         request: Request<SignInRequest>,
     ) -> Result<Response<SignInResponse>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
 
         let req = request.into_inner();
         let username = &req.username;
         let password = &req.password;
 
-        let auth = match self.auth_repository.get_by_username(&db, username).await {
-            Ok(res) => res,
-            Err(err) => return Err(Status::invalid_argument(err)),
-        };
+        let auth = self.auth_repository.get_by_username(&db, username).await?;
 
         let mut auth = match auth {
             Some(auth) => auth,
-            None => return Err(Status::invalid_argument("invalid argument")),
+            None => return Err(ServiceError::invalid_argument("invalid argument").into()),
         };
 
         let parsed_hash = PasswordHash::new(&auth.password).unwrap();
@@ -152,19 +158,19 @@ where
             .is_ok());
 
         auth.last_login_time = Some(Datetime::default());
-        self.auth_repository.update(&db, &auth).await.unwrap();
+        self.auth_repository.update(&db, &auth).await?;
 
         let user_id = auth.id;
 
-        let access_token = generate_access_token(&user_id).unwrap();
-        let refresh_token = generate_refresh_token(&user_id).unwrap();
+        let access_token = generate_access_token(&self.auth_config, &user_id).unwrap();
+        let refresh_token = generate_refresh_token(&self.auth_config, &user_id).unwrap();
         // self.redis_client.set_refresh_token(&refresh_token).unwrap();
 
         Ok(Response::new(SignInResponse {
             user_id: user_id.to_string(),
             access_token,
             refresh_token,
-            expires_in: 3600,
+            expires_in: self.auth_config.access_token_ttl as i64,
         }))
     }
 
@@ -181,11 +187,11 @@ where
         //     .unwrap();
 
         // if res.is_none() {
-        //     return Err(Status::unauthenticated("invalid argument"));
+        //     return Err(ServiceError::unauthenticated("invalid argument").into());
         // }
 
-        let access_token = generate_access_token(&user_id).unwrap();
-        let new_refresh_token = generate_refresh_token(&user_id).unwrap();
+        let access_token = generate_access_token(&self.auth_config, &user_id).unwrap();
+        let new_refresh_token = generate_refresh_token(&self.auth_config, &user_id).unwrap();
 
         // self.redis_client
         //     .delete_refresh_token(&old_refresh_token)
@@ -198,7 +204,7 @@ where
         Ok(Response::new(RefreshTokenResponse {
             access_token,
             refresh_token: new_refresh_token,
-            expires_in: 3600,
+            expires_in: self.auth_config.access_token_ttl as i64,
         }))
     }
 
@@ -206,7 +212,8 @@ where
         &self,
         request: Request<RevokeRefreshTokenRequest>,
     ) -> Result<Response<()>, Status> {
-        let refresh_token = request.into_inner().refresh_token;
+        // FIXME: refresh tokens aren't stored yet, there is nothing to revoke.
+        let _refresh_token = request.into_inner().refresh_token;
 
         // self.redis_client
         //     .delete_refresh_token(&refresh_token)
@@ -215,3 +222,32 @@ where
         Ok(Response::new(()))
     }
 }
+
+/// `[a-z][a-z0-9_]{1,20}`, matched against the whole name. the schema checks of both backends
+/// match it anywhere in the name, so it's enforced here.
+fn is_valid_username(username: &str) -> bool {
+    let mut chars = username.chars();
+
+    (2..=21).contains(&username.len())
+        && chars.next().is_some_and(|c| c.is_ascii_lowercase())
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn username_matches_as_a_whole() {
+        assert!(is_valid_username("ab"));
+        assert!(is_valid_username("a_1"));
+        assert!(is_valid_username(&format!("a{}", "b".repeat(20))));
+
+        assert!(!is_valid_username("a"));
+        assert!(!is_valid_username(&format!("a{}", "b".repeat(21))));
+        assert!(!is_valid_username("1ab"));
+        assert!(!is_valid_username("ab-c"));
+        assert!(!is_valid_username("Abc"));
+        assert!(!is_valid_username("ab c"));
+    }
+}
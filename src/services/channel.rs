@@ -1,146 +1,562 @@
 use futures::lock::Mutex;
-use std::borrow::BorrowMut;
-use std::str::FromStr;
 use std::sync::Arc;
 
-use prost::Message as _;
-use surrealdb::engine::remote::ws::Client;
-use surrealdb::Surreal;
+use surrealdb::sql::Datetime;
 use tonic::{Request, Response, Status};
 
 use crate::chat::broadcaster::Broadcaster;
-use crate::db::surreal::conn;
+use crate::db::filter::Filter;
+use crate::db::traits::attachment::AttachmentRepository;
 use crate::db::traits::channel::ChannelRepository;
+use crate::db::traits::mention::MentionRepository;
 use crate::db::traits::message::MessageRepository;
+use crate::db::traits::message_acknowledge::MessageAcknowledgeRepository;
+use crate::db::traits::message_search::MessageSearchIndex;
+use crate::db::traits::read_state::ReadStateRepository;
 use crate::db::traits::server::ServerRepository;
 use crate::db::traits::server_category::ServerCategoryRepository;
 use crate::db::traits::server_member::ServerMemberRepository;
-use crate::models::channel::{ChannelId, ChannelType, DbChannel};
-use crate::models::message::DbMessage;
+use crate::db::traits::user::UserRepository;
+use crate::db::Database;
+use crate::models::attachment::AttachmentId;
+use crate::models::channel::{
+    ChannelId, ChannelPosition, ChannelType, DbChannel, FILTER_FIELDS, MAX_GROUP_MEMBERS,
+};
+use crate::models::mention::{DbMention, Mentions};
+use crate::models::message::{DbMessage, MessageId, MessageType};
+use crate::models::read_state::DbReadState;
 use crate::models::server::ServerId;
-use crate::models::server_category::{DbServerCategory, ServerCategoryId};
+use crate::models::server_category::{CategoryPosition, DbServerCategory, ServerCategoryId};
 use crate::models::user::UserId;
-use crate::util::pager::PageTokenizer;
+use crate::storage::BlobStore;
+use crate::util::pager::{ListRequest, Pager, CREATE_TIME, LAYOUT};
+use crate::util::resource_name::{CategoryName, ChannelName, ServerName, UserName};
 // use crate::redis::RedisClient;
 
-use super::ycchat::v1::models::Channel as ChannelModel;
+use super::attachment::{create_square_image, parse_attachment_name, release_attachment};
+use super::error::ServiceError;
+use super::ycchat::v1::models::{
+    channel::ChannelType as ChannelTypeMessage, Channel as ChannelModel,
+};
 use super::ycchat::v1::services::channel::channel_service_server::ChannelService as Channel;
 use super::ycchat::v1::services::channel::{
-    CreateChannelRequest, DeleteChannelRequest, ListServerChannelsRequest,
-    ListServerChannelsResponse, SpeechRequest, SpeechResponse, UpdateChannelRequest,
+    AckChannelRequest, AddGroupMemberRequest, CreateChannelRequest, CreateDirectChannelRequest,
+    DeleteChannelRequest, LeaveGroupRequest, ListServerChannelsRequest, ListServerChannelsResponse,
+    RemoveGroupMemberRequest, ReorderChannelsRequest, ReorderChannelsResponse, SpeechRequest,
+    SpeechResponse, UpdateChannelIconRequest, UpdateChannelRequest,
+};
+use super::ycchat::v1::services::connect::{
+    server_signal::Payload, ChannelReceiveMessage, MentionReceived, ReadStateUpdated,
+    ServerLayoutUpdated,
 };
 
-pub struct ChannelService<SM, M, C, S, SC>
+pub struct ChannelService<DB, SM, M, C, S, SC, A, B, MN, SI, U, R, ACK>
 where
-    SM: ServerMemberRepository<Surreal<Client>>,
-    M: MessageRepository<Surreal<Client>>,
-    C: ChannelRepository<Surreal<Client>>,
-    S: ServerRepository<Surreal<Client>>,
-    SC: ServerCategoryRepository<Surreal<Client>>,
+    DB: Database,
+    SM: ServerMemberRepository<DB>,
+    M: MessageRepository<DB>,
+    C: ChannelRepository<DB>,
+    S: ServerRepository<DB>,
+    SC: ServerCategoryRepository<DB>,
+    A: AttachmentRepository<DB>,
+    B: BlobStore,
+    MN: MentionRepository<DB>,
+    SI: MessageSearchIndex<DB>,
+    U: UserRepository<DB>,
+    R: ReadStateRepository<DB>,
+    ACK: MessageAcknowledgeRepository<DB>,
 {
+    db: DB,
     server_member_repository: SM,
     message_repository: M,
     channel_repository: C,
     server_repository: S,
     server_category_repository: SC,
+    attachment_repository: A,
+    blob_store: B,
+    mention_repository: MN,
+    message_search_index: SI,
+    user_repository: U,
+    read_state_repository: R,
+    message_acknowledge_repository: ACK,
     broadcaster: Arc<Mutex<Broadcaster>>, // redis_client: RedisClient,
+    pager: Pager,
 }
 
-impl<SM, M, C, S, SC> ChannelService<SM, M, C, S, SC>
+impl<DB, SM, M, C, S, SC, A, B, MN, SI, U, R, ACK>
+    ChannelService<DB, SM, M, C, S, SC, A, B, MN, SI, U, R, ACK>
 where
-    SM: ServerMemberRepository<Surreal<Client>>,
-    M: MessageRepository<Surreal<Client>>,
-    C: ChannelRepository<Surreal<Client>>,
-    S: ServerRepository<Surreal<Client>>,
-    SC: ServerCategoryRepository<Surreal<Client>>,
+    DB: Database,
+    SM: ServerMemberRepository<DB>,
+    M: MessageRepository<DB>,
+    C: ChannelRepository<DB>,
+    S: ServerRepository<DB>,
+    SC: ServerCategoryRepository<DB>,
+    A: AttachmentRepository<DB>,
+    B: BlobStore,
+    MN: MentionRepository<DB>,
+    SI: MessageSearchIndex<DB>,
+    U: UserRepository<DB>,
+    R: ReadStateRepository<DB>,
+    ACK: MessageAcknowledgeRepository<DB>,
 {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
+        db: DB,
         server_member_repository: SM,
         message_repository: M,
         channel_repository: C,
         server_repository: S,
         server_category_repository: SC,
+        attachment_repository: A,
+        blob_store: B,
+        mention_repository: MN,
+        message_search_index: SI,
+        user_repository: U,
+        read_state_repository: R,
+        message_acknowledge_repository: ACK,
         broadcaster: Arc<Mutex<Broadcaster>>,
+        pager: Pager,
     ) -> Self {
         ChannelService {
+            db,
             server_member_repository,
             message_repository,
             channel_repository,
             server_repository,
             server_category_repository,
+            attachment_repository,
+            blob_store,
+            mention_repository,
+            message_search_index,
+            user_repository,
+            read_state_repository,
+            message_acknowledge_repository,
             broadcaster,
+            pager,
+        }
+    }
+
+    /// `name` is channels/{channelId}
+    async fn get_group_channel(&self, db: &DB, name: &str) -> Result<DbChannel, Status> {
+        let channel_id = ChannelName::parse(name)?.0;
+
+        match self.channel_repository.get(db, &channel_id).await? {
+            Some(channel) if matches!(channel.channel_type, ChannelType::Group { .. }) => {
+                Ok(channel)
+            }
+            Some(_) => Err(ServiceError::failed_precondition("not a group channel.").into()),
+            None => Err(ServiceError::not_found("channel not found.").into()),
+        }
+    }
+
+    /// `name` is servers/{serverId}/categories/{categoryId} of a category in `server_id`.
+    async fn get_server_category(
+        &self,
+        db: &DB,
+        server_id: &ServerId,
+        name: &str,
+    ) -> Result<DbServerCategory, Status> {
+        let name = CategoryName::parse(name)?;
+        if name.server != *server_id {
+            return Err(ServiceError::not_found("server category not found.").into());
+        }
+
+        match self
+            .server_category_repository
+            .get(db, &name.category)
+            .await?
+        {
+            Some(category) if category.server == *server_id => Ok(category),
+            _ => Err(ServiceError::not_found("server category not found.").into()),
+        }
+    }
+
+    /// the position after the last channel in `category` of the server.
+    async fn next_channel_order(
+        &self,
+        db: &DB,
+        server_id: &ServerId,
+        category: Option<ServerCategoryId>,
+    ) -> Result<u64, Status> {
+        let order = self
+            .channel_repository
+            .get_list_by_server_id(db, server_id)
+            .await?
+            .iter()
+            .filter(|channel| channel.category == category)
+            .map(|channel| channel.order + 1)
+            .max()
+            .unwrap_or(0);
+
+        Ok(order)
+    }
+
+    /// stores `message` and delivers it to everyone in `channel` and to `recipients`.
+    async fn send_system_message(
+        &self,
+        db: &DB,
+        channel: &DbChannel,
+        message: DbMessage,
+        recipients: &[UserId],
+    ) -> Result<(), Status> {
+        let message = match self.message_repository.add(db, &message).await? {
+            Some(message) => message,
+            None => return Err(ServiceError::internal("internal error").into()),
+        };
+
+        self.channel_repository
+            .update_last_message_time(db, &channel.id, &message.create_time)
+            .await?;
+
+        let mut members = get_channel_members(db, &self.server_member_repository, channel).await?;
+        for recipient in recipients.iter() {
+            if !members.contains(recipient) {
+                members.push(*recipient);
+            }
+        }
+
+        let channel_receive_message = Payload::ChannelReceiveMessage(ChannelReceiveMessage {
+            message: Some(message.to_message(&[])),
+        });
+
+        self.broadcaster
+            .lock()
+            .await
+            .send_signal(&members, channel_receive_message)
+            .await;
+
+        Ok(())
+    }
+
+    /// drops mentions that don't resolve in `channel` and returns the users to notify.
+    async fn resolve_mentions(
+        &self,
+        db: &DB,
+        author: &UserId,
+        channel: &DbChannel,
+        mentions: &mut Mentions,
+    ) -> Result<Vec<UserId>, Status> {
+        let server_id = match &channel.channel_type {
+            ChannelType::Server { server } => Some(*server),
+            _ => None,
+        };
+
+        if mentions.is_mention_everyone() {
+            // only the server owner can mention everyone until roles are modeled.
+            let is_allowed = match &server_id {
+                Some(server_id) => {
+                    let server = self.server_repository.get_server(db, server_id).await?;
+
+                    server.is_some_and(|server| server.owner == *author)
+                }
+                None => true,
+            };
+
+            if !is_allowed {
+                return Err(
+                    ServiceError::permission_denied("not allowed to mention everyone.").into(),
+                );
+            }
+        }
+
+        let members: Vec<UserId> = if mentions.users.is_empty() && !mentions.is_mention_everyone() {
+            vec![]
+        } else {
+            get_channel_members(db, &self.server_member_repository, channel).await?
+        };
+
+        mentions.users.retain(|user| members.contains(user));
+
+        let mut channels = vec![];
+        for channel_id in mentions.channels.iter() {
+            let mentioned = self.channel_repository.get(db, channel_id).await?;
+
+            let is_same_server = mentioned.is_some_and(|mentioned| match mentioned.channel_type {
+                ChannelType::Server { server } => Some(server) == server_id,
+                _ => false,
+            });
+
+            if is_same_server {
+                channels.push(*channel_id);
+            }
+        }
+        mentions.channels = channels;
+
+        let mut recipients = if mentions.everyone {
+            members.clone()
+        } else if mentions.here {
+            self.broadcaster.lock().await.online_users(&members).await
+        } else {
+            vec![]
+        };
+
+        for user in mentions.users.iter() {
+            if !recipients.contains(user) {
+                recipients.push(*user);
+            }
+        }
+
+        recipients.retain(|user| user != author);
+
+        Ok(recipients)
+    }
+}
+
+/// users who can read `channel`.
+pub async fn get_channel_members<DB, SM>(
+    db: &DB,
+    server_member_repository: &SM,
+    channel: &DbChannel,
+) -> Result<Vec<UserId>, Status>
+where
+    DB: Database,
+    SM: ServerMemberRepository<DB>,
+{
+    let members = match &channel.channel_type {
+        ChannelType::Saved { owner } => vec![*owner],
+        ChannelType::Direct | ChannelType::Group { .. } => channel.members.clone(),
+        ChannelType::Server { server } => server_member_repository
+            .get_server_members_by_server_id(db, server)
+            .await?
+            .into_iter()
+            .map(|server_member| server_member.user)
+            .collect(),
+    };
+
+    Ok(members)
+}
+
+/// whether `user_id` can read and write messages in `channel`.
+pub async fn is_channel_member<DB, SM>(
+    db: &DB,
+    server_member_repository: &SM,
+    channel: &DbChannel,
+    user_id: &UserId,
+) -> Result<bool, Status>
+where
+    DB: Database,
+    SM: ServerMemberRepository<DB>,
+{
+    let is_member = match &channel.channel_type {
+        ChannelType::Saved { owner } => owner == user_id,
+        ChannelType::Direct | ChannelType::Group { .. } => channel.members.contains(user_id),
+        ChannelType::Server { server } => server_member_repository
+            .get_server_member_by_server_id_and_user_id(db, server, user_id)
+            .await?
+            .is_some(),
+    };
+
+    Ok(is_member)
+}
+
+/// whether `user_id` can manage the channel, e.g. its icon and pinned messages.
+/// every participant of a direct or group channel manages it, direct channels have no
+/// settings though.
+pub async fn is_channel_manager<DB, S>(
+    db: &DB,
+    server_repository: &S,
+    channel: &DbChannel,
+    user_id: &UserId,
+) -> Result<bool, Status>
+where
+    DB: Database,
+    S: ServerRepository<DB>,
+{
+    let is_manager = match &channel.channel_type {
+        ChannelType::Saved { owner } => owner == user_id,
+        ChannelType::Direct | ChannelType::Group { .. } => channel.members.contains(user_id),
+        ChannelType::Server { server } => {
+            let server = server_repository.get_server(db, server).await?;
+
+            server.is_some_and(|server| server.owner == *user_id)
         }
+    };
+
+    Ok(is_manager)
+}
+
+/// unread messages and mentions of `user_id` in the channel after `last_read_message`.
+pub async fn get_unread_counts<DB, M, MN>(
+    db: &DB,
+    message_repository: &M,
+    mention_repository: &MN,
+    channel_id: &ChannelId,
+    user_id: &UserId,
+    last_read_message: Option<MessageId>,
+) -> Result<(u64, u64), Status>
+where
+    DB: Database,
+    M: MessageRepository<DB>,
+    MN: MentionRepository<DB>,
+{
+    let unread_message_count = message_repository
+        .count_unread(db, channel_id, user_id, last_read_message)
+        .await?;
+
+    let mention_count = mention_repository
+        .count_by_user_id_and_channel_id(db, user_id, channel_id, last_read_message)
+        .await?;
+
+    Ok((unread_message_count, mention_count))
+}
+
+/// queues the deletion of everything the channel holds, its messages with their acknowledges,
+/// the mentions and the read states. the channel itself is left to the caller.
+pub fn delete_channel_contents_in<DB, M, ACK, MN, R>(
+    transaction: &mut DB::Transaction,
+    message_repository: &M,
+    message_acknowledge_repository: &ACK,
+    mention_repository: &MN,
+    read_state_repository: &R,
+    channel_id: &ChannelId,
+) -> Result<(), ServiceError>
+where
+    DB: Database,
+    M: MessageRepository<DB>,
+    ACK: MessageAcknowledgeRepository<DB>,
+    MN: MentionRepository<DB>,
+    R: ReadStateRepository<DB>,
+{
+    // acknowledges find their channel through the message, delete them first.
+    message_acknowledge_repository.delete_by_channel_id_in(transaction, channel_id)?;
+    mention_repository.delete_by_channel_id_in(transaction, channel_id)?;
+    read_state_repository.delete_by_channel_id_in(transaction, channel_id)?;
+    message_repository.delete_by_channel_id_in(transaction, channel_id)?;
+
+    Ok(())
+}
+
+/// `channels` as seen by `user_id`, with their unread and mention counts.
+pub async fn to_channel_messages<DB, R, M, MN>(
+    db: &DB,
+    read_state_repository: &R,
+    message_repository: &M,
+    mention_repository: &MN,
+    user_id: &UserId,
+    channels: Vec<DbChannel>,
+) -> Result<Vec<ChannelModel>, Status>
+where
+    DB: Database,
+    R: ReadStateRepository<DB>,
+    M: MessageRepository<DB>,
+    MN: MentionRepository<DB>,
+{
+    let channel_ids = channels
+        .iter()
+        .map(|channel| channel.id)
+        .collect::<Vec<ChannelId>>();
+
+    let read_states = read_state_repository
+        .get_list_by_user_id(db, user_id, &channel_ids)
+        .await?;
+
+    let mut messages = Vec::with_capacity(channels.len());
+    for channel in channels.into_iter() {
+        let last_read_message = read_states
+            .iter()
+            .find(|read_state| read_state.channel == channel.id)
+            .map(|read_state| read_state.last_read_message);
+
+        let (unread_message_count, mention_count) = get_unread_counts(
+            db,
+            message_repository,
+            mention_repository,
+            &channel.id,
+            user_id,
+            last_read_message,
+        )
+        .await?;
+
+        let mut message = channel.to_message();
+        message.unread_message_count = unread_message_count;
+        message.mention_count = mention_count;
+        messages.push(message);
     }
+
+    Ok(messages)
 }
 
 #[tonic::async_trait]
-impl<SM, M, C, S, SC> Channel for ChannelService<SM, M, C, S, SC>
+impl<DB, SM, M, C, S, SC, A, B, MN, SI, U, R, ACK> Channel
+    for ChannelService<DB, SM, M, C, S, SC, A, B, MN, SI, U, R, ACK>
 where
-    SM: ServerMemberRepository<Surreal<Client>> + 'static,
-    M: MessageRepository<Surreal<Client>> + 'static,
-    C: ChannelRepository<Surreal<Client>> + 'static,
-    S: ServerRepository<Surreal<Client>> + 'static,
-    SC: ServerCategoryRepository<Surreal<Client>> + 'static,
+    DB: Database,
+    SM: ServerMemberRepository<DB> + 'static,
+    M: MessageRepository<DB> + 'static,
+    C: ChannelRepository<DB> + 'static,
+    S: ServerRepository<DB> + 'static,
+    SC: ServerCategoryRepository<DB> + 'static,
+    A: AttachmentRepository<DB> + 'static,
+    B: BlobStore + 'static,
+    MN: MentionRepository<DB> + 'static,
+    SI: MessageSearchIndex<DB> + 'static,
+    U: UserRepository<DB> + 'static,
+    R: ReadStateRepository<DB> + 'static,
+    ACK: MessageAcknowledgeRepository<DB> + 'static,
 {
     async fn list_server_channels(
         &self,
         request: Request<ListServerChannelsRequest>,
     ) -> Result<Response<ListServerChannelsResponse>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
+
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
 
         let request = request.into_inner();
         let parent = request.parent;
 
-        let page_token = match request.page_token.clone() {
-            Some(page_token) => {
-                let page_token = crate::util::pager::get_page_token(page_token);
-                Some(page_token.unwrap())
-            }
-            None => None,
-        };
-
-        let (page_size, offset_id, prev_page_token) = match page_token {
-            Some(page_token) => (
-                page_token.page_size,
-                page_token
-                    .offset_id
-                    .map(|offset_id| ChannelId::from_string(&offset_id).unwrap()),
-                page_token.prev_page_token,
-            ),
-            None => (request.page_size, None, None),
-        };
+        let server_id = ServerName::parse(&parent)?.0;
 
-        let parent = parent.split('/').collect::<Vec<&str>>();
-        let server_id = ServerId::from_string(parent[1]).unwrap();
-
-        // page_size + 1 갯수만큼 데이터 로드 후 next_page_token None, Some 처리
-        let mut channels = self
-            .channel_repository
-            .get_server_channels(&db, &server_id, page_size + 1, offset_id)
-            .await
-            .unwrap();
+        if self
+            .server_member_repository
+            .get_server_member_by_server_id_and_user_id(&db, &server_id, &user_id)
+            .await?
+            .is_none()
+        {
+            return Err(ServiceError::permission_denied("permission denied.").into());
+        }
 
-        let next_page_token = if channels.len() > usize::try_from(page_size).unwrap() {
-            channels.pop();
+        let filter = request.filter.unwrap_or_default();
+        let parsed_filter = Filter::parse(&filter, FILTER_FIELDS)?;
 
-            let next_page_token = channels.generate_page_token(page_size, request.page_token);
-            next_page_token.map(|token| {
-                let mut pb_buf = vec![];
-                let _ = token.encode(&mut pb_buf);
+        let page = self
+            .pager
+            .list(
+                ListRequest::new(
+                    parent,
+                    request.page_size,
+                    request.page_token,
+                    request.order_by,
+                )
+                .filter(filter),
+                &[LAYOUT, CREATE_TIME],
+                |page| {
+                    self.channel_repository.get_server_channels(
+                        &db,
+                        &server_id,
+                        &parsed_filter,
+                        page,
+                    )
+                },
+            )
+            .await?;
 
-                crate::util::base64_encoder::encode_string(pb_buf)
-            })
-        } else {
-            None
-        };
+        let channels = to_channel_messages(
+            &db,
+            &self.read_state_repository,
+            &self.message_repository,
+            &self.mention_repository,
+            &user_id,
+            page.items,
+        )
+        .await?;
 
         Ok(Response::new(ListServerChannelsResponse {
-            channels: channels
-                .into_iter()
-                .map(|channel| channel.to_message())
-                .collect::<Vec<ChannelModel>>(),
-            next_page_token,
-            prev_page_token,
+            channels,
+            next_page_token: page.next_page_token,
+            prev_page_token: page.prev_page_token,
         }))
     }
 
@@ -148,70 +564,94 @@ where
         &self,
         request: Request<CreateChannelRequest>,
     ) -> Result<Response<ChannelModel>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
 
         let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
-        let user_id = UserId::from_string(&user_id).unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
 
         let channel = match request.into_inner().channel {
             Some(channel) => channel,
-            None => return Err(Status::invalid_argument("invalid arguments")),
+            None => return Err(ServiceError::invalid_field("channel", "required.").into()),
         };
 
-        let name_list: Vec<&str> = channel.name.split('/').collect::<Vec<&str>>();
-        let server_index = name_list
-            .iter()
-            .position(|&s| s == "servers")
-            .map(|idx| idx + 1);
-        let category_index = name_list
-            .iter()
-            .position(|&s| s == "categories")
-            .map(|idx| idx + 1);
+        let channel_type = match ChannelTypeMessage::try_from(channel.channel_type) {
+            Ok(ChannelTypeMessage::Direct) => {
+                return Err(ServiceError::invalid_argument(
+                    "direct channels are created with CreateDirectChannel.",
+                )
+                .into())
+            }
+            Ok(channel_type) => channel_type,
+            Err(_) => {
+                return Err(ServiceError::invalid_field("channel_type", "invalid.").into());
+            }
+        };
 
-        let server = match server_index {
-            Some(idx) => {
-                let server_id: ServerId = ServerId::from_str(name_list[idx]).unwrap();
+        // the name starts with the parent of server channels,
+        // servers/{serverId} or servers/{serverId}/categories/{categoryId}.
+        let parent = match channel.name.find("channels/") {
+            Some(idx) => channel.name[..idx].trim_end_matches('/'),
+            None => channel.name.as_str(),
+        };
 
-                let server = self.server_repository.get_server(&db, &server_id).await;
+        let (server_id, category_name) = if parent.is_empty() {
+            (None, None)
+        } else if let Ok(category_name) = CategoryName::parse(parent) {
+            (Some(category_name.server), Some(parent))
+        } else {
+            (Some(ServerName::parse(parent)?.0), None)
+        };
 
-                match server {
-                    Ok(server) => server,
-                    Err(err) => return Err(Status::not_found(err.as_str())),
-                }
+        let channel_type = match (channel_type, server_id) {
+            (ChannelTypeMessage::Saved, None) => ChannelType::Saved { owner: user_id },
+            (ChannelTypeMessage::Group, None) => ChannelType::Group { owner: user_id },
+            (ChannelTypeMessage::Server, Some(server)) => ChannelType::Server { server },
+            (ChannelTypeMessage::Server, None) => {
+                return Err(ServiceError::invalid_argument(
+                    "server channels are created under servers/{serverId}.",
+                )
+                .into())
+            }
+            _ => {
+                return Err(
+                    ServiceError::invalid_argument("only server channels have a parent.").into(),
+                )
             }
-            None => None,
         };
 
-        let category: Option<DbServerCategory> = match category_index {
-            Some(idx) => {
-                let db = conn().await;
+        if let Some(server_id) = &server_id {
+            let server = match self.server_repository.get_server(&db, server_id).await? {
+                Some(server) => server,
+                None => return Err(ServiceError::not_found("server not found.").into()),
+            };
 
-                let server_category_id: ServerCategoryId =
-                    ServerCategoryId::from_str(name_list[idx]).unwrap();
+            // only the server owner manages its channels, like in is_channel_manager.
+            if server.owner != user_id {
+                return Err(ServiceError::permission_denied("permission denied.").into());
+            }
+        }
 
-                let category = self
-                    .server_category_repository
-                    .get(&db, &server_category_id)
-                    .await;
+        let category = match (&server_id, category_name) {
+            (Some(server_id), Some(category_name)) => Some(
+                self.get_server_category(&db, server_id, category_name)
+                    .await?
+                    .id,
+            ),
+            _ => None,
+        };
 
-                match category {
-                    Ok(category) => match category {
-                        Some(category) => Some(category),
-                        None => return Err(Status::not_found("server category not found.")),
-                    },
-                    Err(err) => return Err(Status::not_found(err.as_str())),
-                }
-            }
-            None => None,
+        let order = match &server_id {
+            Some(server_id) => self.next_channel_order(&db, server_id, category).await?,
+            None => 0,
         };
 
-        let channel = DbChannel::new(user_id, channel, server.map(|server| server.id));
+        let channel = DbChannel::new(channel_type, channel, category, order);
 
-        let added = self.channel_repository.add(&db, &channel).await.unwrap();
+        let added = self.channel_repository.add(&db, &channel).await?;
 
         match added {
             Some(channel) => Ok(Response::new(channel.to_message())),
-            None => Err(Status::internal("internal error")),
+            None => Err(ServiceError::internal("internal error").into()),
         }
     }
 
@@ -219,42 +659,62 @@ where
         &self,
         request: Request<UpdateChannelRequest>,
     ) -> Result<Response<ChannelModel>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
+
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
 
         let req = request.into_inner();
-        let channel = req.channel.unwrap();
+        let channel = req
+            .channel
+            .ok_or_else(|| ServiceError::invalid_field("channel", "required."))?;
 
-        let name = &channel.name; // servers/{UUID}/categories/{UUID}
-        let name = name.split('/').collect::<Vec<&str>>();
+        let channel_id = ChannelName::parse(&channel.name)?.0;
 
-        let channel_index = name
-            .iter()
-            .position(|&s| s == "channels")
-            .map(|idx| idx + 1);
+        let mut exist = match self.channel_repository.get(&db, &channel_id).await? {
+            Some(channel) => channel,
+            None => return Err(ServiceError::not_found("channel not found.").into()),
+        };
 
-        let mut exist = match channel_index {
-            Some(idx) => {
-                let channel_id = ChannelId::from_string(name[idx]).unwrap();
+        // direct channels have no settings.
+        let is_have_permission: bool = !matches!(exist.channel_type, ChannelType::Direct)
+            && is_channel_manager(&db, &self.server_repository, &exist, &user_id).await?;
 
-                match self.channel_repository.get(&db, &channel_id).await {
-                    Ok(channel) => match channel {
-                        Some(channel) => channel,
-                        None => return Err(Status::not_found("channel not found.")),
-                    },
-                    Err(err) => return Err(Status::internal(err.to_string())),
+        if !is_have_permission {
+            return Err(ServiceError::permission_denied("permission denied.").into());
+        }
+
+        let category_name = channel.category.clone();
+        let update_mask = exist.update(channel, req.update_mask)?;
+
+        if update_mask.contains("category") {
+            let category = match (&exist.channel_type, &category_name) {
+                (ChannelType::Server { server }, Some(category)) => {
+                    Some(self.get_server_category(&db, server, category).await?.id)
                 }
-            }
-            None => return Err(Status::invalid_argument("invalid arguments.")),
-        };
+                (_, Some(_)) => {
+                    return Err(ServiceError::invalid_argument(
+                        "only server channels belong to categories.",
+                    )
+                    .into())
+                }
+                (_, None) => None,
+            };
 
-        exist.display_name = channel.display_name;
-        exist.description = channel.description;
+            // a moved channel goes to the end of its new category unless placed explicitly.
+            if let ChannelType::Server { server } = &exist.channel_type {
+                if exist.category != category && !update_mask.contains("order") {
+                    exist.order = self.next_channel_order(&db, server, category).await?;
+                }
+            }
+            exist.category = category;
+        }
 
-        let res = self.channel_repository.update(&db, &exist).await.unwrap();
+        let res = self.channel_repository.update(&db, &exist).await?;
 
         match res {
             Some(res) => Ok(Response::new(res.to_message())),
-            None => Err(Status::internal("internal error")),
+            None => Err(ServiceError::internal("internal error").into()),
         }
     }
 
@@ -262,25 +722,36 @@ where
         &self,
         request: Request<DeleteChannelRequest>,
     ) -> Result<Response<()>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
 
-        let name = request.into_inner().name;
-        let name = name.split('/').collect::<Vec<&str>>();
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
 
-        let channel_index = name
-            .iter()
-            .position(|&s| s == "channels")
-            .map(|idx| idx + 1);
+        let channel_id = ChannelName::parse(&request.into_inner().name)?.0;
 
-        let channel_id: ChannelId = match channel_index {
-            Some(idx) => ChannelId::from_string(name[idx]).unwrap(),
-            None => return Err(Status::invalid_argument("invalid arguments.")),
+        let channel = match self.channel_repository.get(&db, &channel_id).await? {
+            Some(channel) => channel,
+            None => return Err(ServiceError::not_found("channel not found.").into()),
         };
 
+        if !is_channel_manager(&db, &self.server_repository, &channel, &user_id).await? {
+            return Err(ServiceError::permission_denied("permission denied.").into());
+        }
+
+        let mut transaction = DB::Transaction::default();
+
+        delete_channel_contents_in(
+            &mut transaction,
+            &self.message_repository,
+            &self.message_acknowledge_repository,
+            &self.mention_repository,
+            &self.read_state_repository,
+            &channel_id,
+        )?;
         self.channel_repository
-            .delete(&db, &channel_id)
-            .await
-            .unwrap();
+            .delete_in(&mut transaction, &channel_id)?;
+
+        db.commit(transaction).await?;
 
         Ok(Response::new(()))
     }
@@ -289,7 +760,7 @@ where
         &self,
         request: Request<SpeechRequest>,
     ) -> Result<Response<SpeechResponse>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
 
         let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
         let user_id = UserId::from_string(user_id).unwrap();
@@ -297,49 +768,1304 @@ where
         let req = request.into_inner();
         let name = req.name;
         let content = req.content;
+        let attachments = req.attachments; // attachments/{attachmentId}
 
-        let channel_id = ChannelId::from_string(name.split('/').collect::<Vec<&str>>()[1]).unwrap();
-        let channel = match self.channel_repository.get(&db, &channel_id).await.unwrap() {
+        let channel_id = ChannelName::parse(&name)?.0;
+        let channel = match self.channel_repository.get(&db, &channel_id).await? {
             Some(channel) => channel,
-            None => return Err(Status::not_found("invalid arguments.")),
+            None => return Err(ServiceError::not_found("invalid arguments.").into()),
         };
 
-        let is_have_permission: bool = match channel.channel_type {
-            ChannelType::Saved { owner } => owner == user_id,
-            ChannelType::Direct => todo!(),
-            ChannelType::Server { server } => {
-                let server_member = self
-                    .server_member_repository
-                    .get_server_member_by_server_id_and_user_id(&db, &server, &user_id)
-                    .await
-                    .unwrap();
+        let is_have_permission: bool =
+            is_channel_member(&db, &self.server_member_repository, &channel, &user_id).await?;
 
-                server_member.is_some()
+        if !is_have_permission {
+            return Err(ServiceError::permission_denied("permission denied.").into());
+        }
+
+        let mut attachment_ids: Vec<AttachmentId> = vec![];
+        for attachment in attachments.iter() {
+            let attachment_id = parse_attachment_name(attachment)?;
+
+            // only the uploader can attach a file to the message.
+            let uploader = self
+                .attachment_repository
+                .get_uploader(&db, &attachment_id)
+                .await?;
+
+            if uploader != Some(user_id) {
+                return Err(ServiceError::not_found("attachment not found.").into());
             }
-        };
 
-        if !is_have_permission {
-            return Err(Status::permission_denied("permission denied."));
+            if !attachment_ids.contains(&attachment_id) {
+                attachment_ids.push(attachment_id);
+            }
         }
 
-        let message = DbMessage::new(user_id, channel_id, content);
+        let attachments = self
+            .attachment_repository
+            .get_attachments(&db, &attachment_ids)
+            .await?;
 
-        let message = self.message_repository.add(&db, &message).await.unwrap();
+        let mut mentions = Mentions::parse(&content);
+        let recipients = self
+            .resolve_mentions(&db, &user_id, &channel, &mut mentions)
+            .await?;
+
+        let message = DbMessage::new(user_id, channel_id, content, attachment_ids, mentions);
+
+        let message = self.message_repository.add(&db, &message).await?;
         let message = match message {
-            Some(message) => message.to_message(),
-            None => return Err(Status::internal("internal error")),
+            Some(message) => {
+                let mentions = recipients
+                    .iter()
+                    .map(|recipient| DbMention::new(*recipient, message.id, channel_id))
+                    .collect::<Vec<DbMention>>();
+
+                self.mention_repository.add_mentions(&db, &mentions).await?;
+
+                self.message_search_index
+                    .index_message(&db, &message)
+                    .await?;
+
+                self.channel_repository
+                    .update_last_message_time(&db, &channel_id, &message.create_time)
+                    .await?;
+
+                message.to_message(&attachments)
+            }
+            None => return Err(ServiceError::internal("internal error").into()),
         };
 
         {
             let message = message.clone();
 
-            let user_ids: Vec<UserId> = vec![];
+            let members =
+                get_channel_members(&db, &self.server_member_repository, &channel).await?;
             let broadcaster = self.broadcaster.lock().await;
-            broadcaster.send_msg(&user_ids, message).await;
+            broadcaster.send_message(&members, message.clone()).await;
+
+            let mention_received = Payload::MentionReceived(MentionReceived {
+                message: Some(message),
+            });
+            broadcaster.send_signal(&recipients, mention_received).await;
         };
 
         Ok(Response::new(SpeechResponse {
             result: Some(message),
         }))
     }
+
+    async fn update_channel_icon(
+        &self,
+        request: Request<UpdateChannelIconRequest>,
+    ) -> Result<Response<ChannelModel>, Status> {
+        let db = self.db.clone();
+
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
+
+        let req = request.into_inner();
+        let name = req.name; // channels/{channelId}
+        let icon = req.icon; // attachments/{attachmentId}, clear when empty.
+
+        let channel_id = ChannelName::parse(&name)?.0;
+        let mut channel = match self.channel_repository.get(&db, &channel_id).await? {
+            Some(channel) => channel,
+            None => return Err(ServiceError::not_found("channel not found.").into()),
+        };
+
+        // direct channels have no icon.
+        let is_have_permission: bool = !matches!(channel.channel_type, ChannelType::Direct)
+            && is_channel_manager(&db, &self.server_repository, &channel, &user_id).await?;
+
+        if !is_have_permission {
+            return Err(ServiceError::permission_denied("permission denied.").into());
+        }
+
+        let icon = match icon {
+            Some(icon) => Some(
+                create_square_image(
+                    &db,
+                    &self.attachment_repository,
+                    &self.blob_store,
+                    &user_id,
+                    &icon,
+                )
+                .await?,
+            ),
+            None => None,
+        };
+
+        let previous = std::mem::replace(&mut channel.icon, icon.clone());
+        channel.update_time = Some(Datetime::default());
+
+        let res = self.channel_repository.update(&db, &channel).await;
+
+        // the new icon is left unused when nothing was updated.
+        if !matches!(res, Ok(Some(_))) {
+            if let Some(icon) = icon {
+                release_attachment(&db, &self.attachment_repository, &self.blob_store, &icon)
+                    .await
+                    .unwrap_or_else(|err| err.log());
+            }
+        }
+
+        let res = match res? {
+            Some(res) => res,
+            None => return Err(ServiceError::not_found("channel not found.").into()),
+        };
+
+        if let Some(previous) = previous {
+            release_attachment(
+                &db,
+                &self.attachment_repository,
+                &self.blob_store,
+                &previous,
+            )
+            .await
+            .unwrap_or_else(|err| err.log());
+        }
+
+        Ok(Response::new(res.to_message()))
+    }
+
+    async fn create_direct_channel(
+        &self,
+        request: Request<CreateDirectChannelRequest>,
+    ) -> Result<Response<ChannelModel>, Status> {
+        let db = self.db.clone();
+
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
+
+        let user = request.into_inner().user; // users/{userId}
+        let other_user_id = UserName::parse(&user)?.0;
+
+        if other_user_id == user_id {
+            return Err(ServiceError::invalid_argument(
+                "use the saved channel to talk to yourself.",
+            )
+            .into());
+        }
+
+        if self
+            .user_repository
+            .get_user(&db, &other_user_id)
+            .await?
+            .is_none()
+        {
+            return Err(ServiceError::not_found("user not found.").into());
+        }
+
+        // one direct channel per user pair, return the existing one.
+        let exist = self
+            .channel_repository
+            .get_direct_channel(&db, &user_id, &other_user_id)
+            .await?;
+
+        if let Some(exist) = exist {
+            return Ok(Response::new(exist.to_message()));
+        }
+
+        let channel = DbChannel::new_direct(user_id, other_user_id);
+        let added = self.channel_repository.add(&db, &channel).await?;
+
+        match added {
+            Some(channel) => Ok(Response::new(channel.to_message())),
+            None => Err(ServiceError::internal("internal error").into()),
+        }
+    }
+
+    async fn add_group_member(
+        &self,
+        request: Request<AddGroupMemberRequest>,
+    ) -> Result<Response<ChannelModel>, Status> {
+        let db = self.db.clone();
+
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
+
+        let req = request.into_inner();
+        let mut channel = self.get_group_channel(&db, &req.name).await?;
+
+        if !channel.members.contains(&user_id) {
+            return Err(ServiceError::permission_denied("permission denied.").into());
+        }
+
+        let member_id = UserName::parse(&req.user)?.0;
+
+        if channel.members.contains(&member_id) {
+            return Err(ServiceError::already_exists("already a member.").into());
+        }
+
+        if channel.members.len() >= MAX_GROUP_MEMBERS {
+            return Err(ServiceError::failed_precondition(format!(
+                "a group can't have more than {} members.",
+                MAX_GROUP_MEMBERS
+            ))
+            .into());
+        }
+
+        if self
+            .user_repository
+            .get_user(&db, &member_id)
+            .await?
+            .is_none()
+        {
+            return Err(ServiceError::not_found("user not found.").into());
+        }
+
+        channel.members.push(member_id);
+        channel.update_time = Some(Datetime::default());
+
+        let channel = match self.channel_repository.update(&db, &channel).await? {
+            Some(channel) => channel,
+            None => return Err(ServiceError::internal("internal error").into()),
+        };
+
+        let mut message =
+            DbMessage::new_system(user_id, channel.id, MessageType::GroupMemberAdded, None);
+        message.mentions.users = vec![member_id];
+
+        self.send_system_message(&db, &channel, message, &[])
+            .await?;
+
+        Ok(Response::new(channel.to_message()))
+    }
+
+    async fn remove_group_member(
+        &self,
+        request: Request<RemoveGroupMemberRequest>,
+    ) -> Result<Response<ChannelModel>, Status> {
+        let db = self.db.clone();
+
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
+
+        let req = request.into_inner();
+        let mut channel = self.get_group_channel(&db, &req.name).await?;
+
+        // only the owner removes other members.
+        if !matches!(channel.channel_type, ChannelType::Group { owner } if owner == user_id) {
+            return Err(ServiceError::permission_denied("permission denied.").into());
+        }
+
+        let member_id = UserName::parse(&req.user)?.0;
+
+        if member_id == user_id {
+            return Err(
+                ServiceError::invalid_argument("use LeaveGroup to leave the group.").into(),
+            );
+        }
+
+        if !channel.members.contains(&member_id) {
+            return Err(ServiceError::not_found("member not found.").into());
+        }
+
+        channel.members.retain(|member| *member != member_id);
+        channel.update_time = Some(Datetime::default());
+
+        let channel = match self.channel_repository.update(&db, &channel).await? {
+            Some(channel) => channel,
+            None => return Err(ServiceError::internal("internal error").into()),
+        };
+
+        let mut message =
+            DbMessage::new_system(user_id, channel.id, MessageType::GroupMemberRemoved, None);
+        message.mentions.users = vec![member_id];
+
+        // the removed member is told too.
+        self.send_system_message(&db, &channel, message, &[member_id])
+            .await?;
+
+        Ok(Response::new(channel.to_message()))
+    }
+
+    async fn leave_group(
+        &self,
+        request: Request<LeaveGroupRequest>,
+    ) -> Result<Response<()>, Status> {
+        let db = self.db.clone();
+
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
+
+        let req = request.into_inner();
+        let mut channel = self.get_group_channel(&db, &req.name).await?;
+
+        if !channel.members.contains(&user_id) {
+            return Err(ServiceError::permission_denied("permission denied.").into());
+        }
+
+        channel.members.retain(|member| *member != user_id);
+
+        // the last member leaving closes the group, with its messages.
+        let Some(next_owner) = channel.members.first().cloned() else {
+            let mut transaction = DB::Transaction::default();
+
+            delete_channel_contents_in(
+                &mut transaction,
+                &self.message_repository,
+                &self.message_acknowledge_repository,
+                &self.mention_repository,
+                &self.read_state_repository,
+                &channel.id,
+            )?;
+            self.channel_repository
+                .delete_in(&mut transaction, &channel.id)?;
+
+            db.commit(transaction).await?;
+
+            return Ok(Response::new(()));
+        };
+
+        // ownership passes to the longest standing member.
+        if matches!(channel.channel_type, ChannelType::Group { owner } if owner == user_id) {
+            channel.channel_type = ChannelType::Group { owner: next_owner };
+        }
+        channel.update_time = Some(Datetime::default());
+
+        let channel = match self.channel_repository.update(&db, &channel).await? {
+            Some(channel) => channel,
+            None => return Err(ServiceError::internal("internal error").into()),
+        };
+
+        let message =
+            DbMessage::new_system(user_id, channel.id, MessageType::GroupMemberLeft, None);
+
+        self.send_system_message(&db, &channel, message, &[user_id])
+            .await?;
+
+        Ok(Response::new(()))
+    }
+
+    async fn ack_channel(
+        &self,
+        request: Request<AckChannelRequest>,
+    ) -> Result<Response<()>, Status> {
+        let db = self.db.clone();
+
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
+
+        let req = request.into_inner();
+        let channel_id = ChannelName::parse(&req.channel)?.0;
+        let message_id = MessageId::from_string(&req.message_id)
+            .map_err(|_| ServiceError::invalid_field("message_id", "invalid."))?;
+
+        let channel = match self.channel_repository.get(&db, &channel_id).await? {
+            Some(channel) => channel,
+            None => return Err(ServiceError::not_found("channel not found.").into()),
+        };
+
+        if !is_channel_member(&db, &self.server_member_repository, &channel, &user_id).await? {
+            return Err(ServiceError::permission_denied("permission denied.").into());
+        }
+
+        match self.message_repository.get(&db, &message_id).await? {
+            Some(message) if message.channel == channel.id => {}
+            _ => return Err(ServiceError::not_found("message not found.").into()),
+        }
+
+        let read_state = self
+            .read_state_repository
+            .get(&db, &user_id, &channel.id)
+            .await?;
+
+        // acks from a device lagging behind never move the read state back.
+        let read_state = match read_state {
+            Some(read_state) if read_state.last_read_message >= message_id => {
+                return Ok(Response::new(()));
+            }
+            Some(mut read_state) => {
+                read_state.last_read_message = message_id;
+                read_state.update_time = Datetime::default();
+                read_state
+            }
+            None => DbReadState::new(user_id, channel.id, message_id),
+        };
+
+        self.read_state_repository.save(&db, &read_state).await?;
+
+        let (unread_message_count, mention_count) = get_unread_counts(
+            &db,
+            &self.message_repository,
+            &self.mention_repository,
+            &channel.id,
+            &user_id,
+            Some(message_id),
+        )
+        .await?;
+
+        // the user's other devices clear their badges.
+        let read_state_updated = Payload::ReadStateUpdated(ReadStateUpdated {
+            channel: ChannelName(channel.id).to_string(),
+            last_read_message: message_id.to_string(),
+            unread_message_count,
+            mention_count,
+        });
+
+        self.broadcaster
+            .lock()
+            .await
+            .send_signal(&[user_id], read_state_updated)
+            .await;
+
+        Ok(Response::new(()))
+    }
+
+    async fn reorder_channels(
+        &self,
+        request: Request<ReorderChannelsRequest>,
+    ) -> Result<Response<ReorderChannelsResponse>, Status> {
+        let db = self.db.clone();
+
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
+
+        let req = request.into_inner();
+        let server_id = ServerName::parse(&req.parent)?.0;
+
+        let server = match self.server_repository.get_server(&db, &server_id).await? {
+            Some(server) => server,
+            None => return Err(ServiceError::not_found("server not found.").into()),
+        };
+
+        if server.owner != user_id {
+            return Err(ServiceError::permission_denied("permission denied.").into());
+        }
+
+        let categories = self
+            .server_category_repository
+            .get_list_by_server_id(&db, &server.id)
+            .await?;
+
+        let channels = self
+            .channel_repository
+            .get_list_by_server_id(&db, &server.id)
+            .await?;
+
+        let category_positions = req
+            .categories
+            .iter()
+            .map(|position| {
+                Ok(CategoryPosition {
+                    id: CategoryName::parse(&position.category)?.category,
+                    order: position.order,
+                })
+            })
+            .collect::<Result<Vec<CategoryPosition>, ServiceError>>()?;
+
+        let channel_positions = req
+            .channels
+            .iter()
+            .map(|position| {
+                Ok(ChannelPosition {
+                    id: ChannelName::parse(&position.channel)?.0,
+                    category: match &position.category {
+                        Some(category) => Some(CategoryName::parse(category)?.category),
+                        None => None,
+                    },
+                    order: position.order,
+                })
+            })
+            .collect::<Result<Vec<ChannelPosition>, ServiceError>>()?;
+
+        // the layout has to place every category and channel of the server exactly once.
+        let is_every_category = category_positions.len() == categories.len()
+            && categories.iter().all(|category| {
+                category_positions
+                    .iter()
+                    .filter(|position| position.id == category.id)
+                    .count()
+                    == 1
+            });
+
+        let is_every_channel = channel_positions.len() == channels.len()
+            && channels.iter().all(|channel| {
+                channel_positions
+                    .iter()
+                    .filter(|position| position.id == channel.id)
+                    .count()
+                    == 1
+            });
+
+        if !is_every_category || !is_every_channel {
+            return Err(ServiceError::invalid_argument(
+                "the layout must contain every category and channel of the server once.",
+            )
+            .into());
+        }
+
+        let is_known_category = channel_positions.iter().all(|position| {
+            position
+                .category
+                .is_none_or(|category| categories.iter().any(|c| c.id == category))
+        });
+
+        if !is_known_category {
+            return Err(ServiceError::invalid_argument(
+                "channels can only be placed in categories of the server.",
+            )
+            .into());
+        }
+
+        // positions have to be distinct among siblings.
+        let mut category_orders = category_positions
+            .iter()
+            .map(|position| position.order)
+            .collect::<Vec<u32>>();
+        category_orders.sort();
+        category_orders.dedup();
+
+        let mut channel_orders = channel_positions
+            .iter()
+            .map(|position| (position.category, position.order))
+            .collect::<Vec<(Option<ServerCategoryId>, u64)>>();
+        channel_orders.sort();
+        channel_orders.dedup();
+
+        if category_orders.len() != category_positions.len()
+            || channel_orders.len() != channel_positions.len()
+        {
+            return Err(ServiceError::invalid_argument("positions must be distinct.").into());
+        }
+
+        self.channel_repository
+            .update_layout(&db, &category_positions, &channel_positions)
+            .await?;
+
+        let categories = self
+            .server_category_repository
+            .get_list_by_server_id(&db, &server.id)
+            .await?
+            .into_iter()
+            .map(|category| category.to_message())
+            .collect::<Vec<_>>();
+
+        let channels = self
+            .channel_repository
+            .get_list_by_server_id(&db, &server.id)
+            .await?
+            .into_iter()
+            .map(|channel| channel.to_message())
+            .collect::<Vec<ChannelModel>>();
+
+        let members = self
+            .server_member_repository
+            .get_server_members_by_server_id(&db, &server.id)
+            .await?
+            .into_iter()
+            .map(|server_member| server_member.user)
+            .collect::<Vec<UserId>>();
+
+        let server_layout_updated = Payload::ServerLayoutUpdated(ServerLayoutUpdated {
+            server: ServerName(server.id).to_string(),
+            categories: categories.clone(),
+            channels: channels.clone(),
+        });
+
+        self.broadcaster
+            .lock()
+            .await
+            .send_signal(&members, server_layout_updated)
+            .await;
+
+        Ok(Response::new(ReorderChannelsResponse {
+            categories,
+            channels,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost_types::FieldMask;
+    use tonic::Code;
+
+    use super::*;
+    use crate::chat::broadcaster::{Stream, STREAM_BUFFER_SIZE};
+    use crate::services::testing::{add_server, add_server_member, add_user, pager, request};
+    use crate::{
+        db::memory::{self, MemoryDb},
+        models::server::DbServer,
+        services::ycchat::v1::{
+            models::Category,
+            services::channel::{
+                CategoryPosition as CategoryPositionMessage,
+                ChannelPosition as ChannelPositionMessage,
+            },
+        },
+        storage::local::LocalBlobStore,
+        util::resource_name::MessageName,
+    };
+
+    async fn service(db: &MemoryDb) -> impl Channel {
+        connected_service(db, &Arc::new(Mutex::new(Broadcaster::new()))).await
+    }
+
+    /// the service sending its signals through `broadcaster`.
+    async fn connected_service(
+        db: &MemoryDb,
+        broadcaster: &Arc<Mutex<Broadcaster>>,
+    ) -> impl Channel {
+        let repositories = memory::repositories().await;
+
+        ChannelService::new(
+            db.clone(),
+            repositories.server_member,
+            repositories.message,
+            repositories.channel,
+            repositories.server,
+            repositories.server_category,
+            repositories.attachment,
+            LocalBlobStore::new("attachments", "/attachments"),
+            repositories.mention,
+            repositories.message_search,
+            repositories.user,
+            repositories.read_state,
+            repositories.message_acknowledge,
+            broadcaster.clone(),
+            pager(),
+        )
+    }
+
+    fn create_request(name: &str, channel_type: i32) -> CreateChannelRequest {
+        CreateChannelRequest {
+            channel: Some(ChannelModel {
+                name: name.to_string(),
+                display_name: "channel".to_string(),
+                channel_type,
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[tokio::test]
+    async fn create_channel_rejects_an_unknown_type() {
+        let db = MemoryDb::new();
+        let service = service(&db).await;
+
+        let err = service
+            .create_channel(request(UserId::new(), create_request("", 99)))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn create_channel_checks_the_parent_of_the_type() {
+        let db = MemoryDb::new();
+        let service = service(&db).await;
+        let owner = UserId::new();
+        let server = ServerName(add_server(&db, owner).await.id).to_string();
+
+        let server_channel = ChannelTypeMessage::Server as i32;
+        let err = service
+            .create_channel(request(owner, create_request("", server_channel)))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        let saved_channel = ChannelTypeMessage::Saved as i32;
+        let err = service
+            .create_channel(request(owner, create_request(&server, saved_channel)))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        let created = service
+            .create_channel(request(owner, create_request(&server, server_channel)))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(created.channel_type, server_channel);
+    }
+
+    #[tokio::test]
+    async fn only_the_server_owner_creates_server_channels() {
+        let db = MemoryDb::new();
+        let service = service(&db).await;
+        let server = ServerName(add_server(&db, UserId::new()).await.id).to_string();
+
+        let err = service
+            .create_channel(request(
+                UserId::new(),
+                create_request(&server, ChannelTypeMessage::Server as i32),
+            ))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+
+        let missing = ServerName(ServerId::new()).to_string();
+        let err = service
+            .create_channel(request(
+                UserId::new(),
+                create_request(&missing, ChannelTypeMessage::Server as i32),
+            ))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn only_server_members_list_server_channels() {
+        let db = MemoryDb::new();
+        let service = service(&db).await;
+        let owner = UserId::new();
+        let server = add_server(&db, owner).await;
+        let parent = ServerName(server.id).to_string();
+
+        service
+            .create_channel(request(
+                owner,
+                create_request(&parent, ChannelTypeMessage::Server as i32),
+            ))
+            .await
+            .unwrap();
+        let list = || ListServerChannelsRequest {
+            parent: parent.clone(),
+            page_size: 10,
+            ..Default::default()
+        };
+
+        let channels = service
+            .list_server_channels(request(owner, list()))
+            .await
+            .unwrap()
+            .into_inner()
+            .channels;
+        assert_eq!(channels.len(), 1);
+
+        let err = service
+            .list_server_channels(request(UserId::new(), list()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+    }
+
+    #[tokio::test]
+    async fn one_direct_channel_per_user_pair() {
+        let db = MemoryDb::new();
+        let service = service(&db).await;
+        let (user, other) = (add_user(&db).await.id, add_user(&db).await.id);
+        let direct = |from: UserId, to: UserId| {
+            request(
+                from,
+                CreateDirectChannelRequest {
+                    user: UserName(to).to_string(),
+                },
+            )
+        };
+
+        let created = service
+            .create_direct_channel(direct(user, other))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(created.channel_type, ChannelTypeMessage::Direct as i32);
+
+        for (from, to) in [(user, other), (other, user)] {
+            let got = service
+                .create_direct_channel(direct(from, to))
+                .await
+                .unwrap()
+                .into_inner();
+            assert_eq!(got.name, created.name);
+        }
+
+        let err = service
+            .create_direct_channel(direct(user, user))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        let err = service
+            .create_direct_channel(direct(user, UserId::new()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    async fn create_group(service: &impl Channel, owner: UserId) -> String {
+        service
+            .create_channel(request(
+                owner,
+                create_request("", ChannelTypeMessage::Group as i32),
+            ))
+            .await
+            .unwrap()
+            .into_inner()
+            .name
+    }
+
+    #[tokio::test]
+    async fn members_add_and_the_owner_removes_group_members() {
+        let db = MemoryDb::new();
+        let broadcaster = Arc::new(Mutex::new(Broadcaster::new()));
+        let service = connected_service(&db, &broadcaster).await;
+        let owner = UserId::new();
+        let (member, guest) = (add_user(&db).await.id, add_user(&db).await.id);
+        let add = |user_id: UserId, name: &String, member: UserId| {
+            request(
+                user_id,
+                AddGroupMemberRequest {
+                    name: name.clone(),
+                    user: UserName(member).to_string(),
+                },
+            )
+        };
+        let remove = |user_id: UserId, name: &String, member: UserId| {
+            request(
+                user_id,
+                RemoveGroupMemberRequest {
+                    name: name.clone(),
+                    user: UserName(member).to_string(),
+                },
+            )
+        };
+
+        let name = create_group(&service, owner).await;
+        service
+            .add_group_member(add(owner, &name, member))
+            .await
+            .unwrap();
+        let group = service
+            .add_group_member(add(member, &name, guest))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(group.members.len(), 3);
+
+        let err = service
+            .add_group_member(add(owner, &name, guest))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::AlreadyExists);
+
+        let err = service
+            .remove_group_member(remove(member, &name, guest))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+
+        // the removed member is told about it.
+        let (tx, mut rx) = tokio::sync::mpsc::channel(STREAM_BUFFER_SIZE);
+        broadcaster
+            .lock()
+            .await
+            .set_stream(guest, Stream::new(tx))
+            .await;
+        let group = service
+            .remove_group_member(remove(owner, &name, guest))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(group.members.len(), 2);
+        assert!(rx.try_recv().is_ok());
+
+        let err = service
+            .add_group_member(add(guest, &name, guest))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+    }
+
+    #[tokio::test]
+    async fn leaving_passes_the_group_on_and_the_last_member_closes_it() {
+        let db = MemoryDb::new();
+        let service = service(&db).await;
+        let owner = UserId::new();
+        let member = add_user(&db).await.id;
+        let leave = |user_id: UserId, name: &String| {
+            request(user_id, LeaveGroupRequest { name: name.clone() })
+        };
+
+        let name = create_group(&service, owner).await;
+        service
+            .add_group_member(request(
+                owner,
+                AddGroupMemberRequest {
+                    name: name.clone(),
+                    user: UserName(member).to_string(),
+                },
+            ))
+            .await
+            .unwrap();
+
+        service.leave_group(leave(owner, &name)).await.unwrap();
+        let channel_id = ChannelName::parse(&name).unwrap().0;
+        let repositories = memory::repositories().await;
+        let group = repositories
+            .channel
+            .get(&db, &channel_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(group.channel_type, ChannelType::Group { owner } if owner == member));
+        assert_eq!(group.members, vec![member]);
+
+        let err = service.leave_group(leave(owner, &name)).await.unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+
+        service.leave_group(leave(member, &name)).await.unwrap();
+        assert!(repositories
+            .channel
+            .get(&db, &channel_id)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn acks_update_the_unread_counts() {
+        let db = MemoryDb::new();
+        let broadcaster = Arc::new(Mutex::new(Broadcaster::new()));
+        let service = connected_service(&db, &broadcaster).await;
+        let owner = UserId::new();
+        let member = UserId::new();
+        let server = add_server(&db, owner).await;
+        add_server_member(&db, &server, member).await;
+
+        let parent = ServerName(server.id).to_string();
+        let channel = service
+            .create_channel(request(
+                owner,
+                create_request(&parent, ChannelTypeMessage::Server as i32),
+            ))
+            .await
+            .unwrap()
+            .into_inner()
+            .name;
+
+        let mut message_ids = vec![];
+        for content in ["hello", &format!("<@{member}> look"), "bye"] {
+            // message ids only sort by time across milliseconds.
+            tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+            let message = service
+                .speech(request(
+                    owner,
+                    SpeechRequest {
+                        name: channel.clone(),
+                        content: content.to_string(),
+                        attachments: vec![],
+                    },
+                ))
+                .await
+                .unwrap()
+                .into_inner()
+                .result
+                .unwrap();
+            message_ids.push(
+                MessageName::parse(&message.name)
+                    .unwrap()
+                    .message
+                    .to_string(),
+            );
+        }
+
+        let unread_counts = || async {
+            let channels = service
+                .list_server_channels(request(
+                    member,
+                    ListServerChannelsRequest {
+                        parent: parent.clone(),
+                        page_size: 10,
+                        ..Default::default()
+                    },
+                ))
+                .await
+                .unwrap()
+                .into_inner()
+                .channels;
+
+            (channels[0].unread_message_count, channels[0].mention_count)
+        };
+        let ack = |user_id: UserId, message_id: &String| {
+            request(
+                user_id,
+                AckChannelRequest {
+                    channel: channel.clone(),
+                    message_id: message_id.clone(),
+                },
+            )
+        };
+        assert_eq!(unread_counts().await, (3, 1));
+
+        // the member's other devices are told.
+        let (tx, mut rx) = tokio::sync::mpsc::channel(STREAM_BUFFER_SIZE);
+        broadcaster
+            .lock()
+            .await
+            .set_stream(member, Stream::new(tx))
+            .await;
+        service
+            .ack_channel(ack(member, &message_ids[1]))
+            .await
+            .unwrap();
+        match rx.try_recv().unwrap().server_signal.unwrap().payload {
+            Some(Payload::ReadStateUpdated(updated)) => {
+                assert_eq!(updated.last_read_message, message_ids[1]);
+                assert_eq!(
+                    (updated.unread_message_count, updated.mention_count),
+                    (1, 0)
+                );
+            }
+            _ => panic!("expected ReadStateUpdated"),
+        }
+        assert_eq!(unread_counts().await, (1, 0));
+
+        // an older ack leaves the read state alone.
+        service
+            .ack_channel(ack(member, &message_ids[0]))
+            .await
+            .unwrap();
+        assert_eq!(unread_counts().await, (1, 0));
+
+        let err = service
+            .ack_channel(ack(UserId::new(), &message_ids[2]))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+    }
+
+    /// a category of `server`, returns its name.
+    async fn add_category(db: &MemoryDb, server: &DbServer) -> String {
+        let category = DbServerCategory::new(
+            server.clone(),
+            Category {
+                display_name: "category".to_string(),
+                ..Default::default()
+            },
+        );
+        let repositories = memory::repositories().await;
+        repositories
+            .server_category
+            .add(db, &category)
+            .await
+            .unwrap();
+
+        CategoryName {
+            server: server.id,
+            category: category.id,
+        }
+        .to_string()
+    }
+
+    async fn create_server_channel(service: &impl Channel, owner: UserId, parent: &str) -> String {
+        service
+            .create_channel(request(
+                owner,
+                create_request(parent, ChannelTypeMessage::Server as i32),
+            ))
+            .await
+            .unwrap()
+            .into_inner()
+            .name
+    }
+
+    fn reorder(
+        parent: &str,
+        categories: &[(&String, u32)],
+        channels: &[(&String, Option<&String>, u64)],
+    ) -> ReorderChannelsRequest {
+        ReorderChannelsRequest {
+            parent: parent.to_string(),
+            categories: categories
+                .iter()
+                .map(|(category, order)| CategoryPositionMessage {
+                    category: category.to_string(),
+                    order: *order,
+                })
+                .collect(),
+            channels: channels
+                .iter()
+                .map(|(channel, category, order)| ChannelPositionMessage {
+                    channel: channel.to_string(),
+                    category: category.cloned(),
+                    order: *order,
+                })
+                .collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn reorder_applies_the_whole_layout() {
+        let db = MemoryDb::new();
+        let service = service(&db).await;
+        let owner = UserId::new();
+        let server = add_server(&db, owner).await;
+        let parent = ServerName(server.id).to_string();
+        let (first, second) = (
+            add_category(&db, &server).await,
+            add_category(&db, &server).await,
+        );
+        let a = create_server_channel(&service, owner, &parent).await;
+        let b = create_server_channel(&service, owner, &parent).await;
+        let c = create_server_channel(&service, owner, &parent).await;
+
+        let layout = reorder(
+            &parent,
+            &[(&first, 1), (&second, 0)],
+            &[
+                (&a, Some(&second), 1),
+                (&b, None, 0),
+                (&c, Some(&second), 0),
+            ],
+        );
+        let res = service
+            .reorder_channels(request(owner, layout))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(res.categories.len(), 2);
+
+        let listed = service
+            .list_server_channels(request(
+                owner,
+                ListServerChannelsRequest {
+                    parent: parent.clone(),
+                    page_size: 10,
+                    ..Default::default()
+                },
+            ))
+            .await
+            .unwrap()
+            .into_inner()
+            .channels
+            .into_iter()
+            .map(|channel| (channel.name, channel.category, channel.order))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            listed,
+            vec![
+                (b, None, 0),
+                (c, Some(second.clone()), 0),
+                (a, Some(second.clone()), 1),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn reorder_rejects_foreign_or_missing_categories() {
+        let db = MemoryDb::new();
+        let service = service(&db).await;
+        let owner = UserId::new();
+        let server = add_server(&db, owner).await;
+        let parent = ServerName(server.id).to_string();
+        let category = add_category(&db, &server).await;
+        let channel = create_server_channel(&service, owner, &parent).await;
+
+        let other_server = add_server(&db, owner).await;
+        let foreign = add_category(&db, &other_server).await;
+        let missing = CategoryName {
+            server: server.id,
+            category: ServerCategoryId::new(),
+        }
+        .to_string();
+
+        for placed_in in [&foreign, &missing] {
+            let layout = reorder(
+                &parent,
+                &[(&category, 0)],
+                &[(&channel, Some(placed_in), 0)],
+            );
+            let err = service
+                .reorder_channels(request(owner, layout))
+                .await
+                .unwrap_err();
+            assert_eq!(err.code(), Code::InvalidArgument);
+        }
+
+        // every category and channel of the server, once.
+        for layout in [
+            reorder(&parent, &[], &[(&channel, None, 0)]),
+            reorder(&parent, &[(&category, 0)], &[]),
+            reorder(
+                &parent,
+                &[(&category, 0)],
+                &[(&channel, None, 0), (&channel, None, 1)],
+            ),
+        ] {
+            let err = service
+                .reorder_channels(request(owner, layout))
+                .await
+                .unwrap_err();
+            assert_eq!(err.code(), Code::InvalidArgument);
+        }
+
+        let layout = reorder(
+            &parent,
+            &[(&category, 0)],
+            &[(&channel, Some(&category), 0)],
+        );
+        let err = service
+            .reorder_channels(request(UserId::new(), layout))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+
+        let channel_id = ChannelName::parse(&channel).unwrap().0;
+        let repositories = memory::repositories().await;
+        let stored = repositories
+            .channel
+            .get(&db, &channel_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(stored.category.is_none());
+    }
+
+    #[tokio::test]
+    async fn update_channel_moves_the_channel_between_categories() {
+        let db = MemoryDb::new();
+        let service = service(&db).await;
+        let owner = UserId::new();
+        let server = add_server(&db, owner).await;
+        let (first, second) = (
+            add_category(&db, &server).await,
+            add_category(&db, &server).await,
+        );
+        let foreign = add_category(&db, &add_server(&db, owner).await).await;
+
+        let channel = create_server_channel(&service, owner, &first).await;
+        create_server_channel(&service, owner, &second).await;
+        let move_to = |category: Option<&String>| {
+            request(
+                owner,
+                UpdateChannelRequest {
+                    channel: Some(ChannelModel {
+                        name: channel.clone(),
+                        category: category.cloned(),
+                        ..Default::default()
+                    }),
+                    update_mask: Some(FieldMask {
+                        paths: vec!["category".to_string()],
+                    }),
+                },
+            )
+        };
+
+        // to the end of the other category.
+        let moved = service
+            .update_channel(move_to(Some(&second)))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!((moved.category, moved.order), (Some(second.clone()), 1));
+
+        let moved = service
+            .update_channel(move_to(None))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!((moved.category, moved.order), (None, 0));
+
+        let err = service
+            .update_channel(move_to(Some(&foreign)))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
 }
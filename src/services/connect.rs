@@ -1,8 +1,7 @@
 use super::ycchat::v1::services::connect::{
     self, connect_service_server::ConnectService as Connect, ConnectResponse,
 };
-use crate::chat::broadcaster::{Broadcaster, Stream as BroadcastStream};
-use crate::db::traits::channel::ChannelRepository;
+use crate::chat::broadcaster::{Broadcaster, Stream as BroadcastStream, STREAM_BUFFER_SIZE};
 use crate::models::user::UserId;
 use futures::lock::Mutex;
 use std::pin::Pin;
@@ -31,29 +30,36 @@ impl Connect for ConnectService {
         request: tonic::Request<connect::ConnectRequest>,
     ) -> Result<tonic::Response<Self::ConnStream>, tonic::Status> {
         let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
-        let user_id = UserId::from_string(&user_id).unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
 
         let (stream_tx, stream_rx) = mpsc::channel(1); // Fn usage
 
-        let (tx, mut rx) = mpsc::channel(1);
-        {
-            let stream = BroadcastStream::new(tx);
-            self.broadcaster.lock().await.set_stream(user_id, stream);
-        }
+        let (tx, mut rx) = mpsc::channel(STREAM_BUFFER_SIZE);
+        let stream = BroadcastStream::new(tx);
+        let stream_id = stream.id();
+        self.broadcaster
+            .lock()
+            .await
+            .set_stream(user_id, stream)
+            .await;
 
-        let user_id = user_id.to_owned();
+        let broadcaster = self.broadcaster.clone();
         tokio::spawn(async move {
             while let Some(msg) = rx.recv().await {
-                match stream_tx.send(Ok(msg)).await {
-                    Ok(_) => {}
-                    Err(_) => {
-                        eprintln!("[Remote] stream tx sending error. Remote {}", &user_id);
-                    }
+                if stream_tx.send(Ok(msg)).await.is_err() {
+                    // the client disconnected.
+                    break;
                 }
             }
-        });
 
-        println!("connect complete!!!");
+            // close the queue first, signals sent meanwhile fail instead of piling up.
+            drop(rx);
+            broadcaster
+                .lock()
+                .await
+                .remove_stream(user_id, stream_id)
+                .await;
+        });
 
         Ok(Response::new(Box::pin(
             tokio_stream::wrappers::ReceiverStream::new(stream_rx),
@@ -0,0 +1,231 @@
+use std::{collections::HashMap, fmt, time::Duration};
+
+use tonic::{Code, Status};
+use tonic_types::{ErrorDetails, FieldViolation, StatusExt};
+
+use crate::db::{error::RepositoryError, filter::FilterError};
+
+/// `google.rpc.ErrorInfo` domain of every error the services return.
+pub const ERROR_DOMAIN: &str = "ycchat.v1";
+
+/// how long clients wait before retrying an unavailable backend.
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// errors of the service layer, returned to clients as `tonic::Status` with
+/// `google.rpc.ErrorInfo` and, where it applies, `BadRequest` or `RetryInfo` details.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServiceError {
+    /// `field` of the request is malformed, reported as a `BadRequest` field violation.
+    InvalidArgument {
+        field: Option<String>,
+        description: String,
+    },
+    Unauthenticated(String),
+    NotFound(String),
+    AlreadyExists(String),
+    PermissionDenied(String),
+    FailedPrecondition(String),
+    Unavailable(String),
+    Internal(String),
+}
+
+impl ServiceError {
+    pub fn invalid_argument(description: impl Into<String>) -> Self {
+        ServiceError::InvalidArgument {
+            field: None,
+            description: description.into(),
+        }
+    }
+
+    pub fn invalid_field(field: impl Into<String>, description: impl Into<String>) -> Self {
+        ServiceError::InvalidArgument {
+            field: Some(field.into()),
+            description: description.into(),
+        }
+    }
+
+    pub fn unauthenticated(message: impl Into<String>) -> Self {
+        ServiceError::Unauthenticated(message.into())
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        ServiceError::NotFound(message.into())
+    }
+
+    pub fn already_exists(message: impl Into<String>) -> Self {
+        ServiceError::AlreadyExists(message.into())
+    }
+
+    pub fn permission_denied(message: impl Into<String>) -> Self {
+        ServiceError::PermissionDenied(message.into())
+    }
+
+    pub fn failed_precondition(message: impl Into<String>) -> Self {
+        ServiceError::FailedPrecondition(message.into())
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        ServiceError::Internal(message.into())
+    }
+
+    /// writes the error to the server log. errors a request recovers from never reach a client,
+    /// internal errors keep their backend text here.
+    pub fn log(&self) {
+        match self {
+            ServiceError::Internal(message) => eprintln!("internal error: {}", message),
+            err => eprintln!("{}: {}", err.reason(), err),
+        }
+    }
+
+    /// machine readable `ErrorInfo.reason`.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            ServiceError::InvalidArgument { .. } => "INVALID_ARGUMENT",
+            ServiceError::Unauthenticated(_) => "UNAUTHENTICATED",
+            ServiceError::NotFound(_) => "NOT_FOUND",
+            ServiceError::AlreadyExists(_) => "ALREADY_EXISTS",
+            ServiceError::PermissionDenied(_) => "PERMISSION_DENIED",
+            ServiceError::FailedPrecondition(_) => "FAILED_PRECONDITION",
+            ServiceError::Unavailable(_) => "BACKEND_UNAVAILABLE",
+            ServiceError::Internal(_) => "INTERNAL",
+        }
+    }
+
+    fn code(&self) -> Code {
+        match self {
+            ServiceError::InvalidArgument { .. } => Code::InvalidArgument,
+            ServiceError::Unauthenticated(_) => Code::Unauthenticated,
+            ServiceError::NotFound(_) => Code::NotFound,
+            ServiceError::AlreadyExists(_) => Code::AlreadyExists,
+            ServiceError::PermissionDenied(_) => Code::PermissionDenied,
+            ServiceError::FailedPrecondition(_) => Code::FailedPrecondition,
+            ServiceError::Unavailable(_) => Code::Unavailable,
+            ServiceError::Internal(_) => Code::Internal,
+        }
+    }
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServiceError::InvalidArgument {
+                field: Some(field),
+                description,
+            } => write!(f, "{}: {}", field, description),
+            ServiceError::InvalidArgument { description, .. } => write!(f, "{}", description),
+            ServiceError::Unauthenticated(message)
+            | ServiceError::NotFound(message)
+            | ServiceError::AlreadyExists(message)
+            | ServiceError::PermissionDenied(message)
+            | ServiceError::FailedPrecondition(message)
+            | ServiceError::Unavailable(message) => write!(f, "{}", message),
+            // backend details stay in the server log.
+            ServiceError::Internal(_) => write!(f, "internal error."),
+        }
+    }
+}
+
+impl std::error::Error for ServiceError {}
+
+impl From<RepositoryError> for ServiceError {
+    fn from(err: RepositoryError) -> Self {
+        // the backend text names tables, indexes and values, it stays in the server log.
+        let service_err = match &err {
+            RepositoryError::NotFound { resource, .. } => {
+                ServiceError::NotFound(format!("{} not found.", resource))
+            }
+            RepositoryError::Conflict(_) => {
+                ServiceError::AlreadyExists("already exists.".to_string())
+            }
+            RepositoryError::ConstraintViolation(_) => {
+                ServiceError::FailedPrecondition("the record violates a constraint.".to_string())
+            }
+            RepositoryError::Unavailable(_) => {
+                ServiceError::Unavailable("backend unavailable.".to_string())
+            }
+            // logged with its message once it is returned.
+            RepositoryError::Internal(message) => return ServiceError::Internal(message.clone()),
+        };
+
+        err.log();
+
+        service_err
+    }
+}
+
+/// every filter is the `filter` field of a `List*` request.
+impl From<FilterError> for ServiceError {
+    fn from(err: FilterError) -> Self {
+        ServiceError::invalid_field("filter", err.0)
+    }
+}
+
+impl From<ServiceError> for Status {
+    fn from(err: ServiceError) -> Self {
+        if let ServiceError::Internal(_) = &err {
+            err.log();
+        }
+
+        let mut details = ErrorDetails::with_error_info(
+            err.reason(),
+            ERROR_DOMAIN,
+            HashMap::<String, String>::new(),
+        );
+
+        match &err {
+            ServiceError::InvalidArgument {
+                field: Some(field),
+                description,
+            } => {
+                details.set_bad_request(vec![FieldViolation::new(
+                    field.clone(),
+                    description.clone(),
+                )]);
+            }
+            ServiceError::Unavailable(_) => {
+                details.set_retry_info(Some(RETRY_DELAY));
+            }
+            _ => {}
+        }
+
+        Status::with_error_details(err.code(), err.to_string(), details)
+    }
+}
+
+impl From<RepositoryError> for Status {
+    fn from(err: RepositoryError) -> Self {
+        ServiceError::from(err).into()
+    }
+}
+
+impl From<FilterError> for Status {
+    fn from(err: FilterError) -> Self {
+        ServiceError::from(err).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repository_errors_keep_the_backend_text_out_of_the_status() {
+        let detail = "table `server` has no record `server:01H`";
+
+        let status = Status::from(RepositoryError::NotFound {
+            resource: "server",
+            message: detail.to_string(),
+        });
+        assert_eq!(status.code(), Code::NotFound);
+        assert_eq!(status.message(), "server not found.");
+
+        for err in [
+            RepositoryError::Conflict(detail.to_string()),
+            RepositoryError::ConstraintViolation(detail.to_string()),
+            RepositoryError::Unavailable(detail.to_string()),
+            RepositoryError::Internal(detail.to_string()),
+        ] {
+            assert!(!Status::from(err).message().contains(detail));
+        }
+    }
+}
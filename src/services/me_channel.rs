@@ -0,0 +1,163 @@
+use tonic::{Request, Response, Result, Status};
+
+use crate::{
+    db::{
+        traits::{
+            channel::ChannelRepository, mention::MentionRepository, message::MessageRepository,
+            read_state::ReadStateRepository,
+        },
+        Database,
+    },
+    models::user::UserId,
+};
+
+use super::{
+    channel::to_channel_messages,
+    ycchat::v1::services::me::channel::{
+        me_channel_service_server::MeChannelService as MeChannelServiceServer,
+        ListMyDirectChannelsRequest, ListMyDirectChannelsResponse,
+    },
+};
+
+pub struct MeChannelService<DB, C, R, M, MN>
+where
+    DB: Database,
+    C: ChannelRepository<DB>,
+    R: ReadStateRepository<DB>,
+    M: MessageRepository<DB>,
+    MN: MentionRepository<DB>,
+{
+    db: DB,
+    channel_repository: C,
+    read_state_repository: R,
+    message_repository: M,
+    mention_repository: MN,
+}
+
+impl<DB, C, R, M, MN> MeChannelService<DB, C, R, M, MN>
+where
+    DB: Database,
+    C: ChannelRepository<DB>,
+    R: ReadStateRepository<DB>,
+    M: MessageRepository<DB>,
+    MN: MentionRepository<DB>,
+{
+    pub fn new(
+        db: DB,
+        channel_repository: C,
+        read_state_repository: R,
+        message_repository: M,
+        mention_repository: MN,
+    ) -> Self {
+        MeChannelService {
+            db,
+            channel_repository,
+            read_state_repository,
+            message_repository,
+            mention_repository,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl<DB, C, R, M, MN> MeChannelServiceServer for MeChannelService<DB, C, R, M, MN>
+where
+    DB: Database,
+    C: ChannelRepository<DB> + 'static,
+    R: ReadStateRepository<DB> + 'static,
+    M: MessageRepository<DB> + 'static,
+    MN: MentionRepository<DB> + 'static,
+{
+    async fn list_my_direct_channels(
+        &self,
+        request: Request<ListMyDirectChannelsRequest>,
+    ) -> Result<Response<ListMyDirectChannelsResponse>, Status> {
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
+
+        let db = self.db.clone();
+
+        let channels = self
+            .channel_repository
+            .get_direct_channels_by_user_id(&db, &user_id)
+            .await?;
+
+        let channels = to_channel_messages(
+            &db,
+            &self.read_state_repository,
+            &self.message_repository,
+            &self.mention_repository,
+            &user_id,
+            channels,
+        )
+        .await?;
+
+        Ok(Response::new(ListMyDirectChannelsResponse { channels }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use surrealdb::sql::Datetime;
+
+    use super::*;
+    use crate::{
+        db::memory::{self, MemoryDb},
+        models::channel::DbChannel,
+        services::testing::request,
+        util::resource_name::ChannelName,
+    };
+
+    fn at(seconds: i64) -> Datetime {
+        Datetime::from(Utc.timestamp_opt(seconds, 0).unwrap())
+    }
+
+    /// a direct channel of `user` created at `create_time`, last written to at
+    /// `last_message_time`.
+    fn direct_channel(user: UserId, create_time: i64, last_message_time: Option<i64>) -> DbChannel {
+        let mut channel = DbChannel::new_direct(user, UserId::new());
+        channel.create_time = at(create_time);
+        channel.last_message_time = last_message_time.map(at);
+
+        channel
+    }
+
+    #[tokio::test]
+    async fn direct_channels_are_ordered_by_last_activity() {
+        let db = MemoryDb::new();
+        let repositories = memory::repositories().await;
+        let user = UserId::new();
+
+        let quiet = direct_channel(user, 30, None);
+        let old = direct_channel(user, 10, Some(50));
+        let recent = direct_channel(user, 20, Some(40));
+        let others = direct_channel(UserId::new(), 60, Some(60));
+        for channel in [&quiet, &old, &recent, &others] {
+            repositories.channel.add(&db, channel).await.unwrap();
+        }
+
+        let service = MeChannelService::new(
+            db.clone(),
+            repositories.channel,
+            repositories.read_state,
+            repositories.message,
+            repositories.mention,
+        );
+        let channels = service
+            .list_my_direct_channels(request(user, ListMyDirectChannelsRequest {}))
+            .await
+            .unwrap()
+            .into_inner()
+            .channels
+            .into_iter()
+            .map(|channel| channel.name)
+            .collect::<Vec<String>>();
+
+        let expected = [old, recent, quiet]
+            .iter()
+            .map(|channel| ChannelName(channel.id).to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(channels, expected);
+    }
+}
@@ -0,0 +1,133 @@
+use tonic::{Request, Response, Result, Status};
+
+use crate::{
+    db::{
+        traits::{
+            attachment::AttachmentRepository, mention::MentionRepository,
+            message::MessageRepository,
+        },
+        Database,
+    },
+    models::{attachment::AttachmentId, message::MessageId, user::UserId},
+    util::pager::{ListRequest, Pager, CREATE_TIME},
+};
+
+use super::ycchat::v1::services::me::mention::{
+    me_mention_service_server::MeMentionService as MeMentionServiceServer, ListMyMentionsRequest,
+    ListMyMentionsResponse,
+};
+
+pub struct MeMentionService<DB, MN, M, A>
+where
+    DB: Database,
+    MN: MentionRepository<DB>,
+    M: MessageRepository<DB>,
+    A: AttachmentRepository<DB>,
+{
+    db: DB,
+    mention_repository: MN,
+    message_repository: M,
+    attachment_repository: A,
+    pager: Pager,
+}
+
+impl<DB, MN, M, A> MeMentionService<DB, MN, M, A>
+where
+    DB: Database,
+    MN: MentionRepository<DB>,
+    M: MessageRepository<DB>,
+    A: AttachmentRepository<DB>,
+{
+    pub fn new(
+        db: DB,
+        mention_repository: MN,
+        message_repository: M,
+        attachment_repository: A,
+        pager: Pager,
+    ) -> Self {
+        MeMentionService {
+            db,
+            mention_repository,
+            message_repository,
+            attachment_repository,
+            pager,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl<DB, MN, M, A> MeMentionServiceServer for MeMentionService<DB, MN, M, A>
+where
+    DB: Database,
+    MN: MentionRepository<DB> + 'static,
+    M: MessageRepository<DB> + 'static,
+    A: AttachmentRepository<DB> + 'static,
+{
+    async fn list_my_mentions(
+        &self,
+        request: Request<ListMyMentionsRequest>,
+    ) -> Result<Response<ListMyMentionsResponse>, Status> {
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
+
+        let db = self.db.clone();
+        let request = request.into_inner();
+        let page = self
+            .pager
+            .list(
+                ListRequest::new(
+                    format!("users/{}", user_id),
+                    request.page_size,
+                    request.page_token,
+                    request.order_by,
+                ),
+                &[CREATE_TIME],
+                |page| {
+                    self.mention_repository
+                        .get_list_by_user_id(&db, &user_id, page)
+                },
+            )
+            .await?;
+
+        let message_ids = page
+            .items
+            .iter()
+            .map(|mention| mention.message)
+            .collect::<Vec<MessageId>>();
+
+        let message_list = self
+            .message_repository
+            .get_list_by_ids(&db, &message_ids)
+            .await?;
+
+        let attachment_ids = message_list
+            .iter()
+            .flat_map(|message| message.attachments.clone())
+            .collect::<Vec<AttachmentId>>();
+
+        let attachments = self
+            .attachment_repository
+            .get_attachments(&db, &attachment_ids)
+            .await?;
+
+        // keep the inbox order, mentions of deleted messages are skipped.
+        let messages = page
+            .items
+            .iter()
+            .filter_map(|mention| {
+                message_list
+                    .iter()
+                    .find(|message| message.id == mention.message)
+            })
+            .map(|message| message.clone().to_message(&attachments))
+            .collect();
+
+        let res = ListMyMentionsResponse {
+            messages,
+            next_page_token: page.next_page_token,
+            prev_page_token: page.prev_page_token,
+        };
+
+        Ok(Response::new(res))
+    }
+}
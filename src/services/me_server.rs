@@ -1,14 +1,9 @@
-use prost::Message;
-use surrealdb::{engine::remote::ws::Client, sql::Datetime, Surreal};
 use tonic::{Request, Response, Result, Status};
 
 use crate::{
-    db::{
-        surreal::conn,
-        traits::{server::ServerRepository, server_member::ServerMemberRepository},
-    },
-    models::{server::ServerId, user::UserId},
-    util::{self, base64_encoder, pager::PageTokenizer},
+    db::{traits::server::ServerRepository, Database},
+    models::user::UserId,
+    util::pager::{ListRequest, Pager, CREATE_TIME},
 };
 
 use super::ycchat::v1::models::Server;
@@ -17,33 +12,35 @@ use super::ycchat::v1::services::me::server::{
     ListMeServersResponse,
 };
 
-pub struct MeServerService<U, M>
+pub struct MeServerService<DB, U>
 where
-    U: ServerRepository<Surreal<Client>>,
-    M: ServerMemberRepository<Surreal<Client>>,
+    DB: Database,
+    U: ServerRepository<DB>,
 {
+    db: DB,
     server_repository: U,
-    server_member_repository: M,
+    pager: Pager,
 }
 
-impl<U, M> MeServerService<U, M>
+impl<DB, U> MeServerService<DB, U>
 where
-    U: ServerRepository<Surreal<Client>>,
-    M: ServerMemberRepository<Surreal<Client>>,
+    DB: Database,
+    U: ServerRepository<DB>,
 {
-    pub fn new(server_repository: U, server_member_repository: M) -> Self {
+    pub fn new(db: DB, server_repository: U, pager: Pager) -> Self {
         MeServerService {
+            db,
             server_repository,
-            server_member_repository,
+            pager,
         }
     }
 }
 
 #[tonic::async_trait]
-impl<U, M> MeServerServiceServer for MeServerService<U, M>
+impl<DB, U> MeServerServiceServer for MeServerService<DB, U>
 where
-    U: ServerRepository<Surreal<Client>> + 'static,
-    M: ServerMemberRepository<Surreal<Client>> + 'static,
+    DB: Database,
+    U: ServerRepository<DB> + 'static,
 {
     async fn list_me_servers(
         &self,
@@ -52,53 +49,35 @@ where
         let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
         let user_id = UserId::from_string(user_id).unwrap();
 
-        let db = conn().await;
+        let db = self.db.clone();
         let request = request.into_inner();
-        let page_token = match request.page_token.clone() {
-            Some(page_token) => {
-                let page_token = util::pager::get_page_token(page_token);
-                Some(page_token.unwrap())
-            }
-            None => None,
-        };
-
-        let (page_size, offset_id, prev_page_token) = match page_token {
-            Some(page_token) => (
-                page_token.page_size,
-                page_token
-                    .offset_id
-                    .map(|offset_id| ServerId::from_string(&offset_id).unwrap()),
-                page_token.prev_page_token,
-            ),
-            None => (request.page_size, None, None),
-        };
-
-        // page_size + 1 갯수만큼 데이터 로드 후 next_page_token None, Some 처리
-        let mut list = self
-            .server_repository
-            .get_joined_servers(&db, &user_id, page_size + 1, offset_id)
-            .await
-            .unwrap();
-
-        let next_page_token = if list.len() > usize::try_from(page_size).unwrap() {
-            list.pop();
-            let next_page_token = list.generate_page_token(page_size, request.page_token);
-            next_page_token.map(|token| {
-                let mut pb_buf = vec![];
-                let _ = token.encode(&mut pb_buf);
-
-                base64_encoder::encode_string(pb_buf)
-            })
-        } else {
-            None
-        };
+        let page = self
+            .pager
+            .list(
+                ListRequest::new(
+                    format!("users/{}", user_id),
+                    request.page_size,
+                    request.page_token,
+                    request.order_by,
+                ),
+                &[CREATE_TIME],
+                |page| {
+                    self.server_repository
+                        .get_joined_servers(&db, &user_id, page)
+                },
+            )
+            .await?;
 
-        let servers: Vec<Server> = list.iter().map(|item| item.clone().to_message()).collect();
+        let servers: Vec<Server> = page
+            .items
+            .into_iter()
+            .map(|item| item.to_message())
+            .collect();
 
         let res = ListMeServersResponse {
             servers,
-            next_page_token,
-            prev_page_token,
+            next_page_token: page.next_page_token,
+            prev_page_token: page.prev_page_token,
         };
 
         Ok(Response::new(res))
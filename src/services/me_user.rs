@@ -1,52 +1,183 @@
-use surrealdb::engine::remote::ws::Client;
-use surrealdb::Surreal;
+use surrealdb::sql::Datetime;
 use tonic::{Request, Response, Status};
 
-use crate::db::surreal::conn;
+use crate::db::traits::attachment::AttachmentRepository;
 use crate::db::traits::user::UserRepository;
+use crate::db::Database;
+use crate::storage::BlobStore;
 
+use super::attachment::{create_square_image, release_attachment};
+use super::error::ServiceError;
 use super::ycchat::v1::models::User;
 use super::ycchat::v1::services::me::user::{
-    me_user_service_server::MeUserService as MeUserServer, GetMeRequest,
+    me_user_service_server::MeUserService as MeUserServer, GetMeRequest, GetMySettingsRequest,
+    UpdateAvatarRequest, UpdateMySettingsRequest, UserSettings as UserSettingsMessage,
 };
 
-use crate::models::user::UserId;
+use crate::models::user::{UserId, UserSettings};
 
-pub struct MeUserService<U>
+pub struct MeUserService<DB, U, A, B>
 where
-    U: UserRepository<Surreal<Client>>,
+    DB: Database,
+    U: UserRepository<DB>,
+    A: AttachmentRepository<DB>,
+    B: BlobStore,
 {
+    db: DB,
     user_repository: U,
+    attachment_repository: A,
+    blob_store: B,
 }
 
-impl<U> MeUserService<U>
+impl<DB, U, A, B> MeUserService<DB, U, A, B>
 where
-    U: UserRepository<Surreal<Client>>,
+    DB: Database,
+    U: UserRepository<DB>,
+    A: AttachmentRepository<DB>,
+    B: BlobStore,
 {
-    pub async fn new(user_repository: U) -> Self {
-        MeUserService { user_repository }
+    pub async fn new(db: DB, user_repository: U, attachment_repository: A, blob_store: B) -> Self {
+        MeUserService {
+            db,
+            user_repository,
+            attachment_repository,
+            blob_store,
+        }
     }
 }
 
 #[tonic::async_trait]
-impl<U> MeUserServer for MeUserService<U>
+impl<DB, U, A, B> MeUserServer for MeUserService<DB, U, A, B>
 where
-    U: UserRepository<Surreal<Client>> + 'static,
+    DB: Database,
+    U: UserRepository<DB> + 'static,
+    A: AttachmentRepository<DB> + 'static,
+    B: BlobStore + 'static,
 {
     async fn get_me(&self, request: Request<GetMeRequest>) -> Result<Response<User>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
 
         let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
         let user_id = UserId::from_string(user_id).unwrap();
 
-        let user = self.user_repository.get_user(&db, &user_id).await.unwrap();
+        let user = self.user_repository.get_user(&db, &user_id).await?;
         let user = match user {
             Some(user) => user,
             None => {
-                return Err(Status::not_found("not exist"));
+                return Err(ServiceError::not_found("not exist").into());
             }
         };
 
         Ok(Response::new(user.to_message()))
     }
+
+    async fn update_avatar(
+        &self,
+        request: Request<UpdateAvatarRequest>,
+    ) -> Result<Response<User>, Status> {
+        let db = self.db.clone();
+
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
+
+        let avatar = request.into_inner().avatar; // attachments/{attachmentId}, clear when empty.
+
+        let mut user = match self.user_repository.get_user(&db, &user_id).await? {
+            Some(user) => user,
+            None => return Err(ServiceError::not_found("not exist").into()),
+        };
+
+        let avatar = match avatar {
+            Some(avatar) => Some(
+                create_square_image(
+                    &db,
+                    &self.attachment_repository,
+                    &self.blob_store,
+                    &user_id,
+                    &avatar,
+                )
+                .await?,
+            ),
+            None => None,
+        };
+
+        let previous = std::mem::replace(&mut user.avatar, avatar.clone());
+        user.update_time = Some(Datetime::default());
+
+        let res = self.user_repository.update_user(&db, &user).await;
+
+        // the new avatar is left unused when nothing was updated.
+        if !matches!(res, Ok(Some(_))) {
+            if let Some(avatar) = avatar {
+                release_attachment(&db, &self.attachment_repository, &self.blob_store, &avatar)
+                    .await
+                    .unwrap_or_else(|err| err.log());
+            }
+        }
+
+        let res = match res? {
+            Some(res) => res,
+            None => return Err(ServiceError::not_found("user not found.").into()),
+        };
+
+        if let Some(previous) = previous {
+            release_attachment(
+                &db,
+                &self.attachment_repository,
+                &self.blob_store,
+                &previous,
+            )
+            .await
+            .unwrap_or_else(|err| err.log());
+        }
+
+        Ok(Response::new(res.to_message()))
+    }
+
+    async fn get_my_settings(
+        &self,
+        request: Request<GetMySettingsRequest>,
+    ) -> Result<Response<UserSettingsMessage>, Status> {
+        let db = self.db.clone();
+
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
+
+        let user = match self.user_repository.get_user(&db, &user_id).await? {
+            Some(user) => user,
+            None => return Err(ServiceError::not_found("not exist").into()),
+        };
+
+        Ok(Response::new(user.settings.to_message()))
+    }
+
+    async fn update_my_settings(
+        &self,
+        request: Request<UpdateMySettingsRequest>,
+    ) -> Result<Response<UserSettingsMessage>, Status> {
+        let db = self.db.clone();
+
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
+
+        let settings = match request.into_inner().settings {
+            Some(settings) => settings,
+            None => return Err(ServiceError::invalid_field("settings", "required.").into()),
+        };
+
+        let mut user = match self.user_repository.get_user(&db, &user_id).await? {
+            Some(user) => user,
+            None => return Err(ServiceError::not_found("not exist").into()),
+        };
+
+        user.settings = UserSettings::from(settings);
+        user.update_time = Some(Datetime::default());
+
+        let res = match self.user_repository.update_user(&db, &user).await {
+            Ok(Some(res)) => res,
+            _ => return Err(ServiceError::internal("internal error").into()),
+        };
+
+        Ok(Response::new(res.settings.to_message()))
+    }
 }
@@ -1,112 +1,434 @@
-use prost::Message as _;
-use surrealdb::{engine::remote::ws::Client, Surreal};
+use chrono::Timelike;
+use futures::lock::Mutex;
+use prost_types::Timestamp;
+use std::sync::Arc;
+use surrealdb::sql::Datetime;
 use tonic::{Request, Response, Status};
 
 use crate::{
+    chat::broadcaster::Broadcaster,
+    config::LimitsConfig,
     db::{
-        surreal::conn,
+        filter::Filter,
         traits::{
-            channel::ChannelRepository, message::MessageRepository,
-            message_acknowledge::MessageAcknowledgeRepository,
-            server_member::ServerMemberRepository,
+            attachment::AttachmentRepository, channel::ChannelRepository,
+            mention::MentionRepository, message::MessageRepository,
+            message_acknowledge::MessageAcknowledgeRepository, message_search::MessageSearchIndex,
+            server::ServerRepository, server_member::ServerMemberRepository, user::UserRepository,
         },
+        Database,
     },
     models::{
-        channel::{ChannelId, ChannelType},
-        message::MessageId,
+        attachment::AttachmentId,
+        channel::{ChannelType, DbChannel},
+        message::{DbMessage, MessageId, MessageType, FILTER_FIELDS},
         message_acknowledge::DbMessageAcknowledge,
+        message_search::MessageSearchQuery,
         user::UserId,
     },
-    util::{self, pager::PageTokenizer},
+    util::{
+        pager::{ListRequest, Pager, CREATE_TIME},
+        resource_name::{ChannelName, MessageName, ServerName, UserName},
+    },
 };
 
+use super::error::ServiceError;
 use super::{
+    channel::{get_channel_members, is_channel_manager, is_channel_member},
     ycchat::v1::models::Message,
+    ycchat::v1::services::connect::{
+        server_signal::Payload, ChannelPinsUpdated, ChannelReceiveMessage, MessageRead,
+    },
     ycchat::v1::services::message::{
         message_service_server::MessageService as ProtoMessageService, AcknowledgeMessageRequest,
-        DeleteMessageRequest, ListMessagesRequest, ListMessagesResponse, UpdateMessageRequest,
+        BatchAcknowledgeMessagesRequest, DeleteMessageRequest, ListMessageReadersRequest,
+        ListMessageReadersResponse, ListMessagesRequest, ListMessagesResponse,
+        ListPinnedMessagesRequest, ListPinnedMessagesResponse, MessageSearchResult,
+        PinMessageRequest, SearchMessagesRequest, SearchMessagesResponse, UnpinMessageRequest,
+        UpdateMessageRequest,
     },
 };
 
-pub struct MessageService<M, ACK, SM, CH>
+pub struct MessageService<DB, M, ACK, MN, SM, CH, A, S, SI, U>
 where
-    M: MessageRepository<Surreal<Client>>,
-    ACK: MessageAcknowledgeRepository<Surreal<Client>>,
-    SM: ServerMemberRepository<Surreal<Client>>,
-    CH: ChannelRepository<Surreal<Client>>,
+    DB: Database,
+    M: MessageRepository<DB>,
+    ACK: MessageAcknowledgeRepository<DB>,
+    MN: MentionRepository<DB>,
+    SM: ServerMemberRepository<DB>,
+    CH: ChannelRepository<DB>,
+    A: AttachmentRepository<DB>,
+    S: ServerRepository<DB>,
+    SI: MessageSearchIndex<DB>,
+    U: UserRepository<DB>,
 {
+    db: DB,
     channel_repository: CH,
     message_repository: M,
     server_member_repository: SM,
     message_acknowledge_repository: ACK,
+    mention_repository: MN,
+    attachment_repository: A,
+    server_repository: S,
+    message_search_index: SI,
+    user_repository: U,
+    broadcaster: Arc<Mutex<Broadcaster>>,
+    limits: LimitsConfig,
+    pager: Pager,
 }
 
-impl<M, ACK, SM, CH> MessageService<M, ACK, SM, CH>
+impl<DB, M, ACK, MN, SM, CH, A, S, SI, U> MessageService<DB, M, ACK, MN, SM, CH, A, S, SI, U>
 where
-    M: MessageRepository<Surreal<Client>>,
-    ACK: MessageAcknowledgeRepository<Surreal<Client>>,
-    SM: ServerMemberRepository<Surreal<Client>>,
-    CH: ChannelRepository<Surreal<Client>>,
+    DB: Database,
+    M: MessageRepository<DB>,
+    ACK: MessageAcknowledgeRepository<DB>,
+    MN: MentionRepository<DB>,
+    SM: ServerMemberRepository<DB>,
+    CH: ChannelRepository<DB>,
+    A: AttachmentRepository<DB>,
+    S: ServerRepository<DB>,
+    SI: MessageSearchIndex<DB>,
+    U: UserRepository<DB>,
 {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
+        db: DB,
         message_repository: M,
         message_acknowledge_repository: ACK,
+        mention_repository: MN,
         server_member_repository: SM,
         channel_repository: CH,
+        attachment_repository: A,
+        server_repository: S,
+        message_search_index: SI,
+        user_repository: U,
+        broadcaster: Arc<Mutex<Broadcaster>>,
+        limits: LimitsConfig,
+        pager: Pager,
     ) -> Self {
         MessageService {
+            db,
             message_repository,
             server_member_repository,
             channel_repository,
             message_acknowledge_repository,
+            mention_repository,
+            attachment_repository,
+            server_repository,
+            message_search_index,
+            user_repository,
+            broadcaster,
+            limits,
+            pager,
+        }
+    }
+
+    /// `name` is channels/{channelId}/messages/{messageId}
+    async fn get_channel_message(
+        &self,
+        db: &DB,
+        name: &str,
+    ) -> Result<(DbChannel, DbMessage), Status> {
+        let MessageName {
+            channel: channel_id,
+            message: message_id,
+        } = MessageName::parse(name)?;
+
+        let channel = match self.channel_repository.get(db, &channel_id).await? {
+            Some(channel) => channel,
+            None => return Err(ServiceError::not_found("channel not found.").into()),
+        };
+
+        let message = match self.message_repository.get(db, &message_id).await? {
+            Some(message) if message.channel == channel_id => message,
+            _ => return Err(ServiceError::not_found("message not found.").into()),
+        };
+
+        Ok((channel, message))
+    }
+
+    /// sends `system_message` and the latest pin time to everyone in the channel.
+    async fn notify_pins_updated(
+        &self,
+        db: &DB,
+        channel: &DbChannel,
+        system_message: Option<Message>,
+    ) -> Result<(), Status> {
+        let pinned = self
+            .message_repository
+            .get_pinned_list_by_channel_id(db, &channel.id)
+            .await?;
+
+        let last_pin_time = pinned
+            .first()
+            .and_then(|message| message.pin_time.clone())
+            .map(|pin_time| Timestamp {
+                seconds: pin_time.timestamp(),
+                nanos: pin_time.nanosecond() as i32,
+            });
+
+        let members = get_channel_members(db, &self.server_member_repository, channel).await?;
+
+        let broadcaster = self.broadcaster.lock().await;
+        if let Some(system_message) = system_message {
+            let channel_receive_message = Payload::ChannelReceiveMessage(ChannelReceiveMessage {
+                message: Some(system_message),
+            });
+            broadcaster
+                .send_signal(&members, channel_receive_message)
+                .await;
+        }
+
+        let channel_pins_updated = Payload::ChannelPinsUpdated(ChannelPinsUpdated {
+            channel: ChannelName(channel.id).to_string(),
+            last_pin_time,
+        });
+        broadcaster
+            .send_signal(&members, channel_pins_updated)
+            .await;
+
+        Ok(())
+    }
+
+    /// whether `user_id` shares read receipts, which also lets them see others'.
+    async fn is_read_receipts_enabled(&self, db: &DB, user_id: &UserId) -> Result<bool, Status> {
+        let user = self.user_repository.get_user(db, user_id).await?;
+
+        Ok(user.is_some_and(|user| user.settings.read_receipts))
+    }
+
+    /// records that `user_id` read `messages` of `channel`. own messages and messages read
+    /// before are skipped, nothing is recorded while the user keeps read receipts off.
+    async fn acknowledge_messages(
+        &self,
+        db: &DB,
+        channel: &DbChannel,
+        user_id: &UserId,
+        messages: Vec<DbMessage>,
+    ) -> Result<(), Status> {
+        if !self.is_read_receipts_enabled(db, user_id).await? {
+            return Ok(());
+        }
+
+        let messages = messages
+            .into_iter()
+            .filter(|message| message.author != *user_id)
+            .collect::<Vec<DbMessage>>();
+
+        let message_ids = messages
+            .iter()
+            .map(|message| message.id)
+            .collect::<Vec<MessageId>>();
+
+        let exists = self
+            .message_acknowledge_repository
+            .get_list_by_user_and_messages(db, user_id, &message_ids)
+            .await?;
+
+        let messages = messages
+            .into_iter()
+            .filter(|message| !exists.iter().any(|exist| exist.message_id == message.id))
+            .collect::<Vec<DbMessage>>();
+
+        if messages.is_empty() {
+            return Ok(());
         }
+
+        let message_acknowledges = messages
+            .iter()
+            .map(|message| DbMessageAcknowledge::new(message.id, *user_id))
+            .collect::<Vec<DbMessageAcknowledge>>();
+
+        self.message_acknowledge_repository
+            .add_list(db, &message_acknowledges)
+            .await?;
+
+        // live receipts only where every read matters, direct and small group channels.
+        if !matches!(
+            channel.channel_type,
+            ChannelType::Direct | ChannelType::Group { .. }
+        ) {
+            return Ok(());
+        }
+
+        let read_time = message_acknowledges[0].create_time.clone();
+        let read_time = Timestamp {
+            seconds: read_time.timestamp(),
+            nanos: read_time.nanosecond() as i32,
+        };
+
+        let mut authors = messages
+            .iter()
+            .map(|message| message.author)
+            .collect::<Vec<UserId>>();
+        authors.sort();
+        authors.dedup();
+
+        let broadcaster = self.broadcaster.lock().await;
+        for author in authors.into_iter() {
+            let message_read = Payload::MessageRead(MessageRead {
+                channel: ChannelName(channel.id).to_string(),
+                user: UserName(*user_id).to_string(),
+                messages: messages
+                    .iter()
+                    .filter(|message| message.author == author)
+                    .map(|message| {
+                        MessageName {
+                            channel: channel.id,
+                            message: message.id,
+                        }
+                        .to_string()
+                    })
+                    .collect(),
+                read_time: Some(read_time.clone()),
+            });
+
+            broadcaster.send_signal(&[author], message_read).await;
+        }
+
+        Ok(())
     }
 }
 
 #[tonic::async_trait]
-impl<M, ACK, SM, CH> ProtoMessageService for MessageService<M, ACK, SM, CH>
+impl<DB, M, ACK, MN, SM, CH, A, S, SI, U> ProtoMessageService
+    for MessageService<DB, M, ACK, MN, SM, CH, A, S, SI, U>
 where
-    M: MessageRepository<Surreal<Client>> + 'static,
-    ACK: MessageAcknowledgeRepository<Surreal<Client>> + 'static,
-    SM: ServerMemberRepository<Surreal<Client>> + 'static,
-    CH: ChannelRepository<Surreal<Client>> + 'static,
+    DB: Database,
+    M: MessageRepository<DB> + 'static,
+    ACK: MessageAcknowledgeRepository<DB> + 'static,
+    MN: MentionRepository<DB> + 'static,
+    SM: ServerMemberRepository<DB> + 'static,
+    CH: ChannelRepository<DB> + 'static,
+    A: AttachmentRepository<DB> + 'static,
+    S: ServerRepository<DB> + 'static,
+    SI: MessageSearchIndex<DB> + 'static,
+    U: UserRepository<DB> + 'static,
 {
     async fn acknowledge_message(
         &self,
         request: Request<AcknowledgeMessageRequest>,
     ) -> Result<Response<()>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
 
         let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
-        let user_id = UserId::from_string(&user_id).unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
+
+        let name = request.into_inner().name; // channels/{channelId}/messages/{messageId}
+        let (channel, message) = self.get_channel_message(&db, &name).await?;
+
+        if !is_channel_member(&db, &self.server_member_repository, &channel, &user_id).await? {
+            return Err(ServiceError::permission_denied("permission denied.").into());
+        }
+
+        // acknowledging a message twice is a no-op.
+        self.acknowledge_messages(&db, &channel, &user_id, vec![message])
+            .await?;
+
+        Ok(Response::new(()))
+    }
+
+    async fn batch_acknowledge_messages(
+        &self,
+        request: Request<BatchAcknowledgeMessagesRequest>,
+    ) -> Result<Response<()>, Status> {
+        let db = self.db.clone();
+
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
 
         let req = request.into_inner();
-        let name = req.name; // servers/{serverId}/members/{serverMemberId}
-        let name = name.split('/').collect::<Vec<&str>>();
+        let channel_id = ChannelName::parse(&req.parent)?.0;
+        let start = MessageName::parse(&req.start_message)?;
+        let end = MessageName::parse(&req.end_message)?;
+        let (start_id, end_id) = (start.message, end.message);
 
-        let message_id = MessageId::from_string(name[1]).unwrap();
+        if start.channel != channel_id || end.channel != channel_id || start_id > end_id {
+            return Err(ServiceError::invalid_argument(
+                "start_message and end_message must be an ordered range of the channel.",
+            )
+            .into());
+        }
 
-        let exist = self
-            .message_acknowledge_repository
-            .get_by_message_and_user(&db, &message_id, &user_id)
-            .await
-            .unwrap();
+        let channel = match self.channel_repository.get(&db, &channel_id).await? {
+            Some(channel) => channel,
+            None => return Err(ServiceError::not_found("channel not found.").into()),
+        };
 
-        if exist.is_some() {
-            return Err(Status::already_exists("already exist."));
+        if !is_channel_member(&db, &self.server_member_repository, &channel, &user_id).await? {
+            return Err(ServiceError::permission_denied("permission denied.").into());
         }
 
-        self.message_acknowledge_repository
-            .add(&db, &DbMessageAcknowledge::new(message_id, user_id))
-            .await
-            .unwrap();
+        // only the newest messages of a long range are acknowledged.
+        let messages = self
+            .message_repository
+            .get_list_by_channel_id_in_range(
+                &db,
+                &channel.id,
+                &start_id,
+                &end_id,
+                self.limits.max_acknowledge_batch_size,
+            )
+            .await?;
+
+        self.acknowledge_messages(&db, &channel, &user_id, messages)
+            .await?;
 
         Ok(Response::new(()))
     }
 
+    async fn list_message_readers(
+        &self,
+        request: Request<ListMessageReadersRequest>,
+    ) -> Result<Response<ListMessageReadersResponse>, Status> {
+        let db = self.db.clone();
+
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
+
+        let request = request.into_inner();
+        // channels/{channelId}/messages/{messageId}
+        let (channel, message) = self.get_channel_message(&db, &request.parent).await?;
+
+        if !is_channel_member(&db, &self.server_member_repository, &channel, &user_id).await? {
+            return Err(ServiceError::permission_denied("permission denied.").into());
+        }
+
+        if !self.is_read_receipts_enabled(&db, &user_id).await? {
+            return Err(ServiceError::permission_denied("read receipts are turned off.").into());
+        }
+
+        let page = self
+            .pager
+            .list(
+                ListRequest::new(
+                    request.parent,
+                    request.page_size,
+                    request.page_token,
+                    request.order_by,
+                ),
+                &[CREATE_TIME],
+                |page| {
+                    self.message_acknowledge_repository
+                        .get_list_by_message(&db, &message.id, page)
+                },
+            )
+            .await?;
+
+        Ok(Response::new(ListMessageReadersResponse {
+            readers: page
+                .items
+                .into_iter()
+                .map(|reader| reader.to_message())
+                .collect(),
+            next_page_token: page.next_page_token,
+            prev_page_token: page.prev_page_token,
+        }))
+    }
+
     async fn update_message(
         &self,
-        request: Request<UpdateMessageRequest>,
+        _request: Request<UpdateMessageRequest>,
     ) -> Result<Response<Message>, Status> {
         todo!()
     }
@@ -115,32 +437,38 @@ where
         &self,
         request: Request<DeleteMessageRequest>,
     ) -> Result<Response<()>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
 
         let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
-        let user_id = UserId::from_string(&user_id).unwrap();
-
-        let req = request.into_inner();
-        let name = req.name; // servers/{serverId}/members/{serverMemberId}
-        let name = name.split('/').collect::<Vec<&str>>();
-
-        let message_id = MessageId::from_string(name[1]).unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
 
-        let exist = self.message_repository.get(&db, &message_id).await.unwrap();
+        let name = request.into_inner().name; // channels/{channelId}/messages/{messageId}
+        let (channel, message) = self.get_channel_message(&db, &name).await?;
+        let message_id = message.id;
 
-        match exist {
-            Some(exist) => {
-                if exist.author != user_id {
-                    return Err(Status::permission_denied("no permission"));
-                }
-            }
-            None => return Err(Status::not_found("message not found.")),
+        if message.author != user_id {
+            return Err(ServiceError::permission_denied("no permission").into());
         }
 
+        let mut transaction = DB::Transaction::default();
+
+        self.message_acknowledge_repository
+            .delete_by_message_id_in(&mut transaction, &message_id)?;
+        self.mention_repository
+            .delete_by_message_id_in(&mut transaction, &message_id)?;
         self.message_repository
-            .delete(&db, &message_id)
-            .await
-            .unwrap();
+            .delete_in(&mut transaction, &message_id)?;
+
+        db.commit(transaction).await?;
+
+        self.message_search_index
+            .remove_message(&db, &message_id)
+            .await?;
+
+        // the pinned messages of the channel changed with it.
+        if message.pin_time.is_some() {
+            self.notify_pins_updated(&db, &channel, None).await?;
+        }
 
         Ok(Response::new(()))
     }
@@ -149,90 +477,571 @@ where
         &self,
         request: Request<ListMessagesRequest>,
     ) -> Result<Response<ListMessagesResponse>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
 
         let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
         let user_id = UserId::from_string(user_id).unwrap();
 
         let request = request.into_inner();
         let name = request.parent;
-        let page_token = match request.page_token.clone() {
-            Some(page_token) => {
-                let page_token = util::pager::get_page_token(page_token);
+        let channel_id = ChannelName::parse(&name)?.0;
+
+        let filter = request.filter.unwrap_or_default();
+        let parsed_filter = Filter::parse(&filter, FILTER_FIELDS)?;
 
-                Some(page_token.unwrap())
-            }
-            None => None,
+        let channel = match self.channel_repository.get(&db, &channel_id).await? {
+            Some(channel) => channel,
+            None => return Err(ServiceError::not_found("invalid arguments.").into()),
         };
 
-        let (page_size, offset_id, prev_page_token) = match page_token {
-            Some(page_token) => (
-                page_token.page_size,
-                page_token
-                    .offset_id
-                    .map(|offset_id| MessageId::from_string(&offset_id).unwrap()),
-                page_token.prev_page_token,
-            ),
-            None => (request.page_size, None, None),
+        let is_have_permission: bool =
+            is_channel_member(&db, &self.server_member_repository, &channel, &user_id).await?;
+
+        if !is_have_permission {
+            return Err(ServiceError::permission_denied("permission denied.").into());
+        }
+
+        let page = self
+            .pager
+            .list(
+                ListRequest::new(
+                    name,
+                    request.page_size,
+                    request.page_token,
+                    request.order_by,
+                )
+                .filter(filter),
+                &[CREATE_TIME],
+                |page| {
+                    self.message_repository.get_list_by_chnanel_id(
+                        &db,
+                        &channel_id,
+                        &parsed_filter,
+                        page,
+                    )
+                },
+            )
+            .await?;
+
+        let attachment_ids = page
+            .items
+            .iter()
+            .flat_map(|message| message.attachments.clone())
+            .collect::<Vec<AttachmentId>>();
+
+        let attachments = self
+            .attachment_repository
+            .get_attachments(&db, &attachment_ids)
+            .await?;
+
+        let list_message_response = ListMessagesResponse {
+            messages: page
+                .items
+                .into_iter()
+                .map(|message| message.to_message(&attachments))
+                .collect(),
+            next_page_token: page.next_page_token,
+            prev_page_token: page.prev_page_token,
         };
 
-        let channel_id = ChannelId::from_string(name.split('/').collect::<Vec<&str>>()[1]).unwrap();
-        let channel = match self.channel_repository.get(&db, &channel_id).await.unwrap() {
-            Some(channel) => channel,
-            None => return Err(Status::not_found("invalid arguments.")),
+        Ok(Response::new(list_message_response))
+    }
+
+    async fn pin_message(
+        &self,
+        request: Request<PinMessageRequest>,
+    ) -> Result<Response<()>, Status> {
+        let db = self.db.clone();
+
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
+
+        let name = request.into_inner().name; // channels/{channelId}/messages/{messageId}
+        let (channel, mut message) = self.get_channel_message(&db, &name).await?;
+
+        if !is_channel_manager(&db, &self.server_repository, &channel, &user_id).await? {
+            return Err(ServiceError::permission_denied("permission denied.").into());
+        }
+
+        if message.message_type != MessageType::Default {
+            return Err(
+                ServiceError::failed_precondition("system messages can't be pinned.").into(),
+            );
+        }
+
+        if message.pin_time.is_some() {
+            return Err(ServiceError::already_exists("already pinned.").into());
+        }
+
+        let pinned = self
+            .message_repository
+            .get_pinned_list_by_channel_id(&db, &channel.id)
+            .await?;
+
+        if pinned.len() >= self.limits.max_pinned_messages {
+            return Err(ServiceError::failed_precondition(format!(
+                "a channel can't have more than {} pinned messages.",
+                self.limits.max_pinned_messages
+            ))
+            .into());
+        }
+
+        message.pinned_by = Some(user_id);
+        message.pin_time = Some(Datetime::default());
+
+        if self
+            .message_repository
+            .update(&db, &message)
+            .await?
+            .is_none()
+        {
+            return Err(ServiceError::internal("internal error").into());
+        }
+
+        self.message_search_index
+            .index_message(&db, &message)
+            .await?;
+
+        let system_message = DbMessage::new_system(
+            user_id,
+            channel.id,
+            MessageType::ChannelPinnedMessage,
+            Some(message.id),
+        );
+
+        let system_message = match self.message_repository.add(&db, &system_message).await? {
+            Some(system_message) => system_message.to_message(&[]),
+            None => return Err(ServiceError::internal("internal error").into()),
         };
 
-        let is_have_permission: bool = match channel.channel_type {
-            ChannelType::Saved { owner } => owner == user_id,
-            ChannelType::Direct => todo!(),
-            ChannelType::Server { server } => {
-                let server_member = self
-                    .server_member_repository
-                    .get_server_member_by_server_id_and_user_id(&db, &server, &user_id)
-                    .await
-                    .unwrap();
-
-                server_member.is_some()
-            }
+        self.notify_pins_updated(&db, &channel, Some(system_message))
+            .await?;
+
+        Ok(Response::new(()))
+    }
+
+    async fn unpin_message(
+        &self,
+        request: Request<UnpinMessageRequest>,
+    ) -> Result<Response<()>, Status> {
+        let db = self.db.clone();
+
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
+
+        let name = request.into_inner().name; // channels/{channelId}/messages/{messageId}
+        let (channel, mut message) = self.get_channel_message(&db, &name).await?;
+
+        if !is_channel_manager(&db, &self.server_repository, &channel, &user_id).await? {
+            return Err(ServiceError::permission_denied("permission denied.").into());
+        }
+
+        if message.pin_time.is_none() {
+            return Err(ServiceError::failed_precondition("message is not pinned.").into());
+        }
+
+        message.pinned_by = None;
+        message.pin_time = None;
+
+        if self
+            .message_repository
+            .update(&db, &message)
+            .await?
+            .is_none()
+        {
+            return Err(ServiceError::internal("internal error").into());
+        }
+
+        self.message_search_index
+            .index_message(&db, &message)
+            .await?;
+
+        self.notify_pins_updated(&db, &channel, None).await?;
+
+        Ok(Response::new(()))
+    }
+
+    async fn list_pinned_messages(
+        &self,
+        request: Request<ListPinnedMessagesRequest>,
+    ) -> Result<Response<ListPinnedMessagesResponse>, Status> {
+        let db = self.db.clone();
+
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
+
+        let parent = request.into_inner().parent; // channels/{channelId}
+        let channel_id = ChannelName::parse(&parent)?.0;
+
+        let channel = match self.channel_repository.get(&db, &channel_id).await? {
+            Some(channel) => channel,
+            None => return Err(ServiceError::not_found("channel not found.").into()),
         };
 
-        if !is_have_permission {
-            return Err(Status::permission_denied("permission denied."));
+        if !is_channel_member(&db, &self.server_member_repository, &channel, &user_id).await? {
+            return Err(ServiceError::permission_denied("permission denied.").into());
         }
 
         let message_list = self
             .message_repository
-            .get_list_by_chnanel_id(&db, &channel_id, page_size + 1, offset_id)
-            .await;
+            .get_pinned_list_by_channel_id(&db, &channel_id)
+            .await?;
+
+        let attachment_ids = message_list
+            .iter()
+            .flat_map(|message| message.attachments.clone())
+            .collect::<Vec<AttachmentId>>();
+
+        let attachments = self
+            .attachment_repository
+            .get_attachments(&db, &attachment_ids)
+            .await?;
+
+        Ok(Response::new(ListPinnedMessagesResponse {
+            messages: message_list
+                .into_iter()
+                .map(|message| message.to_message(&attachments))
+                .collect(),
+        }))
+    }
+
+    async fn search_messages(
+        &self,
+        request: Request<SearchMessagesRequest>,
+    ) -> Result<Response<SearchMessagesResponse>, Status> {
+        let db = self.db.clone();
 
-        let mut message_list = match message_list {
-            Ok(message_list) => message_list,
-            Err(err) => return Err(Status::internal("message list internal server")),
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
+
+        let request = request.into_inner();
+
+        // empty for every readable channel, servers/{serverId} or channels/{channelId}
+        let parent = request.parent;
+
+        let mut channels = self
+            .channel_repository
+            .get_channels_by_user_id(&db, &user_id)
+            .await?;
+
+        if parent.starts_with("servers/") {
+            let server_id = ServerName::parse(&parent)?.0;
+
+            channels.retain(|channel| match channel.channel_type {
+                ChannelType::Server { server } => server == server_id,
+                _ => false,
+            });
+        } else if !parent.is_empty() {
+            let channel_id = ChannelName::parse(&parent)?.0;
+
+            channels.retain(|channel| channel.id == channel_id);
+        }
+
+        if !parent.is_empty() && channels.is_empty() {
+            return Err(ServiceError::permission_denied("permission denied.").into());
+        }
+
+        let to_datetime = |timestamp: Timestamp| {
+            chrono::DateTime::from_timestamp(timestamp.seconds, timestamp.nanos as u32)
+                .map(Datetime::from)
+        };
+
+        let query = MessageSearchQuery {
+            query: request.query.trim().to_string(),
+            channels: channels.iter().map(|channel| channel.id).collect(),
+            author: match &request.author {
+                Some(author) => Some(UserName::parse(author)?.0),
+                None => None,
+            },
+            mentions: match &request.mentions {
+                Some(mentions) => Some(UserName::parse(mentions)?.0),
+                None => None,
+            },
+            has_attachment: request.has_attachment,
+            start_time: request.start_time.and_then(to_datetime),
+            end_time: request.end_time.and_then(to_datetime),
+            pinned: request.pinned,
         };
 
-        let next_page_token = if message_list.len() > usize::try_from(page_size).unwrap() {
-            message_list.pop();
+        // a page token only continues the search it came from.
+        let page = self
+            .pager
+            .list(
+                ListRequest::new(parent, request.page_size, request.page_token, None)
+                    .filter(query.to_filter()),
+                &[CREATE_TIME],
+                |page| self.message_search_index.search(&db, &query, page),
+            )
+            .await?;
 
-            let next_page_token = message_list.generate_page_token(page_size, request.page_token);
-            next_page_token.map(|token| {
-                let mut pb_buf = vec![];
-                let _ = token.encode(&mut pb_buf);
+        let message_ids = page
+            .items
+            .iter()
+            .map(|hit| hit.id)
+            .collect::<Vec<MessageId>>();
 
-                crate::util::base64_encoder::encode_string(pb_buf)
+        let message_list = self
+            .message_repository
+            .get_list_by_ids(&db, &message_ids)
+            .await?;
+
+        let attachment_ids = message_list
+            .iter()
+            .flat_map(|message| message.attachments.clone())
+            .collect::<Vec<AttachmentId>>();
+
+        let attachments = self
+            .attachment_repository
+            .get_attachments(&db, &attachment_ids)
+            .await?;
+
+        let results = page
+            .items
+            .iter()
+            .filter_map(|hit| {
+                message_list
+                    .iter()
+                    .find(|message| message.id == hit.id)
+                    .map(|message| MessageSearchResult {
+                        message: Some(message.clone().to_message(&attachments)),
+                        snippet: hit.snippet(),
+                    })
             })
-        } else {
-            None
+            .collect();
+
+        Ok(Response::new(SearchMessagesResponse {
+            results,
+            next_page_token: page.next_page_token,
+            prev_page_token: page.prev_page_token,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc::{self, Receiver};
+    use tonic::Code;
+
+    use super::*;
+    use crate::{
+        chat::broadcaster::{Stream, STREAM_BUFFER_SIZE},
+        db::memory::{self, MemoryDb},
+        models::mention::Mentions,
+        services::{
+            testing::{pager, request},
+            ycchat::v1::services::connect::ConnectResponse,
+        },
+    };
+
+    async fn service(
+        db: &MemoryDb,
+        broadcaster: &Arc<Mutex<Broadcaster>>,
+        limits: LimitsConfig,
+    ) -> impl ProtoMessageService {
+        let repositories = memory::repositories().await;
+
+        MessageService::new(
+            db.clone(),
+            repositories.message,
+            repositories.message_acknowledge,
+            repositories.mention,
+            repositories.server_member,
+            repositories.channel,
+            repositories.attachment,
+            repositories.server,
+            repositories.message_search,
+            repositories.user,
+            broadcaster.clone(),
+            limits,
+            pager(),
+        )
+    }
+
+    async fn add_direct_channel(db: &MemoryDb, user: UserId, other: UserId) -> DbChannel {
+        let channel = DbChannel::new_direct(user, other);
+        let repositories = memory::repositories().await;
+        repositories.channel.add(db, &channel).await.unwrap();
+
+        channel
+    }
+
+    /// a message of `author` in `channel`, returns its name.
+    async fn add_message(db: &MemoryDb, channel: &DbChannel, author: UserId) -> String {
+        let message = DbMessage::new(
+            author,
+            channel.id,
+            "message".to_string(),
+            vec![],
+            Mentions::default(),
+        );
+        let repositories = memory::repositories().await;
+        repositories.message.add(db, &message).await.unwrap();
+
+        MessageName {
+            channel: channel.id,
+            message: message.id,
+        }
+        .to_string()
+    }
+
+    async fn connect(
+        broadcaster: &Arc<Mutex<Broadcaster>>,
+        user_id: UserId,
+    ) -> Receiver<ConnectResponse> {
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER_SIZE);
+        broadcaster
+            .lock()
+            .await
+            .set_stream(user_id, Stream::new(tx))
+            .await;
+
+        rx
+    }
+
+    fn pins_updated(rx: &mut Receiver<ConnectResponse>) -> Vec<ChannelPinsUpdated> {
+        std::iter::from_fn(|| rx.try_recv().ok())
+            .filter_map(|response| match response.server_signal?.payload? {
+                Payload::ChannelPinsUpdated(pins_updated) => Some(pins_updated),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn deleting_a_pinned_message_updates_the_pins() {
+        let db = MemoryDb::new();
+        let broadcaster = Arc::new(Mutex::new(Broadcaster::new()));
+        let service = service(&db, &broadcaster, LimitsConfig::default()).await;
+        let (author, other) = (UserId::new(), UserId::new());
+        let mut rx = connect(&broadcaster, other).await;
+
+        let channel = add_direct_channel(&db, author, other).await;
+        let name = add_message(&db, &channel, author).await;
+        service
+            .pin_message(request(author, PinMessageRequest { name: name.clone() }))
+            .await
+            .unwrap();
+        let pinned = pins_updated(&mut rx);
+        assert!(pinned[0].last_pin_time.is_some());
+
+        service
+            .delete_message(request(author, DeleteMessageRequest { name }))
+            .await
+            .unwrap();
+        let unpinned = pins_updated(&mut rx);
+        assert_eq!(unpinned.len(), 1);
+        assert!(unpinned[0].last_pin_time.is_none());
+    }
+
+    #[tokio::test]
+    async fn deleting_a_message_leaves_the_pins_alone() {
+        let db = MemoryDb::new();
+        let broadcaster = Arc::new(Mutex::new(Broadcaster::new()));
+        let service = service(&db, &broadcaster, LimitsConfig::default()).await;
+        let (author, other) = (UserId::new(), UserId::new());
+        let mut rx = connect(&broadcaster, other).await;
+
+        let channel = add_direct_channel(&db, author, other).await;
+        let name = add_message(&db, &channel, author).await;
+        service
+            .delete_message(request(author, DeleteMessageRequest { name }))
+            .await
+            .unwrap();
+        assert!(pins_updated(&mut rx).is_empty());
+    }
+
+    #[tokio::test]
+    async fn pinned_messages_are_announced_and_listed() {
+        let db = MemoryDb::new();
+        let broadcaster = Arc::new(Mutex::new(Broadcaster::new()));
+        let service = service(&db, &broadcaster, LimitsConfig::default()).await;
+        let (author, other) = (UserId::new(), UserId::new());
+        let mut rx = connect(&broadcaster, other).await;
+
+        let channel = add_direct_channel(&db, author, other).await;
+        let name = add_message(&db, &channel, author).await;
+        service
+            .pin_message(request(other, PinMessageRequest { name: name.clone() }))
+            .await
+            .unwrap();
+
+        // the system message, then the pin update.
+        let system_message = match rx.try_recv().unwrap().server_signal.unwrap().payload {
+            Some(Payload::ChannelReceiveMessage(received)) => received.message.unwrap(),
+            _ => panic!("expected the system message"),
         };
+        assert_eq!(
+            system_message.message_type,
+            MessageType::ChannelPinnedMessage.to_message() as i32
+        );
+        assert_eq!(pins_updated(&mut rx).len(), 1);
 
-        let list_message_response = ListMessagesResponse {
-            messages: message_list
-                .into_iter()
-                .map(|message| message.to_message())
-                .collect(),
-            next_page_token,
-            prev_page_token,
+        let pinned = service
+            .list_pinned_messages(request(
+                author,
+                ListPinnedMessagesRequest {
+                    parent: ChannelName(channel.id).to_string(),
+                },
+            ))
+            .await
+            .unwrap()
+            .into_inner()
+            .messages;
+        assert_eq!(pinned.len(), 1);
+        assert_eq!(pinned[0].name, name);
+
+        let err = service
+            .pin_message(request(author, PinMessageRequest { name }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::AlreadyExists);
+    }
+
+    #[tokio::test]
+    async fn only_participants_pin_messages() {
+        let db = MemoryDb::new();
+        let broadcaster = Arc::new(Mutex::new(Broadcaster::new()));
+        let service = service(&db, &broadcaster, LimitsConfig::default()).await;
+        let author = UserId::new();
+
+        let channel = add_direct_channel(&db, author, UserId::new()).await;
+        let name = add_message(&db, &channel, author).await;
+        let err = service
+            .pin_message(request(UserId::new(), PinMessageRequest { name }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+    }
+
+    #[tokio::test]
+    async fn pin_limit_applies_per_channel() {
+        let db = MemoryDb::new();
+        let broadcaster = Arc::new(Mutex::new(Broadcaster::new()));
+        let limits = LimitsConfig {
+            max_pinned_messages: 1,
+            ..Default::default()
         };
+        let service = service(&db, &broadcaster, limits).await;
+        let (author, other) = (UserId::new(), UserId::new());
+        let pin = |name: &String| request(author, PinMessageRequest { name: name.clone() });
 
-        Ok(Response::new(list_message_response))
+        let channel = add_direct_channel(&db, author, other).await;
+        let first = add_message(&db, &channel, author).await;
+        let second = add_message(&db, &channel, author).await;
+        service.pin_message(pin(&first)).await.unwrap();
+
+        let err = service.pin_message(pin(&second)).await.unwrap_err();
+        assert_eq!(err.code(), Code::FailedPrecondition);
+
+        // another channel has its own limit.
+        let elsewhere = add_direct_channel(&db, author, UserId::new()).await;
+        let elsewhere = add_message(&db, &elsewhere, author).await;
+        service.pin_message(pin(&elsewhere)).await.unwrap();
+
+        service
+            .unpin_message(request(author, UnpinMessageRequest { name: first }))
+            .await
+            .unwrap();
+        service.pin_message(pin(&second)).await.unwrap();
     }
 }
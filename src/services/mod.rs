@@ -1,7 +1,11 @@
 pub mod account;
+pub mod attachment;
 pub mod auth;
 pub mod channel;
 pub mod connect;
+pub mod error;
+pub mod me_channel;
+pub mod me_mention;
 pub mod me_server;
 pub mod me_user;
 pub mod message;
@@ -10,6 +14,11 @@ pub mod server_category;
 pub mod server_member;
 pub mod user;
 
+#[cfg(test)]
+mod testing;
+
+// generated from the protobuf submodule, messages the server doesn't use yet are kept.
+#[allow(dead_code)]
 pub mod ycchat {
     pub mod v1 {
         pub mod models {
@@ -29,6 +38,10 @@ pub mod ycchat {
                 tonic::include_proto!("ycchat.v1.services.account");
             }
 
+            pub mod attachment {
+                tonic::include_proto!("ycchat.v1.services.attachment");
+            }
+
             pub mod auth {
                 tonic::include_proto!("ycchat.v1.services.auth");
             }
@@ -54,6 +67,14 @@ pub mod ycchat {
             }
 
             pub mod me {
+                pub mod channel {
+                    tonic::include_proto!("ycchat.v1.services.me.channel");
+                }
+
+                pub mod mention {
+                    tonic::include_proto!("ycchat.v1.services.me.mention");
+                }
+
                 pub mod server {
                     tonic::include_proto!("ycchat.v1.services.me.server");
                 }
@@ -1,106 +1,165 @@
-use prost::Message;
-use surrealdb::{engine::remote::ws::Client, sql::Datetime, Surreal};
+use surrealdb::sql::Datetime;
 use tonic::{Request, Response, Result, Status};
 
 use crate::{
     db::{
-        surreal::conn,
-        traits::{server::ServerRepository, server_member::ServerMemberRepository},
+        filter::Filter,
+        traits::{
+            attachment::AttachmentRepository, channel::ChannelRepository,
+            mention::MentionRepository, message::MessageRepository,
+            message_acknowledge::MessageAcknowledgeRepository, read_state::ReadStateRepository,
+            server::ServerRepository, server_category::ServerCategoryRepository,
+            server_member::ServerMemberRepository,
+        },
+        Database,
     },
     models::{
-        server::{DbServer, ServerId},
+        server::{DbServer, FILTER_FIELDS},
         server_member::DbServerMember,
         user::UserId,
     },
-    util::{self, base64_encoder, pager::PageTokenizer},
+    storage::BlobStore,
+    util::{
+        pager::{ListRequest, Pager, CREATE_TIME},
+        resource_name::ServerName,
+    },
 };
 
+use super::attachment::{create_square_image, release_attachment};
+use super::channel::delete_channel_contents_in;
+use super::error::ServiceError;
+
 use super::ycchat::v1::models::{Server, ServerMember};
 use super::ycchat::v1::services::server::server_service_server::ServerService as ServerServer;
 use super::ycchat::v1::services::server::{
     CreateServerRequest, DeleteServerRequest, EnterServerRequest, GetServerRequest,
-    LeaveServerRequest, ListServersRequest, ListServersResponse, UpdateServerRequest,
+    LeaveServerRequest, ListServersRequest, ListServersResponse, UpdateServerIconRequest,
+    UpdateServerRequest,
 };
 
-pub struct ServerService<U, M>
+pub struct ServerService<DB, U, M, A, B, SC, C, MS, ACK, MN, R>
 where
-    U: ServerRepository<Surreal<Client>>,
-    M: ServerMemberRepository<Surreal<Client>>,
+    DB: Database,
+    U: ServerRepository<DB>,
+    M: ServerMemberRepository<DB>,
+    A: AttachmentRepository<DB>,
+    B: BlobStore,
+    SC: ServerCategoryRepository<DB>,
+    C: ChannelRepository<DB>,
+    MS: MessageRepository<DB>,
+    ACK: MessageAcknowledgeRepository<DB>,
+    MN: MentionRepository<DB>,
+    R: ReadStateRepository<DB>,
 {
+    db: DB,
     server_repository: U,
     server_member_repository: M,
+    attachment_repository: A,
+    blob_store: B,
+    server_category_repository: SC,
+    channel_repository: C,
+    message_repository: MS,
+    message_acknowledge_repository: ACK,
+    mention_repository: MN,
+    read_state_repository: R,
+    pager: Pager,
 }
 
-impl<U, M> ServerService<U, M>
+impl<DB, U, M, A, B, SC, C, MS, ACK, MN, R> ServerService<DB, U, M, A, B, SC, C, MS, ACK, MN, R>
 where
-    U: ServerRepository<Surreal<Client>>,
-    M: ServerMemberRepository<Surreal<Client>>,
+    DB: Database,
+    U: ServerRepository<DB>,
+    M: ServerMemberRepository<DB>,
+    A: AttachmentRepository<DB>,
+    B: BlobStore,
+    SC: ServerCategoryRepository<DB>,
+    C: ChannelRepository<DB>,
+    MS: MessageRepository<DB>,
+    ACK: MessageAcknowledgeRepository<DB>,
+    MN: MentionRepository<DB>,
+    R: ReadStateRepository<DB>,
 {
-    pub fn new(server_repository: U, server_member_repository: M) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        db: DB,
+        server_repository: U,
+        server_member_repository: M,
+        attachment_repository: A,
+        blob_store: B,
+        server_category_repository: SC,
+        channel_repository: C,
+        message_repository: MS,
+        message_acknowledge_repository: ACK,
+        mention_repository: MN,
+        read_state_repository: R,
+        pager: Pager,
+    ) -> Self {
         ServerService {
+            db,
             server_repository,
             server_member_repository,
+            attachment_repository,
+            blob_store,
+            server_category_repository,
+            channel_repository,
+            message_repository,
+            message_acknowledge_repository,
+            mention_repository,
+            read_state_repository,
+            pager,
         }
     }
 }
 
 #[tonic::async_trait]
-impl<U, M> ServerServer for ServerService<U, M>
+impl<DB, U, M, A, B, SC, C, MS, ACK, MN, R> ServerServer
+    for ServerService<DB, U, M, A, B, SC, C, MS, ACK, MN, R>
 where
-    U: ServerRepository<Surreal<Client>> + 'static,
-    M: ServerMemberRepository<Surreal<Client>> + 'static,
+    DB: Database,
+    U: ServerRepository<DB> + 'static,
+    M: ServerMemberRepository<DB> + 'static,
+    A: AttachmentRepository<DB> + 'static,
+    B: BlobStore + 'static,
+    SC: ServerCategoryRepository<DB> + 'static,
+    C: ChannelRepository<DB> + 'static,
+    MS: MessageRepository<DB> + 'static,
+    ACK: MessageAcknowledgeRepository<DB> + 'static,
+    MN: MentionRepository<DB> + 'static,
+    R: ReadStateRepository<DB> + 'static,
 {
     async fn list_servers(
         &self,
         request: Request<ListServersRequest>,
     ) -> Result<Response<ListServersResponse>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
         let request = request.into_inner();
-        let page_token = match request.page_token.clone() {
-            Some(page_token) => {
-                let page_token = util::pager::get_page_token(page_token);
-                Some(page_token.unwrap())
-            }
-            None => None,
-        };
-
-        let (page_size, offset_id, prev_page_token) = match page_token {
-            Some(page_token) => (
-                page_token.page_size,
-                page_token
-                    .offset_id
-                    .map(|offset_id| ServerId::from_string(&offset_id).unwrap()),
-                page_token.prev_page_token,
-            ),
-            None => (request.page_size, None, None),
-        };
-
-        // page_size + 1 갯수만큼 데이터 로드 후 next_page_token None, Some 처리
-        let mut list = self
-            .server_repository
-            .get_servers(&db, page_size + 1, offset_id)
-            .await
-            .unwrap();
-
-        let next_page_token = if list.len() > usize::try_from(page_size).unwrap() {
-            list.pop();
-            let next_page_token = list.generate_page_token(page_size, request.page_token);
-            next_page_token.map(|token| {
-                let mut pb_buf = vec![];
-                let _ = token.encode(&mut pb_buf);
-
-                base64_encoder::encode_string(pb_buf)
-            })
-        } else {
-            None
-        };
 
-        let servers: Vec<Server> = list.iter().map(|item| item.clone().to_message()).collect();
+        let filter = request.filter.unwrap_or_default();
+        let parsed_filter = Filter::parse(&filter, FILTER_FIELDS)?;
+
+        let page = self
+            .pager
+            .list(
+                ListRequest::new("", request.page_size, request.page_token, request.order_by)
+                    .filter(filter),
+                &[CREATE_TIME],
+                |page| {
+                    self.server_repository
+                        .get_servers(&db, &parsed_filter, page)
+                },
+            )
+            .await?;
+
+        let servers: Vec<Server> = page
+            .items
+            .into_iter()
+            .map(|item| item.to_message())
+            .collect();
 
         let res = ListServersResponse {
             servers,
-            next_page_token,
-            prev_page_token,
+            next_page_token: page.next_page_token,
+            prev_page_token: page.prev_page_token,
         };
 
         Ok(Response::new(res))
@@ -110,18 +169,18 @@ where
         &self,
         request: Request<GetServerRequest>,
     ) -> Result<Response<Server>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
 
         let req = request.into_inner();
         let name = req.name;
 
-        let id = ServerId::from_string(name.split('/').collect::<Vec<&str>>()[1]).unwrap();
+        let id = ServerName::parse(&name)?.0;
 
-        let server = self.server_repository.get_server(&db, &id).await.unwrap();
+        let server = self.server_repository.get_server(&db, &id).await?;
 
         match server {
             Some(server) => Ok(Response::new(server.to_message())),
-            None => Err(Status::internal("internal error")),
+            None => Err(ServiceError::not_found("server not found.").into()),
         }
     }
 
@@ -129,84 +188,76 @@ where
         &self,
         request: Request<CreateServerRequest>,
     ) -> Result<Response<Server>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
 
         let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
-        let user_id = UserId::from_string(&user_id).unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
 
         let req = request.into_inner();
 
         let server = match req.server {
             Some(server) => DbServer::new(user_id, server),
-            None => return Err(Status::invalid_argument("invalid arguments")),
-        };
-
-        let server_res = self
-            .server_repository
-            .add_server(&db, &server)
-            .await
-            .unwrap();
-        let server_res = match server_res {
-            Some(server) => server,
-            None => return Err(Status::internal("failed to create server")),
+            None => return Err(ServiceError::invalid_field("server", "required.").into()),
         };
 
         let display_name = "username".to_string(); // FIXME
         let description = "server_description".to_string(); // FIXME
-        let server_id = server_res.id;
 
-        let server_member = DbServerMember::new(display_name, description, server_id, user_id);
+        let server_member = DbServerMember::new(display_name, description, server.id, user_id);
 
-        let server_member = self
-            .server_member_repository
-            .add_server_member(&db, &server_member)
-            .await;
+        // the server and its owner's membership exist together or not at all.
+        let mut transaction = DB::Transaction::default();
+        self.server_repository
+            .add_server_in(&mut transaction, &server)?;
+        self.server_member_repository
+            .add_server_member_in(&mut transaction, &server_member)?;
+        db.commit(transaction).await?;
 
-        match server_member {
-            Ok(_) => {}
-            Err(_err) => return Err(Status::internal("failed to create server_member")),
+        match self.server_repository.get_server(&db, &server.id).await? {
+            Some(server) => Ok(Response::new(server.to_message())),
+            None => Err(ServiceError::internal("failed to create server").into()),
         }
-
-        Ok(Response::new(server_res.to_message()))
     }
 
     async fn update_server(
         &self,
         request: Request<UpdateServerRequest>,
     ) -> Result<Response<Server>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
+
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
 
         let req = request.into_inner();
 
         let server = match req.server {
-            Some(server) => DbServer::from(server),
-            None => return Err(Status::invalid_argument("invalid_arguments")),
+            Some(server) => server,
+            None => return Err(ServiceError::invalid_field("server", "required.").into()),
         };
 
-        let exist_server = self
-            .server_repository
-            .get_server(&db, &server.id)
-            .await
-            .unwrap();
+        let server_id = ServerName::parse(&server.name)?.0;
+
+        let exist_server = self.server_repository.get_server(&db, &server_id).await?;
 
         let mut exist_server = match exist_server {
             Some(exist_server) => exist_server,
-            None => return Err(Status::not_found("not found")),
+            None => return Err(ServiceError::not_found("not found").into()),
         };
 
-        exist_server.display_name = server.display_name;
-        exist_server.description = server.description;
-        exist_server.update_time = Some(Datetime::default());
+        if exist_server.owner != user_id {
+            return Err(ServiceError::permission_denied("permission denied.").into());
+        }
+
+        exist_server.update(server, req.update_mask)?;
 
         let res = self
             .server_repository
             .update_server(&db, &exist_server)
-            .await
-            .unwrap();
+            .await?;
 
         match res {
             Some(res) => Ok(Response::new(res.to_message())),
-            None => Err(Status::internal("internal error")),
+            None => Err(ServiceError::internal("internal error").into()),
         }
     }
 
@@ -214,16 +265,50 @@ where
         &self,
         request: Request<DeleteServerRequest>,
     ) -> Result<Response<()>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
+
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
+
         let req = request.into_inner();
         let name = req.name;
 
-        let id = ServerId::from_string(name.split('/').collect::<Vec<&str>>()[1]).unwrap();
+        let id = ServerName::parse(&name)?.0;
 
+        let server = match self.server_repository.get_server(&db, &id).await? {
+            Some(server) => server,
+            None => return Err(ServiceError::not_found("not found").into()),
+        };
+
+        if server.owner != user_id {
+            return Err(ServiceError::permission_denied("permission denied.").into());
+        }
+
+        let channels = self
+            .channel_repository
+            .get_list_by_server_id(&db, &id)
+            .await?;
+
+        let mut transaction = DB::Transaction::default();
+        for channel in &channels {
+            delete_channel_contents_in(
+                &mut transaction,
+                &self.message_repository,
+                &self.message_acknowledge_repository,
+                &self.mention_repository,
+                &self.read_state_repository,
+                &channel.id,
+            )?;
+        }
+        self.channel_repository
+            .delete_by_server_id_in(&mut transaction, &id)?;
+        self.server_category_repository
+            .delete_by_server_id_in(&mut transaction, &id)?;
+        self.server_member_repository
+            .delete_by_server_id_in(&mut transaction, &id)?;
         self.server_repository
-            .delete_server(&db, &id)
-            .await
-            .unwrap();
+            .delete_server_in(&mut transaction, &id)?;
+        db.commit(transaction).await?;
 
         Ok(Response::new(()))
     }
@@ -232,28 +317,27 @@ where
         &self,
         request: Request<EnterServerRequest>,
     ) -> Result<Response<ServerMember>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
 
         let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
-        let user_id = UserId::from_string(&user_id).unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
 
         let req = request.into_inner();
         let name = req.name;
         let display_name = req.display_name;
         let description = req.description;
 
-        let server_id = ServerId::from_string(name.split('/').collect::<Vec<&str>>()[1]).unwrap();
+        let server_id = ServerName::parse(&name)?.0;
 
         {
             // check exist
             let exist = self
                 .server_member_repository
                 .get_server_member_by_server_id_and_user_id(&db, &server_id, &user_id)
-                .await
-                .unwrap();
+                .await?;
 
             if exist.is_some() {
-                return Err(Status::already_exists("already exist."));
+                return Err(ServiceError::already_exists("already exist.").into());
             }
         }
 
@@ -262,12 +346,11 @@ where
         let server_member = self
             .server_member_repository
             .add_server_member(&db, &server_member)
-            .await
-            .unwrap();
+            .await?;
 
         match server_member {
             Some(server_member) => Ok(Response::new(server_member.to_message())),
-            None => Err(Status::internal("internal error")),
+            None => Err(ServiceError::internal("internal error").into()),
         }
     }
 
@@ -275,7 +358,7 @@ where
         &self,
         request: Request<LeaveServerRequest>,
     ) -> Result<Response<()>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
 
         let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
         let user_id = UserId::from_string(user_id).unwrap();
@@ -283,24 +366,334 @@ where
         let req = request.into_inner();
         let name = req.name;
 
-        let server_id = ServerId::from_string(name.split('/').collect::<Vec<&str>>()[1]).unwrap();
+        let server_id = ServerName::parse(&name)?.0;
 
         let exist = self
             .server_member_repository
             .get_server_member_by_server_id_and_user_id(&db, &server_id, &user_id)
-            .await
-            .unwrap();
+            .await?;
 
         match exist {
             Some(exist) => {
-                self.server_member_repository
-                    .delete(&db, &exist.id)
-                    .await
-                    .unwrap();
+                self.server_member_repository.delete(&db, &exist.id).await?;
             }
-            None => return Err(Status::not_found("not found.")),
+            None => return Err(ServiceError::not_found("not found.").into()),
         }
 
         Ok(Response::new(()))
     }
+
+    async fn update_server_icon(
+        &self,
+        request: Request<UpdateServerIconRequest>,
+    ) -> Result<Response<Server>, Status> {
+        let db = self.db.clone();
+
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
+
+        let req = request.into_inner();
+        let name = req.name;
+        let icon = req.icon; // attachments/{attachmentId}, clear when empty.
+
+        let id = ServerName::parse(&name)?.0;
+
+        let mut server = match self.server_repository.get_server(&db, &id).await? {
+            Some(server) => server,
+            None => return Err(ServiceError::not_found("not found").into()),
+        };
+
+        if server.owner != user_id {
+            return Err(ServiceError::permission_denied("permission denied.").into());
+        }
+
+        let icon = match icon {
+            Some(icon) => Some(
+                create_square_image(
+                    &db,
+                    &self.attachment_repository,
+                    &self.blob_store,
+                    &user_id,
+                    &icon,
+                )
+                .await?,
+            ),
+            None => None,
+        };
+
+        let previous = std::mem::replace(&mut server.icon, icon.clone());
+        server.update_time = Some(Datetime::default());
+
+        let res = self.server_repository.update_server(&db, &server).await;
+
+        // the new icon is left unused when nothing was updated.
+        if !matches!(res, Ok(Some(_))) {
+            if let Some(icon) = icon {
+                release_attachment(&db, &self.attachment_repository, &self.blob_store, &icon)
+                    .await
+                    .unwrap_or_else(|err| err.log());
+            }
+        }
+
+        let res = match res? {
+            Some(res) => res,
+            None => return Err(ServiceError::not_found("server not found.").into()),
+        };
+
+        if let Some(previous) = previous {
+            release_attachment(
+                &db,
+                &self.attachment_repository,
+                &self.blob_store,
+                &previous,
+            )
+            .await
+            .unwrap_or_else(|err| err.log());
+        }
+
+        Ok(Response::new(res.to_message()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::Code;
+
+    use super::*;
+    use crate::{
+        db::memory::{self, MemoryDb},
+        services::testing::{add_image, pager, request, MemoryBlobStore},
+    };
+
+    async fn service(db: &MemoryDb, blob_store: &MemoryBlobStore) -> impl ServerServer {
+        let repositories = memory::repositories().await;
+
+        ServerService::new(
+            db.clone(),
+            repositories.server,
+            repositories.server_member,
+            repositories.attachment,
+            blob_store.clone(),
+            repositories.server_category,
+            repositories.channel,
+            repositories.message,
+            repositories.message_acknowledge,
+            repositories.mention,
+            repositories.read_state,
+            pager(),
+        )
+    }
+
+    async fn create_server(service: &impl ServerServer, owner: UserId) -> Server {
+        let server = Server {
+            display_name: "server".to_string(),
+            ..Default::default()
+        };
+
+        service
+            .create_server(request(
+                owner,
+                CreateServerRequest {
+                    server: Some(server),
+                },
+            ))
+            .await
+            .unwrap()
+            .into_inner()
+    }
+
+    #[tokio::test]
+    async fn owner_is_member_of_created_server() {
+        let db = MemoryDb::new();
+        let service = service(&db, &MemoryBlobStore::default()).await;
+        let owner = UserId::new();
+
+        let server = create_server(&service, owner).await;
+        let got = service
+            .get_server(request(
+                owner,
+                GetServerRequest {
+                    name: server.name.clone(),
+                },
+            ))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(got.display_name, "server");
+
+        let member = service
+            .enter_server(request(
+                owner,
+                EnterServerRequest {
+                    name: server.name,
+                    ..Default::default()
+                },
+            ))
+            .await
+            .unwrap_err();
+        assert_eq!(member.code(), Code::AlreadyExists);
+    }
+
+    #[tokio::test]
+    async fn only_owner_updates_and_deletes() {
+        let db = MemoryDb::new();
+        let service = service(&db, &MemoryBlobStore::default()).await;
+        let owner = UserId::new();
+        let other = UserId::new();
+
+        let server = create_server(&service, owner).await;
+        let update = || UpdateServerRequest {
+            server: Some(Server {
+                name: server.name.clone(),
+                display_name: "renamed".to_string(),
+                ..Default::default()
+            }),
+            update_mask: None,
+        };
+        let delete = || DeleteServerRequest {
+            name: server.name.clone(),
+        };
+
+        let err = service
+            .update_server(request(other, update()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+        let err = service
+            .delete_server(request(other, delete()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+
+        let updated = service
+            .update_server(request(owner, update()))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(updated.display_name, "renamed");
+
+        service
+            .delete_server(request(owner, delete()))
+            .await
+            .unwrap();
+        let err = service
+            .get_server(request(
+                owner,
+                GetServerRequest {
+                    name: server.name.clone(),
+                },
+            ))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn members_enter_and_leave() {
+        let db = MemoryDb::new();
+        let service = service(&db, &MemoryBlobStore::default()).await;
+        let member = UserId::new();
+
+        let server = create_server(&service, UserId::new()).await;
+        let enter = || EnterServerRequest {
+            name: server.name.clone(),
+            display_name: "member".to_string(),
+            description: String::new(),
+        };
+        let leave = || LeaveServerRequest {
+            name: server.name.clone(),
+        };
+
+        service
+            .enter_server(request(member, enter()))
+            .await
+            .unwrap();
+        let err = service
+            .enter_server(request(member, enter()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::AlreadyExists);
+
+        service
+            .leave_server(request(member, leave()))
+            .await
+            .unwrap();
+        let err = service
+            .leave_server(request(member, leave()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn owner_sets_replaces_and_clears_the_icon() {
+        let db = MemoryDb::new();
+        let blob_store = MemoryBlobStore::default();
+        let service = service(&db, &blob_store).await;
+        let owner = UserId::new();
+
+        let server = create_server(&service, owner).await;
+        let update_icon = |icon: Option<String>| {
+            request(
+                owner,
+                UpdateServerIconRequest {
+                    name: server.name.clone(),
+                    icon,
+                },
+            )
+        };
+        let icon_key = |server: &Server| {
+            let url = &server.icon.as_ref().unwrap().url;
+            url.strip_prefix("/attachments/").unwrap().to_string()
+        };
+
+        let image = add_image(&db, &blob_store, owner, 100).await;
+        let first = service
+            .update_server_icon(update_icon(Some(image)))
+            .await
+            .unwrap()
+            .into_inner();
+        let first_key = icon_key(&first);
+        assert!(blob_store.contains(&first_key));
+
+        let image = add_image(&db, &blob_store, owner, 100).await;
+        let second = service
+            .update_server_icon(update_icon(Some(image)))
+            .await
+            .unwrap()
+            .into_inner();
+        let second_key = icon_key(&second);
+        assert!(blob_store.contains(&second_key));
+        assert!(!blob_store.contains(&first_key));
+
+        let cleared = service
+            .update_server_icon(update_icon(None))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(cleared.icon.is_none());
+        assert!(!blob_store.contains(&second_key));
+    }
+
+    #[tokio::test]
+    async fn only_the_owner_updates_the_icon() {
+        let db = MemoryDb::new();
+        let blob_store = MemoryBlobStore::default();
+        let service = service(&db, &blob_store).await;
+        let member = UserId::new();
+
+        let server = create_server(&service, UserId::new()).await;
+        let image = add_image(&db, &blob_store, member, 100).await;
+        let err = service
+            .update_server_icon(request(
+                member,
+                UpdateServerIconRequest {
+                    name: server.name.clone(),
+                    icon: Some(image),
+                },
+            ))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+    }
 }
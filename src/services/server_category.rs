@@ -1,17 +1,16 @@
-use prost::Message as _;
-use surrealdb::engine::remote::ws::Client;
-use surrealdb::Surreal;
 use tonic::{Request, Response, Status};
 
-use crate::db::surreal::conn;
+use crate::db::traits::channel::ChannelRepository;
 use crate::db::traits::server::ServerRepository;
 use crate::db::traits::server_category::ServerCategoryRepository;
-use crate::models::server::DbServer;
-use crate::models::server::ServerId;
+use crate::db::Database;
+use crate::models::server::{DbServer, ServerId};
 use crate::models::server_category::DbServerCategory;
-use crate::models::server_category::ServerCategoryId;
-use crate::util::pager::PageTokenizer;
+use crate::models::user::UserId;
+use crate::util::pager::{ListRequest, Pager, CREATE_TIME};
+use crate::util::resource_name::{CategoryName, ServerName};
 
+use super::error::ServiceError;
 use super::ycchat::v1::models::Category as CategoryModel;
 use super::ycchat::v1::services::server::category::category_service_server::CategoryService as Category;
 use super::ycchat::v1::services::server::category::CreateCategoryRequest;
@@ -20,91 +19,99 @@ use super::ycchat::v1::services::server::category::{
     ListCategoriesResponse, UpdateCategoryRequest,
 };
 
-pub struct ServerCategoryService<SC, S>
+pub struct ServerCategoryService<DB, SC, S, C>
 where
-    SC: ServerCategoryRepository<Surreal<Client>>,
-    S: ServerRepository<Surreal<Client>>,
+    DB: Database,
+    SC: ServerCategoryRepository<DB>,
+    S: ServerRepository<DB>,
+    C: ChannelRepository<DB>,
 {
+    db: DB,
     server_repository: S,
     server_category_repository: SC,
+    channel_repository: C,
+    pager: Pager,
 }
 
-impl<SC, S> ServerCategoryService<SC, S>
+impl<DB, SC, S, C> ServerCategoryService<DB, SC, S, C>
 where
-    S: ServerRepository<Surreal<Client>>,
-    SC: ServerCategoryRepository<Surreal<Client>>,
+    DB: Database,
+    S: ServerRepository<DB>,
+    SC: ServerCategoryRepository<DB>,
+    C: ChannelRepository<DB>,
 {
-    pub fn new(server_repository: S, server_category_repository: SC) -> Self {
+    pub fn new(
+        db: DB,
+        server_repository: S,
+        server_category_repository: SC,
+        channel_repository: C,
+        pager: Pager,
+    ) -> Self {
         ServerCategoryService {
+            db,
             server_repository,
             server_category_repository,
+            channel_repository,
+            pager,
         }
     }
+
+    /// whether `user_id` owns the server, only the owner arranges its categories.
+    async fn is_server_owner(
+        &self,
+        db: &DB,
+        server_id: &ServerId,
+        user_id: &UserId,
+    ) -> Result<bool, Status> {
+        let server = self.server_repository.get_server(db, server_id).await?;
+
+        Ok(server.is_some_and(|server| server.owner == *user_id))
+    }
 }
 
 #[tonic::async_trait]
-impl<SC, S> Category for ServerCategoryService<SC, S>
+impl<DB, SC, S, C> Category for ServerCategoryService<DB, SC, S, C>
 where
-    S: ServerRepository<Surreal<Client>> + 'static,
-    SC: ServerCategoryRepository<Surreal<Client>> + 'static,
+    DB: Database,
+    S: ServerRepository<DB> + 'static,
+    SC: ServerCategoryRepository<DB> + 'static,
+    C: ChannelRepository<DB> + 'static,
 {
     async fn list_categories(
         &self,
         request: Request<ListCategoriesRequest>,
     ) -> Result<Response<ListCategoriesResponse>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
 
         let request = request.into_inner();
         let parent = request.parent;
-        let parent = parent.split('/').collect::<Vec<&str>>();
-        let server_id = ServerId::from_string(parent[1]).unwrap();
-
-        let page_token = match request.page_token.clone() {
-            Some(page_token) => {
-                let page_token = crate::util::pager::get_page_token(page_token);
-                Some(page_token.unwrap())
-            }
-            None => None,
-        };
-
-        let (page_size, offset_id, prev_page_token) = match page_token {
-            Some(page_token) => (
-                page_token.page_size,
-                page_token
-                    .offset_id
-                    .map(|offset_id| ServerCategoryId::from_string(&offset_id).unwrap()),
-                page_token.prev_page_token,
-            ),
-            None => (request.page_size, None, None),
-        };
-
-        let mut list = self
-            .server_category_repository
-            .get_server_categories(&db, &server_id, page_size + 1, offset_id)
-            .await
-            .unwrap();
-
-        let next_page_token = if list.len() > usize::try_from(page_size).unwrap() {
-            list.pop();
-
-            let next_page_token = list.generate_page_token(page_size, request.page_token);
-            next_page_token.map(|token| {
-                let mut pb_buf = vec![];
-                let _ = token.encode(&mut pb_buf);
-
-                crate::util::base64_encoder::encode_string(pb_buf)
-            })
-        } else {
-            None
-        };
+        let server_id = ServerName::parse(&parent)?.0;
+
+        let page = self
+            .pager
+            .list(
+                ListRequest::new(
+                    parent,
+                    request.page_size,
+                    request.page_token,
+                    request.order_by,
+                ),
+                &[CREATE_TIME],
+                |page| {
+                    self.server_category_repository
+                        .get_server_categories(&db, &server_id, page)
+                },
+            )
+            .await?;
 
         Ok(Response::new(ListCategoriesResponse {
-            categories: list
+            categories: page
+                .items
                 .into_iter()
                 .map(|category| category.to_message())
                 .collect::<Vec<CategoryModel>>(),
-            next_page_token,
-            prev_page_token,
+            next_page_token: page.next_page_token,
+            prev_page_token: page.prev_page_token,
         }))
     }
 
@@ -112,22 +119,34 @@ where
         &self,
         request: Request<GetCategoryRequest>,
     ) -> Result<Response<GetCategoryResponse>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
 
         let name = request.into_inner().name; // servers/{UUID}/categories/{UUID}
-        let name = name.split('/').collect::<Vec<&str>>();
-        let server_id = ServerId::from_string(name[1]).unwrap();
-        let server_category_id: ServerCategoryId = ServerCategoryId::from_string(name[3]).unwrap();
+        let CategoryName {
+            server: server_id,
+            category: server_category_id,
+        } = CategoryName::parse(&name)?;
 
         let category = self
             .server_category_repository
             .get(&db, &server_category_id)
-            .await
-            .unwrap();
+            .await?;
+
+        let channels = match &category {
+            Some(_) => self
+                .channel_repository
+                .get_list_by_server_id(&db, &server_id)
+                .await?
+                .into_iter()
+                .filter(|channel| channel.category == Some(server_category_id))
+                .map(|channel| channel.to_message())
+                .collect(),
+            None => vec![],
+        };
 
         let res = GetCategoryResponse {
             category: category.map(|item| item.to_message()),
-            channels: vec![],
+            channels,
         };
 
         Ok(Response::new(res))
@@ -137,37 +156,45 @@ where
         &self,
         request: Request<CreateCategoryRequest>,
     ) -> Result<Response<CategoryModel>, Status> {
-        let db = conn().await;
-        let req = request.into_inner();
+        let db = self.db.clone();
+
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
 
-        let category = req.category.unwrap();
+        let req = request.into_inner();
 
-        let name = &category.name; // servers/{UUID}/categories/{UUID}
-        let name = name.split('/').collect::<Vec<&str>>();
-        let server_id = ServerId::from_string(name[1]).unwrap();
+        let category = req
+            .category
+            .ok_or_else(|| ServiceError::invalid_field("category", "required."))?;
 
-        let server = match self.server_repository.get_server(&db, &server_id).await {
-            Ok(server) => server,
-            Err(_) => return Err(Status::not_found("server not found")),
+        // the id of a new category is generated, only its server is read from the name.
+        let server_id = match CategoryName::parse(&category.name) {
+            Ok(name) => name.server,
+            Err(_) => ServerName::parse(&category.name)?.0,
         };
+
+        let server = self.server_repository.get_server(&db, &server_id).await?;
         let server: DbServer = match server {
             Some(server) => server,
             None => {
-                return Err(Status::not_found("not exist"));
+                return Err(ServiceError::not_found("not exist").into());
             }
         };
 
+        if server.owner != user_id {
+            return Err(ServiceError::permission_denied("permission denied.").into());
+        }
+
         let server_category = DbServerCategory::new(server, category);
 
         let res = self
             .server_category_repository
             .add(&db, &server_category)
-            .await
-            .unwrap();
+            .await?;
 
         match res {
             Some(res) => Ok(Response::new(res.to_message())),
-            None => Err(Status::internal("internal error")),
+            None => Err(ServiceError::internal("internal error").into()),
         }
     }
 
@@ -175,38 +202,46 @@ where
         &self,
         request: Request<UpdateCategoryRequest>,
     ) -> Result<Response<CategoryModel>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
+
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
+
         let req = request.into_inner();
-        let category = req.category.unwrap();
+        let category = req
+            .category
+            .ok_or_else(|| ServiceError::invalid_field("category", "required."))?;
 
-        let name = &category.name; // servers/{UUID}/categories/{UUID}
-        let name = name.split('/').collect::<Vec<&str>>();
-        let server_id = ServerId::from_string(name[1]).unwrap();
-        let server_category_id: ServerCategoryId = ServerCategoryId::from_string(name[3]).unwrap();
+        let server_category_id = CategoryName::parse(&category.name)?.category;
 
-        let mut exist_category = self
+        let exist_category = self
             .server_category_repository
             .get(&db, &server_category_id)
-            .await
-            .unwrap();
+            .await?;
 
         if exist_category.is_none() {
-            return Err(Status::not_found("entity not found."));
+            return Err(ServiceError::not_found("entity not found.").into());
         }
 
         let mut exist_category = exist_category.unwrap();
 
-        exist_category.update(category);
+        if !self
+            .is_server_owner(&db, &exist_category.server, &user_id)
+            .await?
+        {
+            return Err(ServiceError::permission_denied("permission denied.").into());
+        }
+
+        exist_category.update(category, req.update_mask)?;
 
         let res = self
             .server_category_repository
             .update(&db, &exist_category)
-            .await
-            .unwrap();
+            .await?;
 
         match res {
             Some(res) => Ok(Response::new(res.to_message())),
-            None => Err(Status::internal("internal error")),
+            None => Err(ServiceError::internal("internal error").into()),
         }
     }
 
@@ -214,20 +249,119 @@ where
         &self,
         request: Request<DeleteCategoryRequest>,
     ) -> Result<Response<()>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
+
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
 
         let req = request.into_inner();
-        let name = req.name; // servers/{serverId}/members/{serverMemberId}
-        let name = name.split('/').collect::<Vec<&str>>();
+        let CategoryName {
+            server: server_id,
+            category: server_category_id,
+        } = CategoryName::parse(&req.name)?;
 
-        let server_id = ServerId::from_string(name[1]).unwrap();
-        let server_category_id: ServerCategoryId = ServerCategoryId::from_string(name[3]).unwrap();
+        match self
+            .server_category_repository
+            .get(&db, &server_category_id)
+            .await?
+        {
+            Some(category) if category.server == server_id => {}
+            _ => return Err(ServiceError::not_found("entity not found.").into()),
+        }
+
+        if !self.is_server_owner(&db, &server_id, &user_id).await? {
+            return Err(ServiceError::permission_denied("permission denied.").into());
+        }
+
+        // channels of the category stay in the server, uncategorized.
+        self.channel_repository
+            .clear_category(&db, &server_category_id)
+            .await?;
 
         self.server_category_repository
             .delete(&db, &server_category_id)
+            .await?;
+
+        Ok(Response::new(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::Code;
+
+    use super::*;
+    use crate::{
+        db::memory::{self, MemoryDb},
+        models::channel::{ChannelType, DbChannel},
+        services::{
+            testing::{add_server, pager, request},
+            ycchat::v1::models::Channel,
+        },
+    };
+
+    #[tokio::test]
+    async fn deleted_categories_leave_their_channels_uncategorized() {
+        let db = MemoryDb::new();
+        let repositories = memory::repositories().await;
+        let owner = UserId::new();
+        let server = add_server(&db, owner).await;
+
+        let category = DbServerCategory::new(server.clone(), CategoryModel::default());
+        repositories
+            .server_category
+            .add(&db, &category)
             .await
             .unwrap();
+        let channel = DbChannel::new(
+            ChannelType::Server { server: server.id },
+            Channel::default(),
+            Some(category.id),
+            0,
+        );
+        repositories.channel.add(&db, &channel).await.unwrap();
+
+        let service = ServerCategoryService::new(
+            db.clone(),
+            repositories.server,
+            repositories.server_category,
+            repositories.channel,
+            pager(),
+        );
+        let delete = |user_id: UserId| {
+            request(
+                user_id,
+                DeleteCategoryRequest {
+                    name: CategoryName {
+                        server: server.id,
+                        category: category.id,
+                    }
+                    .to_string(),
+                },
+            )
+        };
 
-        Ok(Response::new(()))
+        let err = service
+            .delete_category(delete(UserId::new()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+
+        service.delete_category(delete(owner)).await.unwrap();
+
+        let repositories = memory::repositories().await;
+        let channel = repositories
+            .channel
+            .get(&db, &channel.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(channel.category.is_none());
+        assert!(repositories
+            .server_category
+            .get(&db, &category.id)
+            .await
+            .unwrap()
+            .is_none());
     }
 }
@@ -1,138 +1,273 @@
-use prost::Message as _;
-use surrealdb::engine::remote::ws::Client;
-use surrealdb::Surreal;
+use surrealdb::sql::Datetime;
 use tonic::{Request, Response, Status};
 
-use crate::db::surreal::conn;
+use crate::db::filter::Filter;
+use crate::db::traits::attachment::AttachmentRepository;
 use crate::db::traits::server_member::ServerMemberRepository;
-use crate::models::server::ServerId;
-use crate::models::server_member::ServerMemberId;
-use crate::util::pager::PageTokenizer;
+use crate::db::Database;
+use crate::models::server_member::FILTER_FIELDS;
+use crate::models::user::UserId;
+use crate::storage::BlobStore;
+use crate::util::pager::{ListRequest, Pager, CREATE_TIME};
+use crate::util::resource_name::{ServerMemberName, ServerName};
 
+use super::attachment::{create_square_image, release_attachment};
+use super::error::ServiceError;
 use super::ycchat::v1::models::ServerMember;
 
 use super::ycchat::v1::services::server::member::server_member_service_server::ServerMemberService as ServerMemberServer;
 use super::ycchat::v1::services::server::member::{
     GetServerMemberRequest, ListServerMembersRequest, ListServerMembersResponse,
+    UpdateServerMemberAvatarRequest, UpdateServerMemberRequest,
 };
 
-pub struct ServerMemberService<U>
+pub struct ServerMemberService<DB, U, A, B>
 where
-    U: ServerMemberRepository<Surreal<Client>>,
+    DB: Database,
+    U: ServerMemberRepository<DB>,
+    A: AttachmentRepository<DB>,
+    B: BlobStore,
 {
+    db: DB,
     server_member_repository: U,
+    attachment_repository: A,
+    blob_store: B,
+    pager: Pager,
 }
 
-impl<U> ServerMemberService<U>
+impl<DB, U, A, B> ServerMemberService<DB, U, A, B>
 where
-    U: ServerMemberRepository<Surreal<Client>>,
+    DB: Database,
+    U: ServerMemberRepository<DB>,
+    A: AttachmentRepository<DB>,
+    B: BlobStore,
 {
-    pub fn new(server_member_repository: U) -> Self {
+    pub fn new(
+        db: DB,
+        server_member_repository: U,
+        attachment_repository: A,
+        blob_store: B,
+        pager: Pager,
+    ) -> Self {
         ServerMemberService {
+            db,
             server_member_repository,
+            attachment_repository,
+            blob_store,
+            pager,
         }
     }
 }
 
 #[tonic::async_trait]
-impl<U> ServerMemberServer for ServerMemberService<U>
+impl<DB, U, A, B> ServerMemberServer for ServerMemberService<DB, U, A, B>
 where
-    U: ServerMemberRepository<Surreal<Client>> + 'static,
+    DB: Database,
+    U: ServerMemberRepository<DB> + 'static,
+    A: AttachmentRepository<DB> + 'static,
+    B: BlobStore + 'static,
 {
     async fn list_server_members(
         &self,
         request: Request<ListServerMembersRequest>,
     ) -> Result<Response<ListServerMembersResponse>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
         let request = request.into_inner();
         let name = request.parent;
-        let server_id = ServerId::from_string(name.split('/').collect::<Vec<&str>>()[1]).unwrap();
+        let server_id = ServerName::parse(&name)?.0;
 
-        let page_token = match request.page_token.clone() {
-            Some(page_token) => {
-                let page_token = crate::util::pager::get_page_token(page_token);
-                Some(page_token.unwrap())
-            }
-            None => None,
-        };
+        let filter = request.filter.unwrap_or_default();
+        let parsed_filter = Filter::parse(&filter, FILTER_FIELDS)?;
 
-        let (page_size, offset_id, prev_page_token) = match page_token {
-            Some(page_token) => (
-                page_token.page_size,
-                page_token
-                    .offset_id
-                    .map(|offset_id| ServerMemberId::from_string(&offset_id).unwrap()),
-                page_token.prev_page_token,
-            ),
-            None => (request.page_size, None, None),
+        let page = self
+            .pager
+            .list(
+                ListRequest::new(
+                    name,
+                    request.page_size,
+                    request.page_token,
+                    request.order_by,
+                )
+                .filter(filter),
+                &[CREATE_TIME],
+                |page| {
+                    self.server_member_repository.get_server_members(
+                        &db,
+                        &server_id,
+                        &parsed_filter,
+                        page,
+                    )
+                },
+            )
+            .await?;
+
+        let server_members: Vec<ServerMember> = page
+            .items
+            .into_iter()
+            .map(|item| item.to_message())
+            .collect();
+
+        let res = ListServerMembersResponse {
+            server_members,
+            next_page_token: page.next_page_token,
+            prev_page_token: page.prev_page_token,
         };
 
-        let mut list = self
+        Ok(Response::new(res))
+    }
+
+    async fn get_server_member(
+        &self,
+        request: Request<GetServerMemberRequest>,
+    ) -> Result<Response<ServerMember>, Status> {
+        let db = self.db.clone();
+
+        let req = request.into_inner();
+        let name = req.name; // servers/{serverId}/members/{serverMemberId}
+        let ServerMemberName {
+            server: server_id,
+            member: server_member_id,
+        } = ServerMemberName::parse(&name)?;
+
+        let server_member = self
             .server_member_repository
-            .get_server_members(&db, &server_id, page_size + 1, offset_id)
-            .await
-            .unwrap();
+            .get_server_member(&db, &server_member_id)
+            .await?;
+
+        let server_member = match server_member {
+            Some(server_member) if server_member.server == server_id => server_member,
+            _ => {
+                return Err(ServiceError::not_found("not exist").into());
+            }
+        };
+
+        Ok(Response::new(server_member.to_message()))
+    }
 
-        let next_page_token = if list.len() > usize::try_from(page_size).unwrap() {
-            list.pop();
+    async fn update_server_member(
+        &self,
+        request: Request<UpdateServerMemberRequest>,
+    ) -> Result<Response<ServerMember>, Status> {
+        let db = self.db.clone();
 
-            let next_page_token = list.generate_page_token(page_size, request.page_token);
-            next_page_token.map(|token| {
-                let mut pb_buf = vec![];
-                let _ = token.encode(&mut pb_buf);
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
 
-                crate::util::base64_encoder::encode_string(pb_buf)
-            })
-        } else {
-            None
+        let req = request.into_inner();
+        let server_member = match req.server_member {
+            Some(server_member) => server_member,
+            None => return Err(ServiceError::invalid_field("server_member", "required.").into()),
         };
 
-        let server_members: Vec<ServerMember> =
-            list.iter().map(|item| item.clone().to_message()).collect();
+        // servers/{serverId}/members/{serverMemberId}
+        let ServerMemberName {
+            server: server_id,
+            member: server_member_id,
+        } = ServerMemberName::parse(&server_member.name)?;
 
-        let res = ListServerMembersResponse {
-            server_members,
-            next_page_token,
-            prev_page_token,
+        let mut exist = match self
+            .server_member_repository
+            .get_server_member(&db, &server_member_id)
+            .await?
+        {
+            Some(exist) if exist.server == server_id => exist,
+            _ => return Err(ServiceError::not_found("not exist").into()),
         };
 
-        Ok(Response::new(res))
+        if exist.user != user_id {
+            return Err(ServiceError::permission_denied("permission denied.").into());
+        }
+
+        exist.update(server_member, req.update_mask)?;
+
+        match self
+            .server_member_repository
+            .update_server_member(&db, &exist)
+            .await?
+        {
+            Some(res) => Ok(Response::new(res.to_message())),
+            None => Err(ServiceError::internal("internal error").into()),
+        }
     }
 
-    async fn get_server_member(
+    async fn update_server_member_avatar(
         &self,
-        request: Request<GetServerMemberRequest>,
+        request: Request<UpdateServerMemberAvatarRequest>,
     ) -> Result<Response<ServerMember>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
 
-        let user_id = request
-            .metadata()
-            .get("user_id")
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .to_string(); // FIXME
+        let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
 
         let req = request.into_inner();
         let name = req.name; // servers/{serverId}/members/{serverMemberId}
-        let name = name.split('/').collect::<Vec<&str>>();
+        let avatar = req.avatar; // attachments/{attachmentId}, clear when empty.
 
-        let server_id = ServerId::from_string(name[1]).unwrap();
-        let server_member_id = ServerMemberId::from_string(name[3]).unwrap();
+        let ServerMemberName {
+            server: server_id,
+            member: server_member_id,
+        } = ServerMemberName::parse(&name)?;
 
-        let server_member = self
+        let mut server_member = match self
             .server_member_repository
             .get_server_member(&db, &server_member_id)
-            .await
-            .unwrap();
+            .await?
+        {
+            Some(server_member) if server_member.server == server_id => server_member,
+            _ => return Err(ServiceError::not_found("not exist").into()),
+        };
 
-        let server_member = match server_member {
-            Some(server_member) => server_member,
-            None => {
-                return Err(Status::not_found("not exist"));
+        if server_member.user != user_id {
+            return Err(ServiceError::permission_denied("permission denied.").into());
+        }
+
+        let avatar = match avatar {
+            Some(avatar) => Some(
+                create_square_image(
+                    &db,
+                    &self.attachment_repository,
+                    &self.blob_store,
+                    &user_id,
+                    &avatar,
+                )
+                .await?,
+            ),
+            None => None,
+        };
+
+        let previous = std::mem::replace(&mut server_member.avatar, avatar.clone());
+        server_member.update_time = Some(Datetime::default());
+
+        let res = self
+            .server_member_repository
+            .update_server_member(&db, &server_member)
+            .await;
+
+        // the new avatar is left unused when nothing was updated.
+        if !matches!(res, Ok(Some(_))) {
+            if let Some(avatar) = avatar {
+                release_attachment(&db, &self.attachment_repository, &self.blob_store, &avatar)
+                    .await
+                    .unwrap_or_else(|err| err.log());
             }
+        }
+
+        let res = match res? {
+            Some(res) => res,
+            None => return Err(ServiceError::not_found("server member not found.").into()),
         };
 
-        Ok(Response::new(server_member.to_message()))
+        if let Some(previous) = previous {
+            release_attachment(
+                &db,
+                &self.attachment_repository,
+                &self.blob_store,
+                &previous,
+            )
+            .await
+            .unwrap_or_else(|err| err.log());
+        }
+
+        Ok(Response::new(res.to_message()))
     }
 }
@@ -0,0 +1,187 @@
+//! fixtures shared by the service tests, which run on a `MemoryDb`.
+
+use std::{
+    collections::HashMap,
+    io::Cursor,
+    sync::{Arc, Mutex},
+};
+
+use image::{DynamicImage, ImageOutputFormat, RgbImage};
+use tonic::Request;
+
+use crate::{
+    config::PagingConfig,
+    db::{
+        memory::{self, MemoryDb},
+        traits::{
+            attachment::AttachmentRepository, server::ServerRepository,
+            server_member::ServerMemberRepository, user::UserRepository,
+        },
+        Database,
+    },
+    models::{
+        attachment::{Attachment, AttachmentId, AttachmentMetadata},
+        server::DbServer,
+        server_member::DbServerMember,
+        user::{DbUser, UserId},
+    },
+    storage::BlobStore,
+    util::{pager::Pager, resource_name::AttachmentName},
+};
+
+use super::ycchat::v1::models::{Server, User};
+
+/// `message` as sent by `user`, as the auth interceptor leaves it.
+pub fn request<T>(user: UserId, message: T) -> Request<T> {
+    let mut request = Request::new(message);
+    request
+        .metadata_mut()
+        .insert("user_id", user.to_string().parse().unwrap());
+
+    request
+}
+
+pub fn pager() -> Pager {
+    Pager::new(&PagingConfig {
+        page_token_secret: "0123456789abcdef0123456789abcdef".to_string(),
+        page_token_ttl: 60,
+    })
+}
+
+pub async fn add_user(db: &MemoryDb) -> DbUser {
+    let user = DbUser::new(User {
+        display_name: "user".to_string(),
+        ..Default::default()
+    });
+
+    let repositories = memory::repositories().await;
+    repositories.user.add_user(db, &user).await.unwrap();
+
+    user
+}
+
+/// a server of `owner`, who is its first member like after CreateServer.
+pub async fn add_server(db: &MemoryDb, owner: UserId) -> DbServer {
+    let server = DbServer::new(
+        owner,
+        Server {
+            display_name: "server".to_string(),
+            ..Default::default()
+        },
+    );
+
+    let repositories = memory::repositories().await;
+    let mut transaction = Default::default();
+    repositories
+        .server
+        .add_server_in(&mut transaction, &server)
+        .unwrap();
+    db.commit(transaction).await.unwrap();
+
+    add_server_member(db, &server, owner).await;
+
+    server
+}
+
+pub async fn add_server_member(db: &MemoryDb, server: &DbServer, user: UserId) -> DbServerMember {
+    let server_member = DbServerMember::new(String::new(), String::new(), server.id, user);
+
+    let repositories = memory::repositories().await;
+    let mut transaction = Default::default();
+    repositories
+        .server_member
+        .add_server_member_in(&mut transaction, &server_member)
+        .unwrap();
+    db.commit(transaction).await.unwrap();
+
+    server_member
+}
+
+/// blobs kept in the process, clones share them.
+#[derive(Clone, Default)]
+pub struct MemoryBlobStore(Arc<Mutex<HashMap<String, Vec<u8>>>>);
+
+impl MemoryBlobStore {
+    pub fn contains(&self, key: &str) -> bool {
+        self.0.lock().unwrap().contains_key(key)
+    }
+}
+
+#[tonic::async_trait]
+impl BlobStore for MemoryBlobStore {
+    async fn put(&self, key: &str, data: &[u8]) -> Result<(), String> {
+        self.0
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), data.to_vec());
+
+        Ok(())
+    }
+
+    async fn append(&self, key: &str, chunk: &[u8]) -> Result<(), String> {
+        self.0
+            .lock()
+            .unwrap()
+            .entry(key.to_string())
+            .or_default()
+            .extend_from_slice(chunk);
+
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+        Ok(self.0.lock().unwrap().get(key).cloned())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), String> {
+        self.0.lock().unwrap().remove(key);
+
+        Ok(())
+    }
+
+    fn url(&self, key: &str) -> String {
+        format!("/attachments/{key}")
+    }
+}
+
+/// a `size` x `size` png uploaded by `uploader`, returns its attachments/{attachmentId} name.
+pub async fn add_image(
+    db: &MemoryDb,
+    blob_store: &MemoryBlobStore,
+    uploader: UserId,
+    size: u32,
+) -> String {
+    let mut data = vec![];
+    DynamicImage::ImageRgb8(RgbImage::new(size, size))
+        .write_to(&mut Cursor::new(&mut data), ImageOutputFormat::Png)
+        .unwrap();
+
+    let mut attachment = Attachment::new(
+        AttachmentId::new(),
+        String::new(),
+        "image.png".to_string(),
+        "image/png".to_string(),
+        data.len() as i64,
+    );
+    attachment.metadata = Some(AttachmentMetadata {
+        width: Some(size),
+        height: Some(size),
+        ..Default::default()
+    });
+
+    blob_store
+        .put(
+            &Attachment::blob_key(&attachment.id, &attachment.filename),
+            &data,
+        )
+        .await
+        .unwrap();
+    memory::repositories()
+        .await
+        .attachment
+        .add_attachment(db, &attachment, &uploader)
+        .await
+        .unwrap();
+
+    AttachmentName(attachment.id).to_string()
+}
@@ -1,111 +1,94 @@
-use prost::Message as _;
-use surrealdb::engine::remote::ws::Client;
-use surrealdb::sql::Datetime;
-use surrealdb::Surreal;
 use tonic::{Request, Response, Status};
 
-use crate::db::surreal::conn;
+use crate::db::filter::Filter;
 use crate::db::traits::user::UserRepository;
-use crate::util::pager::PageTokenizer;
+use crate::db::Database;
+use crate::util::pager::{ListRequest, Pager, CREATE_TIME};
+use crate::util::resource_name::UserName;
 
+use super::error::ServiceError;
 use super::ycchat::v1::models::User;
 use super::ycchat::v1::services::user::user_service_server::UserService as UserServer;
 use super::ycchat::v1::services::user::{
     CreateUserRequest, DeleteUserRequest, GetUserRequest, ListUsersRequest, ListUsersResponse,
     UpdateUserRequest,
 };
-use crate::models::user::{DbUser, UserId};
+use crate::models::user::{DbUser, UserId, FILTER_FIELDS};
 
-pub struct UserService<U>
+pub struct UserService<DB, U>
 where
-    U: UserRepository<Surreal<Client>>,
+    DB: Database,
+    U: UserRepository<DB>,
 {
+    db: DB,
     user_repository: U,
+    pager: Pager,
 }
 
-impl<U> UserService<U>
+impl<DB, U> UserService<DB, U>
 where
-    U: UserRepository<Surreal<Client>>,
+    DB: Database,
+    U: UserRepository<DB>,
 {
-    pub async fn new(user_repository: U) -> Self {
-        UserService { user_repository }
+    pub async fn new(db: DB, user_repository: U, pager: Pager) -> Self {
+        UserService {
+            db,
+            user_repository,
+            pager,
+        }
     }
 }
 
 #[tonic::async_trait]
-impl<U> UserServer for UserService<U>
+impl<DB, U> UserServer for UserService<DB, U>
 where
-    U: UserRepository<Surreal<Client>> + 'static,
+    DB: Database,
+    U: UserRepository<DB> + 'static,
 {
     async fn list_users(
         &self,
         request: Request<ListUsersRequest>,
     ) -> Result<Response<ListUsersResponse>, Status> {
         let request = request.into_inner();
-        let db = conn().await;
-        let page_token = match request.page_token.clone() {
-            Some(page_token) => {
-                let page_token = crate::util::pager::get_page_token(page_token);
-                Some(page_token.unwrap())
-            }
-            None => None,
-        };
-
-        let (page_size, offset_id, prev_page_token) = match page_token {
-            Some(page_token) => (
-                page_token.page_size,
-                page_token
-                    .offset_id
-                    .map(|offset_id| UserId::from_string(&offset_id).unwrap()),
-                page_token.prev_page_token,
-            ),
-            None => (request.page_size, None, None),
-        };
+        let db = self.db.clone();
 
-        let mut list = self
-            .user_repository
-            .get_users(&db, page_size + 1, offset_id)
-            .await
-            .unwrap();
+        let filter = request.filter.unwrap_or_default();
+        let parsed_filter = Filter::parse(&filter, FILTER_FIELDS)?;
 
-        let next_page_token = if list.len() > usize::try_from(page_size).unwrap() {
-            list.pop();
+        let page = self
+            .pager
+            .list(
+                ListRequest::new("", request.page_size, request.page_token, request.order_by)
+                    .filter(filter),
+                &[CREATE_TIME],
+                |page| self.user_repository.get_users(&db, &parsed_filter, page),
+            )
+            .await?;
 
-            let next_page_token = list.generate_page_token(page_size, request.page_token);
-            next_page_token.map(|token| {
-                let mut pb_buf = vec![];
-                let _ = token.encode(&mut pb_buf);
-
-                crate::util::base64_encoder::encode_string(pb_buf)
-            })
-        } else {
-            None
-        };
-
-        let users: Vec<User> = list.iter().map(|item| item.clone().to_message()).collect();
+        let users: Vec<User> = page.items.iter().map(DbUser::to_message).collect();
 
         let res = ListUsersResponse {
             users,
-            next_page_token,
-            prev_page_token,
+            next_page_token: page.next_page_token,
+            prev_page_token: page.prev_page_token,
         };
 
         Ok(Response::new(res))
     }
 
     async fn get_user(&self, request: Request<GetUserRequest>) -> Result<Response<User>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
 
         let req = request.into_inner();
         let name = req.name;
 
-        let id = UserId::from_string(name.split('/').collect::<Vec<&str>>()[1]).unwrap();
+        let id = UserName::parse(&name)?.0;
 
-        let user = self.user_repository.get_user(&db, &id).await.unwrap();
+        let user = self.user_repository.get_user(&db, &id).await?;
         let user = match user {
             Some(user) => user,
             None => {
-                return Err(Status::not_found("not exist"));
+                return Err(ServiceError::not_found("not exist").into());
             }
         };
 
@@ -116,10 +99,10 @@ where
         &self,
         request: tonic::Request<CreateUserRequest>,
     ) -> Result<tonic::Response<User>, tonic::Status> {
-        let db = conn().await;
+        let db = self.db.clone();
 
         let user_id = request.metadata().get("user_id").unwrap().to_str().unwrap();
-        let user_id = UserId::from_string(&user_id).unwrap();
+        let user_id = UserId::from_string(user_id).unwrap();
 
         let req = request.into_inner();
 
@@ -131,14 +114,14 @@ where
 
                 user
             }
-            None => return Err(Status::invalid_argument("invalid arguments")),
+            None => return Err(ServiceError::invalid_field("user", "required.").into()),
         };
 
-        let res = self.user_repository.add_user(&db, &user).await.unwrap();
+        let res = self.user_repository.add_user(&db, &user).await?;
 
         match res {
             Some(res) => Ok(Response::new(res.to_message())),
-            None => Err(Status::internal("internal error")),
+            None => Err(ServiceError::internal("internal error").into()),
         }
     }
 
@@ -146,38 +129,33 @@ where
         &self,
         request: Request<UpdateUserRequest>,
     ) -> Result<Response<User>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
 
         let req = request.into_inner();
 
         let user = match req.user {
-            Some(user) => DbUser::from(user),
-            None => return Err(Status::invalid_argument("invalid arguments")),
+            Some(user) => user,
+            None => return Err(ServiceError::invalid_field("user", "required.").into()),
         };
-        dbg!(&user);
 
-        let exist_user = self.user_repository.get_user(&db, &user.id).await.unwrap();
+        let user_id = UserName::parse(&user.name)?.0;
+
+        let exist_user = self.user_repository.get_user(&db, &user_id).await?;
 
         let mut exist_user = match exist_user {
             Some(exist_user) => exist_user,
             None => {
-                return Err(Status::not_found("not exist"));
+                return Err(ServiceError::not_found("not exist").into());
             }
         };
 
-        exist_user.display_name = user.display_name;
-        exist_user.description = user.description;
-        exist_user.update_time = Some(Datetime::default());
+        exist_user.update(user, req.update_mask)?;
 
-        let res = self
-            .user_repository
-            .update_user(&db, &exist_user)
-            .await
-            .unwrap();
+        let res = self.user_repository.update_user(&db, &exist_user).await?;
 
         match res {
             Some(res) => Ok(Response::new(res.to_message())),
-            None => Err(Status::internal("internal error")),
+            None => Err(ServiceError::internal("internal error").into()),
         }
     }
 
@@ -185,14 +163,14 @@ where
         &self,
         request: Request<DeleteUserRequest>,
     ) -> Result<Response<()>, Status> {
-        let db = conn().await;
+        let db = self.db.clone();
 
         let req = request.into_inner();
         let name = req.name;
 
-        let id = UserId::from_string(name.split('/').collect::<Vec<&str>>()[1]).unwrap();
+        let id = UserName::parse(&name)?.0;
 
-        self.user_repository.delete_user(&db, &id).await.unwrap();
+        self.user_repository.delete_user(&db, &id).await?;
 
         Ok(Response::new(()))
     }
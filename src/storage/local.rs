@@ -0,0 +1,131 @@
+use std::path::PathBuf;
+
+use tokio::{fs, io::AsyncWriteExt};
+
+use super::BlobStore;
+
+/// files under `root`, served at `base_url` by a web server in front of the chat server,
+/// which doesn't serve them itself.
+#[derive(Clone)]
+pub struct LocalBlobStore {
+    root: PathBuf,
+    base_url: String,
+}
+
+impl LocalBlobStore {
+    pub fn new(root: &str, base_url: &str) -> Self {
+        LocalBlobStore {
+            root: PathBuf::from(root),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, String> {
+        // keys are generated by the server, but never let one escape the root directory.
+        if key
+            .split('/')
+            .any(|segment| segment.is_empty() || segment == "..")
+        {
+            return Err(format!("invalid blob key: {key}"));
+        }
+
+        Ok(self.root.join(key))
+    }
+}
+
+#[tonic::async_trait]
+impl BlobStore for LocalBlobStore {
+    async fn put(&self, key: &str, data: &[u8]) -> Result<(), String> {
+        let path = self.path(key)?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|err| err.to_string())?;
+        }
+
+        fs::write(&path, data).await.map_err(|err| err.to_string())
+    }
+
+    async fn append(&self, key: &str, chunk: &[u8]) -> Result<(), String> {
+        let path = self.path(key)?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|err| err.to_string())?;
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await
+            .map_err(|err| err.to_string())?;
+
+        file.write_all(chunk).await.map_err(|err| err.to_string())?;
+        file.flush().await.map_err(|err| err.to_string())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+        let path = self.path(key)?;
+
+        match fs::read(&path).await {
+            Ok(data) => Ok(Some(data)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), String> {
+        let path = self.path(key)?;
+
+        match fs::remove_file(&path).await {
+            Ok(_) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
+    /// keys are built by `Attachment::blob_key` and `Attachment::thumbnail_blob_key`, out of
+    /// characters a url path keeps as they are.
+    fn url(&self, key: &str) -> String {
+        format!("{}/{}", self.base_url, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::attachment::{Attachment, AttachmentId};
+
+    #[test]
+    fn url_of_blob_keys() {
+        let store = LocalBlobStore::new("./data", "https://cdn.example.com/attachments/");
+        let id = AttachmentId::from_string("01HGW2N5EQNG50CB2HBCN4A8GT").unwrap();
+
+        assert_eq!(
+            store.url(&Attachment::blob_key(&id, "photo-1_a.b.jpg")),
+            "https://cdn.example.com/attachments/01HGW2N5EQNG50CB2HBCN4A8GT/photo-1_a.b.jpg"
+        );
+        // anything else in a filename is replaced, the url needs no encoding.
+        assert_eq!(
+            store.url(&Attachment::blob_key(&id, "a b#c?d%e/f~\u{e9}.png")),
+            "https://cdn.example.com/attachments/01HGW2N5EQNG50CB2HBCN4A8GT/a_b_c_d_e_f__.png"
+        );
+        assert_eq!(
+            store.url(&Attachment::thumbnail_blob_key(&id, 256)),
+            "https://cdn.example.com/attachments/01HGW2N5EQNG50CB2HBCN4A8GT/thumbnails/256.jpg"
+        );
+    }
+
+    #[test]
+    fn path_stays_under_root() {
+        let store = LocalBlobStore::new("./data", "");
+
+        assert!(store.path("a/b.jpg").is_ok());
+        assert!(store.path("../b.jpg").is_err());
+        assert!(store.path("a//b.jpg").is_err());
+        assert!(store.path("/b.jpg").is_err());
+    }
+}
@@ -0,0 +1,14 @@
+pub mod local;
+
+#[tonic::async_trait]
+pub trait BlobStore: Sync + Send {
+    async fn put(&self, key: &str, data: &[u8]) -> Result<(), String>;
+
+    async fn append(&self, key: &str, chunk: &[u8]) -> Result<(), String>;
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, String>;
+
+    async fn delete(&self, key: &str) -> Result<(), String>;
+
+    fn url(&self, key: &str) -> String;
+}
@@ -0,0 +1,123 @@
+use prost_types::FieldMask;
+
+use crate::services::error::ServiceError;
+
+/// the fields an Update* request changes, following `update_mask` semantics of AIP-134.
+#[derive(Debug, Clone)]
+pub struct UpdateMask {
+    paths: Vec<String>,
+}
+
+impl UpdateMask {
+    /// `fields` are the updatable paths of the resource and whether the request populated them.
+    /// without a mask only the populated fields are updated, `*` replaces every field.
+    pub fn new(
+        update_mask: Option<FieldMask>,
+        fields: &[(&str, bool)],
+    ) -> Result<Self, ServiceError> {
+        let paths = update_mask.map(|mask| mask.paths).unwrap_or_default();
+
+        if paths.is_empty() {
+            return Ok(UpdateMask {
+                paths: fields
+                    .iter()
+                    .filter(|(_, populated)| *populated)
+                    .map(|(path, _)| path.to_string())
+                    .collect(),
+            });
+        }
+
+        if paths.iter().any(|path| path == "*") {
+            if paths.len() != 1 {
+                return Err(ServiceError::invalid_field(
+                    "update_mask",
+                    "update_mask `*` can't be combined with other paths.",
+                ));
+            }
+
+            return Ok(UpdateMask {
+                paths: fields.iter().map(|(path, _)| path.to_string()).collect(),
+            });
+        }
+
+        if let Some(path) = paths
+            .iter()
+            .find(|path| !fields.iter().any(|(field, _)| field == path))
+        {
+            return Err(ServiceError::invalid_field(
+                "update_mask",
+                format!("update_mask path `{}` is not updatable.", path),
+            ));
+        }
+
+        Ok(UpdateMask { paths })
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.paths.iter().any(|p| p == path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIELDS: &[(&str, bool)] = &[("display_name", true), ("description", false)];
+
+    fn mask(paths: &[&str]) -> Option<FieldMask> {
+        Some(FieldMask {
+            paths: paths.iter().map(|path| path.to_string()).collect(),
+        })
+    }
+
+    #[test]
+    fn no_mask_updates_populated_fields() {
+        for update_mask in [None, mask(&[])] {
+            let update_mask = UpdateMask::new(update_mask, FIELDS).unwrap();
+
+            assert!(update_mask.contains("display_name"));
+            assert!(!update_mask.contains("description"));
+        }
+    }
+
+    #[test]
+    fn paths_update_listed_fields() {
+        let update_mask = UpdateMask::new(mask(&["description"]), FIELDS).unwrap();
+
+        assert!(!update_mask.contains("display_name"));
+        assert!(update_mask.contains("description"));
+    }
+
+    #[test]
+    fn wildcard_updates_every_field() {
+        let update_mask = UpdateMask::new(mask(&["*"]), FIELDS).unwrap();
+
+        assert!(update_mask.contains("display_name"));
+        assert!(update_mask.contains("description"));
+    }
+
+    #[test]
+    fn wildcard_stands_alone() {
+        let err = UpdateMask::new(mask(&["*", "description"]), FIELDS).unwrap_err();
+
+        assert!(matches!(
+            err,
+            ServiceError::InvalidArgument { field: Some(field), .. } if field == "update_mask"
+        ));
+    }
+
+    #[test]
+    fn unknown_path_is_rejected() {
+        for path in ["name", "create_time", "display_name.first", ""] {
+            let err = UpdateMask::new(mask(&["display_name", path]), FIELDS).unwrap_err();
+
+            assert_eq!(
+                err,
+                ServiceError::invalid_field(
+                    "update_mask",
+                    format!("update_mask path `{}` is not updatable.", path),
+                )
+            );
+        }
+    }
+}
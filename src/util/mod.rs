@@ -1,3 +1,4 @@
 pub mod base64_encoder;
+pub mod field_mask;
 pub mod pager;
-pub mod variable;
+pub mod resource_name;
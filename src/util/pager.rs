@@ -1,49 +1,566 @@
-use prost::{DecodeError, Message};
+use std::future::Future;
 
-use crate::services::ycchat::v1::models::PageToken;
+use hmac::{Hmac, Mac};
+use prost::Message;
+use prost_types::Timestamp;
+use sha2::Sha256;
+use tonic::Status;
+use ulid::Ulid;
 
-pub fn get_page_token(page_token: String) -> Result<PageToken, DecodeError> {
-    let decoded = super::base64_encoder::decode(page_token).unwrap();
+use crate::{
+    config::PagingConfig,
+    db::{
+        error::RepositoryError,
+        page::{Cursor, Key, Order, Page},
+    },
+    services::{error::ServiceError, ycchat::v1::models::PageToken},
+};
 
-    let buf = bytes::Bytes::from(decoded);
+/// page size of a request that doesn't set one.
+pub const DEFAULT_PAGE_SIZE: u32 = 50;
+/// larger page sizes are lowered to this one.
+pub const MAX_PAGE_SIZE: u32 = 200;
 
-    PageToken::decode(buf)
+/// a field `order_by` of a list may name.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SortKey {
+    pub field: &'static str,
+    pub key: Key,
+    /// the order of a request without `order_by`, when the key is the first of its list.
+    pub default_order: Order,
 }
 
-pub trait PageTokenizer {
-    fn generate_page_token(
-        &self,
-        page_size: i32,
-        prev_page_token: Option<String>,
-    ) -> Option<String>;
+/// newest first unless the request asks for `create_time`.
+pub const CREATE_TIME: SortKey = SortKey {
+    field: "create_time",
+    key: Key::Id,
+    default_order: Order::Descending,
+};
+
+/// the channel layout, from the top.
+pub const LAYOUT: SortKey = SortKey {
+    field: "layout",
+    key: Key::Layout,
+    default_order: Order::Ascending,
+};
+
+/// a sort key and its direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Sort<'a> {
+    key: &'a SortKey,
+    order: Order,
 }
 
 pub trait PageItem {
-    fn get_item_id(&self) -> String;
+    fn get_item_id(&self) -> Ulid;
 }
 
-impl<ITEM: PageItem> PageTokenizer for Vec<ITEM> {
-    fn generate_page_token(
-        &self,
-        page_size: i32,
-        prev_page_token: Option<String>,
-    ) -> Option<String> {
-        let offset_id = self.last().map(|item| item.get_item_id().to_string());
+/// bytes of the HMAC-SHA256 tag at the end of a page token.
+const TAG_LEN: usize = 32;
+
+/// the paging fields of a `List*` request. its tokens only page the same `parent` with the
+/// same `filter`.
+pub struct ListRequest {
+    pub parent: String,
+    pub filter: String,
+    pub page_size: i32,
+    pub page_token: Option<String>,
+    pub order_by: Option<String>,
+}
 
-        let next_page_token = PageToken {
+impl ListRequest {
+    pub fn new(
+        parent: impl Into<String>,
+        page_size: i32,
+        page_token: Option<String>,
+        order_by: Option<String>,
+    ) -> Self {
+        ListRequest {
+            parent: parent.into(),
+            filter: String::new(),
             page_size,
-            order_by: None,
-            offset_id,
-            prev_page_token,
+            page_token,
+            order_by,
+        }
+    }
+
+    pub fn filter(mut self, filter: impl Into<String>) -> Self {
+        self.filter = filter.into();
+        self
+    }
+}
+
+/// a page of records and the tokens of the pages on either side of it.
+pub struct ListResponse<T> {
+    pub items: Vec<T>,
+    pub next_page_token: Option<String>,
+    pub prev_page_token: Option<String>,
+}
+
+/// lists pages and signs their tokens. clones share the key.
+#[derive(Clone)]
+pub struct Pager {
+    mac: Hmac<Sha256>,
+    /// seconds
+    page_token_ttl: i64,
+}
+
+impl Pager {
+    pub fn new(config: &PagingConfig) -> Self {
+        Pager {
+            mac: Hmac::new_from_slice(config.page_token_secret.as_bytes())
+                .expect("HMAC takes keys of any size."),
+            page_token_ttl: i64::try_from(config.page_token_ttl).unwrap_or(i64::MAX),
+        }
+    }
+
+    /// reads the page `request` asks for with `fetch`, sorted by one of `sort_keys`, the first
+    /// by default. one record more than the page holds is fetched to know whether the list goes
+    /// on past it.
+    pub async fn list<T, F, Fut>(
+        &self,
+        request: ListRequest,
+        sort_keys: &[SortKey],
+        fetch: F,
+    ) -> Result<ListResponse<T>, Status>
+    where
+        T: PageItem,
+        F: FnOnce(Page) -> Fut,
+        Fut: Future<Output = Result<Vec<T>, RepositoryError>>,
+    {
+        let (page_size, sort, cursor) = self.parse(&request, sort_keys)?;
+
+        let mut items = fetch(Page::new(page_size + 1, sort.key.key, sort.order, cursor)).await?;
+
+        let has_more = items.len() > page_size as usize;
+        if has_more {
+            // the extra record is the farthest from the cursor.
+            match cursor {
+                Some(Cursor::Before(_)) => {
+                    items.remove(0);
+                }
+                _ => {
+                    items.pop();
+                }
+            }
+        }
+
+        let first = items.first().map(PageItem::get_item_id);
+        let last = items.last().map(PageItem::get_item_id);
+
+        let (next, prev) = match cursor {
+            None => (last.filter(|_| has_more).map(Cursor::After), None),
+            Some(Cursor::After(_)) => (
+                last.filter(|_| has_more).map(Cursor::After),
+                first.map(Cursor::Before),
+            ),
+            Some(Cursor::Before(_)) => (
+                last.map(Cursor::After),
+                first.filter(|_| has_more).map(Cursor::Before),
+            ),
+        };
+
+        let token = |cursor: Cursor| self.encode(&request, page_size, sort, cursor);
+
+        Ok(ListResponse {
+            items,
+            next_page_token: next.map(token),
+            prev_page_token: prev.map(token),
+        })
+    }
+
+    fn parse<'a>(
+        &self,
+        request: &ListRequest,
+        sort_keys: &'a [SortKey],
+    ) -> Result<(u32, Sort<'a>, Option<Cursor>), ServiceError> {
+        let Ok(page_size) = u32::try_from(request.page_size) else {
+            return Err(ServiceError::invalid_field(
+                "page_size",
+                "must not be negative.",
+            ));
+        };
+
+        let sort = parse_order_by(request.order_by.as_deref().unwrap_or(""), sort_keys)
+            .ok_or_else(|| ServiceError::invalid_field("order_by", "unsupported order."))?;
+
+        let page_token = match request.page_token.as_deref() {
+            Some(page_token) if !page_token.is_empty() => self.decode(request, page_token)?,
+            _ => return Ok((clamp(page_size), sort, None)),
+        };
+
+        let token_sort = page_token
+            .order_by
+            .as_deref()
+            .and_then(|order_by| parse_order_by(order_by, sort_keys))
+            .ok_or_else(|| ServiceError::invalid_field("page_token", "invalid page token."))?;
+
+        if request.order_by.is_some() && token_sort != sort {
+            return Err(ServiceError::invalid_field(
+                "order_by",
+                "must not change between pages.",
+            ));
+        }
+
+        let id = page_token
+            .offset_id
+            .as_deref()
+            .and_then(|id| Ulid::from_string(id).ok())
+            .ok_or_else(|| ServiceError::invalid_field("page_token", "invalid page token."))?;
+
+        let cursor = if page_token.before {
+            Cursor::Before(id)
+        } else {
+            Cursor::After(id)
+        };
+
+        // the size may change between pages, the token keeps the previous one.
+        let page_size = if page_size > 0 {
+            page_size
+        } else {
+            u32::try_from(page_token.page_size).unwrap_or(0)
+        };
+
+        Ok((clamp(page_size), token_sort, Some(cursor)))
+    }
+
+    /// the token, followed by its tag over the token, `parent` and `filter` of the request.
+    fn encode(&self, request: &ListRequest, page_size: u32, sort: Sort, cursor: Cursor) -> String {
+        let (offset_id, before) = match cursor {
+            Cursor::After(id) => (id, false),
+            Cursor::Before(id) => (id, true),
+        };
+
+        let order = match sort.order {
+            Order::Ascending => "asc",
+            Order::Descending => "desc",
+        };
+
+        let expire_time = chrono::Utc::now()
+            .timestamp()
+            .saturating_add(self.page_token_ttl);
+
+        let page_token = PageToken {
+            page_size: page_size as i32,
+            order_by: Some(format!("{} {}", sort.key.field, order)),
+            offset_id: Some(offset_id.to_string()),
+            before,
+            expire_time: Some(Timestamp {
+                seconds: expire_time,
+                nanos: 0,
+            }),
         };
 
-        let mut pb_buf = vec![];
-        let _ = next_page_token.encode(&mut pb_buf);
+        let mut token = page_token.encode_to_vec();
+        let tag = self.mac(request, &token).finalize().into_bytes();
+        token.extend_from_slice(&tag);
+
+        super::base64_encoder::encode_string(token)
+    }
+
+    fn decode(&self, request: &ListRequest, page_token: &str) -> Result<PageToken, ServiceError> {
+        let invalid = || ServiceError::invalid_field("page_token", "invalid page token.");
+
+        let decoded =
+            super::base64_encoder::decode(page_token.to_string()).map_err(|_| invalid())?;
+        if decoded.len() < TAG_LEN {
+            return Err(invalid());
+        }
+
+        // a token of another parent or filter fails here as well.
+        let (token, tag) = decoded.split_at(decoded.len() - TAG_LEN);
+        self.mac(request, token)
+            .verify_slice(tag)
+            .map_err(|_| invalid())?;
+
+        let page_token = PageToken::decode(token).map_err(|_| invalid())?;
+
+        let is_expired = page_token
+            .expire_time
+            .as_ref()
+            .is_none_or(|expire_time| expire_time.seconds <= chrono::Utc::now().timestamp());
+        if is_expired {
+            return Err(ServiceError::invalid_field("page_token", "expired."));
+        }
+
+        Ok(page_token)
+    }
+
+    fn mac(&self, request: &ListRequest, token: &[u8]) -> Hmac<Sha256> {
+        let mut mac = self.mac.clone();
+
+        for field in [request.parent.as_bytes(), request.filter.as_bytes()] {
+            mac.update(&(field.len() as u64).to_be_bytes());
+            mac.update(field);
+        }
+        mac.update(token);
+
+        mac
+    }
+}
+
+fn clamp(page_size: u32) -> u32 {
+    match page_size {
+        0 => DEFAULT_PAGE_SIZE,
+        page_size => page_size.min(MAX_PAGE_SIZE),
+    }
+}
+
+/// `field`, `field asc` or `field desc` of one of the sort keys, or nothing for the first.
+fn parse_order_by<'a>(order_by: &str, sort_keys: &'a [SortKey]) -> Option<Sort<'a>> {
+    let mut words = order_by.split_whitespace();
+
+    let (field, order) = match (words.next(), words.next(), words.next()) {
+        (None, _, _) => {
+            let key = sort_keys.first()?;
+
+            return Some(Sort {
+                key,
+                order: key.default_order,
+            });
+        }
+        (Some(field), None, _) => (field, None),
+        (Some(field), Some(order), None) => match order.to_ascii_lowercase().as_str() {
+            "asc" => (field, Some(Order::Ascending)),
+            "desc" => (field, Some(Order::Descending)),
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    let key = sort_keys.iter().find(|key| key.field == field)?;
+
+    Some(Sort {
+        key,
+        order: order.unwrap_or(Order::Ascending),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::base64_encoder;
+
+    struct Item(Ulid);
+
+    impl PageItem for Item {
+        fn get_item_id(&self) -> Ulid {
+            self.0
+        }
+    }
+
+    fn pager() -> Pager {
+        Pager::new(&PagingConfig {
+            page_token_secret: "0123456789abcdef0123456789abcdef".to_string(),
+            page_token_ttl: 60,
+        })
+    }
+
+    /// lists `count` records by ascending id, as a backend filtering in process does.
+    async fn list(
+        pager: &Pager,
+        count: u128,
+        request: ListRequest,
+    ) -> Result<ListResponse<Item>, Status> {
+        pager
+            .list(request, &[CREATE_TIME], |page| async move {
+                let records = (1..=count).map(|id| Item(Ulid(id))).collect();
+
+                Ok(page.read(records, |item| item.0))
+            })
+            .await
+    }
+
+    fn request(page_size: i32, page_token: Option<String>) -> ListRequest {
+        ListRequest::new(
+            "channels/01HGW2N5EQNG50CB2HBCN4A8GT",
+            page_size,
+            page_token,
+            Some("create_time asc".to_string()),
+        )
+    }
+
+    fn ids(response: &ListResponse<Item>) -> Vec<u128> {
+        response.items.iter().map(|item| item.0 .0).collect()
+    }
+
+    #[tokio::test]
+    async fn pages_forward_and_back() {
+        let pager = pager();
+
+        let first = list(&pager, 5, request(2, None)).await.unwrap();
+        assert_eq!(ids(&first), vec![1, 2]);
+        assert!(first.prev_page_token.is_none());
+
+        let second = list(&pager, 5, request(2, first.next_page_token))
+            .await
+            .unwrap();
+        assert_eq!(ids(&second), vec![3, 4]);
+        assert!(second.prev_page_token.is_some());
+
+        let last = list(&pager, 5, request(2, second.next_page_token))
+            .await
+            .unwrap();
+        assert_eq!(ids(&last), vec![5]);
+        assert!(last.next_page_token.is_none());
+
+        let back = list(&pager, 5, request(2, last.prev_page_token))
+            .await
+            .unwrap();
+        assert_eq!(ids(&back), vec![3, 4]);
+        assert!(back.next_page_token.is_some());
+
+        let start = list(&pager, 5, request(2, back.prev_page_token))
+            .await
+            .unwrap();
+        assert_eq!(ids(&start), vec![1, 2]);
+        assert!(start.prev_page_token.is_none());
+        assert!(start.next_page_token.is_some());
+    }
+
+    #[tokio::test]
+    async fn default_order_is_newest_first() {
+        let pager = pager();
+        let request = ListRequest::new("", 2, None, None);
+
+        let first = list(&pager, 5, request).await.unwrap();
+        assert_eq!(ids(&first), vec![5, 4]);
+
+        let request = ListRequest::new("", 2, first.next_page_token, None);
+        let next = list(&pager, 5, request).await.unwrap();
+        assert_eq!(ids(&next), vec![3, 2]);
+    }
+
+    #[tokio::test]
+    async fn short_list_has_no_tokens() {
+        let response = list(&pager(), 2, request(2, None)).await.unwrap();
+
+        assert_eq!(ids(&response), vec![1, 2]);
+        assert!(response.next_page_token.is_none());
+        assert!(response.prev_page_token.is_none());
+    }
+
+    #[tokio::test]
+    async fn page_size_is_clamped() {
+        let pager = pager();
+
+        let response = list(&pager, 500, request(0, None)).await.unwrap();
+        assert_eq!(response.items.len(), DEFAULT_PAGE_SIZE as usize);
+
+        let response = list(&pager, 500, request(1000, None)).await.unwrap();
+        assert_eq!(response.items.len(), MAX_PAGE_SIZE as usize);
+
+        let err = list(&pager, 500, request(-1, None)).await.err().unwrap();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn page_token_keeps_page_size() {
+        let pager = pager();
+
+        let first = list(&pager, 10, request(3, None)).await.unwrap();
+        let next = list(&pager, 10, request(0, first.next_page_token))
+            .await
+            .unwrap();
+
+        assert_eq!(ids(&next), vec![4, 5, 6]);
+    }
+
+    #[tokio::test]
+    async fn order_by_must_not_change() {
+        let pager = pager();
+
+        let first = list(&pager, 5, request(2, None)).await.unwrap();
+        let mut request = request(2, first.next_page_token);
+        request.order_by = Some("create_time desc".to_string());
+
+        let err = list(&pager, 5, request).await.err().unwrap();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+    }
+
+    fn is_invalid_token(err: &Status) -> bool {
+        err.code() == tonic::Code::InvalidArgument && err.message().starts_with("page_token")
+    }
+
+    #[tokio::test]
+    async fn tampered_token_is_rejected() {
+        let pager = pager();
+
+        let first = list(&pager, 5, request(2, None)).await.unwrap();
+        let token = first.next_page_token.unwrap();
+        let mut decoded = base64_encoder::decode(token).unwrap();
+
+        // a byte of the token, then a byte of the tag.
+        for index in [1, decoded.len() - 1] {
+            decoded[index] ^= 1;
+            let tampered = base64_encoder::encode_string(decoded.clone());
+            decoded[index] ^= 1;
+
+            let err = list(&pager, 5, request(2, Some(tampered)))
+                .await
+                .err()
+                .unwrap();
+            assert!(is_invalid_token(&err));
+        }
+
+        let err = list(&pager, 5, request(2, Some("garbage".to_string())))
+            .await
+            .err()
+            .unwrap();
+        assert!(is_invalid_token(&err));
+    }
+
+    #[tokio::test]
+    async fn token_of_another_key_is_rejected() {
+        let first = list(&pager(), 5, request(2, None)).await.unwrap();
+
+        let other = Pager::new(&PagingConfig {
+            page_token_secret: "fedcba9876543210fedcba9876543210".to_string(),
+            page_token_ttl: 60,
+        });
+        let err = list(&other, 5, request(2, first.next_page_token))
+            .await
+            .err()
+            .unwrap();
+        assert!(is_invalid_token(&err));
+    }
+
+    #[tokio::test]
+    async fn token_is_bound_to_parent_and_filter() {
+        let pager = pager();
+
+        let first = list(&pager, 5, request(2, None).filter("author = \"users/a\""))
+            .await
+            .unwrap();
+        let token = first.next_page_token.unwrap();
+
+        let mut other_parent = request(2, Some(token.clone())).filter("author = \"users/a\"");
+        other_parent.parent = "channels/01HGW2N5EQNG50CB2HBCN4A8GV".to_string();
+        let err = list(&pager, 5, other_parent).await.err().unwrap();
+        assert!(is_invalid_token(&err));
+
+        let other_filter = request(2, Some(token.clone())).filter("author = \"users/b\"");
+        let err = list(&pager, 5, other_filter).await.err().unwrap();
+        assert!(is_invalid_token(&err));
+
+        let same = request(2, Some(token)).filter("author = \"users/a\"");
+        assert_eq!(ids(&list(&pager, 5, same).await.unwrap()), vec![3, 4]);
+    }
 
-        let buf = super::base64_encoder::encode_string(pb_buf);
+    #[tokio::test]
+    async fn expired_token_is_rejected() {
+        let pager = Pager::new(&PagingConfig {
+            page_token_secret: "0123456789abcdef0123456789abcdef".to_string(),
+            page_token_ttl: 0,
+        });
 
-        Some(buf)
+        let first = list(&pager, 5, request(2, None)).await.unwrap();
+        let err = list(&pager, 5, request(2, first.next_page_token))
+            .await
+            .err()
+            .unwrap();
 
-        // todo!("test")
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+        assert_eq!(err.message(), "page_token: expired.");
     }
 }
@@ -0,0 +1,227 @@
+use std::fmt;
+
+use ulid::Ulid;
+
+use crate::{
+    models::{
+        attachment::AttachmentId, channel::ChannelId, message::MessageId, server::ServerId,
+        server_category::ServerCategoryId, server_member::ServerMemberId, user::UserId,
+    },
+    services::error::ServiceError,
+};
+
+/// ids of `name` in the order of the `{}` segments of `pattern`, e.g. `channels/{}/messages/{}`.
+fn parse_ids<const N: usize>(name: &str, pattern: &str) -> Result<[Ulid; N], ServiceError> {
+    let invalid = || {
+        ServiceError::invalid_argument(format!(
+            "invalid resource name `{}`, expected `{}`.",
+            name, pattern
+        ))
+    };
+
+    let segments = name.split('/').collect::<Vec<&str>>();
+    let pattern_segments = pattern.split('/').collect::<Vec<&str>>();
+
+    if segments.len() != pattern_segments.len() {
+        return Err(invalid());
+    }
+
+    let mut ids = [Ulid::nil(); N];
+    let mut index = 0;
+
+    for (segment, pattern_segment) in segments.iter().zip(pattern_segments.iter()) {
+        if *pattern_segment != "{}" {
+            if segment != pattern_segment {
+                return Err(invalid());
+            }
+
+            continue;
+        }
+
+        let id = Ulid::from_string(segment).map_err(|_| invalid())?;
+        *ids.get_mut(index).ok_or_else(invalid)? = id;
+        index += 1;
+    }
+
+    Ok(ids)
+}
+
+/// `users/{user}`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UserName(pub UserId);
+
+impl UserName {
+    pub fn parse(name: &str) -> Result<Self, ServiceError> {
+        let [user] = parse_ids(name, "users/{}")?;
+
+        Ok(UserName(user))
+    }
+}
+
+impl fmt::Display for UserName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "users/{}", self.0)
+    }
+}
+
+/// `servers/{server}`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServerName(pub ServerId);
+
+impl ServerName {
+    pub fn parse(name: &str) -> Result<Self, ServiceError> {
+        let [server] = parse_ids(name, "servers/{}")?;
+
+        Ok(ServerName(server))
+    }
+}
+
+impl fmt::Display for ServerName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "servers/{}", self.0)
+    }
+}
+
+/// `servers/{server}/members/{member}`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServerMemberName {
+    pub server: ServerId,
+    pub member: ServerMemberId,
+}
+
+impl ServerMemberName {
+    pub fn parse(name: &str) -> Result<Self, ServiceError> {
+        let [server, member] = parse_ids(name, "servers/{}/members/{}")?;
+
+        Ok(ServerMemberName { server, member })
+    }
+}
+
+impl fmt::Display for ServerMemberName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "servers/{}/members/{}", self.server, self.member)
+    }
+}
+
+/// `servers/{server}/categories/{category}`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CategoryName {
+    pub server: ServerId,
+    pub category: ServerCategoryId,
+}
+
+impl CategoryName {
+    pub fn parse(name: &str) -> Result<Self, ServiceError> {
+        let [server, category] = parse_ids(name, "servers/{}/categories/{}")?;
+
+        Ok(CategoryName { server, category })
+    }
+}
+
+impl fmt::Display for CategoryName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "servers/{}/categories/{}", self.server, self.category)
+    }
+}
+
+/// `channels/{channel}`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelName(pub ChannelId);
+
+impl ChannelName {
+    pub fn parse(name: &str) -> Result<Self, ServiceError> {
+        let [channel] = parse_ids(name, "channels/{}")?;
+
+        Ok(ChannelName(channel))
+    }
+}
+
+impl fmt::Display for ChannelName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "channels/{}", self.0)
+    }
+}
+
+/// `channels/{channel}/messages/{message}`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageName {
+    pub channel: ChannelId,
+    pub message: MessageId,
+}
+
+impl MessageName {
+    pub fn parse(name: &str) -> Result<Self, ServiceError> {
+        let [channel, message] = parse_ids(name, "channels/{}/messages/{}")?;
+
+        Ok(MessageName { channel, message })
+    }
+}
+
+impl fmt::Display for MessageName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "channels/{}/messages/{}", self.channel, self.message)
+    }
+}
+
+/// `attachments/{attachment}`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AttachmentName(pub AttachmentId);
+
+impl AttachmentName {
+    pub fn parse(name: &str) -> Result<Self, ServiceError> {
+        let [attachment] = parse_ids(name, "attachments/{}")?;
+
+        Ok(AttachmentName(attachment))
+    }
+}
+
+impl fmt::Display for AttachmentName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "attachments/{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHANNEL: &str = "01HGW2N5EQNG50CB2HBCN4A8GT";
+    const MESSAGE: &str = "01HGW2PDN3C9W6K9M0Q4CZ8Y7V";
+
+    #[test]
+    fn parses_ids_in_pattern_order() {
+        let name = format!("channels/{}/messages/{}", CHANNEL, MESSAGE);
+        let MessageName { channel, message } = MessageName::parse(&name).unwrap();
+
+        assert_eq!(channel, Ulid::from_string(CHANNEL).unwrap());
+        assert_eq!(message, Ulid::from_string(MESSAGE).unwrap());
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let name = format!("servers/{}/members/{}", CHANNEL, MESSAGE);
+        assert_eq!(ServerMemberName::parse(&name).unwrap().to_string(), name);
+
+        let name = format!("attachments/{}", CHANNEL);
+        assert_eq!(AttachmentName::parse(&name).unwrap().to_string(), name);
+    }
+
+    #[test]
+    fn rejects_malformed_names() {
+        for name in [
+            String::new(),
+            "channels".to_string(),
+            format!("channels/{}/", CHANNEL),
+            format!("channels/{}/messages/{}", CHANNEL, MESSAGE),
+            format!("users/{}", CHANNEL),
+            format!("channels//{}", CHANNEL),
+            "channels/not-an-id".to_string(),
+            format!("/channels/{}", CHANNEL),
+        ] {
+            assert!(ChannelName::parse(&name).is_err(), "{:?}", name);
+        }
+
+        assert!(MessageName::parse(&format!("channels/{}/members/{}", CHANNEL, MESSAGE)).is_err());
+        assert!(CategoryName::parse(&format!("servers/{}/categories", CHANNEL)).is_err());
+    }
+}